@@ -0,0 +1,134 @@
+//! Multi-color "PCB art".
+//!
+//! The colors of a drawing are mapped to the layer stack of a PCB, e.g. gold
+//! areas become exposed copper and white areas become silkscreen.
+
+use crate::{
+    layers::Layer,
+    svg::{self, Color},
+};
+
+/// What a color of the drawing represents on the PCB.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Role {
+    /// Exposed copper (copper with an opening in the soldermask)
+    Copper,
+    /// Copper covered by the soldermask
+    MaskedCopper,
+    /// Silkscreen print
+    Silkscreen,
+    /// Bare substrate (opening in the soldermask without copper)
+    Substrate,
+    /// Plain soldermask, i.e. no polygons at all
+    Soldermask,
+}
+
+impl Role {
+    /// Parse a role name, as used with `--art-color`.
+    pub fn from_name(name: &str) -> Option<Role> {
+        match name {
+            "copper" => Some(Role::Copper),
+            "masked-copper" => Some(Role::MaskedCopper),
+            "silkscreen" => Some(Role::Silkscreen),
+            "substrate" => Some(Role::Substrate),
+            "soldermask" => Some(Role::Soldermask),
+            _ => None,
+        }
+    }
+
+    /// Title of the role, e.g. for the packages of `--package-per-color`.
+    pub fn title(self) -> &'static str {
+        match self {
+            Role::Copper => "Copper",
+            Role::MaskedCopper => "Masked Copper",
+            Role::Silkscreen => "Silkscreen",
+            Role::Substrate => "Substrate",
+            Role::Soldermask => "Soldermask",
+        }
+    }
+
+    /// Layers on which polygons are needed to produce this role.
+    pub fn layers(self) -> &'static [Layer] {
+        match self {
+            Role::Copper => &[Layer::TopCopper, Layer::TopStopMask],
+            Role::MaskedCopper => &[Layer::TopCopper],
+            Role::Silkscreen => &[Layer::TopLegend],
+            Role::Substrate => &[Layer::TopStopMask],
+            Role::Soldermask => &[],
+        }
+    }
+}
+
+/// Colors mapped to roles if not overridden with `--art-color`.
+pub const DEFAULT_PALETTE: [(Color, Role); 7] = [
+    ([255, 215, 0], Role::Copper),         // Gold
+    ([184, 115, 51], Role::Copper),        // Copper
+    ([255, 255, 255], Role::Silkscreen),   // White
+    ([0, 0, 0], Role::Soldermask),         // Black
+    ([0, 100, 0], Role::Soldermask),       // Dark green
+    ([144, 238, 144], Role::MaskedCopper), // Light green
+    ([210, 180, 140], Role::Substrate),    // Tan
+];
+
+/// Parse a color mapping in the format `COLOR=ROLE`.
+pub fn parse_mapping(value: &str) -> Result<(Color, Role), String> {
+    let (color, role) = value
+        .split_once('=')
+        .ok_or_else(|| "Expected COLOR=ROLE".to_string())?;
+    let color = svg::parse_color(color).ok_or_else(|| format!("Unknown color {:?}", color))?;
+    let role = Role::from_name(role.trim()).ok_or_else(|| {
+        format!(
+            "Unknown role {:?} (expected copper, masked-copper, silkscreen, substrate or soldermask)",
+            role
+        )
+    })?;
+    Ok((color, role))
+}
+
+/// Role of the palette color closest to the given color.
+pub fn classify(color: Color, palette: &[(Color, Role)]) -> Option<Role> {
+    let distance = |other: &Color| -> i32 {
+        color
+            .iter()
+            .zip(other)
+            .map(|(a, b)| (i32::from(*a) - i32::from(*b)).pow(2))
+            .sum()
+    };
+    palette
+        .iter()
+        .min_by_key(|(other, _)| distance(other))
+        .map(|(_, role)| *role)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mapping() {
+        assert_eq!(
+            parse_mapping("#ff0000=silkscreen"),
+            Ok(([255, 0, 0], Role::Silkscreen))
+        );
+        assert!(parse_mapping("#ff0000").is_err());
+        assert!(parse_mapping("nocolor=copper").is_err());
+        assert!(parse_mapping("red=paint").is_err());
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify([250, 210, 10], &DEFAULT_PALETTE),
+            Some(Role::Copper)
+        );
+        assert_eq!(
+            classify([240, 240, 240], &DEFAULT_PALETTE),
+            Some(Role::Silkscreen)
+        );
+        assert_eq!(
+            classify([20, 40, 20], &DEFAULT_PALETTE),
+            Some(Role::Soldermask)
+        );
+        assert_eq!(classify([0, 0, 0], &[]), None);
+    }
+}
@@ -0,0 +1,410 @@
+//! Converting all files in a directory.
+//!
+//! Metadata for the individual files can be provided in a CSV file with a
+//! header row, e.g.:
+//!
+//! ```csv
+//! file,name,description,keywords,scale
+//! acme.svg,ACME Logo,The ACME company logo,"logo,acme",10
+//! oshw.svg,OSHW Logo,,,"5,10"
+//! ```
+//!
+//! Only the `file` column is required. Empty values fall back to the command
+//! line arguments (or the file name, for the element name).
+//!
+//! Names and descriptions can also be generated from templates with
+//! placeholders for the file name, dimensions and date, e.g.
+//! `--name-template "Logo {stem} ({width}mm)"`.
+//!
+//! The converted files with their elements and warnings, and the skipped files
+//! can be summarized in a report (`--report`).
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    format_float, ElementKind, GERBER_EXTENSIONS,
+};
+
+/// File extensions of the files converted in a directory (in addition to
+/// Gerber files).
+const INPUT_EXTENSIONS: [&str; 5] = ["svg", "svgz", "pdf", "ai", "kicad_mod"];
+
+/// A row of the metadata CSV file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    file: String,
+    name: Option<String>,
+    description: Option<String>,
+    keywords: Option<String>,
+    scale: Option<String>,
+}
+
+/// Metadata for a single file.
+#[derive(Default, Debug, PartialEq)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub keywords: Option<String>,
+    /// Footprint widths in mm
+    pub scales: Option<Vec<f64>>,
+}
+
+/// Values of the placeholders in `--name-template` and
+/// `--description-template`.
+pub struct TemplateValues<'a> {
+    /// File name, e.g. `acme.svg`
+    pub file: &'a str,
+    /// File name without extension, e.g. `acme`
+    pub stem: &'a str,
+    /// Size of the (first) footprint in mm
+    pub width: f64,
+    pub height: f64,
+    /// Generation date, e.g. `2024-03-01`
+    pub date: &'a str,
+}
+
+/// Replace the placeholders `{file}`, `{stem}`, `{width}`, `{height}` and
+/// `{date}` in a template. Literal braces are written as `{{` and `}}`.
+pub fn expand_template(template: &str, values: &TemplateValues) -> Result<String> {
+    let error = |reason: String| Error::Template {
+        template: template.to_string(),
+        reason,
+    };
+    let mut expanded = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                expanded.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                expanded.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| error("unclosed placeholder".to_string()))?;
+                match &rest[..end] {
+                    "file" => expanded.push_str(values.file),
+                    "stem" => expanded.push_str(values.stem),
+                    "width" => expanded.push_str(&format_float(values.width)),
+                    "height" => expanded.push_str(&format_float(values.height)),
+                    "date" => expanded.push_str(values.date),
+                    placeholder => {
+                        return Err(error(format!(
+                            "unknown placeholder {{{}}} (use file, stem, width, height or date)",
+                            placeholder
+                        )))
+                    }
+                }
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return Err(error("unmatched } (write }} for a brace)".to_string())),
+            c => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Parse a list of footprint widths separated by commas or whitespace.
+fn parse_scales(value: &str) -> Option<Vec<f64>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Parse the metadata CSV, returning the metadata by file name.
+fn parse_metadata<R: io::Read>(
+    reader: R,
+) -> std::result::Result<HashMap<String, Metadata>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let mut entries = HashMap::new();
+    for record in reader.deserialize() {
+        let record: Record = record.map_err(|e| e.to_string())?;
+        let scales = match &record.scale {
+            Some(scale) => Some(
+                parse_scales(scale)
+                    .ok_or_else(|| format!("Invalid scale {:?} for {:?}", scale, record.file))?,
+            ),
+            None => None,
+        };
+        let metadata = Metadata {
+            name: record.name,
+            description: record.description,
+            keywords: record.keywords,
+            scales,
+        };
+        if entries.insert(record.file.clone(), metadata).is_some() {
+            return Err(format!("Duplicate entry for {:?}", record.file));
+        }
+    }
+    Ok(entries)
+}
+
+/// Load the metadata CSV file.
+pub fn load_metadata(path: &Path) -> Result<HashMap<String, Metadata>> {
+    let file = fs::File::open(path).map_err(|source| Error::ReadFile {
+        path: path.to_owned(),
+        source,
+    })?;
+    parse_metadata(file).map_err(|reason| Error::MetadataCsv {
+        path: path.to_owned(),
+        reason,
+    })
+}
+
+/// List the files to convert in a directory and the other files (which are
+/// skipped), sorted by name.
+pub fn input_files(dir: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let read_error = |source| Error::ReadFile {
+        path: dir.to_owned(),
+        source,
+    };
+    let mut files = vec![];
+    let mut skipped = vec![];
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let extension = extension.as_deref().unwrap_or("");
+        if !path.is_file() {
+            continue;
+        }
+        if INPUT_EXTENSIONS.contains(&extension) || GERBER_EXTENSIONS.contains(&extension) {
+            files.push(path);
+        } else {
+            skipped.push(path);
+        }
+    }
+    files.sort();
+    skipped.sort();
+    Ok((files, skipped))
+}
+
+/// A converted file in the report of `--report`.
+pub struct ReportEntry {
+    pub file: String,
+    pub name: String,
+    /// The generated elements with their UUIDs
+    pub elements: Vec<(ElementKind, String)>,
+    /// Warnings reported while converting the file
+    pub warnings: Vec<String>,
+}
+
+/// Summary of a directory conversion (`--report`), e.g. to attach to the pull
+/// request adding the elements to a library.
+pub struct Report<'a> {
+    /// The converted files in each library
+    pub libraries: Vec<(&'a Path, &'a [ReportEntry])>,
+    /// Files which were not converted, with the reason
+    pub skipped: &'a [(String, &'static str)],
+}
+
+impl Report<'_> {
+    /// Write the report as JSON if the file name ends with `.json`, as
+    /// Markdown otherwise.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let contents = match json {
+            true => format!("{:#}\n", self.json()),
+            false => self.markdown(),
+        };
+        fs::write(path, contents).map_err(|source| Error::WriteFile {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    fn markdown(&self) -> String {
+        // Pipes would end the table cell
+        let cell = |value: &str| value.replace('|', "\\|");
+        let mut lines = vec!["# svg2librepcb report".to_string()];
+        for (library, files) in &self.libraries {
+            lines.extend([
+                String::new(),
+                format!("Library: `{}`", library.display()),
+                String::new(),
+                "| File | Name | Elements | Warnings |".to_string(),
+                "| --- | --- | --- | --- |".to_string(),
+            ]);
+            for entry in files.iter() {
+                let elements: Vec<String> = entry
+                    .elements
+                    .iter()
+                    .map(|(kind, uuid)| format!("`{}/{}`", kind.dir_name(), uuid))
+                    .collect();
+                let warnings: Vec<String> = entry.warnings.iter().map(|w| cell(w)).collect();
+                lines.push(format!(
+                    "| {} | {} | {} | {} |",
+                    cell(&entry.file),
+                    cell(&entry.name),
+                    elements.join("<br>"),
+                    warnings.join("<br>")
+                ));
+            }
+        }
+        if !self.skipped.is_empty() {
+            lines.extend([String::new(), "## Skipped".to_string(), String::new()]);
+            for (file, reason) in self.skipped {
+                lines.push(format!("- `{}`: {}", file, reason));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn json(&self) -> serde_json::Value {
+        let libraries: Vec<serde_json::Value> = self
+            .libraries
+            .iter()
+            .map(|(library, files)| {
+                let files: Vec<serde_json::Value> = files
+                    .iter()
+                    .map(|entry| {
+                        let elements: Vec<serde_json::Value> = entry
+                            .elements
+                            .iter()
+                            .map(|(kind, uuid)| {
+                                serde_json::json!({"kind": kind.to_string(), "uuid": uuid})
+                            })
+                            .collect();
+                        serde_json::json!({
+                            "file": entry.file,
+                            "name": entry.name,
+                            "elements": elements,
+                            "warnings": entry.warnings,
+                        })
+                    })
+                    .collect();
+                serde_json::json!({"library": library, "files": files})
+            })
+            .collect();
+        let skipped: Vec<serde_json::Value> = self
+            .skipped
+            .iter()
+            .map(|(file, reason)| serde_json::json!({"file": file, "reason": reason}))
+            .collect();
+        serde_json::json!({
+            "libraries": libraries,
+            "skipped": skipped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        let csv = "file,name,keywords,scale\n\
+                   a.svg,Logo A,,10\n\
+                   b.svg, Logo B ,\"logo,b\",\"5, 10\"\n";
+        let entries = parse_metadata(csv.as_bytes()).unwrap();
+        assert_eq!(
+            entries["a.svg"],
+            Metadata {
+                name: Some("Logo A".into()),
+                scales: Some(vec![10.0]),
+                ..Default::default()
+            }
+        );
+        assert_eq!(entries["b.svg"].name.as_deref(), Some("Logo B"));
+        assert_eq!(entries["b.svg"].keywords.as_deref(), Some("logo,b"));
+        assert_eq!(entries["b.svg"].scales, Some(vec![5.0, 10.0]));
+    }
+
+    #[test]
+    fn test_report() {
+        let files = [ReportEntry {
+            file: "acme.svg".into(),
+            name: "ACME | Logo".into(),
+            elements: vec![
+                (ElementKind::Package, "b7f1".into()),
+                (ElementKind::Device, "df83".into()),
+            ],
+            warnings: vec!["ignored-text: Texts in the SVG are ignored".into()],
+        }];
+        let team_files = [ReportEntry {
+            file: "acme.svg".into(),
+            name: "ACME | Logo".into(),
+            elements: vec![(ElementKind::Package, "04c2".into())],
+            warnings: vec![],
+        }];
+        let report = Report {
+            libraries: vec![
+                (Path::new("Logos.lplib"), &files),
+                (Path::new("Team.lplib"), &team_files),
+            ],
+            skipped: &[("notes.txt".into(), "not a supported input file")],
+        };
+        assert_eq!(
+            report.markdown(),
+            "# svg2librepcb report\n\n\
+             Library: `Logos.lplib`\n\n\
+             | File | Name | Elements | Warnings |\n\
+             | --- | --- | --- | --- |\n\
+             | acme.svg | ACME \\| Logo | `pkg/b7f1`<br>`dev/df83` | ignored-text: Texts in the SVG are ignored |\n\n\
+             Library: `Team.lplib`\n\n\
+             | File | Name | Elements | Warnings |\n\
+             | --- | --- | --- | --- |\n\
+             | acme.svg | ACME \\| Logo | `pkg/04c2` |  |\n\n\
+             ## Skipped\n\n\
+             - `notes.txt`: not a supported input file\n"
+        );
+        let json = report.json();
+        assert_eq!(
+            json["libraries"][0]["files"][0]["elements"][1]["kind"],
+            "device"
+        );
+        assert_eq!(json["libraries"][1]["library"], "Team.lplib");
+        assert_eq!(json["skipped"][0]["file"], "notes.txt");
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let values = TemplateValues {
+            file: "acme.svg",
+            stem: "acme",
+            width: 10.0,
+            height: 4.25,
+            date: "2024-03-01",
+        };
+        assert_eq!(
+            expand_template("Logo {stem} ({width}x{height}mm)", &values).unwrap(),
+            "Logo acme (10.0x4.25mm)"
+        );
+        assert_eq!(
+            expand_template("{file}, {date} {{draft}}", &values).unwrap(),
+            "acme.svg, 2024-03-01 {draft}"
+        );
+        for template in ["{name}", "{stem", "stem}"] {
+            assert!(expand_template(template, &values).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_parse_metadata_errors() {
+        assert!(parse_metadata("file,scale\na.svg,big\n".as_bytes()).is_err());
+        assert!(parse_metadata("file,color\na.svg,red\n".as_bytes()).is_err());
+        assert!(parse_metadata("file\na.svg\na.svg\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Node ordering as written by LibrePCB.
+//!
+//! LibrePCB writes the child nodes of an element in a fixed order (e.g. pins
+//! before polygons before texts). Writing the same order avoids diffs when an
+//! element is opened and saved again in the library editor.
+//!
+//! The generated files are line based: Every child node of an element or
+//! footprint starts on its own line, which allows to reorder them without
+//! parsing the S-expressions.
+
+/// Order of child nodes within elements and footprints.
+const NODE_ORDER: [&str; 32] = [
+    "name",
+    "description",
+    "keywords",
+    "author",
+    "version",
+    "created",
+    "deprecated",
+    "generated_by",
+    "category",
+    "schematic_only",
+    "default_value",
+    "prefix",
+    "assembly_type",
+    "component",
+    "package",
+    "3d_position",
+    "attribute",
+    "signal",
+    "variant",
+    "pin",
+    "pad",
+    "3d_model",
+    "footprint",
+    "polygon",
+    "circle",
+    "stroke_text",
+    "text",
+    "zone",
+    "hole",
+    "part",
+    // Unknown nodes are placed here
+    "",
+    "approved",
+];
+
+/// Name of the node starting on the given line.
+fn node_name(line: &str) -> &str {
+    line.trim_start()
+        .trim_start_matches('(')
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .next()
+        .unwrap_or("")
+}
+
+/// Rank of a node in [`NODE_ORDER`].
+fn rank(name: &str) -> usize {
+    NODE_ORDER
+        .iter()
+        .position(|n| *n == name)
+        .or_else(|| NODE_ORDER.iter().position(|n| n.is_empty()))
+        .unwrap_or(0)
+}
+
+/// Sort the children of a node spanning the given lines.
+///
+/// The first line is the node header, the last line the closing parenthesis.
+/// Every child starts on a new line with an indentation of `depth + 1`, and
+/// spans all following lines with a deeper indentation (plus its closing
+/// parenthesis). Children of footprints are sorted as well.
+fn sort_node(lines: &[String], depth: usize) -> Vec<String> {
+    if lines.len() < 2 {
+        return lines.to_vec();
+    }
+    let inner = &lines[1..lines.len() - 1];
+    let prefix = format!("{}(", " ".repeat(depth + 1));
+    let mut children: Vec<&[String]> = vec![];
+    let mut start = 0;
+    for (i, line) in inner.iter().enumerate().skip(1) {
+        if line.starts_with(&prefix) {
+            children.push(&inner[start..i]);
+            start = i;
+        }
+    }
+    if !inner.is_empty() {
+        children.push(&inner[start..]);
+    }
+    children.sort_by_key(|child| {
+        let name = node_name(&child[0]);
+        // Approvals are sorted by their content
+        let approval = if name == "approved" { &child[..] } else { &[] };
+        (rank(name), approval)
+    });
+
+    let mut sorted = vec![lines[0].clone()];
+    for child in children {
+        if node_name(&child[0]) == "footprint" {
+            sorted.extend(sort_node(child, depth + 1));
+        } else {
+            sorted.extend_from_slice(child);
+        }
+    }
+    sorted.push(lines[lines.len() - 1].clone());
+    sorted
+}
+
+/// Reorder the nodes of a generated element.
+pub fn sort_nodes(lines: &[String]) -> Vec<String> {
+    sort_node(lines, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_nodes() {
+        let lines: Vec<String> = [
+            "(librepcb_symbol 11111111-1111-4111-8111-111111111111",
+            " (name \"Logo\")",
+            " (approved missing_prefix)",
+            " (text 22222222-2222-4222-8222-222222222222 (layer sym_names)",
+            "  (height 2.5)",
+            " )",
+            " (approved missing_categories)",
+            " (polygon 33333333-3333-4333-8333-333333333333 (layer sym_outlines)",
+            " )",
+            " (pin 44444444-4444-4444-8444-444444444444 (name \"1\"))",
+            ")",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let expected: Vec<String> = [
+            "(librepcb_symbol 11111111-1111-4111-8111-111111111111",
+            " (name \"Logo\")",
+            " (pin 44444444-4444-4444-8444-444444444444 (name \"1\"))",
+            " (polygon 33333333-3333-4333-8333-333333333333 (layer sym_outlines)",
+            " )",
+            " (text 22222222-2222-4222-8222-222222222222 (layer sym_names)",
+            "  (height 2.5)",
+            " )",
+            " (approved missing_categories)",
+            " (approved missing_prefix)",
+            ")",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        assert_eq!(sort_nodes(&lines), expected);
+    }
+}
@@ -0,0 +1,185 @@
+//! Optional TOML configuration files.
+//!
+//! Example of a config file passed with `--config`:
+//!
+//! ```toml
+//! [layer-aliases]
+//! silk = "top_placement"
+//! mask = "top_stop_mask"
+//!
+//! [warnings]
+//! ignored-text = "error"
+//! save-settings = "ignore"
+//! ```
+//!
+//! A library can pin defaults for everybody generating elements for it with
+//! a `.svg2librepcb.toml` file in the library directory. Options given on the
+//! command line take precedence:
+//!
+//! ```toml
+//! format-version = "1"
+//! author = "Logo Team"
+//! component-category = "e29f0cb3-ef6d-4203-b854-d75150cbae0b"
+//! package-category = "9b4b0c5e-4b5a-4b8a-8f3b-2bcd1c8f8d6e"
+//! layers = ["top_documentation"]
+//!
+//! [layer-aliases]
+//! silk = "top_placement"
+//! ```
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Level, Result, WARNING_NAMES},
+    layers::Layer,
+};
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Friendly names for layers, mapping to a legacy or modern layer name
+    pub layer_aliases: HashMap<String, String>,
+    /// Levels of warnings, by name
+    pub warnings: HashMap<String, Level>,
+}
+
+impl Config {
+    /// Load and validate a config file.
+    pub fn load(path: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(path).map_err(|source| Error::ReadFile {
+            path: path.to_owned(),
+            source,
+        })?;
+        let config: Config = toml::from_str(&contents).map_err(|e| Error::Config {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })?;
+        check_layer_aliases(&config.layer_aliases).map_err(|reason| Error::Config {
+            path: path.to_owned(),
+            reason,
+        })?;
+        if let Some(name) = config
+            .warnings
+            .keys()
+            .find(|name| !WARNING_NAMES.contains(&name.as_str()))
+        {
+            return Err(Error::Config {
+                path: path.to_owned(),
+                reason: format!(
+                    "Unknown warning {:?} (known warnings: {})",
+                    name,
+                    WARNING_NAMES.join(", ")
+                ),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Resolve a layer name or alias.
+    pub fn resolve_layer(&self, name: &str) -> Option<Layer> {
+        let name = self.layer_aliases.get(name).map_or(name, String::as_str);
+        Layer::from_name(name)
+    }
+}
+
+/// Ensure that all layer aliases point to existing layers.
+fn check_layer_aliases(aliases: &HashMap<String, String>) -> std::result::Result<(), String> {
+    for (alias, target) in aliases {
+        if Layer::from_name(target).is_none() {
+            return Err(format!(
+                "Layer alias {:?} points to unknown layer {:?}",
+                alias, target
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Name of the library config file, in the library directory.
+pub const LIBRARY_CONFIG_FILE: &str = ".svg2librepcb.toml";
+
+/// Defaults pinned by a library (see the module documentation).
+#[derive(Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LibraryConfig {
+    /// LibrePCB file format version ("0.1" or "1")
+    pub format_version: Option<String>,
+    pub author: Option<String>,
+    /// Component category UUID
+    pub component_category: Option<String>,
+    /// Package category UUID
+    pub package_category: Option<String>,
+    /// Additional footprint layers (layer names or aliases)
+    pub layers: Vec<String>,
+    /// Layer aliases, in addition to those of the config file
+    pub layer_aliases: HashMap<String, String>,
+}
+
+impl LibraryConfig {
+    /// Load the config file of a library, if it has one.
+    pub fn load(library: &Path) -> Result<Option<LibraryConfig>> {
+        let path = library.join(LIBRARY_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let error = |reason: String| Error::Config {
+            path: path.clone(),
+            reason,
+        };
+        let contents = fs::read_to_string(&path).map_err(|source| Error::ReadFile {
+            path: path.clone(),
+            source,
+        })?;
+        let config: LibraryConfig = toml::from_str(&contents).map_err(|e| error(e.to_string()))?;
+        check_layer_aliases(&config.layer_aliases).map_err(error)?;
+        Ok(Some(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn test_resolve_layer() {
+        let config: Config = toml::from_str(
+            r#"
+            [layer-aliases]
+            silk = "top_placement"
+            mask = "top_stop_mask"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.resolve_layer("silk"), Some(Layer::TopLegend));
+        assert_eq!(config.resolve_layer("mask"), Some(Layer::TopStopMask));
+        assert_eq!(config.resolve_layer("top_legend"), Some(Layer::TopLegend));
+        assert_eq!(config.resolve_layer("top_cu"), Some(Layer::TopCopper));
+        assert_eq!(config.resolve_layer("copper"), None);
+    }
+
+    #[test]
+    fn test_library_config() {
+        let dir = TempDir::new("test").unwrap();
+        let library = dir.path();
+        assert_eq!(LibraryConfig::load(library).unwrap(), None);
+
+        let path = library.join(LIBRARY_CONFIG_FILE);
+        fs::write(
+            &path,
+            "format-version = \"1\"\nlayers = [\"silk\"]\n[layer-aliases]\nsilk = \"top_placement\"\n",
+        )
+        .unwrap();
+        let config = LibraryConfig::load(library).unwrap().unwrap();
+        assert_eq!(config.format_version.as_deref(), Some("1"));
+        assert_eq!(config.author, None);
+        assert_eq!(config.layers, vec!["silk"]);
+
+        fs::write(&path, "[layer-aliases]\nsilk = \"silkscreen\"\n").unwrap();
+        assert!(LibraryConfig::load(library).is_err());
+        fs::write(&path, "category = \"x\"\n").unwrap();
+        assert!(LibraryConfig::load(library).is_err());
+    }
+}
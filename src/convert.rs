@@ -0,0 +1,150 @@
+//! Conversions using external programs.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::temp::TempDir;
+
+/// Error when running an external program.
+enum RunError {
+    NotInstalled(String),
+    Failed(String),
+}
+
+impl From<RunError> for String {
+    fn from(error: RunError) -> String {
+        match error {
+            RunError::NotInstalled(program) => {
+                format!("{} is not installed (or not in PATH)", program)
+            }
+            RunError::Failed(reason) => reason,
+        }
+    }
+}
+
+/// Run a command, returning its error output on failure.
+fn run(command: &mut Command) -> Result<(), RunError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let result = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output();
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(RunError::NotInstalled(program)),
+        Err(e) => Err(RunError::Failed(format!(
+            "Could not run {}: {}",
+            program, e
+        ))),
+        Ok(output) if !output.status.success() => Err(RunError::Failed(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Convert all texts of an SVG document to paths using Inkscape.
+pub fn text_to_path(svg: &str) -> Result<String, String> {
+    let dir = TempDir::new("convert").map_err(|e| e.to_string())?;
+    let input = dir.path().join("input.svg");
+    let output = dir.path().join("output.svg");
+    fs::write(&input, svg).map_err(|e| e.to_string())?;
+    let mut export_filename = OsString::from("--export-filename=");
+    export_filename.push(&output);
+    run(Command::new("inkscape")
+        .arg("--export-text-to-path")
+        .arg("--export-plain-svg")
+        .arg(export_filename)
+        .arg(&input))?;
+    fs::read_to_string(&output).map_err(|e| e.to_string())
+}
+
+/// Convert the first page of a PDF (or PDF compatible Adobe Illustrator) file
+/// to SVG, using pdftocairo or Inkscape, whichever is installed.
+pub fn pdf_to_svg(path: &Path) -> Result<String, String> {
+    let dir = TempDir::new("convert").map_err(|e| e.to_string())?;
+    let output = dir.path().join("output.svg");
+    let pdftocairo = run(Command::new("pdftocairo")
+        .args(["-svg", "-f", "1", "-l", "1"])
+        .arg(path)
+        .arg(&output));
+    if let Err(RunError::NotInstalled(_)) = pdftocairo {
+        let mut export_filename = OsString::from("--export-filename=");
+        export_filename.push(&output);
+        run(Command::new("inkscape")
+            .arg("--pdf-page=1")
+            .arg("--export-plain-svg")
+            .arg(export_filename)
+            .arg(path))
+        .map_err(|e| match e {
+            RunError::NotInstalled(_) => {
+                "Neither pdftocairo nor inkscape is installed (or in PATH)".to_string()
+            }
+            e => e.into(),
+        })?;
+    } else {
+        pdftocairo?;
+    }
+    fs::read_to_string(&output).map_err(|e| e.to_string())
+}
+
+/// Programs (with their arguments) which read the clipboard, tried in this
+/// order. Inkscape offers its own SVG type in addition to
+/// the standard one, which is preferred by the X11 and Wayland readers.
+fn clipboard_readers() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "windows") {
+        vec![(
+            "powershell",
+            vec!["-NoProfile", "-Command", "Get-Clipboard -Raw"],
+        )]
+    } else if cfg!(target_os = "macos") {
+        vec![("pbpaste", vec![])]
+    } else {
+        let mut readers = vec![];
+        for mime_type in ["image/x-inkscape-svg", "image/svg+xml", "text/plain"] {
+            if env::var_os("WAYLAND_DISPLAY").is_some() {
+                readers.push(("wl-paste", vec!["--no-newline", "--type", mime_type]));
+            }
+            readers.push((
+                "xclip",
+                vec!["-selection", "clipboard", "-target", mime_type, "-o"],
+            ));
+        }
+        readers
+    }
+}
+
+/// Read an SVG document from the system clipboard.
+pub fn read_clipboard() -> Result<String, String> {
+    let mut installed = false;
+    for (program, args) in clipboard_readers() {
+        let output = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Could not run {}: {}", program, e)),
+            Ok(output) => {
+                installed = true;
+                let text = String::from_utf8_lossy(&output.stdout);
+                if output.status.success() && text.contains("<svg") {
+                    return Ok(text.into_owned());
+                }
+            }
+        }
+    }
+    if installed {
+        Err("The clipboard does not contain an SVG document".to_string())
+    } else {
+        Err("No clipboard program is installed (xclip or wl-paste on Linux)".to_string())
+    }
+}
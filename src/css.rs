@@ -0,0 +1,233 @@
+//! Style sheets of SVG documents (`<style>` elements), which programs like
+//! Figma or Illustrator use instead of presentation attributes:
+//!
+//! ```css
+//! .cls-1 { fill: #fff; }
+//! path.hidden, #guides { display: none; }
+//! ```
+//!
+//! Only simple selectors are supported: `*`, element names, classes, ids and
+//! combinations of them (e.g. `path.logo`). Rules with other selectors (e.g.
+//! descendant combinators or pseudo-classes) and at-rules are ignored.
+
+use roxmltree::{Document, Node};
+
+/// A simple selector, e.g. `path.logo.dark` or `#guides`.
+#[derive(Debug, PartialEq, Default)]
+struct Selector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Selector {
+    /// Parse a simple selector, or return `None` if it is not supported.
+    fn parse(selector: &str) -> Option<Selector> {
+        let is_name = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        };
+        let mut result = Selector::default();
+        // The element name (or `*`) is followed by the classes and ids
+        let start = selector.find(['.', '#']).unwrap_or(selector.len());
+        match &selector[..start] {
+            "" if selector.is_empty() => return None,
+            "" | "*" => {}
+            tag if is_name(tag) => result.tag = Some(tag.to_string()),
+            _ => return None,
+        }
+        let mut rest = &selector[start..];
+        while let Some(kind) = rest.chars().next() {
+            let end = rest[1..].find(['.', '#']).map_or(rest.len(), |end| end + 1);
+            let name = &rest[1..end];
+            if !is_name(name) {
+                return None;
+            }
+            match kind {
+                '.' => result.classes.push(name.to_string()),
+                _ => result.id = Some(name.to_string()),
+            }
+            rest = &rest[end..];
+        }
+        Some(result)
+    }
+
+    fn matches(&self, node: Node) -> bool {
+        let classes = node.attribute("class").unwrap_or("");
+        self.tag
+            .as_ref()
+            .is_none_or(|tag| node.tag_name().name() == tag)
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| node.attribute("id") == Some(id))
+            && self
+                .classes
+                .iter()
+                .all(|class| classes.split_whitespace().any(|c| c == class))
+    }
+
+    /// Specificity as (ids, classes, element names).
+    fn specificity(&self) -> (usize, usize, usize) {
+        (
+            self.id.is_some() as usize,
+            self.classes.len(),
+            self.tag.is_some() as usize,
+        )
+    }
+}
+
+/// The rules of all `<style>` elements of a document.
+#[derive(Debug, Default)]
+pub struct StyleSheet {
+    /// Selectors with their declarations, in document order
+    rules: Vec<(Selector, Vec<(String, String)>)>,
+}
+
+impl StyleSheet {
+    pub fn new(document: &Document) -> StyleSheet {
+        let mut style_sheet = StyleSheet::default();
+        for node in document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "style")
+        {
+            let css: String = node.children().filter_map(|child| child.text()).collect();
+            style_sheet.parse(&css);
+        }
+        style_sheet
+    }
+
+    fn parse(&mut self, css: &str) {
+        let css = strip_comments(css);
+        let mut rest = &css[..];
+        while let Some(open) = rest.find('{') {
+            // Find the matching brace, skipping nested blocks of at-rules
+            let mut depth = 0;
+            let mut close = rest.len();
+            for (i, c) in rest[open..].char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            close = open + i;
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Statements like `@import url(...);` end with a semicolon
+            let prelude = rest[..open].rsplit(';').next().unwrap_or("").trim();
+            if !prelude.starts_with('@') {
+                let declarations: Vec<(String, String)> = declarations(&rest[open + 1..close])
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+                for selector in prelude.split(',').filter_map(|s| Selector::parse(s.trim())) {
+                    self.rules.push((selector, declarations.clone()));
+                }
+            }
+            rest = &rest[(close + 1).min(rest.len())..];
+        }
+    }
+
+    /// Value of a property for an element, from the rule with the highest
+    /// specificity (or the last one, if equal).
+    pub fn get(&self, node: Node, name: &str) -> Option<&str> {
+        let mut best: Option<((usize, usize, usize), &str)> = None;
+        for (selector, declarations) in &self.rules {
+            if !selector.matches(node) {
+                continue;
+            }
+            let value = declarations
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str());
+            if let Some(value) = value {
+                let specificity = selector.specificity();
+                if best.is_none_or(|(best, _)| specificity >= best) {
+                    best = Some((specificity, value));
+                }
+            }
+        }
+        best.map(|(_, value)| value)
+    }
+}
+
+/// The declarations of a rule or `style` attribute, e.g. `fill: red;
+/// stroke: none` (without `!important`).
+pub fn declarations(block: &str) -> impl Iterator<Item = (&str, &str)> {
+    block.split(';').filter_map(|declaration| {
+        let (name, value) = declaration.split_once(':')?;
+        let value = value.trim();
+        let value = value.strip_suffix("!important").unwrap_or(value).trim();
+        Some((name.trim(), value))
+    })
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector() {
+        assert_eq!(
+            Selector::parse("path.logo.dark"),
+            Some(Selector {
+                tag: Some("path".into()),
+                id: None,
+                classes: vec!["logo".into(), "dark".into()],
+            })
+        );
+        assert_eq!(
+            Selector::parse("#guides"),
+            Some(Selector {
+                id: Some("guides".into()),
+                ..Selector::default()
+            })
+        );
+        assert_eq!(Selector::parse("*"), Some(Selector::default()));
+        for selector in ["g path", "a:hover", "[fill]", "path.", "g > path", ""] {
+            assert_eq!(Selector::parse(selector), None, "{}", selector);
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let svg = concat!(
+            r#"<svg><style><![CDATA[ @import url("a.css"); /* .a { fill: red } */"#,
+            r#" .a, .b { fill: blue; stroke: red !important } path.a { fill: green }"#,
+            r#" @media print { .a { fill: black } } #p { fill: gold } g path { fill: red }"#,
+            r#"]]></style><path id="p" class="a"/><path class="b x"/><path/></svg>"#,
+        );
+        let document = Document::parse(svg).unwrap();
+        let style_sheet = StyleSheet::new(&document);
+        let paths: Vec<Node> = document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "path")
+            .collect();
+        let fills: Vec<_> = paths
+            .iter()
+            .map(|&path| style_sheet.get(path, "fill"))
+            .collect();
+        assert_eq!(fills, vec![Some("gold"), Some("blue"), None]);
+        assert_eq!(style_sheet.get(paths[1], "stroke"), Some("red"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Diagnosis of the environment (`doctor` command).
+//!
+//! Most "nothing shows up in LibrePCB" problems are caused by a library
+//! outside of a workspace or by missing external programs. These are checked
+//! and reported together with a hint how to fix them.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::config::Config;
+
+/// Outcome of a check.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// Result of a single check.
+#[derive(PartialEq, Debug)]
+pub struct Finding {
+    pub status: Status,
+    pub message: String,
+    /// What to do about a problem
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(message: String) -> Self {
+        Finding {
+            status: Status::Ok,
+            message,
+            hint: None,
+        }
+    }
+
+    fn problem(status: Status, message: String, hint: &str) -> Self {
+        Finding {
+            status,
+            message,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+/// External programs: Name, argument to print the version and what they are
+/// used for.
+const PROGRAMS: [(&str, &str, &str); 3] = [
+    (
+        "inkscape",
+        "--version",
+        "needed for --text-to-path and to convert PDF files",
+    ),
+    ("pdftocairo", "-v", "used to convert PDF and AI files"),
+    (
+        "librepcb-cli",
+        "--version",
+        "optional, for --verify and e.g. to check the generated libraries in CI",
+    ),
+];
+
+/// Name of the file which marks the root directory of a LibrePCB workspace.
+const WORKSPACE_FILE: &str = ".librepcb-workspace";
+
+/// Run all checks, for the library and config file if given.
+pub fn diagnose(outpath: Option<&Path>, config: Option<&Path>) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (program, version_arg, purpose) in PROGRAMS {
+        findings.push(check_program(program, version_arg, purpose));
+    }
+    match outpath {
+        Some(path) => findings.extend(check_library(path)),
+        None => findings.push(check_default_workspace()),
+    }
+    if let Some(path) = config {
+        findings.push(match Config::load(path) {
+            Ok(_) => Finding::ok(format!("Config file {:?} is valid", path)),
+            Err(e) => Finding::problem(
+                Status::Error,
+                e.to_string(),
+                "Fix the config file (see the README for the format)",
+            ),
+        });
+    }
+    findings
+}
+
+/// Check whether a program is installed by printing its version.
+fn check_program(program: &str, version_arg: &str, purpose: &str) -> Finding {
+    let output = Command::new(program)
+        .arg(version_arg)
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => {
+            // Some programs print their version to stderr
+            let text = String::from_utf8_lossy(&output.stdout).into_owned()
+                + &String::from_utf8_lossy(&output.stderr);
+            let version = text.lines().map(str::trim).find(|line| !line.is_empty());
+            Finding::ok(format!(
+                "{} found: {}",
+                program,
+                version.unwrap_or("unknown version")
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Finding::problem(
+            Status::Warning,
+            format!("{} not found ({})", program, purpose),
+            &format!("Install {} and make sure it is in PATH", program),
+        ),
+        Err(e) => Finding::problem(
+            Status::Warning,
+            format!("{} cannot be run: {}", program, e),
+            &format!("Check the installation of {}", program),
+        ),
+    }
+}
+
+/// Look for a workspace in the default location of LibrePCB.
+fn check_default_workspace() -> Finding {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"));
+    let workspace = home.map(|home| PathBuf::from(home).join("LibrePCB-Workspace"));
+    match workspace {
+        Some(path) if path.join(WORKSPACE_FILE).is_file() => Finding::ok(format!(
+            "LibrePCB workspace found at {:?} (pass --outpath to check a library)",
+            path
+        )),
+        _ => Finding::problem(
+            Status::Warning,
+            "No LibrePCB workspace found in the default location (~/LibrePCB-Workspace)".into(),
+            "Pass --outpath with the library you convert to, to check it and its workspace",
+        ),
+    }
+}
+
+/// Check that a library exists, is writable and is loaded by LibrePCB.
+fn check_library(path: &Path) -> Vec<Finding> {
+    let mut findings = vec![];
+    if !path.is_dir() {
+        findings.push(Finding::problem(
+            Status::Error,
+            format!("The library {:?} does not exist", path),
+            "Create the library in LibrePCB (Library Manager > Create local library)",
+        ));
+        return findings;
+    }
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+
+    let probe = path.join(".svg2librepcb-doctor");
+    match fs::write(&probe, "").and_then(|_| fs::remove_file(&probe)) {
+        Ok(()) => findings.push(Finding::ok(format!("The library {:?} is writable", path))),
+        Err(e) => findings.push(Finding::problem(
+            Status::Error,
+            format!("The library {:?} is not writable: {}", path, e),
+            "Check the permissions of the library directory",
+        )),
+    }
+
+    if path.join("library.lp").is_file() || path.join(".librepcb-lib").is_file() {
+        findings.push(Finding::ok(format!("{:?} is a LibrePCB library", path)));
+    } else {
+        findings.push(Finding::problem(
+            Status::Warning,
+            format!("{:?} is not a LibrePCB library (no library.lp)", path),
+            "Create the library in LibrePCB (Library Manager > Create local library) and \
+             pass its directory (ending with .lplib) as --outpath",
+        ));
+    }
+
+    match path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join(WORKSPACE_FILE).is_file())
+    {
+        Some(workspace) => {
+            findings.push(Finding::ok(format!(
+                "The library is in the workspace {:?}",
+                workspace
+            )));
+            let local = path.parent().filter(|dir| {
+                dir.file_name().is_some_and(|name| name == "local")
+                    && dir
+                        .parent()
+                        .and_then(Path::file_name)
+                        .is_some_and(|name| name == "libraries")
+            });
+            if local.is_none() {
+                findings.push(Finding::problem(
+                    Status::Error,
+                    "The library is not in the local libraries directory of the workspace".into(),
+                    "LibrePCB only loads libraries from data/libraries/local (or \
+                     v0.1/libraries/local for LibrePCB 0.1) in the workspace",
+                ));
+            }
+        }
+        None => findings.push(Finding::problem(
+            Status::Error,
+            "The library is not inside a LibrePCB workspace".into(),
+            "LibrePCB only shows libraries in the libraries/local directory of the \
+             workspace, use the library path shown in the Library Manager",
+        )),
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn test_check_library() {
+        let dir = TempDir::new("test").unwrap();
+        let root = dir.path();
+        let library = root.join("data/libraries/local/Logos.lplib");
+        fs::create_dir_all(&library).unwrap();
+        fs::write(library.join("library.lp"), "").unwrap();
+
+        // Outside of a workspace
+        let findings = check_library(&library);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(findings[2].status, Status::Error);
+
+        fs::write(root.join(WORKSPACE_FILE), "").unwrap();
+        let findings = check_library(&library);
+        assert!(findings.iter().all(|f| f.status == Status::Ok));
+
+        // Not in the local libraries directory
+        let findings = check_library(&root.join("data"));
+        assert!(findings.iter().any(|f| f.status == Status::Error));
+
+        drop(dir);
+        assert_eq!(check_library(&library)[0].status, Status::Error);
+    }
+}
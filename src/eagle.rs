@@ -0,0 +1,252 @@
+//! Export of footprints as packages of an Eagle library (`.lbr`).
+//!
+//! Eagle uses millimeters with the y-axis pointing up (like LibrePCB) and
+//! numbered layers. Only the layers used by the packages are declared, Eagle
+//! adds the missing ones when opening the library.
+
+use crate::{
+    export::{Footprint, Text},
+    format_float,
+    geometry::{self, Point},
+    layers::Layer,
+};
+
+/// Width of open paths, like the generated LibrePCB polygons.
+const WIRE_WIDTH: &str = "0.2";
+
+/// Eagle layer number and name corresponding to a board layer.
+pub fn layer(layer: Layer) -> (u8, &'static str) {
+    match layer {
+        Layer::TopCopper => (1, "Top"),
+        Layer::BotCopper => (16, "Bottom"),
+        Layer::TopLegend => (21, "tPlace"),
+        Layer::BotLegend => (22, "bPlace"),
+        Layer::TopNames => (25, "tNames"),
+        Layer::BotNames => (26, "bNames"),
+        Layer::TopValues => (27, "tValues"),
+        Layer::BotValues => (28, "bValues"),
+        Layer::TopStopMask => (29, "tStop"),
+        Layer::BotStopMask => (30, "bStop"),
+        Layer::TopSolderPaste => (31, "tCream"),
+        Layer::BotSolderPaste => (32, "bCream"),
+        Layer::TopGlue => (35, "tGlue"),
+        Layer::BotGlue => (36, "bGlue"),
+        Layer::TopCourtyard => (39, "tKeepout"),
+        Layer::BotCourtyard => (40, "bKeepout"),
+        Layer::TopDocumentation => (51, "tDocu"),
+        Layer::BotDocumentation => (52, "bDocu"),
+    }
+}
+
+/// Escape a string for use in XML text or attribute values.
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Generate a library with one package per footprint.
+pub fn make_library(description: &str, footprints: &[Footprint]) -> Vec<String> {
+    let mut layers: Vec<Layer> = footprints
+        .iter()
+        .flat_map(|footprint| {
+            let layers = footprint.layers.iter().map(|(layer, _)| *layer);
+            let texts = footprint.texts.iter().map(|text| text.layer);
+            let fiducials = footprint
+                .fiducials
+                .iter()
+                .map(move |_| match footprint.bottom {
+                    false => Layer::TopCopper,
+                    true => Layer::BotCopper,
+                });
+            layers.chain(texts).chain(fiducials).collect::<Vec<_>>()
+        })
+        .collect();
+    layers.sort_by_key(|l| layer(*l).0);
+    layers.dedup();
+
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="utf-8"?>"#.to_string(),
+        r#"<!DOCTYPE eagle SYSTEM "eagle.dtd">"#.to_string(),
+        r#"<eagle version="7.7.0">"#.to_string(),
+        "<drawing>".to_string(),
+        r#"<grid distance="0.1" unitdist="mm" unit="mm"/>"#.to_string(),
+        "<layers>".to_string(),
+    ];
+    for l in layers {
+        let (number, name) = layer(l);
+        let color = match number {
+            1 | 39 => 4,
+            16 | 40 => 1,
+            _ => 7,
+        };
+        lines.push(format!(
+            r#"<layer number="{}" name="{}" color="{}" fill="1" visible="yes" active="yes"/>"#,
+            number, name, color
+        ));
+    }
+    lines.push("</layers>".to_string());
+    lines.push("<library>".to_string());
+    lines.push(format!(
+        "<description>{}</description>",
+        escape_xml(description)
+    ));
+    lines.push("<packages>".to_string());
+    for footprint in footprints {
+        lines.extend(make_package(footprint));
+    }
+    lines.push("</packages>".to_string());
+    lines.push("<symbols>".to_string());
+    lines.push("</symbols>".to_string());
+    lines.push("<devicesets>".to_string());
+    lines.push("</devicesets>".to_string());
+    lines.push("</library>".to_string());
+    lines.push("</drawing>".to_string());
+    lines.push("</eagle>".to_string());
+    lines
+}
+
+/// Coordinate attributes of a point, e.g. `x="1.5" y="-2.0"`.
+fn xy(prefix: &str, point: Point) -> String {
+    format!(
+        r#"x{p}="{}" y{p}="{}""#,
+        format_float(point.x),
+        format_float(point.y),
+        p = prefix
+    )
+}
+
+/// Generate a package with the polygons, holes, texts and fiducials of a
+/// footprint.
+fn make_package(footprint: &Footprint) -> Vec<String> {
+    let mut lines = vec![];
+    lines.push(format!(
+        r#"<package name="{}">"#,
+        escape_xml(&footprint.name)
+    ));
+    for (l, paths) in &footprint.layers {
+        let (number, _) = layer(*l);
+        for path in paths {
+            if geometry::is_closed(&geometry::points(path)) {
+                lines.push(format!(r#"<polygon width="0" layer="{}">"#, number));
+                for vertex in &path[..path.len() - 1] {
+                    lines.push(format!(
+                        r#"<vertex {} curve="{}"/>"#,
+                        xy("", vertex.position),
+                        format_float(vertex.angle)
+                    ));
+                }
+                lines.push("</polygon>".to_string());
+                continue;
+            }
+            for segment in path.windows(2) {
+                lines.push(format!(
+                    r#"<wire {} {} width="{}" layer="{}" curve="{}"/>"#,
+                    xy("1", segment[0].position),
+                    xy("2", segment[1].position),
+                    WIRE_WIDTH,
+                    number,
+                    format_float(segment[0].angle)
+                ));
+            }
+        }
+    }
+    for (position, diameter) in &footprint.holes {
+        lines.push(format!(
+            r#"<hole {} drill="{}"/>"#,
+            xy("", *position),
+            format_float(*diameter)
+        ));
+    }
+    for text in &footprint.texts {
+        lines.push(make_text(text));
+    }
+
+    // Fiducials are round SMD pads (the stop mask opening is given by the
+    // design rules)
+    let (number, _) = layer(match footprint.bottom {
+        false => Layer::TopCopper,
+        true => Layer::BotCopper,
+    });
+    for (i, (position, diameter)) in footprint.fiducials.iter().enumerate() {
+        lines.push(format!(
+            r#"<smd name="FID{}" {} dx="{d}" dy="{d}" layer="{}" roundness="100" cream="no"/>"#,
+            i + 1,
+            xy("", *position),
+            number,
+            d = format_float(*diameter)
+        ));
+    }
+    lines.push("</package>".to_string());
+    lines
+}
+
+/// Generate a vector font text. The alignment is translated from LibrePCB
+/// (e.g. "left top") to Eagle (e.g. "top-left").
+fn make_text(text: &Text) -> String {
+    let mut align = text.align.split_whitespace();
+    let horizontal = align.next().unwrap_or("left");
+    let vertical = align.next().unwrap_or("bottom");
+    let align = match (vertical, horizontal) {
+        ("center", "center") => "center".to_string(),
+        (vertical, horizontal) => format!("{}-{}", vertical, horizontal),
+    };
+    format!(
+        r#"<text {} size="{}" layer="{}" font="vector" ratio="15" rot="{}R{}" align="{}">{}</text>"#,
+        xy("", text.position),
+        format_float(text.height),
+        layer(text.layer).0,
+        if text.mirror { "M" } else { "" },
+        format_float(text.rotation.rem_euclid(360.0)),
+        align,
+        escape_xml(&text.value)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_library() {
+        let triangle = geometry::to_path(&[
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(0.0, 0.0),
+        ]);
+        let footprint = Footprint {
+            name: "Logo".to_string(),
+            bottom: true,
+            layers: vec![(Layer::BotLegend, vec![triangle])],
+            holes: vec![(Point::new(2.0, 0.0), 1.0)],
+            texts: vec![Text {
+                layer: Layer::BotLegend,
+                value: "A & B".to_string(),
+                position: Point::new(0.0, 1.0),
+                height: 1.0,
+                rotation: -90.0,
+                align: "center top".to_string(),
+                mirror: true,
+            }],
+            fiducials: vec![(Point::new(5.0, 0.0), 1.0)],
+        };
+        let lines = make_library("Logos", &[footprint]);
+        let expected = [
+            r#"<layer number="16" name="Bottom" color="1" fill="1" visible="yes" active="yes"/>"#,
+            r#"<layer number="22" name="bPlace" color="7" fill="1" visible="yes" active="yes"/>"#,
+            r#"<package name="Logo">"#,
+            r#"<polygon width="0" layer="22">"#,
+            r#"<vertex x="1.0" y="1.0" curve="0.0"/>"#,
+            r#"<hole x="2.0" y="0.0" drill="1.0"/>"#,
+            r#"<text x="0.0" y="1.0" size="1.0" layer="22" font="vector" ratio="15" rot="MR270.0" align="top-center">A &amp; B</text>"#,
+            r#"<smd name="FID1" x="5.0" y="0.0" dx="1.0" dy="1.0" layer="16" roundness="100" cream="no"/>"#,
+        ];
+        for line in expected {
+            assert!(lines.iter().any(|l| l == line), "Missing {}", line);
+        }
+        assert_eq!(lines.iter().filter(|l| l.starts_with("<vertex")).count(), 3);
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceSpan};
+use serde::Deserialize;
+
+use crate::{log, ElementKind};
+
+/// Errors that abort a conversion.
+#[derive(Debug, thiserror::Error, Diagnostic)]
+pub enum Error {
+    #[error("Invalid {field} {value:?}: {reason}")]
+    InvalidMetadata {
+        field: &'static str,
+        value: String,
+        reason: String,
+    },
+    #[error("{0} requires --format-version 1")]
+    #[diagnostic(help("Add --format-version 1 to generate elements for LibrePCB 1.x"))]
+    RequiresFormatV1(&'static str),
+    #[error("Invalid config file {path:?}: {reason}")]
+    Config { path: PathBuf, reason: String },
+    #[error("Invalid settings file {path:?}: {reason}")]
+    #[diagnostic(help("Fix or delete the file, it is recreated by the next conversion"))]
+    Settings { path: PathBuf, reason: String },
+    #[error("{0} requires {1}")]
+    RequiresOption(&'static str, &'static str),
+    #[error("{0} is required (it is remembered after the first conversion)")]
+    MissingOption(&'static str),
+    #[error("Cannot scale the footprints to a width of {width} mm: {reason}")]
+    InvalidScale { width: f64, reason: &'static str },
+    #[error("The artwork is only {width:.4} x {height:.4} mm")]
+    #[diagnostic(help(
+        "The drawing is converted assuming 1 SVG user unit = 1 mm, so the document probably uses other units or a scaling viewBox (check it with the validate command). To generate tiny footprints anyway, scale them with --scales or --width-mm"
+    ))]
+    ArtworkTooSmall { width: f64, height: f64 },
+    #[error("Invalid {option} {value}: {reason}")]
+    InvalidParameter {
+        option: &'static str,
+        value: f64,
+        reason: &'static str,
+    },
+    #[error("Invalid metadata JSON: {0}")]
+    MetadataJson(String),
+    #[error("Invalid metadata CSV file {path:?}: {reason}")]
+    MetadataCsv { path: PathBuf, reason: String },
+    #[error("{0} cannot be used when converting a directory")]
+    NotInBatchMode(&'static str),
+    #[error("{0} can only be used when converting a directory")]
+    RequiresBatchMode(&'static str),
+    #[error("Could not convert {path:?}")]
+    BatchFile {
+        path: PathBuf,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("Invalid S-expression snippet {snippet:?}: {reason}")]
+    Snippet { snippet: String, reason: String },
+    #[error("Invalid footprint text {text:?}: {reason}")]
+    FootprintText { text: String, reason: String },
+    #[error("Invalid fiducial position {position:?}: {reason}")]
+    Fiducial { position: String, reason: String },
+    #[error("Invalid {option} {value:?}: {reason}")]
+    Tile {
+        option: &'static str,
+        value: String,
+        reason: String,
+    },
+    #[error("Invalid template {template:?}: {reason}")]
+    Template { template: String, reason: String },
+    #[error("Invalid art color mapping {mapping:?}: {reason}")]
+    ArtColor { mapping: String, reason: String },
+    #[error("Unknown layer or layer alias {0:?}")]
+    #[diagnostic(help(
+        "Use a LibrePCB layer name (e.g. \"top_documentation\") or define an alias in the config file"
+    ))]
+    UnknownLayer(String),
+    #[error("Could not read SVG file {path:?}: {source}")]
+    ReadSvg {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Could not read file {path:?}: {source}")]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Could not convert {path:?} to SVG: {reason}")]
+    #[diagnostic(help(
+        "Install pdftocairo (poppler-utils) or Inkscape, or convert the file to SVG manually"
+    ))]
+    ConvertInput { path: PathBuf, reason: String },
+    #[error("No input file given")]
+    #[diagnostic(help("Pass an SVG file or directory, or use --clipboard"))]
+    MissingInput,
+    #[error("Could not download {url}: {reason}")]
+    Download { url: String, reason: String },
+    #[error("Could not read the clipboard: {0}")]
+    Clipboard(String),
+    #[error("{0} cannot be used together with {1}")]
+    ConflictingOptions(&'static str, &'static str),
+    #[error("{0} can only be used with SVG input files")]
+    SvgOnly(&'static str),
+    #[error("The input has {value} {what}, more than the limit of {limit}")]
+    #[diagnostic(help("Check that the file is not broken, or raise the limit with {option}"))]
+    LimitExceeded {
+        what: &'static str,
+        value: usize,
+        limit: usize,
+        option: &'static str,
+    },
+    #[error("Could not parse {path:?}: {reason}")]
+    ParseInput { path: PathBuf, reason: String },
+    #[error("Could not parse SVG file {path:?}: {reason}")]
+    ParseSvg { path: PathBuf, reason: String },
+    #[error("Could not parse SVG file {path:?}")]
+    #[diagnostic(help("The file is not a well-formed XML document"))]
+    InvalidXml {
+        path: PathBuf,
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label("{reason}")]
+        span: SourceSpan,
+        reason: String,
+    },
+    #[error("Could not convert texts to paths: {0}")]
+    #[diagnostic(help(
+        "Install Inkscape, or convert the texts to paths manually (Path > Object to Path)"
+    ))]
+    TextToPath(String),
+    #[error("Invalid output path {path:?}: {reason}")]
+    #[diagnostic(help("The output path must point to a LibrePCB library directory"))]
+    InvalidOutpath { path: PathBuf, reason: String },
+    #[error("Could not parse existing {element} {path:?}: {reason}")]
+    ParseElement {
+        element: ElementKind,
+        path: PathBuf,
+        reason: String,
+    },
+    #[error("The {element} already exists at {path:?}")]
+    #[diagnostic(help("Use --force to replace it"))]
+    ElementExists { element: ElementKind, path: PathBuf },
+    #[error("The {element} does not exist at {path:?}")]
+    #[diagnostic(help("Pass the UUID of a component of the output library"))]
+    MissingElement { element: ElementKind, path: PathBuf },
+    #[error("Could not {operation} {path:?} for {element}: {source}")]
+    Write {
+        element: ElementKind,
+        operation: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("The file {0:?} already exists")]
+    #[diagnostic(help("Use --force to replace it"))]
+    FileExists(PathBuf),
+    #[error("Could not write file {path:?}: {source}")]
+    WriteFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Validation failed: {failed} of {total} files cannot be converted as expected")]
+    ValidationFailed { failed: usize, total: usize },
+    #[error("Cannot regenerate {path:?}: {reason}")]
+    Regen { path: PathBuf, reason: String },
+    #[error("{0} checks failed, see the hints above")]
+    DoctorFailed(usize),
+    #[cfg(not(feature = "gui"))]
+    #[error("The preview window is not available in this build")]
+    #[diagnostic(help("Build svg2librepcb with `cargo build --release --features gui`"))]
+    GuiUnavailable,
+    #[cfg(feature = "gui")]
+    #[error("Could not show the preview window: {0}")]
+    Preview(String),
+    #[error("{0} requires an interactive terminal")]
+    NotInteractive(&'static str),
+    #[error("Could not read from the terminal: {0}")]
+    Terminal(String),
+    #[error("Cancelled by the user")]
+    Cancelled,
+    #[error("Invalid footprint alignment {value:?}: {reason}")]
+    FootprintAlign { value: String, reason: String },
+    #[error("Invalid element metadata {value:?}: {reason}")]
+    ElementMetadata { value: String, reason: String },
+    #[error("Invalid warning level {value:?}: {reason}")]
+    WarningLevel { value: String, reason: String },
+    #[error("Warnings are treated as errors ({0} reported)")]
+    #[diagnostic(help(
+        "Fix the problems above, or change the level of the warnings with --warning or in the config file"
+    ))]
+    PromotedWarnings(usize),
+    #[error("The post-generation hook {command:?} failed: {reason}")]
+    PostHook { command: String, reason: String },
+    #[error("Could not verify the library with librepcb-cli: {0}")]
+    #[diagnostic(help(
+        "Check that librepcb-cli is installed and can load the library, or open the library in LibrePCB (Library Manager)"
+    ))]
+    Verify(String),
+}
+
+/// Problems which do not abort a conversion.
+#[derive(Debug, thiserror::Error, Diagnostic)]
+pub enum Warning {
+    #[error("Texts in the SVG are ignored")]
+    #[diagnostic(
+        severity(Warning),
+        code("ignored-text"),
+        help(
+            "Convert the texts to paths (Path > Object to Path), or use --text-to-path or --native-text"
+        )
+    )]
+    IgnoredText {
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label(collection)]
+        spans: Vec<LabeledSpan>,
+    },
+    #[error("Gradients and patterns are converted to solid areas")]
+    #[diagnostic(
+        severity(Warning),
+        code("gradient-fill"),
+        help(
+            "Fill the affected elements with a solid color, rasterize gradient fills with --rasterize-gradients, or convert the shading to separate paths (e.g. with Path > Trace Bitmap in Inkscape)"
+        )
+    )]
+    GradientFill {
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label(collection)]
+        spans: Vec<LabeledSpan>,
+    },
+    #[error("Shapes with unsupported paints are skipped")]
+    #[diagnostic(
+        severity(Warning),
+        code("unsupported-paint"),
+        help("Fill or stroke the affected elements with a solid color")
+    )]
+    UnsupportedPaint {
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label(collection)]
+        spans: Vec<LabeledSpan>,
+    },
+    #[error("Files referenced by the SVG could not be resolved")]
+    #[diagnostic(
+        severity(Warning),
+        code("unresolved-reference"),
+        help(
+            "Linked images, fonts and style sheets which are not found are missing from the output, embed them in the SVG or convert the affected elements to paths"
+        )
+    )]
+    UnresolvedReferences {
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label(collection)]
+        spans: Vec<LabeledSpan>,
+    },
+    #[error("Footprint {footprint:?}: {layer} {rule} of {value} mm is below the minimum of {limit} mm (near {x}, {y} in the drawing)")]
+    #[diagnostic(
+        severity(Warning),
+        code("rule-violation"),
+        help("Make the features in the drawing thicker or further apart, or scale it up")
+    )]
+    RuleViolation {
+        footprint: String,
+        layer: &'static str,
+        rule: &'static str,
+        value: String,
+        limit: String,
+        x: String,
+        y: String,
+    },
+    #[error("The library already contains a {element} named {name:?} ({uuids})")]
+    #[diagnostic(
+        severity(Warning),
+        code("duplicate-name"),
+        help("Choose a different --name, or pass the UUID of the existing element (e.g. --uuid-pkg) and --force to replace it")
+    )]
+    DuplicateName {
+        element: ElementKind,
+        name: String,
+        uuids: String,
+    },
+    #[error("The name {name:?} was shortened to {shortened:?}")]
+    #[diagnostic(
+        severity(Warning),
+        code("shortened-name"),
+        help("LibrePCB limits names to 100 characters, choose a shorter --name")
+    )]
+    ShortenedName { name: String, shortened: String },
+    #[error("The remembered settings are ignored: {reason}")]
+    #[diagnostic(
+        severity(Warning),
+        code("load-settings"),
+        help("Fix or delete {path:?}, it is not overwritten while it is invalid")
+    )]
+    LoadSettings { path: PathBuf, reason: String },
+    #[error("Could not save the settings to {path:?}: {reason}")]
+    #[diagnostic(severity(Warning), code("save-settings"))]
+    SaveSettings { path: PathBuf, reason: String },
+    #[error("File {0:?} listed in the metadata CSV file does not exist")]
+    #[diagnostic(severity(Warning), code("unused-metadata"))]
+    UnusedMetadata(String),
+}
+
+/// Names of the warnings (their diagnostic codes), which can be configured
+/// with `--warning` or in the config file.
+pub const WARNING_NAMES: [&str; 10] = [
+    "duplicate-name",
+    "gradient-fill",
+    "ignored-text",
+    "load-settings",
+    "rule-violation",
+    "save-settings",
+    "shortened-name",
+    "unresolved-reference",
+    "unsupported-paint",
+    "unused-metadata",
+];
+
+/// How a warning is reported.
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+    /// Not reported at all
+    Ignore,
+    Warning,
+    /// Reported as an error, failing the conversion
+    Error,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "ignore" => Some(Level::Ignore),
+            "warning" => Some(Level::Warning),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    /// Configured levels of the warnings, by name
+    static LEVELS: RefCell<HashMap<String, Level>> = RefCell::new(HashMap::new());
+    /// Level of the other warnings (errors with --strict)
+    static DEFAULT_LEVEL: Cell<Level> = const { Cell::new(Level::Warning) };
+    /// Number of warnings reported as errors since the last check
+    static PROMOTED: Cell<usize> = const { Cell::new(0) };
+    /// Warnings reported since the last call of `take_reported`
+    static REPORTED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Warnings already printed within `report_once`
+    static PRINTED: RefCell<Option<HashSet<String>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, printing (and promoting) every warning only once, e.g. when the
+/// same input is converted for several libraries. The warnings are still
+/// returned by `take_reported` each time.
+pub fn report_once<T>(f: impl FnOnce() -> T) -> T {
+    PRINTED.with(|cell| *cell.borrow_mut() = Some(HashSet::new()));
+    let result = f();
+    PRINTED.with(|cell| cell.take());
+    result
+}
+
+/// Set the levels of the subsequently reported warnings.
+pub fn set_levels(levels: HashMap<String, Level>, default: Level) {
+    LEVELS.with(|cell| *cell.borrow_mut() = levels);
+    DEFAULT_LEVEL.with(|cell| cell.set(default));
+}
+
+/// Fail if warnings were reported as errors since the last check.
+pub fn check_promoted() -> Result<()> {
+    match PROMOTED.with(|cell| cell.replace(0)) {
+        0 => Ok(()),
+        count => Err(Error::PromotedWarnings(count)),
+    }
+}
+
+/// The warnings (and warnings reported as errors) since the last call, e.g.
+/// `ignored-text: Texts in the SVG are ignored`.
+pub fn take_reported() -> Vec<String> {
+    REPORTED.with(|cell| cell.take())
+}
+
+impl Warning {
+    /// Print the warning to stderr, according to its configured level.
+    pub fn report(self) {
+        let name = self.code().map(|code| code.to_string()).unwrap_or_default();
+        let level = LEVELS
+            .with(|levels| levels.borrow().get(&name).copied())
+            .unwrap_or_else(|| DEFAULT_LEVEL.with(Cell::get));
+        let message = format!("{}: {}", name, self);
+        if level != Level::Ignore {
+            REPORTED.with(|cell| cell.borrow_mut().push(message.clone()));
+        }
+        let printed = PRINTED.with(|cell| match cell.borrow_mut().as_mut() {
+            Some(printed) => !printed.insert(message),
+            None => false,
+        });
+        if printed {
+            return;
+        }
+        match level {
+            Level::Ignore => log::log!("Ignoring warning [{}]: {}", name, self),
+            Level::Warning => {
+                log::write_file(format_args!("warning [{}]: {}", name, self));
+                eprintln!("{:?}", miette::Report::new(self));
+            }
+            Level::Error => {
+                log::write_file(format_args!("error [{}]: {}", name, self));
+                PROMOTED.with(|cell| cell.set(cell.get() + 1));
+                eprintln!("{:?}", miette::Report::new(Promoted(self)));
+            }
+        }
+    }
+}
+
+/// A warning reported as an error.
+#[derive(Debug)]
+struct Promoted(Warning);
+
+impl fmt::Display for Promoted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Promoted {}
+
+impl Diagnostic for Promoted {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(miette::Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.0.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.0.labels()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
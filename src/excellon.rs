@@ -0,0 +1,202 @@
+//! Import of holes from Excellon drill files.
+//!
+//! Only drilled holes are supported, routed slots are rejected. Coordinates
+//! are converted to millimeters with the y-axis pointing down, like the
+//! Gerber import, so that drill files match the artwork they belong to.
+
+use std::collections::HashMap;
+
+use crate::geometry::{Hole, Point};
+
+/// Units and number format of the coordinates.
+struct Format {
+    /// Factor to convert to millimeters
+    unit: f64,
+    /// Number of decimal places of coordinates without decimal point
+    decimal_digits: i32,
+    /// Number of digits of coordinates without decimal point, if trailing
+    /// zeros are omitted
+    leading_zeros: Option<usize>,
+}
+
+impl Format {
+    fn metric() -> Self {
+        Format {
+            unit: 1.0,
+            decimal_digits: 3,
+            leading_zeros: None,
+        }
+    }
+
+    /// Parse a coordinate value.
+    fn coordinate(&self, value: &str) -> Result<f64, String> {
+        let invalid = || format!("Invalid coordinate {:?}", value);
+        if value.contains('.') {
+            return value
+                .parse::<f64>()
+                .map(|v| v * self.unit)
+                .map_err(|_| invalid());
+        }
+        let (sign, digits) = match value.strip_prefix('-') {
+            Some(digits) => (-1.0, digits),
+            None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let mut digits = digits.to_string();
+        if let Some(length) = self.leading_zeros {
+            while digits.len() < length {
+                digits.push('0');
+            }
+        }
+        let integer: f64 = digits.parse().map_err(|_| invalid())?;
+        Ok(sign * integer / 10f64.powi(self.decimal_digits) * self.unit)
+    }
+}
+
+/// Parse the holes of a drill file.
+pub fn parse(source: &str) -> Result<Vec<Hole>, String> {
+    let mut format = Format::metric();
+    let mut tools: HashMap<u32, f64> = HashMap::new();
+    let mut tool = None;
+    let mut position = Point::new(0.0, 0.0);
+    let mut holes = vec![];
+    for line in source.lines() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("METRIC") || line.starts_with("INCH") {
+            let metric = line.starts_with("METRIC");
+            format.unit = if metric { 1.0 } else { 25.4 };
+            format.decimal_digits = if metric { 3 } else { 4 };
+            let integer_digits = if metric { 3 } else { 2 };
+            // Explicit number format, e.g. "METRIC,LZ,000.000"
+            for option in line.split(',').skip(1) {
+                match option {
+                    "LZ" => {
+                        format.leading_zeros = Some(integer_digits + format.decimal_digits as usize)
+                    }
+                    "TZ" => format.leading_zeros = None,
+                    option => {
+                        if let Some((integer, decimal)) = option.split_once('.') {
+                            format.decimal_digits = decimal.len() as i32;
+                            if format.leading_zeros.is_some() {
+                                format.leading_zeros = Some(integer.len() + decimal.len());
+                            }
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+        // Slots given by G85 are between two coordinates, e.g. "X1Y1G85X2Y1"
+        if line.contains("G85") || line.starts_with("G00") || line.starts_with("M15") {
+            return Err("Routed slots are not supported".into());
+        }
+        if line.starts_with("G91") || line.starts_with("ICI,ON") {
+            return Err("Incremental coordinates are not supported".into());
+        }
+        if line == "M30" || line == "M00" {
+            break;
+        }
+        if let Some(definition) = line.strip_prefix('T') {
+            let end = definition
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(definition.len());
+            let number: u32 = definition[..end]
+                .parse()
+                .map_err(|_| format!("Invalid tool {:?}", line))?;
+            match definition[end..].strip_prefix('C') {
+                Some(rest) => {
+                    let diameter_end = rest
+                        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                        .unwrap_or(rest.len());
+                    let diameter: f64 = rest[..diameter_end]
+                        .parse()
+                        .map_err(|_| format!("Invalid tool {:?}", line))?;
+                    tools.insert(number, diameter * format.unit);
+                }
+                None => tool = Some(number),
+            }
+            continue;
+        }
+        if line.starts_with('X') || line.starts_with('Y') {
+            let y_start = line.find('Y').unwrap_or(line.len());
+            if let Some(x) = line[..y_start].strip_prefix('X') {
+                position.x = format.coordinate(x)?;
+            }
+            if let Some(y) = line[y_start..].strip_prefix('Y') {
+                position.y = format.coordinate(y)?;
+            }
+            let diameter = match tool {
+                // Tool 0 means that no tool is selected
+                Some(0) | None => return Err("Hole without a selected tool".into()),
+                Some(number) => *tools
+                    .get(&number)
+                    .ok_or_else(|| format!("Undefined tool T{}", number))?,
+            };
+            holes.push(Hole {
+                // Drill files use a y-axis pointing up
+                position: Point::new(position.x, -position.y),
+                diameter,
+            });
+        }
+    }
+    Ok(holes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let drill = "M48
+; Drill file
+METRIC,TZ
+T1C0.800
+T2C3.2
+%
+G90
+G05
+T1
+X1.5Y-2.0
+X3.0
+T2
+X10.0Y10.0
+M30
+";
+        assert_eq!(
+            parse(drill).unwrap(),
+            vec![
+                Hole {
+                    position: Point::new(1.5, 2.0),
+                    diameter: 0.8
+                },
+                Hole {
+                    position: Point::new(3.0, 2.0),
+                    diameter: 0.8
+                },
+                Hole {
+                    position: Point::new(10.0, -10.0),
+                    diameter: 3.2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_implicit_decimals() {
+        let drill = "M48\nINCH,LZ\nT01C0.0400\n%\nT01\nX01Y005\nM30\n";
+        let holes = parse(drill).unwrap();
+        assert!((holes[0].position.x - 25.4).abs() < 1e-9);
+        assert!((holes[0].position.y + 12.7).abs() < 1e-9);
+        assert!((holes[0].diameter - 1.016).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("M48\nMETRIC\n%\nX1.0Y1.0\n").is_err());
+        assert!(parse("M48\nMETRIC\nT1C1.0\n%\nT1\nG00X1.0Y1.0\n").is_err());
+        assert!(parse("M48\nMETRIC\nT1C1.0\n%\nT1\nX1.0Y1.0G85X2.0Y1.0\n").is_err());
+    }
+}
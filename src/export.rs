@@ -0,0 +1,73 @@
+//! Footprints for other EDA tools (see `--format`).
+//!
+//! The footprints are prepared like LibrePCB footprints, i.e. aligned and in
+//! millimeters with the y-axis pointing up, and then written by the backend
+//! of the output format (see the `kicad` and `eagle` modules).
+
+use crate::{
+    geometry::{Path, Point, Vertex},
+    layers::Layer,
+};
+
+/// A footprint to export.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Footprint {
+    /// Name which can be used as file name (see [`file_name`])
+    pub name: String,
+    /// Whether the footprint is on the bottom side
+    pub bottom: bool,
+    pub layers: Vec<(Layer, Vec<Path>)>,
+    /// Positions and diameters of non-plated holes
+    pub holes: Vec<(Point, f64)>,
+    pub texts: Vec<Text>,
+    /// Positions and diameters of fiducials
+    pub fiducials: Vec<(Point, f64)>,
+}
+
+/// A text of a footprint.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Text {
+    pub layer: Layer,
+    pub value: String,
+    pub position: Point,
+    pub height: f64,
+    /// Rotation in degrees, counterclockwise
+    pub rotation: f64,
+    /// Horizontal and vertical alignment, e.g. "left bottom"
+    pub align: String,
+    pub mirror: bool,
+}
+
+/// Name which can be used as file or package name, e.g. `Logo_Top_Copper` for
+/// "Logo (Top Copper)".
+pub fn file_name(name: &str) -> String {
+    name.split(|c: char| !(c.is_alphanumeric() || "-_.+".contains(c)))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Mirror a path vertically (e.g. from LibrePCB to KiCad coordinates), which
+/// also inverts the direction of arcs.
+pub fn invert_y(path: &[Vertex]) -> Path {
+    path.iter()
+        .map(|vertex| Vertex {
+            position: Point::new(vertex.position.x, -vertex.position.y),
+            angle: -vertex.angle,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name("Logo Top Copper (10.0 mm)"),
+            "Logo_Top_Copper_10.0_mm"
+        );
+        assert_eq!(file_name("A/B\\C"), "A_B_C");
+    }
+}
@@ -0,0 +1,1742 @@
+//! Geometry types and helpers for processing the flattened SVG paths.
+
+use std::f64::consts::PI;
+
+use i_overlay::{
+    core::{fill_rule::FillRule, overlay_rule::OverlayRule},
+    float::single::SingleFloatOverlay,
+};
+
+/// A point in SVG coordinates.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+}
+
+/// A sequence of connected points. Polylines are never empty.
+pub type Polyline = Vec<Point>;
+
+/// A polyline vertex, followed by an arc segment with the given angle (in
+/// degrees, positive in the direction from the x-axis to the y-axis) or by a
+/// straight segment if the angle is zero.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Vertex {
+    pub position: Point,
+    pub angle: f64,
+}
+
+impl Vertex {
+    pub fn new(position: Point, angle: f64) -> Self {
+        Vertex { position, angle }
+    }
+}
+
+/// A sequence of connected vertices, like a LibrePCB polygon.
+pub type Path = Vec<Vertex>;
+
+/// A drilled hole.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Hole {
+    pub position: Point,
+    pub diameter: f64,
+}
+
+/// Shape of the ends of stroked lines.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+pub enum LineCap {
+    /// End exactly at the end points
+    Butt,
+    /// Extend by a half circle
+    Round,
+    /// Extend by half the stroke width
+    Square,
+}
+
+/// Shape of the corners of stroked lines.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+pub enum LineJoin {
+    /// Sharp corners (beveled if they are too pointed)
+    Miter,
+    /// Rounded corners
+    Round,
+    /// Cut off corners
+    Bevel,
+}
+
+/// How lines are stroked, like the SVG stroke properties.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Stroke {
+    pub width: f64,
+    pub cap: LineCap,
+    pub join: LineJoin,
+}
+
+/// Bridges which cut the holes of filled areas open (`bridge_holes()`).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bridges {
+    pub width: f64,
+    /// Number of bridges per hole, evenly spaced around it
+    pub count: usize,
+    /// Direction of the first bridge from the center of the hole, in degrees
+    /// counterclockwise from the x-axis (as displayed, i.e. with the y-axis
+    /// pointing up)
+    pub angle: f64,
+}
+
+/// Miter joins longer than this (relative to the stroke width) are beveled,
+/// like the default `stroke-miterlimit` of SVG.
+const MITER_LIMIT: f64 = 4.0;
+
+/// Precision of the arc angles in the generated files (in degrees).
+const ANGLE_PRECISION: f64 = 0.001;
+
+/// Maximum distance between the ends of a closed polyline in mm, so that
+/// floating point jitter of the exported coordinates doesn't turn filled
+/// areas into lines.
+const CLOSED_TOLERANCE: f64 = 1e-5;
+
+/// An axis-aligned rectangle.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Rect {
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+}
+
+impl Rect {
+    /// Whether a point lies inside the rectangle (or on its edges).
+    pub fn contains(&self, point: Point) -> bool {
+        (self.x_min..=self.x_max).contains(&point.x) && (self.y_min..=self.y_max).contains(&point.y)
+    }
+
+    /// Signed distance of a point to one of the four edges, positive inside.
+    fn distance(&self, edge: usize, point: Point) -> f64 {
+        match edge {
+            0 => point.x - self.x_min,
+            1 => self.x_max - point.x,
+            2 => point.y - self.y_min,
+            _ => self.y_max - point.y,
+        }
+    }
+}
+
+/// Convert the polylines returned by svg2polylines, dropping empty ones.
+pub fn from_svg(polylines: &[svg2polylines::Polyline]) -> Vec<Polyline> {
+    let mut polylines: Vec<Polyline> = polylines
+        .iter()
+        .map(|polyline| {
+            polyline
+                .into_iter()
+                .map(|pair| Point::new(pair.x, pair.y))
+                .collect::<Polyline>()
+        })
+        .filter(|polyline| !polyline.is_empty())
+        .collect();
+    close(&mut polylines, false);
+    polylines
+}
+
+/// Whether a polyline is closed, i.e. ends at its starting point (within
+/// `CLOSED_TOLERANCE`).
+pub fn is_closed(polyline: &[Point]) -> bool {
+    let (first, last) = (polyline[0], polyline[polyline.len() - 1]);
+    (last.x - first.x).hypot(last.y - first.y) <= CLOSED_TOLERANCE
+}
+
+/// Make the ends of closed polylines identical, or with `all`, close all
+/// polylines with at least three points.
+pub fn close(polylines: &mut [Polyline], all: bool) {
+    for polyline in polylines.iter_mut().filter(|polyline| polyline.len() > 2) {
+        if is_closed(polyline) {
+            let last = polyline.len() - 1;
+            polyline[last] = polyline[0];
+        } else if all {
+            polyline.push(polyline[0]);
+        }
+    }
+}
+
+/// Area enclosed by a polyline (shoelace formula).
+pub fn area(polyline: &[Point]) -> f64 {
+    signed_area(polyline).abs()
+}
+
+/// Area enclosed by a polyline, positive if the polyline runs from the x-axis
+/// towards the y-axis.
+fn signed_area(polyline: &[Point]) -> f64 {
+    let n = polyline.len();
+    let doubled: f64 = (0..n)
+        .map(|i| {
+            let (a, b) = (polyline[i], polyline[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    doubled / 2.0
+}
+
+/// Whether a point lies inside a closed polyline.
+pub fn contains(polyline: &[Point], point: Point) -> bool {
+    let mut inside = false;
+    for (a, b) in polyline.iter().zip(&polyline[1..]) {
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Grow the areas enclosed by the closed polylines by a distance (or shrink
+/// them, if the distance is negative).
+///
+/// Polylines inside an odd number of other polylines are holes, which shrink
+/// when the area grows. Open polylines are not changed.
+pub fn offset(polylines: &[Polyline], distance: f64) -> Vec<Polyline> {
+    let is_polygon = |polyline: &Polyline| polyline.len() >= 4 && is_closed(polyline);
+    polylines
+        .iter()
+        .enumerate()
+        .map(|(i, polyline)| {
+            if !is_polygon(polyline) {
+                return polyline.clone();
+            }
+            let depth = polylines
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| *j != i && is_polygon(other) && contains(other, polyline[0]))
+                .count();
+            let distance = if depth % 2 == 0 { distance } else { -distance };
+            offset_polygon(polyline, distance)
+        })
+        .collect()
+}
+
+/// Move the edges of a closed polyline outwards by a distance, with mitered
+/// corners.
+fn offset_polygon(polyline: &[Point], distance: f64) -> Polyline {
+    let mut points: Polyline = vec![];
+    for &point in &polyline[..polyline.len() - 1] {
+        if points.last() != Some(&point) {
+            points.push(point);
+        }
+    }
+    let n = points.len();
+    if n < 3 {
+        return polyline.to_vec();
+    }
+
+    // Outward normal of the segment from a to b
+    let orientation = signed_area(&points).signum();
+    let normal = |a: Point, b: Point| {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx.hypot(dy);
+        (orientation * dy / length, -orientation * dx / length)
+    };
+    let mut offset: Polyline = (0..n)
+        .map(|i| {
+            let (prev, point, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            let (n1, n2) = (normal(prev, point), normal(point, next));
+            // The miter length is limited to twice the distance at sharp corners
+            let factor = distance / (1.0 + n1.0 * n2.0 + n1.1 * n2.1).max(0.5);
+            Point::new(
+                point.x + (n1.0 + n2.0) * factor,
+                point.y + (n1.1 + n2.1) * factor,
+            )
+        })
+        .collect();
+    offset.push(offset[0]);
+    offset
+}
+
+/// Bounding box of all polylines, if there are any points.
+pub fn bounds(polylines: &[Polyline]) -> Option<Rect> {
+    let mut points = polylines.iter().flatten();
+    let first = points.next()?;
+    Some(points.fold(
+        Rect {
+            x_min: first.x,
+            y_min: first.y,
+            x_max: first.x,
+            y_max: first.y,
+        },
+        |rect, point| Rect {
+            x_min: rect.x_min.min(point.x),
+            y_min: rect.y_min.min(point.y),
+            x_max: rect.x_max.max(point.x),
+            y_max: rect.y_max.max(point.y),
+        },
+    ))
+}
+
+/// Centroid (center of mass) of the areas enclosed by the closed polylines,
+/// if they enclose any area.
+///
+/// Polylines inside an odd number of other polylines are holes, which are
+/// subtracted. Open polylines are ignored.
+pub fn centroid(polylines: &[Polyline]) -> Option<Point> {
+    let is_polygon = |polyline: &Polyline| polyline.len() >= 4 && is_closed(polyline);
+    let (mut total, mut x, mut y) = (0.0, 0.0, 0.0);
+    for (i, polyline) in polylines.iter().enumerate() {
+        if !is_polygon(polyline) {
+            continue;
+        }
+        let depth = polylines
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| *j != i && is_polygon(other) && contains(other, polyline[0]))
+            .count();
+        let signed = signed_area(polyline);
+        if signed == 0.0 {
+            continue;
+        }
+        let (mut cx, mut cy) = (0.0, 0.0);
+        for pair in polyline.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let cross = a.x * b.y - b.x * a.y;
+            cx += (a.x + b.x) * cross;
+            cy += (a.y + b.y) * cross;
+        }
+        let area = if depth % 2 == 0 {
+            signed.abs()
+        } else {
+            -signed.abs()
+        };
+        total += area;
+        x += cx / (6.0 * signed) * area;
+        y += cy / (6.0 * signed) * area;
+    }
+    (total > 0.0).then(|| Point::new(x / total, y / total))
+}
+
+/// Subtract the areas enclosed by the closed polylines of `other` from the
+/// closed polylines (both with the even-odd rule). Open polylines are kept.
+///
+/// Areas with holes are returned as a single polyline, with the holes
+/// connected to the outline by a zero-width cut.
+pub fn subtract(polylines: &[Polyline], other: &[Polyline]) -> Vec<Polyline> {
+    let mut result: Vec<Polyline> = polylines
+        .iter()
+        .filter(|polyline| !is_closed(polyline))
+        .cloned()
+        .collect();
+    result.extend(overlay(
+        polylines,
+        other,
+        OverlayRule::Difference,
+        FillRule::EvenOdd,
+    ));
+    result
+}
+
+/// The maximum number of pixels of a raster traced by `trace`.
+pub const MAX_TRACE_PIXELS: usize = 4_000_000;
+
+/// The number of columns and rows of the raster that `trace` samples for the
+/// polylines with the given pixel size.
+pub fn raster_size(polylines: &[Polyline], pixel: f64) -> (usize, usize) {
+    match bounds(polylines) {
+        Some(rect) => (
+            ((rect.x_max - rect.x_min) / pixel).ceil().max(1.0) as usize,
+            ((rect.y_max - rect.y_min) / pixel).ceil().max(1.0) as usize,
+        ),
+        None => (0, 0),
+    }
+}
+
+/// Trace the areas enclosed by the closed polylines from a raster with the
+/// given pixel size: Each pixel gets the class of its center, and the areas
+/// of the pixels of each class (in the order of their first pixel) are
+/// returned, clipped to the polylines (with the even-odd rule). Pixels
+/// without a class are left out.
+pub fn trace<T: PartialEq + Copy>(
+    polylines: &[Polyline],
+    pixel: f64,
+    classify: impl Fn(Point) -> Option<T>,
+) -> Vec<(T, Vec<Polyline>)> {
+    let rect = match bounds(polylines) {
+        Some(rect) => rect,
+        None => return vec![],
+    };
+    let (columns, rows) = raster_size(polylines, pixel);
+    // Runs of pixels of the same class are merged into one rectangle
+    let mut classes: Vec<(T, Vec<Polyline>)> = vec![];
+    for row in 0..rows {
+        let y = rect.y_min + row as f64 * pixel;
+        let mut run: Option<(T, usize)> = None;
+        for column in 0..=columns {
+            let class = (column < columns)
+                .then(|| {
+                    let x = rect.x_min + (column as f64 + 0.5) * pixel;
+                    classify(Point::new(x, y + 0.5 * pixel))
+                })
+                .flatten();
+            if run.map(|(run_class, _)| run_class) == class {
+                continue;
+            }
+            if let Some((run_class, start)) = run {
+                let (x_min, x_max) = (
+                    rect.x_min + start as f64 * pixel,
+                    rect.x_min + column as f64 * pixel,
+                );
+                let rectangle = vec![
+                    Point::new(x_min, y),
+                    Point::new(x_max, y),
+                    Point::new(x_max, y + pixel),
+                    Point::new(x_min, y + pixel),
+                    Point::new(x_min, y),
+                ];
+                match classes.iter_mut().find(|(c, _)| *c == run_class) {
+                    Some((_, rectangles)) => rectangles.push(rectangle),
+                    None => classes.push((run_class, vec![rectangle])),
+                }
+            }
+            run = class.map(|class| (class, column));
+        }
+    }
+    classes
+        .into_iter()
+        .map(|(class, rectangles)| {
+            let traced = overlay(
+                polylines,
+                &rectangles,
+                OverlayRule::Intersect,
+                FillRule::EvenOdd,
+            );
+            (class, traced)
+        })
+        .collect()
+}
+
+/// Split the areas enclosed by the closed polylines into smaller pieces until
+/// none of them has more than `max_vertices` vertices. The areas are cut in
+/// half along the longer side of their bounding box, so the pieces fit
+/// together seamlessly. Open polylines are kept.
+pub fn split(polylines: &[Polyline], max_vertices: usize) -> Vec<Polyline> {
+    let (closed, mut result): (Vec<Polyline>, Vec<Polyline>) = polylines
+        .iter()
+        .cloned()
+        .partition(|polyline| is_closed(polyline));
+    split_closed(closed, max_vertices, 0, &mut result);
+    result
+}
+
+fn split_closed(
+    polylines: Vec<Polyline>,
+    max_vertices: usize,
+    depth: u32,
+    result: &mut Vec<Polyline>,
+) {
+    let oversized = polylines
+        .iter()
+        .any(|polyline| polyline.len() > max_vertices);
+    // The recursion is limited for degenerate input (e.g. many vertices at
+    // the same position)
+    let rect = match bounds(&polylines) {
+        Some(rect) if oversized && depth < 32 => rect,
+        _ => {
+            result.extend(polylines);
+            return;
+        }
+    };
+    let halves = if rect.x_max - rect.x_min >= rect.y_max - rect.y_min {
+        let x = (rect.x_min + rect.x_max) / 2.0;
+        [Rect { x_max: x, ..rect }, Rect { x_min: x, ..rect }]
+    } else {
+        let y = (rect.y_min + rect.y_max) / 2.0;
+        [Rect { y_max: y, ..rect }, Rect { y_min: y, ..rect }]
+    };
+    for half in &halves {
+        let window = vec![
+            Point::new(half.x_min, half.y_min),
+            Point::new(half.x_max, half.y_min),
+            Point::new(half.x_max, half.y_max),
+            Point::new(half.x_min, half.y_max),
+            Point::new(half.x_min, half.y_min),
+        ];
+        let pieces = overlay(
+            &polylines,
+            &[window],
+            OverlayRule::Intersect,
+            FillRule::EvenOdd,
+        );
+        split_closed(pieces, max_vertices, depth + 1, result);
+    }
+}
+
+/// Boolean operation on the areas enclosed by the closed polylines (with the
+/// fill rule), returning closed polylines with connected holes.
+fn overlay(
+    polylines: &[Polyline],
+    other: &[Polyline],
+    rule: OverlayRule,
+    fill_rule: FillRule,
+) -> Vec<Polyline> {
+    let contours = |polylines: &[Polyline]| -> Vec<Vec<[f64; 2]>> {
+        polylines
+            .iter()
+            .filter(|polyline| polyline.len() >= 4 && is_closed(polyline))
+            .map(|polyline| {
+                polyline[..polyline.len() - 1]
+                    .iter()
+                    .map(|point| [point.x, point.y])
+                    .collect()
+            })
+            .collect()
+    };
+    let shapes = contours(polylines).overlay(&contours(other), rule, fill_rule);
+    let mut result = vec![];
+    for shape in shapes {
+        let mut contours = shape
+            .into_iter()
+            .map(|contour| contour.into_iter().map(|[x, y]| Point::new(x, y)).collect());
+        let outline = match contours.next() {
+            Some(outline) => outline,
+            None => continue,
+        };
+        let mut polyline = contours.fold(outline, connect_hole);
+        polyline.push(polyline[0]);
+        result.push(polyline);
+    }
+    result
+}
+
+/// Connect a hole to an (open) outline at their closest vertices, resulting
+/// in a single outline which goes around the hole.
+fn connect_hole(outline: Polyline, hole: Polyline) -> Polyline {
+    let distance = |a: Point, b: Point| (a.x - b.x).hypot(a.y - b.y);
+    let mut closest = (0, 0, f64::INFINITY);
+    for (i, &a) in outline.iter().enumerate() {
+        for (j, &b) in hole.iter().enumerate() {
+            if distance(a, b) < closest.2 {
+                closest = (i, j, distance(a, b));
+            }
+        }
+    }
+    let (i, j, _) = closest;
+    let mut connected = outline[..=i].to_vec();
+    connected.extend_from_slice(&hole[j..]);
+    connected.extend_from_slice(&hole[..=j]);
+    connected.extend_from_slice(&outline[i..]);
+    connected
+}
+
+/// Cut the holes out of the areas enclosed by the closed polylines, with
+/// bridges from each hole to the outside of the area around it. Without
+/// bridges, filled areas cannot have holes in LibrePCB.
+///
+/// Polylines inside an odd number of other polylines are holes. Holes where
+/// a bridge cannot be placed (or with zero-width bridges) are connected to the
+/// outline by a zero-width cut. Open polylines are kept.
+pub fn bridge_holes(polylines: &[Polyline], bridges: &Bridges) -> Vec<Polyline> {
+    let is_polygon = |polyline: &Polyline| polyline.len() >= 4 && is_closed(polyline);
+    let polygons: Vec<&Polyline> = polylines.iter().filter(|p| is_polygon(p)).collect();
+    let encloses = |i: usize, j: usize| i != j && contains(polygons[i], polygons[j][0]);
+    let depths: Vec<usize> = (0..polygons.len())
+        .map(|j| (0..polygons.len()).filter(|&i| encloses(i, j)).count())
+        .collect();
+
+    let mut result: Vec<Polyline> = polylines
+        .iter()
+        .filter(|polyline| !is_polygon(polyline))
+        .cloned()
+        .collect();
+    for (i, area) in polygons.iter().enumerate() {
+        if depths[i] % 2 == 1 {
+            continue;
+        }
+        let holes: Vec<&Polyline> = (0..polygons.len())
+            .filter(|&j| depths[j] == depths[i] + 1 && encloses(i, j))
+            .map(|j| polygons[j])
+            .collect();
+        if holes.is_empty() {
+            result.push(area.to_vec());
+            continue;
+        }
+        let mut cutouts: Vec<Polyline> = vec![];
+        for hole in holes {
+            cutouts.push(hole.clone());
+            for k in 0..bridges.count {
+                let angle = (bridges.angle + 360.0 * k as f64 / bridges.count as f64).to_radians();
+                // The y-axis points down in SVG coordinates
+                let direction = (angle.cos(), -angle.sin());
+                cutouts.extend(bridge(area, hole, direction, bridges.width));
+            }
+        }
+        // With the non-zero fill rule, all cutouts must have the same
+        // orientation, since bridges overlap the holes
+        for cutout in &mut cutouts {
+            if signed_area(cutout) < 0.0 {
+                cutout.reverse();
+            }
+        }
+        let mut outline = area.to_vec();
+        if signed_area(&outline) < 0.0 {
+            outline.reverse();
+        }
+        result.extend(overlay(
+            &[outline],
+            &cutouts,
+            OverlayRule::Difference,
+            FillRule::NonZero,
+        ));
+    }
+    result
+}
+
+/// A bridge from a hole to the outside of the area around it, in a direction
+/// from the center of the hole.
+fn bridge(area: &[Point], hole: &[Point], direction: (f64, f64), width: f64) -> Option<Polyline> {
+    if width <= 0.0 {
+        return None;
+    }
+    let center = centroid(&[hole.to_vec()])?;
+    let hits = |polyline: &[Point]| -> Vec<f64> {
+        polyline
+            .windows(2)
+            .filter_map(|pair| ray_intersection(center, direction, pair[0], pair[1]))
+            .filter(|t| *t > 0.0)
+            .collect()
+    };
+    // From where the ray leaves the hole to where it leaves the area next
+    let start = hits(hole).into_iter().reduce(f64::max)?;
+    let end = hits(area)
+        .into_iter()
+        .filter(|t| *t > start)
+        .reduce(f64::min)?;
+    let (start, end) = (start - width, end + width);
+    let normal = (-direction.1 * width / 2.0, direction.0 * width / 2.0);
+    let at = |t: f64, side: f64| {
+        Point::new(
+            center.x + direction.0 * t + normal.0 * side,
+            center.y + direction.1 * t + normal.1 * side,
+        )
+    };
+    Some(vec![
+        at(start, 1.0),
+        at(end, 1.0),
+        at(end, -1.0),
+        at(start, -1.0),
+        at(start, 1.0),
+    ])
+}
+
+/// Distance along a ray (with a unit direction) to the segment from a to b.
+pub fn ray_intersection(origin: Point, direction: (f64, f64), a: Point, b: Point) -> Option<f64> {
+    let (ex, ey) = (b.x - a.x, b.y - a.y);
+    let denominator = direction.0 * ey - direction.1 * ex;
+    if denominator.abs() < 1e-12 {
+        return None;
+    }
+    let (wx, wy) = (a.x - origin.x, a.y - origin.y);
+    let t = (wx * ey - wy * ex) / denominator;
+    let u = (wx * direction.1 - wy * direction.0) / denominator;
+    (0.0..=1.0).contains(&u).then_some(t)
+}
+
+/// Remove closed polylines enclosing less than the minimum area (e.g.
+/// speckles or export artifacts). Open polylines are kept.
+pub fn remove_small(polylines: &[Polyline], min_area: f64) -> Vec<Polyline> {
+    polylines
+        .iter()
+        .filter(|polyline| !is_closed(polyline) || area(polyline) >= min_area)
+        .cloned()
+        .collect()
+}
+
+/// Width of the bounding box of all polylines.
+pub fn width(polylines: &[Polyline]) -> f64 {
+    let xs = || polylines.iter().flatten().map(|point| point.x);
+    match xs().reduce(f64::min) {
+        Some(x_min) => xs().fold(x_min, f64::max) - x_min,
+        None => 0.0,
+    }
+}
+
+/// Scale all polylines by a factor, relative to the origin.
+pub fn scale(polylines: &[Polyline], factor: f64) -> Vec<Polyline> {
+    polylines
+        .iter()
+        .map(|polyline| {
+            polyline
+                .iter()
+                .map(|point| Point::new(point.x * factor, point.y * factor))
+                .collect()
+        })
+        .collect()
+}
+
+/// Convert the polylines to the filled areas covered by stroking them, with
+/// round joins and caps flattened within the tolerance.
+///
+/// Closed polylines are stroked as rings (with a join instead of caps at the
+/// start). Areas with holes are returned like by `subtract()`.
+pub fn outline(polylines: &[Polyline], stroke: &Stroke, tolerance: f64) -> Vec<Polyline> {
+    let half = stroke.width / 2.0;
+    let unit = |a: Point, b: Point| {
+        let length = (b.x - a.x).hypot(b.y - a.y);
+        ((b.x - a.x) / length, (b.y - a.y) / length)
+    };
+    let at = |point: Point, (dx, dy): (f64, f64), factor: f64| {
+        Point::new(point.x + dx * factor, point.y + dy * factor)
+    };
+
+    // The stroke is the union of the segments, joins and caps
+    let mut pieces: Vec<Polyline> = vec![];
+    for polyline in polylines {
+        let mut points = polyline.clone();
+        points.dedup();
+        let closed = points.len() > 2 && is_closed(&points);
+        if points.len() == 1 {
+            // Only caps are drawn for zero-length lines
+            let point = points[0];
+            match stroke.cap {
+                LineCap::Butt => {}
+                LineCap::Round => pieces.push(circle(point, half, tolerance)),
+                LineCap::Square => pieces.push(vec![
+                    Point::new(point.x - half, point.y - half),
+                    Point::new(point.x + half, point.y - half),
+                    Point::new(point.x + half, point.y + half),
+                    Point::new(point.x - half, point.y + half),
+                    Point::new(point.x - half, point.y - half),
+                ]),
+            }
+            continue;
+        }
+
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let (dx, dy) = unit(a, b);
+            let normal = (-dy, dx);
+            pieces.push(vec![
+                at(a, normal, half),
+                at(b, normal, half),
+                at(b, normal, -half),
+                at(a, normal, -half),
+                at(a, normal, half),
+            ]);
+        }
+
+        let n = points.len();
+        let corners = (1..n - 1).map(|i| (points[i - 1], points[i], points[i + 1]));
+        let closing = closed.then(|| (points[n - 2], points[0], points[1]));
+        for (prev, point, next) in corners.chain(closing) {
+            let (d1, d2) = (unit(prev, point), unit(point, next));
+            let cross = d1.0 * d2.1 - d1.1 * d2.0;
+            let dot = d1.0 * d2.0 + d1.1 * d2.1;
+            if cross.abs() < 1e-12 && dot > 0.0 {
+                continue;
+            }
+            // The outer side of the corner, opposite to the turn
+            let side = if cross > 0.0 { 1.0 } else { -1.0 };
+            let (n1, n2) = ((side * d1.1, -side * d1.0), (side * d2.1, -side * d2.0));
+            let bevel = vec![point, at(point, n1, half), at(point, n2, half), point];
+            match stroke.join {
+                LineJoin::Round => pieces.push(circle(point, half, tolerance)),
+                LineJoin::Bevel => pieces.push(bevel),
+                LineJoin::Miter => {
+                    let ratio = 1.0 / ((1.0 + dot) / 2.0).sqrt();
+                    if ratio > MITER_LIMIT {
+                        pieces.push(bevel);
+                        continue;
+                    }
+                    let (mx, my) = (n1.0 + n2.0, n1.1 + n2.1);
+                    let length = mx.hypot(my);
+                    let tip = at(point, (mx / length, my / length), half * ratio);
+                    pieces.push(vec![
+                        point,
+                        at(point, n1, half),
+                        tip,
+                        at(point, n2, half),
+                        point,
+                    ]);
+                }
+            }
+        }
+
+        if !closed {
+            for (end, direction) in [
+                (points[0], unit(points[1], points[0])),
+                (points[n - 1], unit(points[n - 2], points[n - 1])),
+            ] {
+                let normal = (-direction.1, direction.0);
+                match stroke.cap {
+                    LineCap::Butt => {}
+                    LineCap::Round => pieces.push(circle(end, half, tolerance)),
+                    LineCap::Square => {
+                        let extended = at(end, direction, half);
+                        pieces.push(vec![
+                            at(end, normal, half),
+                            at(extended, normal, half),
+                            at(extended, normal, -half),
+                            at(end, normal, -half),
+                            at(end, normal, half),
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    // With the non-zero fill rule, all pieces must have the same orientation
+    for piece in &mut pieces {
+        if signed_area(piece) < 0.0 {
+            piece.reverse();
+        }
+    }
+    overlay(&pieces, &[], OverlayRule::Subject, FillRule::NonZero)
+}
+
+/// Mirror all polylines at the y-axis.
+pub fn mirror(polylines: &[Polyline]) -> Vec<Polyline> {
+    polylines
+        .iter()
+        .map(|polyline| {
+            polyline
+                .iter()
+                .map(|point| Point::new(-point.x, point.y))
+                .collect()
+        })
+        .collect()
+}
+
+/// Repeat the polylines in a grid of `columns` × `rows` copies, offset by
+/// `pitch` (in x and y direction) from each other.
+pub fn tile(
+    polylines: &[Polyline],
+    columns: usize,
+    rows: usize,
+    pitch: (f64, f64),
+) -> Vec<Polyline> {
+    let mut result = Vec::with_capacity(polylines.len() * columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (dx, dy) = (column as f64 * pitch.0, row as f64 * pitch.1);
+            result.extend(polylines.iter().map(|polyline| {
+                polyline
+                    .iter()
+                    .map(|point| Point::new(point.x + dx, point.y + dy))
+                    .collect::<Polyline>()
+            }));
+        }
+    }
+    result
+}
+
+/// Convert a polyline to a path with straight segments only.
+pub fn to_path(polyline: &[Point]) -> Path {
+    polyline
+        .iter()
+        .map(|&position| Vertex::new(position, 0.0))
+        .collect()
+}
+
+/// The vertex positions of a path.
+pub fn points(path: &[Vertex]) -> Polyline {
+    path.iter().map(|vertex| vertex.position).collect()
+}
+
+/// Replace the corners of a polyline with arcs of the given radius.
+///
+/// The radius is reduced where the adjacent segments are too short, so that
+/// the arcs of neighboring corners do not overlap.
+pub fn round_corners(polyline: &[Point], radius: f64) -> Path {
+    let closed = polyline.len() > 2 && is_closed(polyline);
+    let points = match closed {
+        true => &polyline[..polyline.len() - 1],
+        false => polyline,
+    };
+    let n = points.len();
+    let mut path = vec![];
+    for (i, &corner) in points.iter().enumerate() {
+        let neighbors = if closed {
+            Some((points[(i + n - 1) % n], points[(i + 1) % n]))
+        } else if i > 0 && i + 1 < n {
+            Some((points[i - 1], points[i + 1]))
+        } else {
+            None
+        };
+        match neighbors.and_then(|(prev, next)| fillet(prev, corner, next, radius)) {
+            Some((start, end, angle)) => {
+                push_vertex(&mut path, Vertex::new(start, angle));
+                push_vertex(&mut path, Vertex::new(end, 0.0));
+            }
+            None => push_vertex(&mut path, Vertex::new(corner, 0.0)),
+        }
+    }
+    if closed {
+        let first = path[0].position;
+        push_vertex(&mut path, Vertex::new(first, 0.0));
+    }
+    path
+}
+
+/// Split straight segments longer than `max_length` into segments of equal
+/// length. Arc segments are kept as they are.
+pub fn resample(path: &[Vertex], max_length: f64) -> Path {
+    let mut resampled = vec![];
+    for pair in path.windows(2) {
+        let (start, end) = (pair[0].position, pair[1].position);
+        let length = (end.x - start.x).hypot(end.y - start.y);
+        let parts = match pair[0].angle == 0.0 {
+            true => ((length / max_length).ceil() as usize).max(1),
+            false => 1,
+        };
+        resampled.push(pair[0]);
+        for i in 1..parts {
+            resampled.push(Vertex::new(lerp(start, end, i as f64 / parts as f64), 0.0));
+        }
+    }
+    resampled.extend(path.last());
+    resampled
+}
+
+/// Split arc segments sweeping by more than `max_sweep` degrees into arcs of
+/// equal sweep.
+pub fn limit_sweep(path: &[Vertex], max_sweep: f64) -> Path {
+    let mut limited = vec![];
+    for pair in path.windows(2) {
+        let (start, end, sweep) = (pair[0].position, pair[1].position, pair[0].angle);
+        let parts = (sweep.abs() / max_sweep).ceil() as usize;
+        if parts <= 1 {
+            limited.push(pair[0]);
+            continue;
+        }
+        // The center is on the perpendicular bisector of the chord
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let distance = 0.5 / (sweep / 2.0).to_radians().tan();
+        let center = Point::new(
+            (start.x + end.x) / 2.0 - dy * distance,
+            (start.y + end.y) / 2.0 + dx * distance,
+        );
+        let (x, y) = (start.x - center.x, start.y - center.y);
+        let step = sweep / parts as f64;
+        for i in 0..parts {
+            let (sin, cos) = (step * i as f64).to_radians().sin_cos();
+            let position = Point::new(center.x + x * cos - y * sin, center.y + x * sin + y * cos);
+            limited.push(Vertex::new(position, step));
+        }
+    }
+    limited.extend(path.last());
+    limited
+}
+
+/// Round the arc angles to the precision of the generated files, so that
+/// nearly straight arcs become straight segments.
+pub fn quantize_angles(path: &mut [Vertex]) {
+    for vertex in path {
+        vertex.angle = (vertex.angle / ANGLE_PRECISION).round() * ANGLE_PRECISION;
+        if vertex.angle.abs() < ANGLE_PRECISION / 2.0 {
+            vertex.angle = 0.0;
+        }
+    }
+}
+
+/// Append a vertex, replacing the previous one if it is at the same position
+/// (e.g. where the arcs of two corners use up a whole segment).
+fn push_vertex(path: &mut Path, vertex: Vertex) {
+    match path.last_mut() {
+        Some(last) if last.position == vertex.position && last.angle == 0.0 => *last = vertex,
+        _ => path.push(vertex),
+    }
+}
+
+/// Start and end point and angle (in degrees) of the arc rounding a corner,
+/// or `None` if there is no corner.
+fn fillet(prev: Point, corner: Point, next: Point, radius: f64) -> Option<(Point, Point, f64)> {
+    let (ux, uy) = (prev.x - corner.x, prev.y - corner.y);
+    let (vx, vy) = (next.x - corner.x, next.y - corner.y);
+    let (length_u, length_v) = (ux.hypot(uy), vx.hypot(vy));
+    if length_u < f64::EPSILON || length_v < f64::EPSILON {
+        return None;
+    }
+    let cos = ((ux * vx + uy * vy) / (length_u * length_v)).clamp(-1.0, 1.0);
+    let corner_angle = cos.acos();
+    let sweep = PI - corner_angle;
+    if sweep < 1e-6 {
+        return None;
+    }
+    // Each arc may use up to half of the adjacent segments
+    let distance = (radius / (corner_angle / 2.0).tan())
+        .min(length_u / 2.0)
+        .min(length_v / 2.0);
+    let start = Point::new(
+        corner.x + ux / length_u * distance,
+        corner.y + uy / length_u * distance,
+    );
+    let end = Point::new(
+        corner.x + vx / length_v * distance,
+        corner.y + vy / length_v * distance,
+    );
+    let cross =
+        (corner.x - prev.x) * (next.y - corner.y) - (corner.y - prev.y) * (next.x - corner.x);
+    Some((start, end, sweep.to_degrees().copysign(cross)))
+}
+
+/// Minimum number of segments replaced with an arc by [`fit_arcs`].
+const MIN_ARC_SEGMENTS: usize = 3;
+
+/// Replace runs of segments which approximate a circular arc (e.g. from
+/// flattened curves) with arc segments.
+///
+/// All points of a run are within `tolerance` of the arc, which sweeps by at
+/// most 180 degrees.
+pub fn fit_arcs(polyline: &[Point], tolerance: f64) -> Path {
+    let mut path = vec![];
+    let mut i = 0;
+    while i < polyline.len() {
+        // Extend the arc as far as possible
+        let mut arc = None;
+        for j in i + MIN_ARC_SEGMENTS..polyline.len() {
+            match fit_arc(&polyline[i..=j], tolerance) {
+                Some(sweep) => arc = Some((j, sweep)),
+                None => break,
+            }
+        }
+        match arc {
+            Some((end, sweep)) => {
+                path.push(Vertex::new(polyline[i], sweep.to_degrees()));
+                i = end;
+            }
+            None => {
+                path.push(Vertex::new(polyline[i], 0.0));
+                i += 1;
+            }
+        }
+    }
+    path
+}
+
+/// Sweep angle (in radians) of the arc approximated by the points, if any.
+fn fit_arc(points: &[Point], tolerance: f64) -> Option<f64> {
+    let n = points.len() - 1;
+    let center = circumcenter(points[0], points[n / 3], points[2 * n / 3])?;
+    let distance = |p: Point| (p.x - center.x).hypot(p.y - center.y);
+    let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+    let radius = distance(points[0]);
+    let mut sweep = 0.0;
+    for pair in points.windows(2) {
+        if (distance(pair[1]) - radius).abs() > tolerance {
+            return None;
+        }
+        let step = (angle(pair[1]) - angle(pair[0]) + PI).rem_euclid(2.0 * PI) - PI;
+        // All segments must turn in the same direction and be short enough
+        // that the arc does not deviate from them
+        let sagitta = radius * (1.0 - (step / 2.0).cos());
+        if step * sweep < 0.0 || sagitta > tolerance {
+            return None;
+        }
+        sweep += step;
+    }
+    (sweep.abs() <= PI).then_some(sweep)
+}
+
+/// Center of the circle through three points.
+pub fn circumcenter(a: Point, b: Point, c: Point) -> Option<Point> {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < f64::EPSILON {
+        return None;
+    }
+    let (a2, b2, c2) = (
+        a.x.powi(2) + a.y.powi(2),
+        b.x.powi(2) + b.y.powi(2),
+        c.x.powi(2) + c.y.powi(2),
+    );
+    Some(Point::new(
+        (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    ))
+}
+
+/// Flatten a circular arc, starting at `start_angle` and sweeping by `sweep`
+/// (in radians, positive in the direction from the x-axis to the y-axis).
+///
+/// The number of segments is chosen so that the deviation from the arc is at
+/// most `tolerance`, with at least 8 segments per full circle.
+pub fn arc(center: Point, radius: f64, start_angle: f64, sweep: f64, tolerance: f64) -> Polyline {
+    let max_step = if tolerance > 0.0 && tolerance < radius {
+        (2.0 * (1.0 - tolerance / radius).acos()).min(PI / 4.0)
+    } else {
+        PI / 4.0
+    };
+    let segments = ((sweep.abs() / max_step).ceil() as usize).max(1);
+    (0..=segments)
+        .map(|i| {
+            let angle = start_angle + sweep * i as f64 / segments as f64;
+            Point::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Flatten a circle into a closed polyline.
+pub fn circle(center: Point, radius: f64, tolerance: f64) -> Polyline {
+    let mut polyline = arc(center, radius, 0.0, 2.0 * PI, tolerance);
+    let last = polyline.len() - 1;
+    polyline[last] = polyline[0];
+    polyline
+}
+
+/// Number of segments to flatten an arc with, so that the distance between
+/// the segments and the arc is at most the tolerance.
+pub fn arc_steps(sweep: f64, radius: f64, tolerance: f64) -> usize {
+    // Limit the number of segments of tiny tolerances
+    let tolerance = tolerance.max(radius * 1e-6);
+    let step = if tolerance < radius {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        std::f64::consts::FRAC_PI_2
+    };
+    // At least 4 segments per full turn
+    let step = step.min(std::f64::consts::FRAC_PI_2);
+    (sweep.abs() / step).ceil().max(1.0) as usize
+}
+
+/// Center and diameter of a closed polyline which approximates a circle (e.g.
+/// a flattened SVG circle), with all vertices and the enclosed area within
+/// the tolerance of the circle.
+pub fn fit_circle(polyline: &[Point], tolerance: f64) -> Option<(Point, f64)> {
+    // Flattened circles have at least 4 segments
+    if polyline.len() < 5 || !is_closed(polyline) {
+        return None;
+    }
+    let points = &polyline[..polyline.len() - 1];
+    let n = points.len() as f64;
+    let center = Point::new(
+        points.iter().map(|p| p.x).sum::<f64>() / n,
+        points.iter().map(|p| p.y).sum::<f64>() / n,
+    );
+    let distance = |p: &Point| (p.x - center.x).hypot(p.y - center.y);
+    let radius = points.iter().map(distance).sum::<f64>() / n;
+    let round = points
+        .iter()
+        .all(|p| (distance(p) - radius).abs() <= tolerance);
+    // The area rules out shapes which zigzag around the circle
+    let area_error = (area(polyline) - PI * radius.powi(2)).abs();
+    // Polygons with fewer segments than a circle of this size flattened with
+    // the tolerance (e.g. squares) are not circles
+    let segments = arc_steps(2.0 * PI, radius, tolerance);
+    (round
+        && radius > 0.0
+        && points.len() >= segments
+        && area_error <= 2.0 * PI * radius * tolerance)
+        .then_some((center, 2.0 * radius))
+}
+
+/// Convex hull of a set of points in counterclockwise order (Andrew's
+/// monotone chain), as an open polyline.
+pub fn convex_hull(points: &[Point]) -> Polyline {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    fn chain<'a>(points: impl Iterator<Item = &'a Point>) -> Polyline {
+        let cross =
+            |o: Point, a: Point, b: Point| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+        let mut chain: Polyline = vec![];
+        for &point in points {
+            while chain.len() >= 2
+                && cross(chain[chain.len() - 2], chain[chain.len() - 1], point) <= 0.0
+            {
+                chain.pop();
+            }
+            chain.push(point);
+        }
+        // The last point is the first point of the other chain
+        chain.pop();
+        chain
+    }
+    let mut hull = chain(sorted.iter());
+    hull.extend(chain(sorted.iter().rev()));
+    hull
+}
+
+/// Point at parameter `t` on the segment from `a` to `b`.
+fn lerp(a: Point, b: Point, t: f64) -> Point {
+    Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+/// Clip polylines to a rectangle.
+///
+/// Closed polylines are clipped as polygons, open polylines are split into
+/// the pieces lying inside the rectangle. Polylines completely outside of the
+/// rectangle are dropped.
+pub fn clip(polylines: &[Polyline], rect: &Rect) -> Vec<Polyline> {
+    let mut clipped = vec![];
+    for polyline in polylines {
+        if is_closed(polyline) {
+            let polygon = clip_polygon(polyline, rect);
+            if polygon.len() > 3 {
+                clipped.push(polygon);
+            }
+        } else {
+            clipped.extend(clip_open(polyline, rect));
+        }
+    }
+    clipped
+}
+
+/// Clip a closed polyline (Sutherland-Hodgman).
+fn clip_polygon(polyline: &[Point], rect: &Rect) -> Polyline {
+    let mut output = polyline[..polyline.len() - 1].to_vec();
+    for edge in 0..4 {
+        let input = std::mem::take(&mut output);
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            let d_current = rect.distance(edge, current);
+            let d_previous = rect.distance(edge, previous);
+            let crossing = || lerp(previous, current, d_previous / (d_previous - d_current));
+            if d_current >= 0.0 {
+                if d_previous < 0.0 {
+                    output.push(crossing());
+                }
+                output.push(current);
+            } else if d_previous >= 0.0 {
+                output.push(crossing());
+            }
+        }
+    }
+    // Vertices on an edge result in duplicates
+    output.dedup();
+    if let Some(&first) = output.first() {
+        if output.len() > 1 && output[output.len() - 1] == first {
+            output.pop();
+        }
+        output.push(first);
+    }
+    output
+}
+
+/// Clip an open polyline (Liang-Barsky per segment).
+fn clip_open(polyline: &[Point], rect: &Rect) -> Vec<Polyline> {
+    let mut pieces = vec![];
+    let mut piece: Polyline = vec![];
+    for segment in polyline.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let (mut t_start, mut t_end) = (0.0_f64, 1.0_f64);
+        for edge in 0..4 {
+            let (d_a, d_b) = (rect.distance(edge, a), rect.distance(edge, b));
+            if d_a < 0.0 && d_b < 0.0 {
+                t_start = f64::INFINITY;
+            } else if d_a < 0.0 {
+                t_start = t_start.max(d_a / (d_a - d_b));
+            } else if d_b < 0.0 {
+                t_end = t_end.min(d_a / (d_a - d_b));
+            }
+        }
+        if t_start > t_end {
+            continue;
+        }
+        if t_start > 0.0 || piece.is_empty() {
+            if piece.len() > 1 {
+                pieces.push(std::mem::take(&mut piece));
+            }
+            piece = vec![lerp(a, b, t_start)];
+        }
+        piece.push(lerp(a, b, t_end));
+        if t_end < 1.0 {
+            pieces.push(std::mem::take(&mut piece));
+        }
+    }
+    if piece.len() > 1 {
+        pieces.push(piece);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: f64) -> Polyline {
+        vec![
+            Point::new(0.0, 0.0),
+            Point::new(size, 0.0),
+            Point::new(size, size),
+            Point::new(0.0, size),
+            Point::new(0.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn test_is_closed() {
+        assert!(is_closed(&square(1.0)));
+        assert!(!is_closed(&square(1.0)[..4]));
+        let mut jittered = square(1.0);
+        jittered[4] = Point::new(1e-9, -1e-9);
+        assert!(is_closed(&jittered));
+    }
+
+    #[test]
+    fn test_close() {
+        let mut jittered = square(1.0);
+        jittered[4] = Point::new(1e-9, 0.0);
+        let open = square(1.0)[..4].to_vec();
+        let line = open[..2].to_vec();
+        let mut polylines = vec![jittered, open.clone(), line.clone()];
+        close(&mut polylines, false);
+        assert_eq!(polylines, vec![square(1.0), open, line.clone()]);
+        close(&mut polylines, true);
+        assert_eq!(polylines, vec![square(1.0), square(1.0), line]);
+    }
+
+    #[test]
+    fn test_area() {
+        assert_eq!(area(&square(2.0)), 4.0);
+        let mut reversed = square(3.0);
+        reversed.reverse();
+        assert_eq!(area(&reversed), 9.0);
+    }
+
+    #[test]
+    fn test_width_and_scale() {
+        let polylines = vec![square(2.0), vec![Point::new(-1.0, 5.0)]];
+        assert_eq!(width(&polylines), 3.0);
+        let scaled = scale(&polylines, 2.0);
+        assert_eq!(width(&scaled), 6.0);
+        assert_eq!(scaled[1], vec![Point::new(-2.0, 10.0)]);
+        assert_eq!(width(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_arc() {
+        let quarter = arc(Point::new(1.0, 1.0), 2.0, 0.0, PI / 2.0, 1.0);
+        assert_eq!(quarter.len(), 3);
+        assert_eq!(quarter[0], Point::new(3.0, 1.0));
+        assert!((quarter[2].x - 1.0).abs() < 1e-9);
+        assert!((quarter[2].y - 3.0).abs() < 1e-9);
+
+        // Smaller tolerances result in more segments
+        let fine = circle(Point::new(0.0, 0.0), 10.0, 0.01);
+        assert!(fine.len() > 50);
+        assert!(is_closed(&fine));
+        let max_deviation = fine
+            .windows(2)
+            .map(|segment| {
+                let mid = lerp(segment[0], segment[1], 0.5);
+                10.0 - mid.x.hypot(mid.y)
+            })
+            .fold(0.0, f64::max);
+        assert!(max_deviation <= 0.01);
+    }
+
+    #[test]
+    fn test_fit_circle() {
+        let center = Point::new(3.0, -2.0);
+        let (fitted, diameter) = fit_circle(&circle(center, 1.5, 0.01), 0.01).unwrap();
+        assert!((fitted.x - center.x).abs() < 1e-6 && (fitted.y - center.y).abs() < 1e-6);
+        assert!((diameter - 3.0).abs() < 1e-6);
+        assert_eq!(fit_circle(&square(3.0), 0.01), None);
+        // Small circles are flattened with few segments (like
+        // `svg::flatten_ellipses()`)
+        let steps = arc_steps(2.0 * PI, 0.1, 0.02);
+        assert_eq!(steps, 5);
+        let small: Polyline = (0..=steps)
+            .map(|step| {
+                let (sin, cos) = (2.0 * PI * (step % steps) as f64 / steps as f64).sin_cos();
+                Point::new(center.x + 0.1 * cos, center.y + 0.1 * sin)
+            })
+            .collect();
+        assert!(fit_circle(&small, 0.02).is_some());
+        let mut open = circle(center, 1.5, 0.01);
+        open.pop();
+        assert_eq!(fit_circle(&open, 0.01), None);
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(2.0, 0.0),
+            Point::new(2.0, 2.0),
+            Point::new(0.0, 2.0),
+            Point::new(0.0, 0.0),
+        ];
+        assert_eq!(
+            convex_hull(&points),
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 0.0),
+                Point::new(2.0, 2.0),
+                Point::new(0.0, 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clip() {
+        let rect = Rect {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 1.0,
+            y_max: 1.0,
+        };
+
+        // Closed polylines are cut at the edges, outside ones are dropped
+        let outside = square(1.0)
+            .iter()
+            .map(|p| Point::new(p.x + 5.0, p.y))
+            .collect();
+        let clipped = clip(&[square(2.0), outside], &rect);
+        assert_eq!(clipped.len(), 1);
+        assert!(is_closed(&clipped[0]));
+        assert_eq!(area(&clipped[0]), 1.0);
+
+        // Open polylines are split into the pieces inside
+        let zigzag = vec![
+            Point::new(0.5, 0.5),
+            Point::new(2.0, 0.5),
+            Point::new(2.0, 0.25),
+            Point::new(0.5, 0.25),
+        ];
+        assert_eq!(
+            clip(&[zigzag], &rect),
+            vec![
+                vec![Point::new(0.5, 0.5), Point::new(1.0, 0.5)],
+                vec![Point::new(1.0, 0.25), Point::new(0.5, 0.25)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_corners() {
+        let near = |a: Point, b: Point| (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9;
+        let path = round_corners(&square(10.0), 1.0);
+        assert_eq!(path.len(), 9);
+        assert!(near(path[0].position, Point::new(0.0, 1.0)));
+        assert!((path[0].angle - 90.0).abs() < 1e-9);
+        assert!(near(path[1].position, Point::new(1.0, 0.0)));
+        assert_eq!(path[1].angle, 0.0);
+        assert_eq!(path[8].position, path[0].position);
+
+        // The radius is limited by the segment lengths
+        let path = round_corners(&square(10.0), 100.0);
+        assert!((path[1].position.x - 5.0).abs() < 1e-9);
+
+        // End points of open polylines and straight segments are kept
+        let line = vec![
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(2.0, 0.0),
+        ];
+        assert_eq!(round_corners(&line, 1.0), to_path(&line));
+    }
+
+    #[test]
+    fn test_fit_arcs() {
+        // Quarter circle followed by a straight line
+        let mut polyline = arc(Point::new(0.0, 0.0), 10.0, 0.0, PI / 2.0, 0.01);
+        polyline.push(Point::new(-10.0, 10.0));
+        let path = fit_arcs(&polyline, 0.01);
+        assert_eq!(path.len(), 3);
+        assert!((path[0].angle - 90.0).abs() < 1e-6);
+        assert_eq!(path[1].position, polyline[polyline.len() - 2]);
+        assert_eq!(path[1].angle, 0.0);
+
+        // Full circles are split into arcs of at most 180 degrees
+        let path = fit_arcs(&circle(Point::new(0.0, 0.0), 1.0, 0.001), 0.001);
+        assert!(path.len() <= 5);
+        assert!(path.iter().map(|v| v.angle).sum::<f64>() > 350.0);
+
+        // The corners of a square are on a circle, but not close to it
+        assert_eq!(fit_arcs(&square(1.0), 0.1), to_path(&square(1.0)));
+    }
+
+    #[test]
+    fn test_resample() {
+        let path = resample(&to_path(&square(1.0)), 0.4);
+        assert_eq!(path.len(), 13);
+        assert_eq!(path[1].position, Point::new(1.0 / 3.0, 0.0));
+        assert_eq!(path[12].position, Point::new(0.0, 0.0));
+
+        // Arcs are not split
+        let arc = vec![
+            Vertex::new(Point::new(0.0, 0.0), 180.0),
+            Vertex::new(Point::new(1.0, 0.0), 0.0),
+        ];
+        assert_eq!(resample(&arc, 0.1), arc);
+    }
+
+    #[test]
+    fn test_limit_sweep() {
+        // A semicircle, counterclockwise around the origin
+        let arc = vec![
+            Vertex::new(Point::new(1.0, 0.0), 180.0),
+            Vertex::new(Point::new(-1.0, 0.0), 0.0),
+        ];
+        let limited = limit_sweep(&arc, 90.0);
+        assert_eq!(limited.len(), 3);
+        assert_eq!(limited[0], Vertex::new(Point::new(1.0, 0.0), 90.0));
+        assert!((limited[1].position.x).abs() < 1e-9);
+        assert!((limited[1].position.y - 1.0).abs() < 1e-9);
+        assert_eq!(limited[1].angle, 90.0);
+        assert_eq!(limit_sweep(&arc, 180.0), arc);
+
+        let mut quantized = vec![
+            Vertex::new(Point::new(0.0, 0.0), 89.99999),
+            Vertex::new(Point::new(1.0, 0.0), -0.0002),
+        ];
+        quantize_angles(&mut quantized);
+        assert_eq!((quantized[0].angle, quantized[1].angle), (90.0, 0.0));
+    }
+
+    #[test]
+    fn test_offset() {
+        let square = |min: f64, max: f64| {
+            vec![
+                Point::new(min, min),
+                Point::new(max, min),
+                Point::new(max, max),
+                Point::new(min, max),
+                Point::new(min, min),
+            ]
+        };
+        let mut hole = square(3.0, 7.0);
+        hole.reverse();
+        let line = vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0)];
+        let offset = offset(&[square(0.0, 10.0), hole, line.clone()], 1.0);
+        let near = |a: &[Point], b: &[Point]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9)
+        };
+        assert!(near(&offset[0], &square(-1.0, 11.0)));
+        let mut shrunk = square(4.0, 6.0);
+        shrunk.reverse();
+        assert!(near(&offset[1], &shrunk));
+        assert_eq!(offset[2], line);
+    }
+
+    #[test]
+    fn test_tile() {
+        let line = vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0)];
+        let result = tile(&[line], 3, 2, (2.0, -5.0));
+        assert_eq!(result.len(), 6);
+        assert_eq!(result[1], vec![Point::new(2.0, 0.0), Point::new(3.0, 0.0)]);
+        assert_eq!(
+            result[5],
+            vec![Point::new(4.0, -5.0), Point::new(5.0, -5.0)]
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        let polylines = vec![
+            vec![Point::new(1.0, 2.0), Point::new(-1.0, 5.0)],
+            vec![Point::new(3.0, 0.0)],
+        ];
+        assert_eq!(
+            bounds(&polylines),
+            Some(Rect {
+                x_min: -1.0,
+                y_min: 0.0,
+                x_max: 3.0,
+                y_max: 5.0,
+            })
+        );
+        assert_eq!(bounds(&[]), None);
+    }
+
+    #[test]
+    fn test_centroid() {
+        // An L shape: A 2x2 square with the top right 1x1 quarter cut out
+        let outline = square(2.0);
+        let cutout: Polyline = square(1.0)
+            .iter()
+            .map(|point| Point::new(point.x + 1.0, point.y + 1.0))
+            .collect();
+        let center = centroid(&[outline.clone(), cutout]).unwrap();
+        assert!((center.x - 5.0 / 6.0).abs() < 1e-9);
+        assert!((center.y - 5.0 / 6.0).abs() < 1e-9);
+        let mut reversed = outline;
+        reversed.reverse();
+        assert_eq!(centroid(&[reversed]), Some(Point::new(1.0, 1.0)));
+        assert_eq!(
+            centroid(&[vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)]]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_outline() {
+        let stroked_area = |polyline: &Polyline, cap, join| -> f64 {
+            let stroke = Stroke {
+                width: 2.0,
+                cap,
+                join,
+            };
+            let outlined = outline(std::slice::from_ref(polyline), &stroke, 0.001);
+            assert_eq!(outlined.len(), 1);
+            area(&outlined[0])
+        };
+        let line = vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let butt = stroked_area(&line, LineCap::Butt, LineJoin::Miter);
+        assert!((butt - 20.0).abs() < 1e-9);
+        let square_cap = stroked_area(&line, LineCap::Square, LineJoin::Miter);
+        assert!((square_cap - 24.0).abs() < 1e-9);
+        let round_cap = stroked_area(&line, LineCap::Round, LineJoin::Miter);
+        assert!((round_cap - (20.0 + PI)).abs() < 0.01);
+
+        // A right angle: The miter fills the outer corner, the bevel half of it
+        let corner = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+        ];
+        let miter = stroked_area(&corner, LineCap::Butt, LineJoin::Miter);
+        assert!((miter - 40.0).abs() < 1e-9);
+        let bevel = stroked_area(&corner, LineCap::Butt, LineJoin::Bevel);
+        assert!((bevel - 39.5).abs() < 1e-9);
+
+        // A closed square is stroked as a ring
+        let ring = stroked_area(&square(10.0), LineCap::Butt, LineJoin::Miter);
+        assert!((ring - (144.0 - 64.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bridge_holes() {
+        let hole: Polyline = square(4.0)
+            .iter()
+            .map(|point| Point::new(point.x + 3.0, point.y + 3.0))
+            .collect();
+        let polylines = vec![square(10.0), hole];
+        let bridges = |width, count| Bridges {
+            width,
+            count,
+            angle: 0.0,
+        };
+        let total_area = |polylines: &[Polyline]| -> f64 {
+            polylines.iter().map(|polyline| area(polyline)).sum()
+        };
+
+        // One bridge to the right (3 mm long) leaves a C shape
+        let bridged = bridge_holes(&polylines, &bridges(1.0, 1));
+        assert_eq!(bridged.len(), 1);
+        assert!((total_area(&bridged) - (100.0 - 16.0 - 3.0)).abs() < 1e-9);
+        let right = bridged[0].iter().filter(|point| point.x == 10.0).count();
+        assert!(right >= 4);
+
+        // Two bridges split the ring in halves
+        let halves = bridge_holes(&polylines, &bridges(1.0, 2));
+        assert_eq!(halves.len(), 2);
+        assert!((total_area(&halves) - (100.0 - 16.0 - 6.0)).abs() < 1e-9);
+
+        // Without bridges, the hole is connected by a zero-width cut
+        let cut = bridge_holes(&polylines, &bridges(0.0, 1));
+        assert_eq!(cut.len(), 1);
+        assert!((total_area(&cut) - 84.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_remove_small() {
+        let triangle = |size: f64| {
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(size, 0.0),
+                Point::new(0.0, size),
+                Point::new(0.0, 0.0),
+            ]
+        };
+        let line = vec![Point::new(0.0, 0.0), Point::new(0.01, 0.0)];
+        let polylines = vec![triangle(0.1), triangle(2.0), line.clone()];
+        assert_eq!(remove_small(&polylines, 0.5), vec![triangle(2.0), line]);
+    }
+
+    #[test]
+    fn test_trace() {
+        let square = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 0.0),
+        ];
+        // A gradient from left to right, the dark half is traced
+        let traced = trace(std::slice::from_ref(&square), 1.0, |point| {
+            (point.x < 5.0).then_some(())
+        });
+        assert_eq!(traced.len(), 1);
+        assert_eq!(traced[0].1.len(), 1);
+        assert!((area(&traced[0].1[0]).abs() - 50.0).abs() < 1e-6);
+
+        // Clipped to the shape
+        let triangle = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 0.0),
+        ];
+        let traced = trace(&[triangle], 1.0, |point| Some(point.y < 5.0));
+        let areas: Vec<(bool, f64)> = traced
+            .iter()
+            .map(|(class, polylines)| (*class, polylines.iter().map(|p| area(p).abs()).sum()))
+            .collect();
+        assert_eq!(areas.len(), 2);
+        assert_eq!((areas[0].0, areas[1].0), (true, false));
+        assert!((areas[0].1 - 37.5).abs() < 1e-6 && (areas[1].1 - 12.5).abs() < 1e-6);
+
+        assert_eq!(raster_size(std::slice::from_ref(&square), 0.3), (34, 34));
+        assert_eq!(raster_size(&[], 1.0), (0, 0));
+    }
+
+    #[test]
+    fn test_subtract() {
+        let square = |min: f64, max: f64| {
+            vec![
+                Point::new(min, min),
+                Point::new(max, min),
+                Point::new(max, max),
+                Point::new(min, max),
+                Point::new(min, min),
+            ]
+        };
+        let line = vec![Point::new(0.0, 0.0), Point::new(20.0, 0.0)];
+
+        // Overlapping: the remaining area is an L shape
+        let result = subtract(&[square(0.0, 10.0), line.clone()], &[square(5.0, 15.0)]);
+        assert_eq!(result[0], line);
+        assert_eq!(result.len(), 2);
+        assert!((area(&result[1]) - 75.0).abs() < 1e-6);
+
+        // Inside: the hole is connected to the outline
+        let result = subtract(&[square(0.0, 10.0)], &[square(4.0, 6.0)]);
+        assert_eq!(result.len(), 1);
+        assert!(is_closed(&result[0]));
+        assert_eq!(result[0].len(), 4 + 4 + 2 + 1);
+        assert!((area(&result[0]) - 96.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_split() {
+        let circle = circle(Point::new(0.0, 0.0), 10.0, 0.001);
+        let line = vec![Point::new(0.0, 0.0), Point::new(20.0, 0.0)];
+        assert!(circle.len() > 100);
+
+        // Small enough: unchanged
+        let result = split(&[circle.clone(), line.clone()], circle.len());
+        assert_eq!(result, vec![line.clone(), circle.clone()]);
+
+        // Split into pieces with the same total area
+        let result = split(&[circle.clone(), line.clone()], 50);
+        assert_eq!(result[0], line);
+        assert!(result.len() > 3);
+        assert!(result[1..].iter().all(|piece| piece.len() <= 50));
+        let total: f64 = result[1..].iter().map(|piece| area(piece)).sum();
+        assert!((total - area(&circle)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,486 @@
+//! Import of Gerber (RS-274X) artwork.
+//!
+//! Flashes, strokes and regions are converted to filled polygons. Only the
+//! standard apertures (circle, rectangle, obround and polygon) are supported,
+//! aperture macros, step and repeat and clear polarity are rejected. Strokes
+//! are converted to one polygon per segment, which LibrePCB merges when
+//! rendering.
+//!
+//! Coordinates are converted to millimeters with the y-axis pointing down,
+//! like in SVG documents.
+
+use std::{collections::HashMap, f64::consts::PI};
+
+use crate::geometry::{self, Point, Polyline};
+
+/// A standard aperture.
+#[derive(Debug)]
+enum Aperture {
+    /// Convex outline, centered at the origin
+    Shape(Polyline),
+    /// Aperture macro (or other unsupported template) with the given name
+    Unsupported(String),
+}
+
+/// Interpolation mode set by G01/G02/G03.
+#[derive(PartialEq, Clone, Copy)]
+enum Interpolation {
+    Linear,
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Coordinate format (%FS%) and unit (%MO%).
+struct Format {
+    integer_digits: usize,
+    decimal_digits: usize,
+    trailing_zeros_omitted: bool,
+    /// Factor to convert to millimeters
+    unit: f64,
+}
+
+struct Parser {
+    tolerance: f64,
+    format: Format,
+    apertures: HashMap<u32, Aperture>,
+    aperture: Option<u32>,
+    interpolation: Interpolation,
+    multi_quadrant: bool,
+    region: Option<Vec<Polyline>>,
+    position: Point,
+    polylines: Vec<Polyline>,
+}
+
+/// Convert Gerber artwork to polylines.
+pub fn parse(source: &str, tolerance: f64) -> Result<Vec<Polyline>, String> {
+    let mut parser = Parser {
+        tolerance,
+        format: Format {
+            integer_digits: 3,
+            decimal_digits: 6,
+            trailing_zeros_omitted: false,
+            unit: 1.0,
+        },
+        apertures: HashMap::new(),
+        aperture: None,
+        interpolation: Interpolation::Linear,
+        // Should be set explicitly with G75, which is omitted by some tools
+        multi_quadrant: true,
+        region: None,
+        position: Point::new(0.0, 0.0),
+        polylines: vec![],
+    };
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c == '%' {
+            let end = rest[1..].find('%').ok_or("Unclosed extended command")? + 1;
+            for command in rest[1..end].split('*') {
+                let command: String = command.split_whitespace().collect();
+                if !command.is_empty() {
+                    parser.extended_command(&command)?;
+                }
+            }
+            rest = &rest[end + 1..];
+        } else {
+            let end = rest.find('*').ok_or("Missing '*' after command")?;
+            let command = &rest[..end];
+            rest = &rest[end + 1..];
+            // Comments may contain whitespace
+            if !command.starts_with("G04") {
+                let command: String = command.split_whitespace().collect();
+                if parser.word_command(&command)? {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Gerber files use a y-axis pointing up
+    Ok(parser
+        .polylines
+        .into_iter()
+        .map(|polyline| {
+            polyline
+                .into_iter()
+                .map(|point| Point::new(point.x, -point.y))
+                .collect()
+        })
+        .collect())
+}
+
+/// Split a leading (signed) number off a string.
+fn split_number(s: &str) -> (&str, &str) {
+    let end = s
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && (c == '-' || c == '+'))))
+        .map_or(s.len(), |(i, _)| i);
+    s.split_at(end)
+}
+
+/// Parse a decimal number.
+fn decimal(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number {:?}", value))
+}
+
+impl Parser {
+    /// Handle a command within `%…%`.
+    fn extended_command(&mut self, command: &str) -> Result<(), String> {
+        let code = command.get(..2).unwrap_or(command);
+        let args = command.get(2..).unwrap_or("");
+        match code {
+            "FS" => {
+                let x = args.find('X').ok_or("Invalid format specification")?;
+                let digits: Vec<usize> = args[x + 1..]
+                    .chars()
+                    .take(2)
+                    .map(|c| c.to_digit(10).map(|d| d as usize))
+                    .collect::<Option<_>>()
+                    .ok_or("Invalid format specification")?;
+                if digits.len() != 2 {
+                    return Err("Invalid format specification".into());
+                }
+                self.format.integer_digits = digits[0];
+                self.format.decimal_digits = digits[1];
+                self.format.trailing_zeros_omitted = args.starts_with('T');
+                if args[..x].contains('I') {
+                    return Err("Incremental coordinates are not supported".into());
+                }
+            }
+            "MO" => {
+                self.format.unit = match args {
+                    "MM" => 1.0,
+                    "IN" => 25.4,
+                    _ => return Err(format!("Invalid unit {:?}", args)),
+                };
+            }
+            "AD" => self.define_aperture(args)?,
+            "LP" if args == "C" => {
+                return Err("Clear polarity (%LPC%) is not supported".into());
+            }
+            "SR" if args.is_empty() => {}
+            "SR" => return Err("Step and repeat (%SR%) is not supported".into()),
+            "AB" => return Err("Block apertures (%AB%) are not supported".into()),
+            // Attributes, aperture macros (rejected when used) and deprecated
+            // image parameters without effect on the geometry
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handle an aperture definition like `D10C,0.5`.
+    fn define_aperture(&mut self, definition: &str) -> Result<(), String> {
+        let definition = definition
+            .strip_prefix('D')
+            .ok_or_else(|| format!("Invalid aperture definition {:?}", definition))?;
+        let (number, template) = split_number(definition);
+        let number: u32 = number
+            .parse()
+            .map_err(|_| format!("Invalid aperture number {:?}", number))?;
+        let (name, params) = template.split_once(',').unwrap_or((template, ""));
+        let params = params
+            .split('X')
+            .filter(|param| !param.is_empty())
+            .map(|param| decimal(param).map(|value| value * self.format.unit))
+            .collect::<Result<Vec<f64>, String>>()?;
+        let param = |index: usize| {
+            params
+                .get(index)
+                .copied()
+                .ok_or_else(|| format!("Missing parameter for aperture D{}", number))
+        };
+        let origin = Point::new(0.0, 0.0);
+        let aperture = match name {
+            "C" => Aperture::Shape(circle_outline(origin, param(0)? / 2.0, self.tolerance)),
+            "R" => {
+                let (w, h) = (param(0)? / 2.0, param(1)? / 2.0);
+                Aperture::Shape(vec![
+                    Point::new(-w, -h),
+                    Point::new(w, -h),
+                    Point::new(w, h),
+                    Point::new(-w, h),
+                ])
+            }
+            "O" => {
+                let (w, h) = (param(0)?, param(1)?);
+                let radius = w.min(h) / 2.0;
+                let (dx, dy) = ((w / 2.0 - radius).max(0.0), (h / 2.0 - radius).max(0.0));
+                let mut points = circle_outline(Point::new(-dx, -dy), radius, self.tolerance);
+                points.extend(circle_outline(Point::new(dx, dy), radius, self.tolerance));
+                Aperture::Shape(geometry::convex_hull(&points))
+            }
+            "P" => {
+                // The vertex count and rotation are not in the unit
+                let radius = param(0)? / 2.0;
+                let vertices = params.get(1).map_or(0.0, |v| v / self.format.unit) as usize;
+                if vertices < 3 {
+                    return Err(format!("Invalid polygon aperture D{}", number));
+                }
+                let rotation = params.get(2).map_or(0.0, |r| r / self.format.unit);
+                Aperture::Shape(
+                    (0..vertices)
+                        .map(|i| {
+                            let angle =
+                                rotation.to_radians() + 2.0 * PI * i as f64 / vertices as f64;
+                            Point::new(radius * angle.cos(), radius * angle.sin())
+                        })
+                        .collect(),
+                )
+            }
+            _ => Aperture::Unsupported(name.to_string()),
+        };
+        self.apertures.insert(number, aperture);
+        Ok(())
+    }
+
+    /// Outline of the current aperture.
+    fn aperture_shape(&self) -> Result<&Polyline, String> {
+        let number = self.aperture.ok_or("No aperture selected")?;
+        match self.apertures.get(&number) {
+            Some(Aperture::Shape(shape)) => Ok(shape),
+            Some(Aperture::Unsupported(name)) => Err(format!(
+                "Aperture D{} uses the aperture macro {:?}, which is not supported",
+                number, name
+            )),
+            None => Err(format!("Undefined aperture D{}", number)),
+        }
+    }
+
+    /// Parse a coordinate value according to the format specification.
+    fn coordinate(&self, value: &str) -> Result<f64, String> {
+        let (sign, digits) = match value.strip_prefix('-') {
+            Some(digits) => (-1.0, digits),
+            None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let mut digits = digits.to_string();
+        if self.format.trailing_zeros_omitted {
+            let length = self.format.integer_digits + self.format.decimal_digits;
+            while digits.len() < length {
+                digits.push('0');
+            }
+        }
+        let integer: f64 = digits
+            .parse()
+            .map_err(|_| format!("Invalid coordinate {:?}", value))?;
+        Ok(sign * integer / 10f64.powi(self.format.decimal_digits as i32) * self.format.unit)
+    }
+
+    /// Handle a word command like `G01X100Y200D01`. Returns `true` at the end
+    /// of the file.
+    fn word_command(&mut self, command: &str) -> Result<bool, String> {
+        let mut rest = command;
+        while let Some(code) = rest.strip_prefix('G') {
+            let (number, after) = split_number(code);
+            match number.parse::<u32>() {
+                Ok(1) => self.interpolation = Interpolation::Linear,
+                Ok(2) => self.interpolation = Interpolation::Clockwise,
+                Ok(3) => self.interpolation = Interpolation::CounterClockwise,
+                Ok(36) => self.region = Some(vec![]),
+                Ok(37) => {
+                    for mut contour in self.region.take().unwrap_or_default() {
+                        if contour.len() > 2 {
+                            if !geometry::is_closed(&contour) {
+                                contour.push(contour[0]);
+                            }
+                            self.polylines.push(contour);
+                        }
+                    }
+                }
+                Ok(74) => self.multi_quadrant = false,
+                Ok(75) => self.multi_quadrant = true,
+                Ok(70) => self.format.unit = 25.4,
+                Ok(71) => self.format.unit = 1.0,
+                Ok(91) => return Err("Incremental coordinates are not supported".into()),
+                Ok(_) => {}
+                Err(_) => return Err(format!("Invalid command {:?}", command)),
+            }
+            rest = after;
+        }
+        if rest.starts_with("M02") || rest.starts_with("M00") {
+            return Ok(true);
+        }
+        if rest.is_empty() || rest.starts_with('M') {
+            return Ok(false);
+        }
+
+        let (mut x, mut y, mut i, mut j, mut operation) = (None, None, 0.0, 0.0, None);
+        while let Some(letter) = rest.chars().next() {
+            let (value, after) = split_number(&rest[letter.len_utf8()..]);
+            match letter {
+                'X' => x = Some(self.coordinate(value)?),
+                'Y' => y = Some(self.coordinate(value)?),
+                'I' => i = self.coordinate(value)?,
+                'J' => j = self.coordinate(value)?,
+                'D' => {
+                    operation = Some(
+                        value
+                            .parse::<u32>()
+                            .map_err(|_| format!("Invalid command {:?}", command))?,
+                    )
+                }
+                _ => return Err(format!("Invalid command {:?}", command)),
+            }
+            rest = after;
+        }
+        let target = Point::new(x.unwrap_or(self.position.x), y.unwrap_or(self.position.y));
+        match operation {
+            Some(1) | None => self.interpolate(target, Point::new(i, j))?,
+            Some(2) => {
+                if let Some(region) = &mut self.region {
+                    region.push(vec![target]);
+                }
+            }
+            Some(3) => {
+                let flash = self
+                    .aperture_shape()?
+                    .iter()
+                    .map(|p| Point::new(p.x + target.x, p.y + target.y))
+                    .collect();
+                self.push_shape(flash);
+            }
+            Some(number) if number >= 10 => self.aperture = Some(number),
+            Some(number) => return Err(format!("Unsupported operation D{:02}", number)),
+        }
+        self.position = target;
+        Ok(false)
+    }
+
+    /// Draw from the current position to the target (D01).
+    fn interpolate(&mut self, target: Point, offset: Point) -> Result<(), String> {
+        let start = self.position;
+        let path = match self.interpolation {
+            Interpolation::Linear => vec![start, target],
+            interpolation => {
+                if !self.multi_quadrant {
+                    return Err("Single quadrant arcs (G74) are not supported".into());
+                }
+                let center = Point::new(start.x + offset.x, start.y + offset.y);
+                let radius = offset.x.hypot(offset.y);
+                let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+                let mut sweep = (angle(target) - angle(start)).rem_euclid(2.0 * PI);
+                if interpolation == Interpolation::Clockwise {
+                    sweep -= 2.0 * PI;
+                }
+                if start == target {
+                    sweep = if sweep < 0.0 { -2.0 * PI } else { 2.0 * PI };
+                }
+                let mut arc = geometry::arc(center, radius, angle(start), sweep, self.tolerance);
+                let last = arc.len() - 1;
+                arc[last] = target;
+                arc
+            }
+        };
+        if let Some(region) = &mut self.region {
+            match region.last_mut() {
+                Some(contour) => contour.extend_from_slice(&path[1..]),
+                None => region.push(path),
+            }
+            return Ok(());
+        }
+        let shape = self.aperture_shape()?.clone();
+        for segment in path.windows(2) {
+            let mut points: Polyline = shape
+                .iter()
+                .map(|p| Point::new(p.x + segment[0].x, p.y + segment[0].y))
+                .collect();
+            points.extend(
+                shape
+                    .iter()
+                    .map(|p| Point::new(p.x + segment[1].x, p.y + segment[1].y)),
+            );
+            self.push_shape(geometry::convex_hull(&points));
+        }
+        Ok(())
+    }
+
+    /// Add an open outline as a closed polyline.
+    fn push_shape(&mut self, mut outline: Polyline) {
+        if outline.len() > 2 {
+            outline.push(outline[0]);
+            self.polylines.push(outline);
+        }
+    }
+}
+
+/// Outline of a circle (without repeating the first point).
+fn circle_outline(center: Point, radius: f64, tolerance: f64) -> Polyline {
+    let mut outline = geometry::circle(center, radius, tolerance);
+    outline.pop();
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTWORK: &str = "G04 Test artwork*
+%FSLAX26Y26*%
+%MOMM*%
+%ADD10C,1.0*%
+%ADD11R,2.0X1.0*%
+%AMTHERMAL*1,1,$1,0,0*%
+%ADD12THERMAL,1.0*%
+D11*
+X5000000Y5000000D03*
+D10*
+X0Y0D02*
+G01*
+X10000000Y0D01*
+G36*
+X0Y0D02*
+X1000000Y0D01*
+Y1000000D01*
+X0D01*
+Y0D01*
+G37*
+M02*
+";
+
+    #[test]
+    fn test_parse() {
+        let polylines = parse(ARTWORK, 0.1).unwrap();
+        assert_eq!(polylines.len(), 3);
+        assert!(polylines.iter().all(|p| geometry::is_closed(p)));
+
+        // Flash of the rectangle aperture (y-axis inverted)
+        assert_eq!(geometry::area(&polylines[0]), 2.0);
+        assert!(polylines[0].contains(&Point::new(6.0, -5.5)));
+
+        // Stroke with round ends
+        let bounds = |polyline: &Polyline| {
+            polyline.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+                (min.min(p.x), max.max(p.x))
+            })
+        };
+        let (x_min, x_max) = bounds(&polylines[1]);
+        assert!((x_min + 0.5).abs() < 1e-9 && (x_max - 10.5).abs() < 1e-9);
+
+        // Region
+        assert_eq!(polylines[2].len(), 5);
+        assert_eq!(geometry::area(&polylines[2]), 1.0);
+    }
+
+    #[test]
+    fn test_arc() {
+        let arc = "%FSLAX26Y26*%%MOMM*%%ADD10C,0.1*%D10*G75*X1000000Y0D02*\
+                   G03X-1000000Y0I-1000000J0D01*M02*";
+        let polylines = parse(arc, 0.01).unwrap();
+        // Counterclockwise from (1, 0) to (-1, 0) via (0, 1), y-axis inverted
+        let points: Vec<Point> = polylines.iter().flatten().copied().collect();
+        assert!(points.iter().any(|p| p.x.abs() < 0.1 && p.y < -0.9));
+        assert!(points.iter().all(|p| p.y < 0.1));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let macro_flash = ARTWORK.replace("D11*\n", "D12*\n");
+        assert!(parse(&macro_flash, 0.1).is_err());
+        assert!(parse("%FSLAX26Y26*%%LPC*%", 0.1).is_err());
+        assert!(parse("%FSLAX26Y26*%X0Y0D03*", 0.1).is_err());
+        // Non-ASCII letters are invalid commands
+        assert!(parse("%FSLAX26Y26*%\n%MOMM*%\né1*\n", 0.1).is_err());
+    }
+}
@@ -0,0 +1,544 @@
+//! Import and export of the graphics of KiCad footprints (`.kicad_mod`).
+//!
+//! When importing, lines, rectangles, circles, arcs and polygons are
+//! converted, both in the current format and the legacy `(module …)` format
+//! of KiCad 5. Lines and outlines with a stroke width are converted to filled
+//! polygons with round ends, like KiCad draws them. Texts and pads are
+//! ignored. KiCad uses millimeters with the y-axis pointing down, like SVG
+//! documents.
+//!
+//! Exported footprints use the format of KiCad 7.
+
+use std::f64::consts::PI;
+
+use crate::{
+    escape_string,
+    export::{self, Footprint, Text},
+    format_float,
+    geometry::{self, LineCap, LineJoin, Point, Polyline, Stroke, Vertex},
+    layers::Layer,
+    sexpr::{self, List},
+    STROKE_WIDTH_RATIO,
+};
+
+/// Convert the graphics of a footprint, optionally only those on the given
+/// layers (e.g. `F.SilkS`).
+pub fn parse(source: &str, layers: &[String], tolerance: f64) -> Result<Vec<Polyline>, String> {
+    let root = sexpr::parse(source)?;
+    if !matches!(root.name(), Some("footprint") | Some("module")) {
+        return Err("Not a KiCad footprint".into());
+    }
+    let mut polylines = vec![];
+    for item in root.lists() {
+        let layer = item.child("layer").and_then(|layer| layer.arg(0));
+        let selected = match layer {
+            Some(layer) => layers.is_empty() || layers.iter().any(|l| l == layer),
+            None => false,
+        };
+        if !selected {
+            continue;
+        }
+        // Lines with a width are drawn with round ends and corners
+        let stroke = match stroke_width(item)? {
+            width if width > 0.0 => Some(Stroke {
+                width,
+                cap: LineCap::Round,
+                join: LineJoin::Round,
+            }),
+            _ => None,
+        };
+        match item.name() {
+            Some("fp_line") => {
+                let line = vec![point(item, "start")?, point(item, "end")?];
+                add_line(&mut polylines, line, stroke.as_ref(), tolerance);
+            }
+            Some("fp_rect") => {
+                let (a, b) = (point(item, "start")?, point(item, "end")?);
+                let corners = vec![a, Point::new(b.x, a.y), b, Point::new(a.x, b.y), a];
+                let filled = is_filled(item, false);
+                add_shape(&mut polylines, corners, filled, stroke.as_ref(), tolerance);
+            }
+            Some("fp_circle") => {
+                let (center, end) = (point(item, "center")?, point(item, "end")?);
+                let radius = (end.x - center.x).hypot(end.y - center.y);
+                let circle = geometry::circle(center, radius, tolerance);
+                let filled = is_filled(item, false);
+                add_shape(&mut polylines, circle, filled, stroke.as_ref(), tolerance);
+            }
+            Some("fp_arc") => {
+                let arc = arc(item, tolerance)?;
+                add_line(&mut polylines, arc, stroke.as_ref(), tolerance);
+            }
+            Some("fp_poly") => {
+                let mut points = item
+                    .child("pts")
+                    .ok_or("Polygon without points")?
+                    .lists()
+                    .map(|xy| Ok(Point::new(number(xy, 0)?, number(xy, 1)?)))
+                    .collect::<Result<Polyline, String>>()?;
+                if points.len() < 2 {
+                    continue;
+                }
+                if !geometry::is_closed(&points) {
+                    points.push(points[0]);
+                }
+                // Polygons were always filled before KiCad 6
+                let filled = is_filled(item, true);
+                add_shape(&mut polylines, points, filled, stroke.as_ref(), tolerance);
+            }
+            _ => {}
+        }
+    }
+    Ok(polylines)
+}
+
+/// Numeric argument of a list.
+fn number(list: &List, index: usize) -> Result<f64, String> {
+    let value = list.arg(index).unwrap_or("");
+    value.parse().map_err(|_| {
+        format!(
+            "Invalid number {:?} in {}",
+            value,
+            list.name().unwrap_or("list")
+        )
+    })
+}
+
+/// Point given by a child list like `(start 1.0 2.0)`.
+fn point(item: &List, name: &str) -> Result<Point, String> {
+    let list = item.child(name).ok_or_else(|| {
+        format!(
+            "Missing {} in {}",
+            name,
+            item.name().unwrap_or("graphic item")
+        )
+    })?;
+    Ok(Point::new(number(list, 0)?, number(list, 1)?))
+}
+
+/// Whether a closed shape is filled (`(fill solid)` or `(fill yes)`).
+fn is_filled(item: &List, default: bool) -> bool {
+    match item.child("fill").and_then(|fill| fill.arg(0)) {
+        Some(fill) => matches!(fill, "solid" | "yes" | "true"),
+        None => default,
+    }
+}
+
+/// Stroke width of a graphic item, given by `(stroke (width 0.12))` or
+/// (before KiCad 7) by `(width 0.12)`. Items without a width are drawn as
+/// thin lines.
+fn stroke_width(item: &List) -> Result<f64, String> {
+    let width = item
+        .child("stroke")
+        .and_then(|stroke| stroke.child("width"))
+        .or_else(|| item.child("width"));
+    match width {
+        Some(width) => number(width, 0),
+        None => Ok(0.0),
+    }
+}
+
+/// Add an open line, as the area covered by its stroke.
+fn add_line(
+    polylines: &mut Vec<Polyline>,
+    points: Polyline,
+    stroke: Option<&Stroke>,
+    tolerance: f64,
+) {
+    match stroke {
+        Some(stroke) => polylines.extend(geometry::outline(&[points], stroke, tolerance)),
+        None => polylines.push(points),
+    }
+}
+
+/// Add a closed shape, filled and/or with its outline.
+///
+/// Closed polylines are always filled, so thin outlines are split into two
+/// open polylines.
+fn add_shape(
+    polylines: &mut Vec<Polyline>,
+    points: Polyline,
+    filled: bool,
+    stroke: Option<&Stroke>,
+    tolerance: f64,
+) {
+    if let Some(stroke) = stroke {
+        polylines.extend(geometry::outline(
+            std::slice::from_ref(&points),
+            stroke,
+            tolerance,
+        ));
+    } else if !filled {
+        let last = points.len() - 1;
+        polylines.push(points[..last].to_vec());
+        polylines.push(vec![points[last - 1], points[last]]);
+    }
+    if filled {
+        polylines.push(points);
+    }
+}
+
+/// Flatten an arc, given by start, mid and end point or (before KiCad 6) by
+/// center, start point and angle in degrees.
+fn arc(item: &List, tolerance: f64) -> Result<Polyline, String> {
+    if let Some(angle) = item.child("angle") {
+        let (center, start) = (point(item, "start")?, point(item, "end")?);
+        let radius = (start.x - center.x).hypot(start.y - center.y);
+        let start_angle = (start.y - center.y).atan2(start.x - center.x);
+        let sweep = number(angle, 0)?.to_radians();
+        return Ok(geometry::arc(center, radius, start_angle, sweep, tolerance));
+    }
+
+    let (start, mid, end) = (
+        point(item, "start")?,
+        point(item, "mid")?,
+        point(item, "end")?,
+    );
+    let center = geometry::circumcenter(start, mid, end).ok_or("Arc with collinear points")?;
+    let radius = (start.x - center.x).hypot(start.y - center.y);
+    let angle = |p: Point| (p.y - center.y).atan2(p.x - center.x);
+    let normalize = |a: f64| a.rem_euclid(2.0 * PI);
+    let start_angle = angle(start);
+    let mut sweep = normalize(angle(end) - start_angle);
+    if normalize(angle(mid) - start_angle) > sweep {
+        // The mid point is not on the positive sweep, go the other way round
+        sweep -= 2.0 * PI;
+    }
+    Ok(geometry::arc(center, radius, start_angle, sweep, tolerance))
+}
+
+/// Stroke width of open paths, like the generated LibrePCB polygons.
+const LINE_WIDTH: &str = "0.2";
+
+/// The KiCad layer corresponding to a board layer.
+pub fn layer_name(layer: Layer) -> &'static str {
+    match layer {
+        Layer::TopCopper => "F.Cu",
+        Layer::BotCopper => "B.Cu",
+        Layer::TopLegend | Layer::TopNames => "F.SilkS",
+        Layer::BotLegend | Layer::BotNames => "B.SilkS",
+        Layer::TopStopMask => "F.Mask",
+        Layer::BotStopMask => "B.Mask",
+        Layer::TopSolderPaste => "F.Paste",
+        Layer::BotSolderPaste => "B.Paste",
+        Layer::TopGlue => "F.Adhes",
+        Layer::BotGlue => "B.Adhes",
+        Layer::TopDocumentation | Layer::TopValues => "F.Fab",
+        Layer::BotDocumentation | Layer::BotValues => "B.Fab",
+        Layer::TopCourtyard => "F.CrtYd",
+        Layer::BotCourtyard => "B.CrtYd",
+    }
+}
+
+/// Generate a footprint (the content of a `.kicad_mod` file). Reference and
+/// value texts are added, but hidden.
+pub fn make_footprint(footprint: &Footprint, description: &str, tags: &str) -> Vec<String> {
+    let (side, silkscreen, fab) = match footprint.bottom {
+        false => ("F.Cu", "F.SilkS", "F.Fab"),
+        true => ("B.Cu", "B.SilkS", "B.Fab"),
+    };
+    let mut lines = vec![];
+    lines.push(format!(
+        r#"(footprint "{}" (version 20221018) (generator svg2librepcb)"#,
+        escape_string(&footprint.name)
+    ));
+    lines.push(format!(r#"  (layer "{}")"#, side));
+    if !description.is_empty() {
+        lines.push(format!(r#"  (descr "{}")"#, escape_string(description)));
+    }
+    if !tags.is_empty() {
+        lines.push(format!(r#"  (tags "{}")"#, escape_string(tags)));
+    }
+    lines.push("  (attr board_only exclude_from_pos_files exclude_from_bom)".to_string());
+    let name = footprint.name.as_str();
+    for (kind, value, layer) in [("reference", "REF**", silkscreen), ("value", name, fab)] {
+        lines.push(format!(
+            r#"  (fp_text {} "{}" (at 0 0) (layer "{}") hide"#,
+            kind,
+            escape_string(value),
+            layer
+        ));
+        lines.push("    (effects (font (size 1 1) (thickness 0.15)))".to_string());
+        lines.push("  )".to_string());
+    }
+
+    // KiCad uses the y-axis of SVG documents
+    for (layer, paths) in &footprint.layers {
+        let paths: Vec<geometry::Path> = paths.iter().map(|path| export::invert_y(path)).collect();
+        lines.extend(make_shapes(layer_name(*layer), &paths));
+    }
+    for (position, diameter) in &footprint.holes {
+        lines.push(format!(
+            r#"  (pad "" np_thru_hole circle (at {}) (size {d} {d}) (drill {d}) (layers "*.Cu" "*.Mask"))"#,
+            xy(*position),
+            d = format_float(*diameter)
+        ));
+    }
+    for text in &footprint.texts {
+        lines.extend(make_text(text));
+    }
+
+    // Fiducials are pads with a stop mask opening of twice their diameter
+    let layers = match footprint.bottom {
+        false => r#""F.Cu" "F.Mask""#,
+        true => r#""B.Cu" "B.Mask""#,
+    };
+    for (position, diameter) in &footprint.fiducials {
+        lines.push(format!(
+            r#"  (pad "" smd circle (at {}) (size {d} {d}) (layers {}) (solder_mask_margin {}))"#,
+            xy(*position),
+            layers,
+            format_float(diameter / 2.0),
+            d = format_float(*diameter)
+        ));
+    }
+    lines.push(")".to_string());
+    lines
+}
+
+/// Format a point in LibrePCB coordinates as KiCad coordinates.
+fn xy(point: Point) -> String {
+    format!("{} {}", format_float(point.x), format_float(-point.y))
+}
+
+/// Generate the graphics for the paths (in KiCad coordinates) on a layer.
+/// Closed paths become filled polygons, open paths lines and arcs.
+fn make_shapes(layer: &str, paths: &[geometry::Path]) -> Vec<String> {
+    let xy = |point: Point| format!("{} {}", format_float(point.x), format_float(point.y));
+    let mut lines = vec![];
+    for path in paths {
+        if geometry::is_closed(&geometry::points(path)) {
+            lines.push("  (fp_poly".to_string());
+            lines.push("    (pts".to_string());
+            for segment in path.windows(2) {
+                let (start, end) = (segment[0], segment[1]);
+                if start.angle == 0.0 {
+                    lines.push(format!("      (xy {})", xy(start.position)));
+                } else {
+                    lines.push(format!(
+                        "      (arc (start {}) (mid {}) (end {}))",
+                        xy(start.position),
+                        xy(arc_mid(start, end.position)),
+                        xy(end.position)
+                    ));
+                }
+            }
+            lines.push("    )".to_string());
+            lines.push(format!(
+                r#"    (stroke (width 0) (type solid)) (fill solid) (layer "{}")"#,
+                layer
+            ));
+            lines.push("  )".to_string());
+            continue;
+        }
+        for segment in path.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let shape = if start.angle == 0.0 {
+                format!(
+                    "(fp_line (start {}) (end {})",
+                    xy(start.position),
+                    xy(end.position)
+                )
+            } else {
+                format!(
+                    "(fp_arc (start {}) (mid {}) (end {})",
+                    xy(start.position),
+                    xy(arc_mid(start, end.position)),
+                    xy(end.position)
+                )
+            };
+            lines.push(format!(
+                r#"  {} (stroke (width {}) (type solid)) (layer "{}"))"#,
+                shape, LINE_WIDTH, layer
+            ));
+        }
+    }
+    lines
+}
+
+/// Mid point of the arc segment from a vertex to the end point.
+fn arc_mid(start: Vertex, end: Point) -> Point {
+    let (a, b) = (start.position, end);
+    let bulge = (start.angle.to_radians() / 4.0).tan() / 2.0;
+    Point::new(
+        (a.x + b.x) / 2.0 + (b.y - a.y) * bulge,
+        (a.y + b.y) / 2.0 - (b.x - a.x) * bulge,
+    )
+}
+
+/// Generate a user text. The alignment is translated to KiCad's
+/// justification, where the default is centered.
+fn make_text(text: &Text) -> Vec<String> {
+    let mut justify: Vec<&str> = text
+        .align
+        .split_whitespace()
+        .filter(|a| *a != "center")
+        .collect();
+    if text.mirror {
+        justify.push("mirror");
+    }
+    let justify = match justify.is_empty() {
+        true => String::new(),
+        false => format!(" (justify {})", justify.join(" ")),
+    };
+    vec![
+        format!(
+            r#"  (fp_text user "{}" (at {} {}) (layer "{}")"#,
+            escape_string(&text.value),
+            xy(text.position),
+            format_float(text.rotation),
+            layer_name(text.layer)
+        ),
+        format!(
+            "    (effects (font (size {h} {h}) (thickness {})){})",
+            format_float(text.height * STROKE_WIDTH_RATIO),
+            justify,
+            h = format_float(text.height)
+        ),
+        "  )".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOOTPRINT: &str = r#"(footprint "Logo" (version 20221018) (generator pcbnew)
+  (layer "F.Cu")
+  (fp_text reference "REF**" (at 0 0) (layer "F.SilkS"))
+  (fp_line (start 0 0) (end 1 0) (stroke (width 0.12) (type solid)) (layer "F.SilkS"))
+  (fp_rect (start 0 0) (end 2 1) (stroke (width 0.1)) (fill none) (layer "F.Fab"))
+  (fp_poly (pts (xy 0 0) (xy 1 0) (xy 1 1)) (stroke (width 0)) (fill solid) (layer "F.Cu"))
+  (fp_arc (start 1 0) (mid 0 1) (end -1 0) (stroke (width 0.1)) (layer "F.SilkS"))
+)"#;
+
+    #[test]
+    fn test_parse() {
+        // Thin lines are kept as lines
+        let thin = FOOTPRINT
+            .replace("(width 0.12)", "(width 0)")
+            .replace("(width 0.1)", "(width 0)");
+        let polylines = parse(&thin, &[], 0.01).unwrap();
+        // Line, rectangle outline (2 parts), polygon and arc
+        assert_eq!(polylines.len(), 5);
+        assert_eq!(
+            polylines[0],
+            vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0)]
+        );
+        assert!(!geometry::is_closed(&polylines[1]));
+        assert_eq!(polylines[3].len(), 4);
+        assert!(geometry::is_closed(&polylines[3]));
+
+        // The arc runs through the mid point at (0, 1)
+        let arc = &polylines[4];
+        assert!(arc.iter().all(|p| (p.x.hypot(p.y) - 1.0).abs() < 1e-9));
+        assert!(arc.iter().all(|p| p.y > -1e-9));
+
+        let silkscreen = parse(&thin, &["F.SilkS".to_string()], 0.01).unwrap();
+        assert_eq!(silkscreen.len(), 2);
+    }
+
+    #[test]
+    fn test_stroke_width() {
+        // Lines and outlines with a width are converted to their area
+        let polylines = parse(FOOTPRINT, &[], 0.01).unwrap();
+        assert!(polylines
+            .iter()
+            .all(|polyline| geometry::is_closed(polyline)));
+        let line = parse(FOOTPRINT, &["F.SilkS".to_string()], 0.01).unwrap();
+        let bounds = geometry::bounds(&line).unwrap();
+        assert!((bounds.x_min + 1.05).abs() < 1e-9);
+        assert!((bounds.x_max - 1.06).abs() < 1e-9);
+        assert!((bounds.y_min + 0.06).abs() < 1e-9);
+        assert!((bounds.y_max - 1.05).abs() < 1e-3);
+
+        // The rectangle outline is a ring around the rectangle
+        let outline = parse(FOOTPRINT, &["F.Fab".to_string()], 0.01).unwrap();
+        let bounds = geometry::bounds(&outline).unwrap();
+        assert!((bounds.x_min + 0.05).abs() < 1e-9);
+        assert!((bounds.x_max - 2.05).abs() < 1e-9);
+        let area: f64 = outline
+            .iter()
+            .map(|polyline| geometry::area(polyline))
+            .sum();
+        assert!(area < 2.0 * 0.1 * 3.1);
+
+        // Invalid widths are rejected
+        let invalid = "(footprint x (fp_line (start 0 0) (end 1 1) (width a) (layer F.Cu)))";
+        assert!(parse(invalid, &[], 0.1).is_err());
+    }
+
+    #[test]
+    fn test_legacy_arc() {
+        let module = "(module Logo (layer F.Cu)
+          (fp_arc (start 0 0) (end 1 0) (angle -90) (layer F.SilkS) (width 0.15)))";
+        let polylines = parse(&module.replace("0.15", "0"), &[], 0.01).unwrap();
+        let end = polylines[0][polylines[0].len() - 1];
+        assert!(end.x.abs() < 1e-9);
+        assert!((end.y + 1.0).abs() < 1e-9);
+
+        // The width is given without a stroke
+        let polylines = parse(module, &[], 0.01).unwrap();
+        let radii = || polylines.iter().flatten().map(|p| p.x.hypot(p.y));
+        assert!((radii().fold(0.0, f64::max) - 1.075).abs() < 1e-3);
+        assert!((radii().fold(2.0, f64::min) - 0.925).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_export() {
+        let square = geometry::to_path(&[
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(0.0, 1.0),
+            Point::new(0.0, 0.0),
+        ]);
+        let line = geometry::to_path(&[Point::new(0.0, 0.0), Point::new(2.0, 0.0)]);
+        let footprint = Footprint {
+            name: "Logo".to_string(),
+            bottom: false,
+            layers: vec![(Layer::TopCopper, vec![square, line])],
+            holes: vec![(Point::new(0.0, 1.0), 0.5)],
+            texts: vec![],
+            fiducials: vec![],
+        };
+        let lines = make_footprint(&footprint, "", "");
+        assert!(lines.contains(
+            &r#"  (pad "" np_thru_hole circle (at 0.0 -1.0) (size 0.5 0.5) (drill 0.5) (layers "*.Cu" "*.Mask"))"#
+                .to_string()
+        ));
+
+        // The generated footprint can be imported again (with the y-axis
+        // pointing down), with the width of the line
+        let polylines = parse(&lines.join("\n"), &[], 0.01).unwrap();
+        assert!(geometry::is_closed(&polylines[0]));
+        assert_eq!(polylines[0][2], Point::new(1.0, -1.0));
+        let line = geometry::bounds(&polylines[1..]).unwrap();
+        assert!((line.x_min + 0.1).abs() < 1e-9);
+        assert!((line.x_max - 2.1).abs() < 1e-9);
+        assert!((line.y_max - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_arc_mid() {
+        // Quarter circle around the origin, from the x-axis to the y-axis
+        let mid = arc_mid(
+            Vertex::new(Point::new(1.0, 0.0), 90.0),
+            Point::new(0.0, 1.0),
+        );
+        let expected = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((mid.x - expected).abs() < 1e-9);
+        assert!((mid.y - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("(kicad_pcb)", &[], 0.1).is_err());
+        assert!(parse(
+            "(footprint x (fp_line (start 0 a) (end 1 1) (layer F.Cu)))",
+            &[],
+            0.1
+        )
+        .is_err());
+    }
+}
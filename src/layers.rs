@@ -0,0 +1,164 @@
+//! Board layer names for the different LibrePCB versions.
+//!
+//! LibrePCB 1.0 renamed the placement layers to legend layers
+//! (`top_placement` became `top_legend`), the other layers (e.g. `top_cu`)
+//! kept their names. Internally, layers are referenced through [`Layer`] and
+//! only translated to a name when generating the output.
+
+/// Which set of layer names to emit.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+pub enum LayerNames {
+    /// Layer names used up to LibrePCB 0.1.x
+    Legacy,
+    /// Layer names used since LibrePCB 1.0
+    Modern,
+}
+
+/// A board layer.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Layer {
+    TopCopper,
+    BotCopper,
+    TopLegend,
+    BotLegend,
+    TopStopMask,
+    BotStopMask,
+    TopSolderPaste,
+    BotSolderPaste,
+    TopGlue,
+    BotGlue,
+    TopDocumentation,
+    BotDocumentation,
+    TopCourtyard,
+    BotCourtyard,
+    TopNames,
+    BotNames,
+    TopValues,
+    BotValues,
+}
+
+/// Translation table: Layer, legacy name, modern name.
+const LAYER_NAMES: [(Layer, &str, &str); 18] = [
+    (Layer::TopCopper, "top_cu", "top_cu"),
+    (Layer::BotCopper, "bot_cu", "bot_cu"),
+    (Layer::TopLegend, "top_placement", "top_legend"),
+    (Layer::BotLegend, "bot_placement", "bot_legend"),
+    (Layer::TopStopMask, "top_stop_mask", "top_stop_mask"),
+    (Layer::BotStopMask, "bot_stop_mask", "bot_stop_mask"),
+    (
+        Layer::TopSolderPaste,
+        "top_solder_paste",
+        "top_solder_paste",
+    ),
+    (
+        Layer::BotSolderPaste,
+        "bot_solder_paste",
+        "bot_solder_paste",
+    ),
+    (Layer::TopGlue, "top_glue", "top_glue"),
+    (Layer::BotGlue, "bot_glue", "bot_glue"),
+    (
+        Layer::TopDocumentation,
+        "top_documentation",
+        "top_documentation",
+    ),
+    (
+        Layer::BotDocumentation,
+        "bot_documentation",
+        "bot_documentation",
+    ),
+    (Layer::TopCourtyard, "top_courtyard", "top_courtyard"),
+    (Layer::BotCourtyard, "bot_courtyard", "bot_courtyard"),
+    (Layer::TopNames, "top_names", "top_names"),
+    (Layer::BotNames, "bot_names", "bot_names"),
+    (Layer::TopValues, "top_values", "top_values"),
+    (Layer::BotValues, "bot_values", "bot_values"),
+];
+
+impl Layer {
+    /// Return the name of this layer in the requested naming scheme.
+    pub fn name(self, names: LayerNames) -> &'static str {
+        let (_, legacy, modern) = LAYER_NAMES
+            .iter()
+            .find(|(layer, _, _)| *layer == self)
+            .expect("Layer missing in translation table");
+        match names {
+            LayerNames::Legacy => legacy,
+            LayerNames::Modern => modern,
+        }
+    }
+
+    /// Look up a layer by its legacy or modern name.
+    pub fn from_name(name: &str) -> Option<Layer> {
+        LAYER_NAMES
+            .iter()
+            .find(|(_, legacy, modern)| *legacy == name || *modern == name)
+            .map(|(layer, _, _)| *layer)
+    }
+
+    /// The corresponding layer on the other side of the board.
+    pub fn mirrored(self) -> Layer {
+        match self {
+            Layer::TopCopper => Layer::BotCopper,
+            Layer::BotCopper => Layer::TopCopper,
+            Layer::TopLegend => Layer::BotLegend,
+            Layer::BotLegend => Layer::TopLegend,
+            Layer::TopStopMask => Layer::BotStopMask,
+            Layer::BotStopMask => Layer::TopStopMask,
+            Layer::TopSolderPaste => Layer::BotSolderPaste,
+            Layer::BotSolderPaste => Layer::TopSolderPaste,
+            Layer::TopGlue => Layer::BotGlue,
+            Layer::BotGlue => Layer::TopGlue,
+            Layer::TopDocumentation => Layer::BotDocumentation,
+            Layer::BotDocumentation => Layer::TopDocumentation,
+            Layer::TopCourtyard => Layer::BotCourtyard,
+            Layer::BotCourtyard => Layer::TopCourtyard,
+            Layer::TopNames => Layer::BotNames,
+            Layer::BotNames => Layer::TopNames,
+            Layer::TopValues => Layer::BotValues,
+            Layer::BotValues => Layer::TopValues,
+        }
+    }
+
+    /// Human readable name, used e.g. for footprint names.
+    pub fn title(self) -> &'static str {
+        match self {
+            Layer::TopCopper => "Top Copper",
+            Layer::BotCopper => "Bottom Copper",
+            Layer::TopLegend => "Top Placement",
+            Layer::BotLegend => "Bottom Placement",
+            Layer::TopStopMask => "Top Stop Mask",
+            Layer::BotStopMask => "Bottom Stop Mask",
+            Layer::TopSolderPaste => "Top Solder Paste",
+            Layer::BotSolderPaste => "Bottom Solder Paste",
+            Layer::TopGlue => "Top Glue",
+            Layer::BotGlue => "Bottom Glue",
+            Layer::TopDocumentation => "Top Documentation",
+            Layer::BotDocumentation => "Bottom Documentation",
+            Layer::TopCourtyard => "Top Courtyard",
+            Layer::BotCourtyard => "Bottom Courtyard",
+            Layer::TopNames => "Top Names",
+            Layer::BotNames => "Bottom Names",
+            Layer::TopValues => "Top Values",
+            Layer::BotValues => "Bottom Values",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_names() {
+        assert_eq!(Layer::TopCopper.name(LayerNames::Legacy), "top_cu");
+        assert_eq!(Layer::TopCopper.name(LayerNames::Modern), "top_cu");
+        assert_eq!(Layer::TopLegend.name(LayerNames::Modern), "top_legend");
+        for (layer, legacy, modern) in LAYER_NAMES {
+            assert_eq!(Layer::from_name(legacy), Some(layer));
+            assert_eq!(Layer::from_name(modern), Some(layer));
+            assert_eq!(layer.mirrored().mirrored(), layer);
+        }
+        assert_eq!(Layer::from_name("sym_outlines"), None);
+    }
+}
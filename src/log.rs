@@ -0,0 +1,82 @@
+//! Verbose log of the conversion (`--verbose` and `--log-file`).
+//!
+//! The log describes the decisions taken for the input (e.g. which paths are
+//! skipped and which layers they end up on), followed by the warnings and
+//! errors. The log file receives it regardless of `--verbose`, which is useful
+//! when the console output is not visible (e.g. in Inkscape).
+
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::File,
+    io::{LineWriter, Write},
+    path::Path,
+};
+
+use crate::error::{Error, Result};
+
+struct Logger {
+    /// Print the log to stderr
+    verbose: bool,
+    file: Option<LineWriter<File>>,
+}
+
+thread_local! {
+    static LOGGER: RefCell<Logger> = const {
+        RefCell::new(Logger {
+            verbose: false,
+            file: None,
+        })
+    };
+}
+
+/// Start logging, creating (or truncating) the log file.
+pub fn init(verbose: bool, path: Option<&Path>) -> Result<()> {
+    // The Inkscape extension passes an empty path if no file is selected
+    let file = path
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(|path| {
+            File::create(path)
+                .map(LineWriter::new)
+                .map_err(|source| Error::WriteFile {
+                    path: path.to_owned(),
+                    source,
+                })
+        })
+        .transpose()?;
+    LOGGER.with(|logger| *logger.borrow_mut() = Logger { verbose, file });
+    Ok(())
+}
+
+/// Write a line to the log, use the `log!` macro instead.
+pub fn write(message: fmt::Arguments) {
+    LOGGER.with(|logger| {
+        let mut logger = logger.borrow_mut();
+        if logger.verbose {
+            eprintln!("{}", message);
+        }
+        if let Some(file) = &mut logger.file {
+            // The log must not abort the conversion
+            let _ = writeln!(file, "{}", message);
+        }
+    });
+}
+
+/// Write a line to the log file only, for messages which are printed
+/// anyway (e.g. warnings).
+pub fn write_file(message: fmt::Arguments) {
+    LOGGER.with(|logger| {
+        if let Some(file) = &mut logger.borrow_mut().file {
+            let _ = writeln!(file, "{}", message);
+        }
+    });
+}
+
+/// Write a line to the log, with `format!` arguments.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::log::write(format_args!($($arg)*))
+    };
+}
+
+pub(crate) use log;
@@ -2,35 +2,190 @@
 #![allow(clippy::useless_format)]
 
 use std::{
+    cell::Cell,
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    fmt,
     fs::{self, read_to_string},
+    io::{self, IsTerminal, Read},
+    ops::Range,
     path::{Path, PathBuf},
-    process::exit,
+    process::{exit, Command, Stdio},
+    sync::Arc,
 };
 
-use anyhow::{Context, Result};
-use chrono::Utc;
-use clap::{self, Parser};
-use svg2polylines::{self, Polyline};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use clap::{self, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use flate2::read::GzDecoder;
+use miette::{LabeledSpan, NamedSource};
 use uuid::Uuid;
 
-#[derive(Parser, Debug)]
-#[clap(author, version, about)]
+mod art;
+mod batch;
+mod canonical;
+mod config;
+mod convert;
+mod css;
+mod doctor;
+mod eagle;
+mod error;
+mod excellon;
+mod export;
+mod geometry;
+mod gerber;
+mod kicad;
+mod layers;
+mod log;
+mod metadata;
+mod picker;
+#[cfg(feature = "gui")]
+mod preview;
+mod provenance;
+mod remote;
+mod rules;
+mod settings;
+mod sexpr;
+mod svg;
+mod temp;
+mod template;
+mod update;
+mod validation;
+
+use config::{Config, LibraryConfig};
+use error::{Error, Level, Result, Warning};
+use geometry::{Hole, LineCap, LineJoin, Polyline};
+use layers::{Layer, LayerNames};
+use log::log;
+use metadata::JsonMetadata;
+use settings::Settings;
+use svg::Text;
+use temp::TempDir;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true,
+    subcommand_value_name = "COMMAND",
+    subcommand_help_heading = "COMMANDS"
+)]
 struct Args {
-    /// The SVG file to load
+    /// Run a command instead of converting a file
+    #[clap(subcommand)]
+    command: Option<Subcommand>,
+
+    /// The SVG file to load (PDF and AI files are converted to SVG with
+    /// pdftocairo or Inkscape, KiCad footprints and Gerber files are
+    /// supported as well), a directory to convert all contained files, or an
+    /// https:// URL to download the file from
+    #[clap(default_value = "", hide_default_value = true)]
     svgfile: PathBuf,
+    /// Read the SVG document from the clipboard instead of a file (e.g. an
+    /// object copied in Inkscape)
+    #[clap(long)]
+    clipboard: bool,
 
-    /// Output path
+    /// Output path (required, unless saved in the settings by a previous
+    /// conversion). May be repeated to write the elements into several
+    /// libraries, of which only the first is remembered
+    #[clap(long = "outpath", value_name = "OUTPATH", help_heading = "DIRECTORIES")]
+    outpaths: Vec<PathBuf>,
+    /// Create the output path if it does not exist, as a new LibrePCB
+    /// library named after the directory (or a plain directory with --format)
     #[clap(long, help_heading = "DIRECTORIES")]
-    outpath: PathBuf,
+    mkdirs: bool,
+    /// Config file (TOML)
+    #[clap(long, help_heading = "DIRECTORIES")]
+    config: Option<PathBuf>,
 
-    /// Resulting LibrePCB package name
-    #[clap(long, help_heading = "METADATA")]
+    /// Replace elements that already exist in the library
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "DIRECTORIES"
+    )]
+    force: bool,
+    /// Where to move replaced elements to
+    /// [default: <OUTPATH>/.svg2librepcb-backup]
+    #[clap(long, help_heading = "DIRECTORIES")]
+    backup_dir: Option<PathBuf>,
+    /// Do not carry over manual edits (e.g. pins, pads or additional
+    /// polygons) when replacing existing elements
+    #[clap(long, help_heading = "DIRECTORIES")]
+    discard_edits: bool,
+    /// Do not keep a backup of replaced elements
+    #[clap(long, conflicts_with = "backup-dir", help_heading = "DIRECTORIES")]
+    no_backup: bool,
+    /// Shell command to run after a successful conversion, with the
+    /// directories of the generated elements appended as arguments
+    #[clap(long, value_name = "COMMAND", help_heading = "DIRECTORIES")]
+    post_hook: Option<String>,
+    /// Verify the library with librepcb-cli after writing, which loads all
+    /// elements and reports broken ones (LibrePCB itself still needs to
+    /// rescan the libraries)
+    #[clap(long, help_heading = "DIRECTORIES")]
+    verify: bool,
+    /// Print the generated elements on stdout, for scripts
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    print: Option<PrintFormat>,
+    /// Store a copy of the input file and the options in the package
+    /// directory, to regenerate the package later
+    #[clap(long, help_heading = "DIRECTORIES")]
+    keep_source: bool,
+
+    /// Resulting LibrePCB package name (required, unless converting a
+    /// directory)
+    #[clap(
+        long,
+        default_value = "",
+        hide_default_value = true,
+        help_heading = "METADATA"
+    )]
     name: String,
+    /// Replace characters that are not allowed in LibrePCB element names and
+    /// shorten overly long names (with an ellipsis) instead of failing
+    #[clap(long, help_heading = "METADATA")]
+    sanitize_names: bool,
+    /// Generate the name from a template with the placeholders {file},
+    /// {stem}, {width}, {height} (of the footprint in mm) and {date}, e.g.
+    /// "Logo {stem} ({width}mm)"
+    #[clap(
+        long,
+        conflicts_with = "name",
+        value_name = "TEMPLATE",
+        help_heading = "METADATA"
+    )]
+    name_template: Option<String>,
     /// Resulting LibrePCB package description
     #[clap(long, default_value = "", help_heading = "METADATA")]
     description: String,
-    /// Resulting LibrePCB package author
-    #[clap(long, help_heading = "METADATA")]
+    /// Read the (possibly multi-line) package description from a file
+    #[clap(long, conflicts_with = "description", help_heading = "METADATA")]
+    description_file: Option<PathBuf>,
+    /// Generate the description from a template, with the same placeholders
+    /// as --name-template
+    #[clap(
+        long,
+        conflicts_with_all = &["description", "description-file"],
+        value_name = "TEMPLATE",
+        help_heading = "METADATA"
+    )]
+    description_template: Option<String>,
+    /// Resulting LibrePCB package author (required, unless saved in the
+    /// settings by a previous conversion)
+    #[clap(
+        long,
+        default_value = "",
+        hide_default_value = true,
+        help_heading = "METADATA"
+    )]
     author: String,
     /// Resulting LibrePCB package version
     #[clap(long, default_value = "0.1.0", help_heading = "METADATA")]
@@ -38,6 +193,115 @@ struct Args {
     /// Resulting LibrePCB package keywords
     #[clap(long, default_value = "", help_heading = "METADATA")]
     keywords: String,
+    /// Add keywords derived from the file name, the Inkscape layer names and
+    /// the content (e.g. "pcb art" and the size)
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "METADATA"
+    )]
+    auto_keywords: bool,
+    /// Use a different metadata value for one kind of element, e.g.
+    /// "symbol.description=Outline of the logo" (ELEMENT is symbol, component,
+    /// package or device, FIELD is description, keywords, author, version or
+    /// created; may be repeated)
+    #[clap(long, value_name = "ELEMENT.FIELD=VALUE", help_heading = "METADATA")]
+    element_metadata: Vec<String>,
+
+    /// All metadata as a JSON object, or @FILE to read it from a JSON file
+    /// (keys: name, description, author, version, keywords, prefix,
+    /// default-value, component-category, package-category)
+    #[clap(
+        long,
+        value_name = "JSON",
+        conflicts_with_all = &[
+            "name",
+            "description",
+            "description-file",
+            "author",
+            "version",
+            "keywords",
+            "prefix",
+            "default-value",
+            "uuid-cmpcat",
+            "uuid-pkgcat",
+        ],
+        help_heading = "METADATA"
+    )]
+    metadata_json: Option<String>,
+
+    /// CSV file with the metadata of the files when converting a directory
+    /// (columns: file, name, description, keywords, scale)
+    #[clap(long, help_heading = "METADATA")]
+    metadata_csv: Option<PathBuf>,
+    /// Write a summary of a directory conversion (the converted files with
+    /// their elements and warnings, and the skipped files) to a Markdown file,
+    /// or a JSON file if the name ends with .json
+    #[clap(long, value_name = "FILE", help_heading = "DIRECTORIES")]
+    report: Option<PathBuf>,
+
+    /// Resulting LibrePCB component prefix (e.g. "LOGO")
+    #[clap(long, default_value = "", help_heading = "METADATA")]
+    prefix: String,
+    /// Resulting LibrePCB component default value
+    #[clap(long, default_value = "", help_heading = "METADATA")]
+    default_value: String,
+    /// Add an attribute to the resulting LibrePCB component (e.g.
+    /// "ART_REVISION=B" or "VOLTAGE:voltage:millivolt=500", may be repeated)
+    #[clap(
+        long = "attribute",
+        value_name = "KEY[:TYPE[:UNIT]]=VALUE",
+        help_heading = "METADATA"
+    )]
+    attributes: Vec<String>,
+    /// Norm of the resulting LibrePCB component variant (e.g. "IEC 60617")
+    #[clap(long, default_value = "", help_heading = "METADATA")]
+    norm: String,
+    /// Name of the resulting LibrePCB component variant
+    #[clap(long, default_value = "default", help_heading = "METADATA")]
+    variant_name: String,
+    /// Description of the resulting LibrePCB component variant
+    #[clap(long, default_value = "", help_heading = "METADATA")]
+    variant_description: String,
+    /// Suffix of the gate of the component variant, appended to the
+    /// designator of the symbol in schematics (e.g. "A")
+    #[clap(long, default_value = "", help_heading = "METADATA")]
+    gate_suffix: String,
+    /// Whether the symbol of the component variant must be placed in the
+    /// schematic
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "true",
+        default_missing_value = "true",
+        help_heading = "METADATA"
+    )]
+    gate_required: bool,
+    /// Mark the resulting LibrePCB component as schematic-only
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "METADATA"
+    )]
+    schematic_only: bool,
+
+    /// Manufacturer part number of the resulting LibrePCB device (requires
+    /// --format-version 1)
+    #[clap(long, help_heading = "METADATA")]
+    mpn: Option<String>,
+    /// Manufacturer of the resulting LibrePCB device part
+    #[clap(long, requires = "mpn", help_heading = "METADATA")]
+    manufacturer: Option<String>,
 
     /// Resulting LibrePCB package UUID [default: random]
     #[clap(long, help_heading = "UUIDS")]
@@ -51,6 +315,32 @@ struct Args {
     /// Resulting LibrePCB device UUID [default: random]
     #[clap(long, help_heading = "UUIDS")]
     uuid_dev: Option<String>,
+    /// Seed for generating deterministic UUIDs (for all UUIDs not specified
+    /// explicitly, including those of polygons and texts)
+    #[clap(long, help_heading = "UUIDS")]
+    uuid_seed: Option<u64>,
+    /// Only generate a package and a device for this existing LibrePCB
+    /// component, instead of a new symbol and component
+    #[clap(
+        long,
+        value_name = "UUID",
+        conflicts_with_all = &[
+            "uuid-sym",
+            "uuid-cmp",
+            "symbol-snippet",
+            "attributes",
+            "prefix",
+            "default-value",
+            "schematic-only",
+            "norm",
+            "variant-name",
+            "gate-suffix",
+            "symbol-layer",
+            "bounding-box-symbol",
+        ],
+        help_heading = "UUIDS"
+    )]
+    existing_cmp: Option<String>,
     /// Resulting LibrePCB package category UUID
     #[clap(long, help_heading = "UUIDS")]
     uuid_pkgcat: Option<String>,
@@ -59,33 +349,1388 @@ struct Args {
     uuid_cmpcat: Option<String>,
 
     /// Generate copper layer
-    #[clap(long, default_value = "true", help_heading = "LAYERS")]
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        default_value = "true",
+        help_heading = "LAYERS"
+    )]
     layer_copper: bool,
     /// Generate placement layer
-    #[clap(long, default_value = "true", help_heading = "LAYERS")]
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        default_value = "true",
+        help_heading = "LAYERS"
+    )]
     layer_placement: bool,
     /// Generate stop mask layer
-    #[clap(long, default_value = "true", help_heading = "LAYERS")]
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        default_value = "true",
+        help_heading = "LAYERS"
+    )]
     layer_stopmask: bool,
+    /// Generate an additional footprint with the artwork as exposed copper,
+    /// i.e. on the copper and the stop mask layer
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "LAYERS"
+    )]
+    exposed_copper: bool,
+    /// Expansion of the stop mask opening of --exposed-copper in mm (negative
+    /// values shrink the opening)
+    #[clap(
+        long,
+        value_name = "MM",
+        default_value = "0",
+        allow_hyphen_values = true,
+        help_heading = "LAYERS"
+    )]
+    mask_expansion: f64,
+    /// Generate an additional package (e.g. "MyName (Mask Window)") with a
+    /// stop mask opening around the artwork, which leaves the artwork itself
+    /// covered by the soldermask
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        conflicts_with = "device-per-scale",
+        help_heading = "LAYERS"
+    )]
+    mask_window: bool,
+    /// Margin of the --mask-window opening around the artwork in mm
+    /// [default: 1]
+    #[clap(
+        long,
+        value_name = "MM",
+        requires = "mask-window",
+        help_heading = "LAYERS"
+    )]
+    mask_window_margin: Option<f64>,
+    /// Convert filled circles with at least this diameter (in mm, before
+    /// --scales) to test point pads: Bare copper with a stop mask opening and
+    /// (with --format-version 1) without solder paste
+    #[clap(long, value_name = "MM", help_heading = "LAYERS")]
+    circle_pads: Option<f64>,
+    /// Generate a mirrored copy of every footprint on the bottom layers
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "LAYERS"
+    )]
+    both_sides: bool,
+    /// Generate an additional footprint on this layer (layer name or alias
+    /// from the config file, may be repeated)
+    #[clap(long = "layer", value_name = "LAYER", help_heading = "LAYERS")]
+    layers: Vec<String>,
+    /// Layer naming scheme of the target LibrePCB version (legacy: 0.1.x,
+    /// modern: 1.0 and newer) [default: depends on --format-version]
+    #[clap(long, value_enum, help_heading = "LAYERS")]
+    layer_names: Option<LayerNames>,
 
+    /// Approve the library check messages which are expected for artwork
+    /// elements (e.g. missing courtyard), requires --format-version 1
+    #[clap(long, help_heading = "PARAMETERS")]
+    approve_checks: bool,
+    /// LibrePCB file format version to generate
+    #[clap(long, value_enum, default_value = "0.1", help_heading = "PARAMETERS")]
+    format_version: FormatVersion,
+    /// Kind of library to generate
+    #[clap(
+        long = "format",
+        value_enum,
+        default_value = "librepcb",
+        help_heading = "PARAMETERS"
+    )]
+    output_format: OutputFormat,
     /// Flattening tolerance
     #[clap(long, default_value = "0.15", help_heading = "PARAMETERS")]
     flattening_tolerance: f64,
+    /// Flattening tolerance for the symbol, which can usually be coarser than
+    /// for the footprints [default: same as --flattening-tolerance]
+    #[clap(long, help_heading = "PARAMETERS")]
+    symbol_tolerance: Option<f64>,
+    /// Maximum distance between circles, ellipses and elliptical arcs and
+    /// their flattened segments [default: 0.02]
+    #[clap(long, value_name = "MM", help_heading = "PARAMETERS")]
+    ellipse_tolerance: Option<f64>,
+    /// Trace shapes filled with a gradient from a raster with this pixel size
+    /// instead of filling them solid: With --pcb-art, each pixel gets the
+    /// role of its color, otherwise the dark pixels are filled. The size is
+    /// in the units of the SVG document, before any scaling with --width-mm
+    /// or --scales
+    #[clap(long, value_name = "SIZE", help_heading = "PARAMETERS")]
+    rasterize_gradients: Option<f64>,
+    /// Draw only the bounding box of the artwork in the symbol, which keeps
+    /// schematics with large artwork fast to render
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    bounding_box_symbol: bool,
+    /// Height of the NAME and VALUE texts of the symbol in mm [default: 2.5]
+    #[clap(long, value_name = "MM", help_heading = "PARAMETERS")]
+    label_height: Option<f64>,
+    /// Horizontal alignment of the NAME and VALUE texts of the symbol (above
+    /// and below the artwork)
+    #[clap(
+        long,
+        value_enum,
+        default_value = "center",
+        help_heading = "PARAMETERS"
+    )]
+    label_align: HorizontalAlign,
+    /// Layer of the artwork in the symbol
+    #[clap(
+        long,
+        value_enum,
+        default_value = "sym_outlines",
+        help_heading = "LAYERS"
+    )]
+    symbol_layer: SymbolLayer,
+    /// Add a filled rectangle around the artwork on the hidden grab area
+    /// layer of the symbol, to select it anywhere within its bounds
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    symbol_grab_area: bool,
+    /// Clip the geometry to the viewBox of the SVG document, discarding
+    /// objects outside of the page
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    clip_viewbox: bool,
+    /// Convert texts to paths with Inkscape (which must be installed) instead
+    /// of ignoring them
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    text_to_path: bool,
+    /// Convert texts to editable LibrePCB texts instead of ignoring them
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    native_text: bool,
+    /// Interpret the colors of the drawing as "PCB art" and generate a single
+    /// footprint with copper, stop mask and silkscreen polygons (e.g. gold as
+    /// exposed copper, white as silkscreen and black or dark green as
+    /// soldermask)
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    pcb_art: bool,
+    /// Map a color to copper, masked-copper, silkscreen, substrate or
+    /// soldermask with --pcb-art (e.g. "#ff0000=silkscreen", may be repeated)
+    #[clap(long, value_name = "COLOR=ROLE", help_heading = "PARAMETERS")]
+    art_color: Vec<String>,
+    /// List the colors of the drawing and assign them to the PCB layers
+    /// interactively in the terminal (implies --pcb-art)
+    #[clap(long, help_heading = "PARAMETERS")]
+    pick_colors: bool,
+    /// Excellon drill file with holes to add to the footprints (in the same
+    /// coordinate system as the artwork, e.g. of a Gerber file)
+    #[clap(long, value_name = "FILE", help_heading = "PARAMETERS")]
+    drill_file: Option<PathBuf>,
+    /// TOML file with fabrication rules (min-trace-width, min-clearance,
+    /// min-drill, min-silk-width in mm) to check the footprints against,
+    /// reporting violations as warnings
+    #[clap(long, value_name = "FILE", help_heading = "PARAMETERS")]
+    rules: Option<PathBuf>,
+    /// Change how a warning is reported (e.g. "ignored-text=error", levels:
+    /// ignore, warning, error, may be repeated). Overrides the levels of the
+    /// config file
+    #[clap(long, value_name = "NAME=LEVEL", help_heading = "PARAMETERS")]
+    warning: Vec<String>,
+    /// Treat all warnings as errors, unless configured otherwise
+    #[clap(long, help_heading = "PARAMETERS")]
+    strict: bool,
+    /// Print how the input is converted (e.g. which paths are skipped and on
+    /// which layers they end up)
+    #[clap(long, short)]
+    verbose: bool,
+    /// Write the verbose log, warnings and errors to a file (also without
+    /// --verbose), e.g. when the console output is not visible in Inkscape
+    #[clap(long, value_name = "FILE", help_heading = "DIRECTORIES")]
+    log_file: Option<PathBuf>,
+    /// Only import the graphics on this layer when converting a KiCad
+    /// footprint (e.g. F.SilkS, may be repeated) [default: all layers]
+    #[clap(
+        long = "kicad-layer",
+        value_name = "LAYER",
+        help_heading = "PARAMETERS"
+    )]
+    kicad_layers: Vec<String>,
+    /// Additional S-expression nodes to add to the symbol (or @FILE to read
+    /// them from a file, may be repeated)
+    #[clap(long, value_name = "SNIPPET", help_heading = "PARAMETERS")]
+    symbol_snippet: Vec<String>,
+    /// Additional S-expression nodes to add to the package (or @FILE to read
+    /// them from a file, may be repeated)
+    #[clap(long, value_name = "SNIPPET", help_heading = "PARAMETERS")]
+    package_snippet: Vec<String>,
+    /// Render the file of an element with a custom template instead, e.g.
+    /// "package=package.lp.j2" (element: symbol, component, package or device,
+    /// may be repeated)
+    #[clap(
+        long = "template",
+        value_name = "ELEMENT=FILE",
+        help_heading = "PARAMETERS"
+    )]
+    templates: Vec<String>,
+    /// Add a text to the footprints, e.g. "{{BOARD}};x=0;y=-5;layer=top_legend"
+    /// (options: x, y, layer, height, rotation, align, may be repeated)
+    #[clap(long, value_name = "TEXT", help_heading = "PARAMETERS")]
+    footprint_text: Vec<String>,
+    /// Add a fiducial (copper dot, stop mask opening and plane keepout) to the
+    /// footprints at this position in mm relative to the origin (may be
+    /// repeated)
+    #[clap(
+        long,
+        value_name = "X,Y",
+        allow_hyphen_values = true,
+        help_heading = "PARAMETERS"
+    )]
+    fiducial: Vec<String>,
+    /// Diameter of the copper dot of fiducials in mm (the stop mask opening is
+    /// twice as large, the keepout three times)
+    #[clap(
+        long,
+        value_name = "MM",
+        default_value = "1",
+        help_heading = "PARAMETERS"
+    )]
+    fiducial_diameter: f64,
+    /// Draw a cross of this size in mm on the documentation layer at the
+    /// origin of the footprints, to see their anchor point when placing them
+    /// [default: 1]
+    #[clap(
+        long,
+        value_name = "MM",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "1",
+        help_heading = "PARAMETERS"
+    )]
+    origin_cross: Option<f64>,
+    /// Repeat the artwork in a grid of COLSxROWS copies within each footprint,
+    /// e.g. for background patterns or test coupons
+    #[clap(long, value_name = "COLSxROWS", help_heading = "PARAMETERS")]
+    tile: Option<String>,
+    /// Distance between the tiles in mm (by default the size of the artwork,
+    /// positive y values place the rows below each other)
+    #[clap(
+        long,
+        value_name = "X,Y",
+        requires = "tile",
+        allow_hyphen_values = true,
+        help_heading = "PARAMETERS"
+    )]
+    pitch: Option<String>,
+    /// Generate the footprints at each of these widths (in mm, comma
+    /// separated) instead of the original size
+    #[clap(
+        long,
+        value_delimiter = ',',
+        value_name = "WIDTHS",
+        help_heading = "PARAMETERS"
+    )]
+    scales: Vec<f64>,
+    /// Generate a separate package and device for each of the --scales
+    /// widths, all using the same component
+    #[clap(long, requires = "scales", help_heading = "PARAMETERS")]
+    device_per_scale: bool,
+    /// Generate a separate package and device for each role of the --pcb-art
+    /// colors (e.g. "MyName (Copper)"), all using the same component and
+    /// aligned together
+    #[clap(long, conflicts_with = "device-per-scale", help_heading = "PARAMETERS")]
+    package_per_color: bool,
+    /// Scale the footprints to this width (in mm)
+    #[clap(
+        long,
+        value_name = "MM",
+        conflicts_with_all = &["scales", "gui"],
+        help_heading = "PARAMETERS"
+    )]
+    width_mm: Option<f64>,
+    /// Scale the footprints to this height (in mm)
+    #[clap(
+        long,
+        value_name = "MM",
+        conflicts_with_all = &["scales", "gui"],
+        help_heading = "PARAMETERS"
+    )]
+    height_mm: Option<f64>,
+    /// How the artwork is fitted if both --width-mm and --height-mm are given
+    #[clap(
+        long,
+        value_enum,
+        default_value = "contain",
+        help_heading = "PARAMETERS"
+    )]
+    fit: Fit,
+    /// Round the corners of the polygons with this radius (in mm)
+    #[clap(long, value_name = "RADIUS", help_heading = "PARAMETERS")]
+    round_corners: Option<f64>,
+    /// Replace flattened curves with arcs (within the flattening tolerance),
+    /// which makes the generated files much smaller
+    #[clap(
+        long,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true",
+        help_heading = "PARAMETERS"
+    )]
+    fit_arcs: bool,
+    /// Split straight segments into evenly spaced segments of at most this
+    /// length (in mm)
+    #[clap(long, value_name = "LENGTH", help_heading = "PARAMETERS")]
+    max_segment_length: Option<f64>,
+    /// Split arc segments (of --round-corners or --fit-arcs) into arcs
+    /// sweeping by at most this angle (in degrees)
+    #[clap(long, value_name = "DEGREES", help_heading = "PARAMETERS")]
+    max_arc_sweep: Option<f64>,
+    /// Remove the silkscreen from the stop mask openings (e.g. exposed copper)
+    /// of --pcb-art, keeping this clearance (in mm)
+    #[clap(long, value_name = "MM", help_heading = "PARAMETERS")]
+    silkscreen_clearance: Option<f64>,
+    /// Remove closed shapes with an area below this threshold (in mm², after
+    /// scaling) from the footprints, e.g. speckles or export artifacts
+    #[clap(long, value_name = "MM2", help_heading = "PARAMETERS")]
+    min_area: Option<f64>,
+    /// Ignore paths with a lower opacity (0 to 1), e.g. semi-transparent
+    /// guide overlays
+    #[clap(long, value_name = "OPACITY", help_heading = "PARAMETERS")]
+    min_opacity: Option<f64>,
+    /// Convert stroked paths to filled areas with the stroke width of the
+    /// SVG, instead of 0.2 mm lines along the paths
+    #[clap(long, help_heading = "PARAMETERS")]
+    outline_strokes: bool,
+    /// Treat all paths as closed, filling open paths instead of drawing them
+    /// as 0.2 mm lines
+    #[clap(long, help_heading = "PARAMETERS")]
+    treat_all_closed: bool,
+    /// Line cap of the outlined strokes [default: from the SVG]
+    #[clap(
+        long,
+        value_enum,
+        value_name = "CAP",
+        requires = "outline-strokes",
+        help_heading = "PARAMETERS"
+    )]
+    stroke_cap: Option<LineCap>,
+    /// Line join of the outlined strokes [default: from the SVG]
+    #[clap(
+        long,
+        value_enum,
+        value_name = "JOIN",
+        requires = "outline-strokes",
+        help_heading = "PARAMETERS"
+    )]
+    stroke_join: Option<LineJoin>,
+    /// Cut holes (e.g. of the letter O) out of the filled areas around them,
+    /// with bridges to the outside of the areas
+    #[clap(long, help_heading = "PARAMETERS")]
+    bridge_holes: bool,
+    /// Width of the bridges (in mm, 0 for invisible cuts) [default: 0.2]
+    #[clap(
+        long,
+        value_name = "MM",
+        requires = "bridge-holes",
+        help_heading = "PARAMETERS"
+    )]
+    bridge_width: Option<f64>,
+    /// Number of bridges per hole [default: 1]
+    #[clap(
+        long,
+        value_name = "N",
+        requires = "bridge-holes",
+        help_heading = "PARAMETERS"
+    )]
+    bridge_count: Option<usize>,
+    /// Direction of the first bridge from the center of the hole (in
+    /// degrees, counterclockwise from the right), to place the bridges where
+    /// they are least visible [default: 270, i.e. downwards]
+    #[clap(
+        long,
+        value_name = "DEGREES",
+        requires = "bridge-holes",
+        allow_hyphen_values = true,
+        help_heading = "PARAMETERS"
+    )]
+    bridge_angle: Option<f64>,
+    /// Split polygons with more vertices than this into smaller pieces, as
+    /// huge polygons make LibrePCB slow
+    #[clap(long, value_name = "N", help_heading = "PARAMETERS")]
+    max_polygon_vertices: Option<usize>,
+    /// Fail if the SVG document has more elements [default: 100000]
+    #[clap(long, value_name = "N", help_heading = "PARAMETERS")]
+    max_elements: Option<usize>,
+    /// Fail if the data of an SVG path is longer (in bytes) [default:
+    /// 10000000]
+    #[clap(long, value_name = "BYTES", help_heading = "PARAMETERS")]
+    max_path_length: Option<usize>,
+    /// Fail if the flattened paths have more vertices in total [default:
+    /// 5000000]
+    #[clap(long, value_name = "N", help_heading = "PARAMETERS")]
+    max_total_vertices: Option<usize>,
     /// Align the centerpoint
     #[clap(long, value_enum, default_value = "none", help_heading = "PARAMETERS")]
     align: Align,
+    /// Align a footprint by its own artwork instead of --align, e.g. "Top
+    /// Placement=bottom-left" (by the title of the footprint, may be repeated)
+    #[clap(long, value_name = "FOOTPRINT=ALIGN", help_heading = "PARAMETERS")]
+    footprint_align: Vec<String>,
+
+    /// Which filled polygons can be used to grab the element in LibrePCB
+    #[clap(
+        long,
+        value_enum,
+        default_value = "largest",
+        help_heading = "PARAMETERS"
+    )]
+    grab_area: GrabArea,
+
+    /// Only convert the element with this id (may be repeated, passed in by
+    /// Inkscape for the current selection)
+    #[clap(
+        long = "id",
+        alias = "include-id",
+        value_name = "ID",
+        help_heading = "PARAMETERS"
+    )]
+    ids: Vec<String>,
+    /// Show a preview window to adjust the flattening tolerance and the width
+    /// before writing the library (requires the "gui" feature)
+    #[clap(long, help_heading = "PARAMETERS")]
+    gui: bool,
+
+    /// Set by the Inkscape extension, which expects the SVG document on stdout
+    #[clap(
+        long,
+        hide = true,
+        action = clap::ArgAction::Set,
+        min_values = 0,
+        require_equals = true,
+        default_value = "false",
+        default_missing_value = "true"
+    )]
+    inkscape: bool,
+
+    /// Command line arguments, without the input file and the options which
+    /// are recorded with their effective values (see --keep-source)
+    #[clap(skip)]
+    passed_args: Vec<String>,
+    /// Whether --format-version was given on the command line (otherwise the
+    /// library config file may set it)
+    #[clap(skip)]
+    format_version_given: bool,
+    /// The library (or directory) currently written to, one of --outpath
+    #[clap(skip)]
+    outpath: PathBuf,
+    /// Config file of the output library
+    #[clap(skip)]
+    library_config: LibraryConfig,
+}
+
+thread_local! {
+    /// State of the deterministic UUID generator (if seeded with --uuid-seed)
+    static UUID_RNG: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Make all subsequently generated UUIDs deterministic.
+fn seed_uuids(seed: u64) {
+    UUID_RNG.with(|state| state.set(Some(seed)));
+}
 
-    /// Passed in by Inkscape, ignored, not currently supported
-    #[clap(long, hide(true))]
-    id: Option<Vec<String>>,
+/// SplitMix64 step, returns the next output and state.
+fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31), state)
 }
 
 fn make_uuid() -> Uuid {
-    Uuid::new_v4()
+    UUID_RNG.with(|state| match state.get() {
+        Some(seed) => {
+            let (high, seed) = splitmix64(seed);
+            let (low, seed) = splitmix64(seed);
+            state.set(Some(seed));
+            let bytes = ((u128::from(high) << 64) | u128::from(low)).to_be_bytes();
+            uuid::Builder::from_random_bytes(bytes).into_uuid()
+        }
+        None => Uuid::new_v4(),
+    })
 }
 
+/// Timestamp for the `created` field of elements, with second precision like
+/// LibrePCB writes it.
+///
+/// Honors the `SOURCE_DATE_EPOCH` environment variable for reproducible
+/// output.
+fn created_timestamp() -> String {
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .and_then(|epoch| Utc.timestamp_opt(epoch, 0).single())
+        .unwrap_or_else(Utc::now);
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Load an SVG file, converting PDF and AI files to SVG first and
+/// decompressing SVGZ files.
 fn load_svg(path: &Path) -> Result<String> {
-    Ok(read_to_string(path)?)
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if let Some("pdf") | Some("ai") = extension.as_deref() {
+        return convert::pdf_to_svg(path).map_err(|reason| Error::ConvertInput {
+            path: path.to_owned(),
+            reason,
+        });
+    }
+    let read_error = |source| Error::ReadSvg {
+        path: path.to_owned(),
+        source,
+    };
+    let mut data = fs::read(path).map_err(read_error)?;
+
+    // SVGZ files are gzip compressed (detected by the contents, since they
+    // are sometimes saved with the .svg extension)
+    if data.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = vec![];
+        GzDecoder::new(&data[..])
+            .read_to_end(&mut decompressed)
+            .map_err(read_error)?;
+        data = decompressed;
+    }
+    String::from_utf8(data).map_err(|e| read_error(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// The first bytes of gzip compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Parse and flatten the paths of an SVG document.
+///
+/// If the document is not well-formed XML, the error points to the offending
+/// location.
+fn parse_svg(path: &Path, svg: &str, tolerance: f64) -> Result<Vec<Polyline>> {
+    let polylines = svg2polylines::parse(svg, tolerance, true)
+        .map_err(|reason| xml_error(path, svg, reason))?;
+    Ok(geometry::from_svg(&polylines))
+}
+
+/// The error for a document which could not be parsed, pointing to the
+/// offending location if it is not well-formed XML.
+fn xml_error(path: &Path, svg: &str, reason: String) -> Error {
+    match svg::check_xml(svg) {
+        Err((offset, reason)) => Error::InvalidXml {
+            path: path.to_owned(),
+            source_code: named_source(path, svg),
+            span: offset.into(),
+            reason,
+        },
+        Ok(()) => Error::ParseSvg {
+            path: path.to_owned(),
+            reason,
+        },
+    }
+}
+
+/// The SVG document, for showing snippets in diagnostics.
+fn named_source(path: &Path, svg: &str) -> Arc<NamedSource<String>> {
+    Arc::new(NamedSource::new(
+        path.display().to_string(),
+        svg.to_string(),
+    ))
+}
+
+/// File extensions of Gerber files.
+const GERBER_EXTENSIONS: [&str; 12] = [
+    "gbr", "ger", "art", "pho", "gtl", "gbl", "gto", "gbo", "gts", "gbs", "gtp", "gbp",
+];
+
+// Commands which inspect input files or the environment, or regenerate
+// existing packages, instead of converting a file. They are optional, so that
+// conversions (which start with the input file) don't need a subcommand. (Not
+// a doc comment, which clap would use as the description of the program.)
+#[derive(clap::Subcommand, Clone, Debug)]
+enum Subcommand {
+    /// Print the dimensions and statistics of the artwork without generating
+    /// anything
+    Measure(MeasureArgs),
+    /// Check files for features which are not converted as expected (e.g.
+    /// for pre-commit hooks), failing if any file has errors
+    Validate(ValidateArgs),
+    /// Check for the external programs, the LibrePCB workspace, the output
+    /// library and the config file, e.g. if generated elements don't show up
+    /// in LibrePCB
+    Doctor(DoctorArgs),
+    /// Regenerate packages with the input file and options stored by
+    /// --keep-source (e.g. after updating svg2librepcb)
+    Regen(RegenArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct MeasureArgs {
+    /// The file to measure (any input file supported by the conversion)
+    svgfile: PathBuf,
+    /// Flattening tolerance
+    #[clap(long, default_value = "0.15")]
+    flattening_tolerance: f64,
+    /// Clip the geometry to the viewBox of the SVG document
+    #[clap(long)]
+    clip_viewbox: bool,
+    /// Convert texts to paths with Inkscape (which must be installed)
+    #[clap(long)]
+    text_to_path: bool,
+    /// Only measure the element with this id (may be repeated)
+    #[clap(long = "id", value_name = "ID")]
+    ids: Vec<String>,
+    /// Only import the graphics on this layer of a KiCad footprint (may be
+    /// repeated)
+    #[clap(long = "kicad-layer", value_name = "LAYER")]
+    kicad_layers: Vec<String>,
+    /// Report the layers of multi-color "PCB art" separately
+    #[clap(long)]
+    pcb_art: bool,
+    /// Ignore paths with a lower opacity (0 to 1)
+    #[clap(long, value_name = "OPACITY")]
+    min_opacity: Option<f64>,
+    /// Treat all paths as closed
+    #[clap(long)]
+    treat_all_closed: bool,
+    /// Map a color to a role with --pcb-art (may be repeated)
+    #[clap(long, value_name = "COLOR=ROLE")]
+    art_color: Vec<String>,
+    /// Also print the dimensions when scaled to these widths (in mm, comma
+    /// separated)
+    #[clap(long, value_delimiter = ',', value_name = "WIDTHS")]
+    scales: Vec<f64>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ValidateArgs {
+    /// The files to check
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+    /// Fail on warnings as well
+    #[clap(long)]
+    strict: bool,
+    /// Only print the summary
+    #[clap(long, short)]
+    quiet: bool,
+    /// Flattening tolerance (in mm, like for the conversion) with which the
+    /// files are parsed to check them
+    #[clap(long, default_value = "0.15")]
+    flattening_tolerance: f64,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct DoctorArgs {
+    /// The library to check (as passed to --outpath when converting)
+    #[clap(long)]
+    outpath: Option<PathBuf>,
+    /// Config file (TOML) to check
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct RegenArgs {
+    /// Package directories, or libraries to regenerate all packages with
+    /// stored options
+    #[clap(required = true)]
+    paths: Vec<PathBuf>,
+    /// Only print the conversions, without regenerating anything
+    #[clap(long)]
+    dry_run: bool,
+    /// Options replacing the stored ones (after `--`, e.g. `-- --fit-arcs`)
+    #[clap(last = true)]
+    overrides: Vec<String>,
+}
+
+/// Supported input file formats, detected by the file extension.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum InputFormat {
+    /// SVG documents (and PDF/AI files, which are converted to SVG)
+    Svg,
+    /// KiCad footprints (`.kicad_mod`)
+    KiCad,
+    /// Gerber (RS-274X) artwork
+    Gerber,
+}
+
+impl InputFormat {
+    fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("kicad_mod") => InputFormat::KiCad,
+            Some(extension) if GERBER_EXTENSIONS.contains(&extension) => InputFormat::Gerber,
+            _ => InputFormat::Svg,
+        }
+    }
+}
+
+/// Palette for --pcb-art, with the --art-color mappings taking precedence
+/// over the default palette.
+fn art_palette(mappings: &[String]) -> Result<Vec<(svg::Color, art::Role)>> {
+    let mut palette = mappings
+        .iter()
+        .map(|value| {
+            art::parse_mapping(value).map_err(|reason| Error::ArtColor {
+                mapping: value.clone(),
+                reason,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    palette.extend_from_slice(&art::DEFAULT_PALETTE);
+    Ok(palette)
+}
+
+/// Limits for the input, with the options of the command line.
+fn input_limits(args: &Args) -> Limits {
+    let default = Limits::default();
+    Limits {
+        elements: args.max_elements.unwrap_or(default.elements),
+        path_length: args.max_path_length.unwrap_or(default.path_length),
+        vertices: args.max_total_vertices.unwrap_or(default.vertices),
+    }
+}
+
+/// Options for loading and parsing an input file.
+struct InputOptions<'a> {
+    clip_viewbox: bool,
+    text_to_path: bool,
+    native_text: bool,
+    pcb_art: bool,
+    ids: &'a [String],
+    kicad_layers: &'a [String],
+    /// Paths with a lower opacity are removed
+    min_opacity: f64,
+    /// Convert strokes to filled areas, with the line cap and join (taken
+    /// from the SVG if not given)
+    outline_strokes: Option<(Option<LineCap>, Option<LineJoin>)>,
+    /// Close all open paths
+    treat_all_closed: bool,
+    /// Chord error of circles, ellipses and elliptical arcs
+    ellipse_tolerance: f64,
+    /// Pixel size to rasterize shapes filled with gradients
+    rasterize_gradients: Option<f64>,
+    /// Warn about external references which can not be resolved
+    report_refs: bool,
+    limits: Limits,
+}
+
+/// Limits for the size of the input, to fail with a clear message instead of
+/// exhausting the memory with broken or adversarial files.
+#[derive(Debug, Copy, Clone)]
+struct Limits {
+    /// Number of SVG elements
+    elements: usize,
+    /// Length of the data of a single SVG path, in bytes
+    path_length: usize,
+    /// Number of vertices of all flattened paths
+    vertices: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            elements: 100_000,
+            path_length: 10_000_000,
+            vertices: 5_000_000,
+        }
+    }
+}
+
+impl Limits {
+    /// Fail if a value exceeds its limit.
+    fn check(value: usize, limit: usize, what: &'static str, option: &'static str) -> Result<()> {
+        if value > limit {
+            return Err(Error::LimitExceeded {
+                what,
+                value,
+                limit,
+                option,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the vertices of the flattened paths.
+    fn check_vertices(&self, polylines: &[Polyline]) -> Result<()> {
+        let vertices = polylines.iter().map(Vec::len).sum();
+        Limits::check(vertices, self.vertices, "vertices", "--max-total-vertices")
+    }
+
+    /// Check the number of elements and the length of the paths of an SVG
+    /// document (the input file, or the document converted from it).
+    fn check_svg(&self, svg: &str) -> Result<()> {
+        // Errors of malformed documents are reported when parsing the paths
+        if let Ok((elements, longest_path)) = svg::size(svg) {
+            Limits::check(elements, self.elements, "elements", "--max-elements")?;
+            Limits::check(
+                longest_path,
+                self.path_length,
+                "bytes of data in a path",
+                "--max-path-length",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check the estimated vertices of an SVG document before flattening it
+    /// (see `svg::estimate_vertices`), which could use a lot of memory.
+    fn check_estimated_vertices(
+        &self,
+        svg: &str,
+        tolerance: Option<f64>,
+        ellipse_tolerance: f64,
+    ) -> Result<()> {
+        let vertices = svg::estimate_vertices(svg, tolerance, ellipse_tolerance).unwrap_or(0);
+        Limits::check(
+            vertices,
+            self.vertices,
+            "vertices after flattening (estimated)",
+            "--max-total-vertices",
+        )
+    }
+}
+
+/// An input file, loaded for parsing.
+struct Input<'a> {
+    path: &'a Path,
+    format: InputFormat,
+    /// Contents of the file (converted to SVG for PDF/AI files)
+    source: String,
+    /// The selected elements of an SVG document (with texts converted to
+    /// paths, if requested), without the shapes of `gradient_fills`
+    selected_svg: String,
+    /// The shapes filled with gradients which are rasterized, with a copy of
+    /// the selected elements which only contains the shape
+    gradient_fills: Vec<(svg::GradientFill, String)>,
+    /// Pixel size to rasterize the `gradient_fills`
+    rasterize_gradients: Option<f64>,
+    clip_rect: Option<geometry::Rect>,
+    kicad_layers: &'a [String],
+    outline_strokes: Option<(Option<LineCap>, Option<LineJoin>)>,
+    treat_all_closed: bool,
+    limits: Limits,
+}
+
+impl<'a> Input<'a> {
+    /// Load an input file, reporting texts that are ignored.
+    fn load(path: &'a Path, options: &InputOptions<'a>) -> Result<Self> {
+        let format = InputFormat::from_path(path);
+        if format != InputFormat::Svg {
+            let svg_options = [
+                ("--clip-viewbox", options.clip_viewbox),
+                ("--text-to-path", options.text_to_path),
+                ("--native-text", options.native_text),
+                ("--pcb-art", options.pcb_art),
+                ("--id", !options.ids.is_empty()),
+                ("--outline-strokes", options.outline_strokes.is_some()),
+                (
+                    "--rasterize-gradients",
+                    options.rasterize_gradients.is_some(),
+                ),
+            ];
+            if let Some((option, _)) = svg_options.iter().find(|(_, used)| *used) {
+                return Err(Error::SvgOnly(option));
+            }
+        }
+        log!("Loading {} as {:?}", path.display(), format);
+        let source = match format {
+            InputFormat::Svg => load_svg(path)?,
+            _ => read_to_string(path).map_err(|source| Error::ReadFile {
+                path: path.to_owned(),
+                source,
+            })?,
+        };
+        let svg_error = |reason| Error::ParseSvg {
+            path: path.to_owned(),
+            reason,
+        };
+        if format == InputFormat::Svg {
+            options.limits.check_svg(&source)?;
+        }
+        let clip_rect = if options.clip_viewbox {
+            let view_box = svg::view_box(&source).map_err(svg_error)?;
+            Some(
+                view_box
+                    .ok_or_else(|| svg_error("The document has no viewBox to clip to".into()))?,
+            )
+        } else {
+            None
+        };
+        // Hidden elements (e.g. construction layers) are not converted
+        let mut converted_svg = match format {
+            InputFormat::Svg => svg::remove_hidden(&source, options.min_opacity)
+                .map_err(|reason| xml_error(path, &source, reason))?,
+            _ => source.clone(),
+        };
+        if format == InputFormat::Svg && options.report_refs {
+            let unresolved = unresolved_refs(path, &source);
+            if !unresolved.is_empty() {
+                Warning::UnresolvedReferences {
+                    source_code: named_source(path, &source),
+                    spans: unresolved
+                        .into_iter()
+                        .map(|(range, message)| LabeledSpan::new_with_span(Some(message), range))
+                        .collect(),
+                }
+                .report();
+            }
+        }
+        if format == InputFormat::Svg {
+            let gradients = svg::gradient_shapes(&source, options.rasterize_gradients.is_some());
+            if !gradients.is_empty() {
+                Warning::GradientFill {
+                    source_code: named_source(path, &source),
+                    spans: gradients
+                        .into_iter()
+                        .map(|(range, id)| {
+                            let label = match id {
+                                Some(id) => format!("#{}", id),
+                                None => "filled solid".to_string(),
+                            };
+                            LabeledSpan::new_with_span(Some(label), range)
+                        })
+                        .collect(),
+                }
+                .report();
+            }
+        }
+        // Only the texts of the selected elements are converted or reported
+        let text_svg = match format {
+            InputFormat::Svg if !options.ids.is_empty() => {
+                svg::select_ids(&converted_svg, options.ids).map_err(svg_error)?
+            }
+            _ => converted_svg.clone(),
+        };
+        let text_ranges = match format {
+            InputFormat::Svg => svg::text_ranges(&text_svg),
+            _ => vec![],
+        };
+        if !text_ranges.is_empty() && !options.native_text {
+            if options.text_to_path {
+                converted_svg = convert::text_to_path(&converted_svg).map_err(Error::TextToPath)?;
+                options.limits.check_svg(&converted_svg)?;
+            } else {
+                Warning::IgnoredText {
+                    source_code: named_source(path, &text_svg),
+                    spans: text_ranges
+                        .into_iter()
+                        .map(|range| {
+                            LabeledSpan::new_with_span(Some("not converted".into()), range)
+                        })
+                        .collect(),
+                }
+                .report();
+            }
+        }
+
+        // Circles and ellipses are flattened more finely than other curves,
+        // since facets are easily visible on them
+        if format == InputFormat::Svg {
+            options.limits.check_estimated_vertices(
+                &converted_svg,
+                None,
+                options.ellipse_tolerance,
+            )?;
+            converted_svg = svg::flatten_ellipses(&converted_svg, options.ellipse_tolerance)
+                .map_err(|reason| xml_error(path, &converted_svg, reason))?;
+        }
+
+        // Alignment and bounds are computed from the selected elements only
+        let selected_svg = if options.ids.is_empty() {
+            converted_svg
+        } else {
+            svg::select_ids(&converted_svg, options.ids).map_err(svg_error)?
+        };
+        // Shapes filled with gradients are traced separately, each from a
+        // copy of the document without the other shapes
+        let (selected_svg, gradient_fills) = match options.rasterize_gradients {
+            Some(_) => {
+                let fills = svg::gradient_fills(&selected_svg).map_err(svg_error)?;
+                let fills = fills
+                    .into_iter()
+                    .map(|fill| {
+                        let fill_svg =
+                            svg::isolate_shape(&selected_svg, &fill.range).map_err(svg_error)?;
+                        Ok((fill, fill_svg))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let ranges: Vec<_> = fills.iter().map(|(fill, _)| fill.range.clone()).collect();
+                (svg::remove_ranges(&selected_svg, &ranges), fills)
+            }
+            None => (selected_svg, vec![]),
+        };
+        // Shapes are split by their color for PCB art and by their stroke for
+        // outlining, skipping those whose paint is not supported. Errors are
+        // reported when parsing the paths.
+        let skipped = if options.pcb_art {
+            svg::split_by_color(&selected_svg).map(|split| split.skipped)
+        } else if options.outline_strokes.is_some() {
+            svg::split_by_stroke(&selected_svg, None, None).map(|split| split.skipped)
+        } else {
+            Ok(vec![])
+        };
+        let skipped = skipped.unwrap_or_default();
+        if !skipped.is_empty() {
+            Warning::UnsupportedPaint {
+                source_code: named_source(path, &selected_svg),
+                spans: skipped
+                    .into_iter()
+                    .map(|(range, reason)| LabeledSpan::new_with_span(Some(reason), range))
+                    .collect(),
+            }
+            .report();
+        }
+        Ok(Input {
+            path,
+            format,
+            source,
+            selected_svg,
+            gradient_fills,
+            rasterize_gradients: options.rasterize_gradients,
+            clip_rect,
+            kicad_layers: options.kicad_layers,
+            outline_strokes: options.outline_strokes,
+            treat_all_closed: options.treat_all_closed,
+            limits: options.limits,
+        })
+    }
+
+    /// Parse the (selected) artwork.
+    fn parse(&self, tolerance: f64) -> Result<Vec<Polyline>> {
+        let mut polylines = self.parse_svg(&self.selected_svg, tolerance)?;
+        // The dark parts of gradients are filled, like with the brightness
+        // cutoff of a bitmap tracer
+        let is_dark = |[r, g, b]: svg::Color| {
+            let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+            (luminance < 127.5).then_some(())
+        };
+        for (_, traced) in self.trace_gradients(tolerance, is_dark)? {
+            polylines.extend(traced);
+        }
+        self.limits.check_vertices(&polylines)?;
+        Ok(polylines)
+    }
+
+    /// Trace the shapes filled with gradients from a raster (with
+    /// --rasterize-gradients), classifying the pixels by their color.
+    /// Returns the traced areas of each class.
+    fn trace_gradients<T: PartialEq + Copy>(
+        &self,
+        tolerance: f64,
+        classify: impl Fn(svg::Color) -> Option<T>,
+    ) -> Result<Vec<(T, Vec<Polyline>)>> {
+        let mut classes: Vec<(T, Vec<Polyline>)> = vec![];
+        let pixel = match self.rasterize_gradients {
+            Some(pixel) => pixel,
+            None => return Ok(classes),
+        };
+        for (fill, fill_svg) in &self.gradient_fills {
+            let shape = self.parse_svg(fill_svg, tolerance)?;
+            let colors = match fill.colors(&shape) {
+                Some(colors) => colors,
+                None => continue,
+            };
+            let (columns, rows) = geometry::raster_size(&shape, pixel);
+            if columns.saturating_mul(rows) > geometry::MAX_TRACE_PIXELS {
+                return Err(Error::InvalidParameter {
+                    option: "--rasterize-gradients",
+                    value: pixel,
+                    reason: "the raster of the shapes filled with gradients has too many pixels",
+                });
+            }
+            let traced = geometry::trace(&shape, pixel, |point| colors(point).and_then(&classify));
+            for (class, polylines) in traced {
+                match classes.iter_mut().find(|(c, _)| *c == class) {
+                    Some((_, all)) => all.extend(polylines),
+                    None => classes.push((class, polylines)),
+                }
+            }
+        }
+        log!(
+            "Traced {} shapes filled with gradients with pixels of size {}",
+            self.gradient_fills.len(),
+            pixel
+        );
+        Ok(classes)
+    }
+
+    /// Parse the artwork, using a modified SVG document for SVG files.
+    fn parse_svg(&self, selected_svg: &str, tolerance: f64) -> Result<Vec<Polyline>> {
+        let input_error = |reason| Error::ParseInput {
+            path: self.path.to_owned(),
+            reason,
+        };
+        if self.format == InputFormat::Svg {
+            // Remaining arcs are flattened with the same tolerance
+            self.limits
+                .check_estimated_vertices(selected_svg, Some(tolerance), tolerance)?;
+        }
+        let polylines = match self.format {
+            InputFormat::Svg => match self.outline_strokes {
+                Some((cap, join)) => self.parse_strokes(selected_svg, cap, join, tolerance)?,
+                None => parse_svg(self.path, selected_svg, tolerance)?,
+            },
+            InputFormat::KiCad => {
+                kicad::parse(&self.source, self.kicad_layers, tolerance).map_err(input_error)?
+            }
+            InputFormat::Gerber => gerber::parse(&self.source, tolerance).map_err(input_error)?,
+        };
+        self.limits.check_vertices(&polylines)?;
+        let mut polylines = match &self.clip_rect {
+            Some(rect) => geometry::clip(&polylines, rect),
+            None => polylines,
+        };
+        geometry::close(&mut polylines, self.treat_all_closed);
+        let closed = polylines
+            .iter()
+            .filter(|polyline| geometry::is_closed(polyline))
+            .count();
+        log!(
+            "Parsed {} paths ({} closed, {} open) with tolerance {}",
+            polylines.len(),
+            closed,
+            polylines.len() - closed,
+            tolerance
+        );
+        Ok(polylines)
+    }
+
+    /// Parse an SVG document, converting the strokes to filled areas.
+    fn parse_strokes(
+        &self,
+        selected_svg: &str,
+        cap: Option<LineCap>,
+        join: Option<LineJoin>,
+        tolerance: f64,
+    ) -> Result<Vec<Polyline>> {
+        let split =
+            svg::split_by_stroke(selected_svg, cap, join).map_err(|reason| Error::ParseSvg {
+                path: self.path.to_owned(),
+                reason,
+            })?;
+        let mut polylines = vec![];
+        for (paint, group_svg) in split.groups {
+            let group = parse_svg(self.path, &group_svg, tolerance)?;
+            if paint.fill {
+                // Open paths which are not stroked are still drawn as lines
+                polylines.extend(
+                    group
+                        .iter()
+                        .filter(|polyline| paint.stroke.is_none() || geometry::is_closed(polyline))
+                        .cloned(),
+                );
+            }
+            if let Some(stroke) = paint.stroke {
+                log!(
+                    "Outlining {} paths with a {} mm stroke ({:?} cap, {:?} join)",
+                    group.len(),
+                    stroke.width,
+                    stroke.cap,
+                    stroke.join
+                );
+                polylines.extend(geometry::outline(&group, &stroke, tolerance));
+            }
+        }
+        Ok(polylines)
+    }
+
+    /// Parse the artwork as "PCB art", assigning the paths to roles by their
+    /// color. Returns all visible polylines and the polylines of each role
+    /// (see `art_layers()`).
+    fn parse_art(
+        &self,
+        palette: &[(svg::Color, art::Role)],
+        tolerance: f64,
+    ) -> Result<(Vec<Polyline>, ArtRoles)> {
+        let split = svg::split_by_color(&self.selected_svg).map_err(|reason| Error::ParseSvg {
+            path: self.path.to_owned(),
+            reason,
+        })?;
+        let mut visible = vec![];
+        let mut roles: ArtRoles = vec![];
+        for (color, group_svg) in split.groups {
+            let role =
+                art::classify(color, palette).expect("The palette contains the default colors");
+            let layers_text: Vec<&str> = role.layers().iter().map(|layer| layer.title()).collect();
+            log!(
+                "Color {} is {:?} ({})",
+                picker::hex(color),
+                role,
+                match &layers_text[..] {
+                    [] => "skipped".to_string(),
+                    layers => layers.join(", "),
+                }
+            );
+            if role.layers().is_empty() {
+                continue;
+            }
+            let group_polylines = self.parse_svg(&group_svg, tolerance)?;
+            match roles.iter_mut().find(|(r, _)| *r == role) {
+                Some((_, polylines)) => polylines.extend_from_slice(&group_polylines),
+                None => roles.push((role, group_polylines.clone())),
+            }
+            visible.extend(group_polylines);
+            self.limits.check_vertices(&visible)?;
+        }
+        // Each pixel of a rasterized gradient gets the role of its color
+        let classify =
+            |color| art::classify(color, palette).filter(|role| !role.layers().is_empty());
+        for (role, traced) in self.trace_gradients(tolerance, classify)? {
+            match roles.iter_mut().find(|(r, _)| *r == role) {
+                Some((_, polylines)) => polylines.extend_from_slice(&traced),
+                None => roles.push((role, traced.clone())),
+            }
+            visible.extend(traced);
+            self.limits.check_vertices(&visible)?;
+        }
+        Ok((visible, roles))
+    }
+
+    /// The colors of the shapes of the (selected) SVG document.
+    fn colors(&self) -> Result<Vec<svg::Color>> {
+        let split = svg::split_by_color(&self.selected_svg).map_err(|reason| Error::ParseSvg {
+            path: self.path.to_owned(),
+            reason,
+        })?;
+        Ok(split.groups.into_iter().map(|(color, _)| color).collect())
+    }
+
+    /// Read the texts of the (selected) SVG document.
+    fn texts(&self) -> Result<Vec<Text>> {
+        svg::texts(&self.selected_svg).map_err(|reason| Error::ParseSvg {
+            path: self.path.to_owned(),
+            reason,
+        })
+    }
+}
+
+/// The metadata of a generated element.
+struct Metadata<'a> {
+    name: &'a str,
+    description: &'a str,
+    keywords: &'a str,
+    author: &'a str,
+    version: &'a str,
+    /// Timestamp, see [`created_timestamp`]
+    created: &'a str,
+}
+
+impl Metadata<'_> {
+    /// The metadata nodes at the start of an element file.
+    fn lines(&self) -> Vec<String> {
+        vec![
+            format!(r#" (name "{}")"#, escape_string(self.name)),
+            format!(r#" (description "{}")"#, escape_string(self.description)),
+            format!(r#" (keywords "{}")"#, escape_string(self.keywords)),
+            format!(r#" (author "{}")"#, escape_string(self.author)),
+            format!(r#" (version "{}")"#, escape_string(self.version)),
+            format!(r#" (created {})"#, self.created),
+        ]
+    }
+}
+
+/// The four kinds of library elements generated for every conversion.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ElementKind {
+    Symbol,
+    Component,
+    Package,
+    Device,
+}
+
+impl ElementKind {
+    /// Subdirectory of the library containing elements of this kind.
+    fn dir_name(self) -> &'static str {
+        match self {
+            ElementKind::Symbol => "sym",
+            ElementKind::Component => "cmp",
+            ElementKind::Package => "pkg",
+            ElementKind::Device => "dev",
+        }
+    }
+
+    /// Name of the S-expression file within the element directory.
+    fn file_name(self) -> &'static str {
+        match self {
+            ElementKind::Symbol => "symbol.lp",
+            ElementKind::Component => "component.lp",
+            ElementKind::Package => "package.lp",
+            ElementKind::Device => "device.lp",
+        }
+    }
+}
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            ElementKind::Symbol => "symbol",
+            ElementKind::Component => "component",
+            ElementKind::Package => "package",
+            ElementKind::Device => "device",
+        })
+    }
 }
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
@@ -94,21 +1739,195 @@ enum Align {
     Center,
     TopLeft,
     BottomLeft,
+    /// Center of mass of the filled areas (bounding box center for outlines)
+    Centroid,
+}
+
+/// Horizontal alignment of texts.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl HorizontalAlign {
+    fn name(self) -> &'static str {
+        match self {
+            HorizontalAlign::Left => "left",
+            HorizontalAlign::Center => "center",
+            HorizontalAlign::Right => "right",
+        }
+    }
+}
+
+/// How the artwork is fitted into the rectangle given by --width-mm and
+/// --height-mm.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum Fit {
+    /// Scale uniformly to fit into the rectangle (keeping the aspect ratio)
+    Contain,
+    /// Scale the width and the height independently to fill the rectangle
+    Stretch,
+    /// Scale uniformly to fill the rectangle, cropping what sticks out
+    Cover,
+}
+
+/// Kind of library to generate.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum OutputFormat {
+    /// LibrePCB library elements
+    Librepcb,
+    /// KiCad footprints (.kicad_mod files, e.g. in a .pretty directory)
+    Kicad,
+    /// Eagle library (.lbr file) with the packages
+    Eagle,
+}
+
+/// Output of the generated elements on stdout (`--print`).
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum PrintFormat {
+    /// Lines like `pkg=<uuid>` for `eval` in shell scripts
+    Uuids,
+    /// A JSON object with the name, library and elements
+    Json,
+}
+
+/// Version of the LibrePCB file format to generate.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum FormatVersion {
+    /// LibrePCB 0.1.x
+    #[clap(name = "0.1")]
+    V01,
+    /// LibrePCB 1.x
+    #[clap(name = "1")]
+    V1,
+}
+
+impl FormatVersion {
+    /// Contents of the `.librepcb-*` version file.
+    fn file_version(self) -> &'static str {
+        match self {
+            FormatVersion::V01 => "0.1",
+            FormatVersion::V1 => "1",
+        }
+    }
+
+    /// Layer names used by this file format version.
+    fn layer_names(self) -> LayerNames {
+        match self {
+            FormatVersion::V01 => LayerNames::Legacy,
+            FormatVersion::V1 => LayerNames::Modern,
+        }
+    }
+}
+
+/// Which filled polygons are used as grab area.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum GrabArea {
+    /// All filled polygons
+    All,
+    /// Only the filled polygon with the largest area
+    Largest,
+    /// No polygons
+    None,
+}
+
+/// Layer of the symbol artwork.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, clap::ValueEnum)]
+enum SymbolLayer {
+    /// Outlines, like the body of other symbols
+    #[clap(name = "sym_outlines")]
+    Outlines,
+    /// Invisible, only to grab the symbol (with --grab-area)
+    #[clap(name = "sym_hidden_grab_areas")]
+    HiddenGrabAreas,
+    /// Documentation, which can be hidden in the schematic editor
+    #[clap(name = "schematic_documentation")]
+    Documentation,
+}
+
+impl SymbolLayer {
+    fn name(self) -> &'static str {
+        match self {
+            SymbolLayer::Outlines => "sym_outlines",
+            SymbolLayer::HiddenGrabAreas => "sym_hidden_grab_areas",
+            SymbolLayer::Documentation => "schematic_documentation",
+        }
+    }
 }
 
 #[derive(Default)]
 struct Bounds {
+    x_min: f64,
+    x_max: f64,
     y_min: f64,
     y_max: f64,
 }
 
-struct Polygon {
-    /// Polygon lines
+/// Layers of a footprint with the polylines on each layer.
+type FootprintLayers = Vec<(Layer, Vec<Polyline>)>;
+
+/// The polylines of each role of "PCB art", in the order of the first color
+/// with that role.
+type ArtRoles = Vec<(art::Role, Vec<Polyline>)>;
+
+/// The layers needed to produce the roles of "PCB art".
+fn art_layers(roles: &[(art::Role, Vec<Polyline>)]) -> FootprintLayers {
+    let mut layers: FootprintLayers = vec![];
+    for (role, role_polylines) in roles {
+        for &layer in role.layers() {
+            match layers.iter_mut().find(|(l, _)| *l == layer) {
+                Some((_, polylines)) => polylines.extend_from_slice(role_polylines),
+                None => layers.push((layer, role_polylines.clone())),
+            }
+        }
+    }
+    // Silkscreen first, since texts are placed on the first layer
+    let order = [Layer::TopLegend, Layer::TopCopper, Layer::TopStopMask];
+    layers.sort_by_key(|(layer, _)| order.iter().position(|l| l == layer));
+    layers
+}
+
+/// Footprints generated at one width (if scaled): The title, side and layers
+/// of each footprint, and the holes, test point pads and texts to add to
+/// them.
+type Variant = (
+    Option<f64>,
+    Vec<(String, bool, FootprintLayers)>,
+    Vec<Hole>,
+    Vec<Hole>,
+    Vec<Text>,
+);
+
+struct Footprint {
+    uuid: Uuid,
     lines: Vec<String>,
-    /// Transformed bounds (in the LibrePCB coordinate system)
-    transformed_bounds: Bounds,
 }
 
+/// Line width of `--origin-cross`, in mm.
+const ORIGIN_CROSS_WIDTH: f64 = 0.1;
+
+/// Default of --ellipse-tolerance, in mm.
+const ELLIPSE_TOLERANCE: f64 = 0.02;
+
+/// Line width of polygons generated from open polylines, in mm.
+const OPEN_LINE_WIDTH: f64 = 0.2;
+
+/// Line width of the outline of `--bounding-box-symbol`, in mm (like the
+/// outlines of the symbols in the LibrePCB base library).
+const SYMBOL_LINE_WIDTH: f64 = 0.254;
+
+/// Footprint title (and package name suffix) of `--mask-window`.
+const MASK_WINDOW: &str = "Mask Window";
+
+/// Artwork smaller than this (in mm, in both directions) is most likely
+/// interpreted in the wrong units.
+const MIN_ARTWORK_SIZE: f64 = 0.1;
+
+/// Stroke width of generated texts relative to their height.
+const STROKE_WIDTH_RATIO: f64 = 0.15;
+
 /// Format a float according to LibrePCB normalization rules.
 fn format_float(val: f64) -> String {
     if val == -0.0 {
@@ -116,6 +1935,10 @@ fn format_float(val: f64) -> String {
         return "0.0".to_string();
     }
     let formatted = format!("{:.3}", val);
+    if formatted == "-0.000" {
+        // Tiny negative values are rounded to zero as well
+        return "0.0".to_string();
+    }
     if formatted.ends_with('0') {
         // 1 trailing zero
         if formatted.chars().rev().nth(1).unwrap() == '0' {
@@ -127,13 +1950,232 @@ fn format_float(val: f64) -> String {
     formatted
 }
 
-fn make_polygon(layer: &str, align: Align, polylines: &[Polyline]) -> Polygon {
-    let mut lines = vec![];
-    if polylines.is_empty() {
-        return Polygon {
-            lines,
-            transformed_bounds: Bounds::default(),
+/// Escape a string for use as a quoted S-expression value.
+fn escape_string(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x0B' => escaped.push_str("\\v"),
+            '\x0C' => escaped.push_str("\\f"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Mirror the holes and texts of a footprint for the bottom side.
+fn mirror_extras(
+    holes: &[Hole],
+    texts: &[Text],
+    footprint_texts: &[FootprintText],
+    layer_names: LayerNames,
+) -> (Vec<Hole>, Vec<Text>, Vec<FootprintText>) {
+    let holes = mirror_holes(holes);
+    let texts = texts
+        .iter()
+        .map(|text| Text {
+            position: geometry::Point::new(-text.position.x, text.position.y),
+            rotation: -text.rotation,
+            ..text.clone()
+        })
+        .collect();
+    let footprint_texts = footprint_texts
+        .iter()
+        .map(|text| FootprintText {
+            layer: Layer::from_name(text.layer)
+                .map_or(text.layer, |layer| layer.mirrored().name(layer_names)),
+            position: (-text.position.0, text.position.1),
+            rotation: -text.rotation,
+            mirror: !text.mirror,
+            ..text.clone()
+        })
+        .collect();
+    (holes, texts, footprint_texts)
+}
+
+/// Mirror holes (or pads) for the bottom side.
+fn mirror_holes(holes: &[Hole]) -> Vec<Hole> {
+    holes
+        .iter()
+        .map(|hole| Hole {
+            position: geometry::Point::new(-hole.position.x, hole.position.y),
+            ..*hole
+        })
+        .collect()
+}
+
+/// Remove the silkscreen from the stop mask openings (e.g. exposed copper)
+/// of the footprints, keeping a clearance. The footprints are placed together
+/// (e.g. the packages of `--package-per-color`), so the silkscreen of each
+/// footprint is clipped against the openings of all of them, except the
+/// opening of `--mask-window`.
+fn clip_silkscreen(specs: &mut [(&str, FootprintLayers)], clearance: f64) {
+    let sides = [
+        (Layer::TopLegend, Layer::TopStopMask),
+        (Layer::BotLegend, Layer::BotStopMask),
+    ];
+    for (legend, stop_mask) in sides {
+        let openings: Vec<Polyline> = specs
+            .iter()
+            .filter(|(title, _)| *title != MASK_WINDOW)
+            .flat_map(|(_, layers)| layers.iter())
+            .filter(|(layer, _)| *layer == stop_mask)
+            .flat_map(|(_, polylines)| polylines.iter().cloned())
+            .collect();
+        if openings.is_empty() {
+            continue;
+        }
+        let openings = geometry::offset(&openings, clearance);
+        for (_, layers) in specs.iter_mut() {
+            if let Some((_, polylines)) = layers.iter_mut().find(|(layer, _)| *layer == legend) {
+                *polylines = geometry::subtract(polylines, &openings);
+            }
+        }
+    }
+}
+
+/// The stop mask of `--mask-window`: A rectangle around the artwork with the
+/// given margin, with the artwork (open lines as drawn) cut out.
+fn mask_window(polylines: &[Polyline], margin: f64, tolerance: f64) -> Vec<Polyline> {
+    let (open, mut artwork): (Vec<Polyline>, Vec<Polyline>) = polylines
+        .iter()
+        .cloned()
+        .partition(|polyline| !geometry::is_closed(polyline));
+    let stroke = geometry::Stroke {
+        width: OPEN_LINE_WIDTH,
+        cap: LineCap::Round,
+        join: LineJoin::Round,
+    };
+    artwork.extend(geometry::outline(&open, &stroke, tolerance));
+    let rect = match geometry::bounds(&artwork) {
+        Some(rect) => rect,
+        None => return vec![],
+    };
+    let (x_min, y_min) = (rect.x_min - margin, rect.y_min - margin);
+    let (x_max, y_max) = (rect.x_max + margin, rect.y_max + margin);
+    let window = vec![
+        geometry::Point::new(x_min, y_min),
+        geometry::Point::new(x_max, y_min),
+        geometry::Point::new(x_max, y_max),
+        geometry::Point::new(x_min, y_max),
+        geometry::Point::new(x_min, y_min),
+    ];
+    geometry::subtract(&[window], &artwork)
+}
+
+/// Convert the polylines to the paths of the generated polygons, optionally
+/// with rounded corners or with arcs fitted to flattened curves, and with
+/// straight segments split to a maximum length and arcs to a maximum sweep.
+/// The arc angles are quantized to the precision of the generated files.
+fn make_paths(
+    polylines: &[Polyline],
+    corner_radius: Option<f64>,
+    arc_tolerance: Option<f64>,
+    max_segment_length: Option<f64>,
+    max_arc_sweep: Option<f64>,
+) -> Vec<geometry::Path> {
+    polylines
+        .iter()
+        .map(|polyline| {
+            let path = match (corner_radius, arc_tolerance) {
+                (Some(radius), _) => geometry::round_corners(polyline, radius),
+                (None, Some(tolerance)) => geometry::fit_arcs(polyline, tolerance),
+                (None, None) => geometry::to_path(polyline),
+            };
+            let path = match max_segment_length {
+                Some(length) => geometry::resample(&path, length),
+                None => path,
+            };
+            let mut path = match max_arc_sweep {
+                Some(sweep) => geometry::limit_sweep(&path, sweep),
+                None => path,
+            };
+            geometry::quantize_angles(&mut path);
+            path
+        })
+        .collect()
+}
+
+/// Settings chosen in the preview window (the width only if it was changed).
+struct PreviewSettings {
+    tolerance: f64,
+    width: Option<f64>,
+}
+
+/// Show the artwork in the preview window (see `--gui`).
+#[cfg(feature = "gui")]
+fn show_preview(args: &Args, art_palette: &[(svg::Color, art::Role)]) -> Result<PreviewSettings> {
+    let input = Input::load(
+        &args.svgfile,
+        &InputOptions {
+            clip_viewbox: args.clip_viewbox,
+            text_to_path: args.text_to_path,
+            native_text: args.native_text,
+            pcb_art: args.pcb_art,
+            ids: &args.ids,
+            kicad_layers: &args.kicad_layers,
+            min_opacity: args.min_opacity.unwrap_or(0.0),
+            outline_strokes: args
+                .outline_strokes
+                .then_some((args.stroke_cap, args.stroke_join)),
+            treat_all_closed: args.treat_all_closed,
+            ellipse_tolerance: args.ellipse_tolerance.unwrap_or(ELLIPSE_TOLERANCE),
+            rasterize_gradients: args.rasterize_gradients,
+            report_refs: true,
+            limits: input_limits(args),
+        },
+    )?;
+    let layer = args.layer_placement.then_some(Layer::TopLegend);
+    let layer = layer.unwrap_or(Layer::TopCopper);
+    let scene = |tolerance: f64| -> Result<preview::Scene> {
+        let layers = if args.pcb_art {
+            art_layers(&input.parse_art(art_palette, tolerance)?.1)
+        } else {
+            vec![(layer, input.parse(tolerance)?)]
         };
+        let paths: Vec<geometry::Path> = layers
+            .iter()
+            .flat_map(|(_, polylines)| polylines.iter().map(|p| geometry::to_path(p)))
+            .collect();
+        let ((dx, dy), _) = alignment(args.align, &paths);
+        Ok(preview::Scene {
+            layers,
+            origin: geometry::Point::new(-dx, -dy),
+        })
+    };
+    let initial_scene = scene(args.flattening_tolerance)?;
+    let all: Vec<Polyline> = initial_scene
+        .layers
+        .iter()
+        .flat_map(|(_, polylines)| polylines.iter().cloned())
+        .collect();
+    let original_width = geometry::width(&all);
+    let initial = preview::Settings {
+        tolerance: args.flattening_tolerance,
+        width: args.scales.first().copied().unwrap_or(original_width),
+    };
+    let settings = preview::show(scene, initial, original_width)?;
+    Ok(PreviewSettings {
+        tolerance: settings.tolerance,
+        width: (settings.width != initial.width).then_some(settings.width),
+    })
+}
+
+#[cfg(not(feature = "gui"))]
+fn show_preview(_args: &Args, _art_palette: &[(svg::Color, art::Role)]) -> Result<PreviewSettings> {
+    Err(Error::GuiUnavailable)
+}
+
+/// Offset (still in SVG coordinates) which aligns the paths, and the bounds
+/// of the aligned paths.
+fn alignment(align: Align, paths: &[geometry::Path]) -> ((f64, f64), Bounds) {
+    if paths.is_empty() {
+        return ((0.0, 0.0), Bounds::default());
     }
 
     // Note: In SVG, the top left point is (0, 0). The y-axis expands
@@ -141,11 +2183,11 @@ fn make_polygon(layer: &str, align: Align, polylines: &[Polyline]) -> Polygon {
     //       expands upwards.
 
     // First, find bounds to allow centering
-    let first_pair = polylines[0][0];
+    let first_pair = paths[0][0].position;
     let (mut x_min, mut x_max, mut y_min, mut y_max) =
         (first_pair.x, first_pair.x, first_pair.y, first_pair.y);
-    for polyline in polylines {
-        for pair in polyline {
+    for path in paths {
+        for pair in geometry::points(path) {
             x_min = pair.x.min(x_min);
             x_max = pair.x.max(x_max);
             y_min = pair.y.min(y_min);
@@ -163,367 +2205,4588 @@ fn make_polygon(layer: &str, align: Align, polylines: &[Polyline]) -> Polygon {
         }
         Align::TopLeft => (-x_min, -y_min),
         Align::BottomLeft => (-x_min, -y_max),
+        Align::Centroid => {
+            let polylines: Vec<Polyline> =
+                paths.iter().map(|path| geometry::points(path)).collect();
+            match geometry::centroid(&polylines) {
+                Some(center) => (-center.x, -center.y),
+                None => (-(x_min + x_max) / 2.0, -(y_min + y_max) / 2.0),
+            }
+        }
+    };
+    let bounds = Bounds {
+        x_min: x_min + dx,
+        x_max: x_max + dx,
+        y_min: y_min + dy,
+        y_max: y_max + dy,
     };
+    ((dx, dy), bounds)
+}
+
+fn make_polygon(
+    layer: &str,
+    offset: (f64, f64),
+    grab_area: GrabArea,
+    paths: &[geometry::Path],
+) -> Vec<String> {
+    let mut lines = vec![];
+    let (dx, dy) = offset;
+
+    // Find the largest closed polyline, which is typically the outline
+    let largest = paths
+        .iter()
+        .map(|path| geometry::points(path))
+        .enumerate()
+        .filter(|(_, polyline)| geometry::is_closed(polyline))
+        .map(|(i, polyline)| (i, geometry::area(&polyline)))
+        .fold(
+            None,
+            |largest: Option<(usize, f64)>, (i, area)| match largest {
+                Some((_, max)) if max >= area => largest,
+                _ => Some((i, area)),
+            },
+        )
+        .map(|(i, _)| i);
 
     // Then generate vertices
-    for polyline in polylines {
-        let closed = polyline[0] == polyline[polyline.len() - 1];
+    for (i, path) in paths.iter().enumerate() {
+        let closed = geometry::is_closed(&geometry::points(path));
         let (width, fill) = match closed {
-            true => ("0.0", "true"),
-            false => ("0.2", "false"),
+            true => ("0.0".to_string(), true),
+            false => (format_float(OPEN_LINE_WIDTH), false),
         };
-        lines.push(format!(r#" (polygon "{}" (layer {})"#, make_uuid(), layer));
+        let grab_area = fill
+            && match grab_area {
+                GrabArea::All => true,
+                GrabArea::Largest => largest == Some(i),
+                GrabArea::None => false,
+            };
+        lines.push(format!(r#" (polygon {} (layer {})"#, make_uuid(), layer));
         lines.push(format!(
-            r#"  (width {0}) (fill {1}) (grab_area {1})"#,
-            width, fill
+            r#"  (width {}) (fill {}) (grab_area {})"#,
+            width, fill, grab_area
         ));
-        for pair in polyline {
+        for vertex in path {
+            // Inverting the axis also inverts the direction of arcs
             lines.push(format!(
-                r#"  (vertex (position {:.3} {:.3}) (angle 0.0))"#,
-                pair.x + dx,
-                -(pair.y + dy) // Invert axis
+                r#"  (vertex (position {} {}) (angle {}))"#,
+                format_float(vertex.position.x + dx),
+                format_float(-(vertex.position.y + dy)), // Invert axis
+                format_float(-vertex.angle)
             ));
         }
         lines.push(r#" )"#.to_string());
     }
+    lines
+}
 
-    Polygon {
-        lines,
-        transformed_bounds: Bounds {
-            y_min: y_min + dy,
-            y_max: y_max + dy,
-        },
-    }
-}
-
+/// Generate a footprint with the paths on one or more layers, moved by the
+/// offset of the alignment. Texts are placed on the first layer, mirrored for
+/// footprints on the bottom side. The test point pads are given with the
+/// UUIDs of their package pads.
 fn make_footprint(
-    layer: &str,
+    uuid: Uuid,
     name: &str,
     description: &str,
-    align: Align,
-    polylines: &[Polyline],
-) -> Vec<String> {
+    offset: (f64, f64),
+    grab_area: GrabArea,
+    format_version: FormatVersion,
+    layers: &[(&str, Vec<geometry::Path>)],
+    pads: &[(&str, Hole)],
+    holes: &[Hole],
+    texts: &[Text],
+    mirror: bool,
+    footprint_texts: &[FootprintText],
+) -> Footprint {
     let mut lines = vec![];
-    lines.push(format!(r#"(footprint {}"#, make_uuid()));
-    lines.push(format!(r#" (name "{}")"#, name));
-    lines.push(format!(r#" (description "{}")"#, description));
-    if !polylines.is_empty() {
-        lines.extend_from_slice(&make_polygon(layer, align, polylines).lines);
+    lines.push(format!(r#"(footprint {}"#, uuid));
+    lines.push(format!(r#" (name "{}")"#, escape_string(name)));
+    lines.push(format!(
+        r#" (description "{}")"#,
+        escape_string(description)
+    ));
+    if format_version == FormatVersion::V1 {
+        lines.push(" (3d_position 0.0 0.0 0.0) (3d_rotation 0.0 0.0 0.0)".to_string());
+    }
+    for (layer, paths) in layers {
+        lines.extend(make_polygon(layer, offset, grab_area, paths));
+    }
+
+    // Pads, holes and texts are aligned together with the polygons
+    let (dx, dy) = offset;
+    let side = if mirror { "bottom" } else { "top" };
+    for (package_pad, pad) in pads {
+        let x = format_float(pad.position.x + dx);
+        let y = format_float(-(pad.position.y + dy)); // Invert axis
+        let size = format_float(pad.diameter);
+        match format_version {
+            FormatVersion::V01 => lines.push(format!(
+                r#" (pad {} (side {}) (shape round) (position {} {}) (rotation 0.0) (size {} {}) (drill 0.0))"#,
+                package_pad, side, x, y, size, size
+            )),
+            FormatVersion::V1 => {
+                lines.push(format!(
+                    r#" (pad {} (side {}) (shape roundrect)"#,
+                    make_uuid(),
+                    side
+                ));
+                lines.push(format!(
+                    r#"  (position {} {}) (rotation 0.0) (size {} {}) (radius 1.0)"#,
+                    x, y, size, size
+                ));
+                lines.push(
+                    "  (stop_mask auto) (solder_paste off) (clearance 0.0) (function testpad)"
+                        .to_string(),
+                );
+                lines.push(format!(r#"  (package_pad {})"#, package_pad));
+                lines.push(r#" )"#.to_string());
+            }
+        }
+    }
+    for hole in holes {
+        let x = format_float(hole.position.x + dx);
+        let y = format_float(-(hole.position.y + dy)); // Invert axis
+        let diameter = format_float(hole.diameter);
+        match format_version {
+            FormatVersion::V01 => lines.push(format!(
+                r#" (hole {} (position {} {}) (diameter {}))"#,
+                make_uuid(),
+                x,
+                y,
+                diameter
+            )),
+            FormatVersion::V1 => {
+                lines.push(format!(
+                    r#" (hole {} (diameter {}) (stop_mask auto)"#,
+                    make_uuid(),
+                    diameter
+                ));
+                lines.push(format!(r#"  (vertex (position {} {}) (angle 0.0))"#, x, y));
+                lines.push(r#" )"#.to_string());
+            }
+        }
+    }
+    if let Some((text_layer, _)) = layers.first() {
+        for text in texts {
+            lines.extend(make_stroke_text(
+                text_layer,
+                text.height,
+                &format!("{} top", text.align),
+                (text.position.x + dx, -(text.position.y + dy)), // Invert axis
+                text.rotation,
+                mirror,
+                &text.content,
+                format_version,
+            ));
+        }
+    }
+    for text in footprint_texts {
+        lines.extend(make_stroke_text(
+            text.layer,
+            text.height,
+            &text.align,
+            text.position,
+            text.rotation,
+            text.mirror,
+            &text.value,
+            format_version,
+        ));
     }
     lines.push(r#")"#.to_string());
+    Footprint { uuid, lines }
+}
+
+/// Generate a cross of the given size at the origin of a footprint, on the
+/// documentation layer of its side.
+fn make_origin_cross(size: f64, bottom: bool, layer_names: LayerNames) -> Vec<String> {
+    let layer = match bottom {
+        true => Layer::BotDocumentation,
+        false => Layer::TopDocumentation,
+    };
+    let half = size / 2.0;
+    let mut lines = vec![];
+    for ((x1, y1), (x2, y2)) in [((-half, 0.0), (half, 0.0)), ((0.0, -half), (0.0, half))] {
+        lines.push(format!(
+            r#" (polygon {} (layer {})"#,
+            make_uuid(),
+            layer.name(layer_names)
+        ));
+        lines.push(format!(
+            r#"  (width {}) (fill false) (grab_area false)"#,
+            format_float(ORIGIN_CROSS_WIDTH)
+        ));
+        for (x, y) in [(x1, y1), (x2, y2)] {
+            lines.push(format!(
+                r#"  (vertex (position {} {}) (angle 0.0))"#,
+                format_float(x),
+                format_float(y)
+            ));
+        }
+        lines.push(" )".to_string());
+    }
+    lines
+}
+
+/// Generate a fiducial of a footprint (position in LibrePCB coordinates).
+///
+/// The plane keepout requires zones, which only exist in LibrePCB 1.x.
+fn make_fiducial(
+    position: (f64, f64),
+    diameter: f64,
+    bottom: bool,
+    layer_names: LayerNames,
+    format_version: FormatVersion,
+) -> Vec<String> {
+    let (x, y) = position;
+    let mut lines = vec![];
+    for (layer, factor) in [(Layer::TopCopper, 1.0), (Layer::TopStopMask, 2.0)] {
+        let layer = if bottom { layer.mirrored() } else { layer };
+        lines.push(format!(
+            r#" (circle {} (layer {}) (width 0.0) (fill true) (grab_area false)"#,
+            make_uuid(),
+            layer.name(layer_names)
+        ));
+        lines.push(format!(
+            r#"  (diameter {}) (position {} {})"#,
+            format_float(diameter * factor),
+            format_float(x),
+            format_float(y)
+        ));
+        lines.push(" )".to_string());
+    }
+    if format_version == FormatVersion::V1 {
+        let radius = diameter * 1.5;
+        lines.push(format!(
+            r#" (zone {} (top {}) (inner false) (bottom {})"#,
+            make_uuid(),
+            !bottom,
+            bottom
+        ));
+        lines.push(
+            "  (no_copper false) (no_planes true) (no_exposure false) (no_devices false)"
+                .to_string(),
+        );
+        for (dx, angle) in [(-radius, 180.0), (radius, 180.0), (-radius, 0.0)] {
+            lines.push(format!(
+                r#"  (vertex (position {} {}) (angle {}))"#,
+                format_float(x + dx),
+                format_float(y),
+                format_float(angle)
+            ));
+        }
+        lines.push(" )".to_string());
+    }
+    lines
+}
+
+/// Parse a fiducial position like `1.5,-2`.
+fn parse_fiducial(value: &str) -> Result<(f64, f64)> {
+    let error = |reason: &str| Error::Fiducial {
+        position: value.to_string(),
+        reason: reason.to_string(),
+    };
+    let (x, y) = value.split_once(',').ok_or_else(|| error("expected X,Y"))?;
+    let parse = |coordinate: &str| {
+        coordinate
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|c| c.is_finite())
+            .ok_or_else(|| error("the coordinates must be numbers"))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
+/// A grid of copies of the artwork (`--tile`).
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Tile {
+    columns: usize,
+    rows: usize,
+    /// Distance between the copies, by default the size of the artwork
+    pitch: Option<(f64, f64)>,
+}
+
+/// Parse a tile grid like `3x2` (columns and rows) and the optional pitch
+/// like `5,-2.5`.
+fn parse_tile(tile: &str, pitch: Option<&str>) -> Result<Tile> {
+    let error = |option, value: &str, reason: &str| Error::Tile {
+        option,
+        value: value.to_string(),
+        reason: reason.to_string(),
+    };
+    let (columns, rows) = tile
+        .split_once(['x', 'X'])
+        .ok_or_else(|| error("--tile", tile, "expected COLSxROWS"))?;
+    let count = |count: &str| {
+        count
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| error("--tile", tile, "the counts must be positive integers"))
+    };
+    let (columns, rows) = (count(columns)?, count(rows)?);
+    let pitch = match pitch {
+        Some(pitch) => {
+            let (x, y) = pitch
+                .split_once(',')
+                .ok_or_else(|| error("--pitch", pitch, "expected X,Y"))?;
+            let parse = |distance: &str| {
+                distance
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|d| d.is_finite())
+                    .ok_or_else(|| error("--pitch", pitch, "the distances must be numbers"))
+            };
+            Some((parse(x)?, parse(y)?))
+        }
+        None => None,
+    };
+    Ok(Tile {
+        columns,
+        rows,
+        pitch,
+    })
+}
+
+/// Generate a stroke text of a footprint (position in LibrePCB coordinates).
+fn make_stroke_text(
+    layer: &str,
+    height: f64,
+    align: &str,
+    position: (f64, f64),
+    rotation: f64,
+    mirror: bool,
+    value: &str,
+    format_version: FormatVersion,
+) -> Vec<String> {
+    let lock = match format_version {
+        FormatVersion::V01 => "",
+        FormatVersion::V1 => " (lock false)",
+    };
+    vec![
+        format!(r#" (stroke_text {} (layer {})"#, make_uuid(), layer),
+        format!(
+            r#"  (height {}) (stroke_width {}) (letter_spacing auto) (line_spacing auto)"#,
+            format_float(height),
+            format_float(height * STROKE_WIDTH_RATIO)
+        ),
+        format!(
+            r#"  (align {}) (position {} {}) (rotation {})"#,
+            align,
+            format_float(position.0),
+            format_float(position.1),
+            format_float(rotation)
+        ),
+        format!(
+            r#"  (auto_rotate true) (mirror {}){} (value "{}")"#,
+            mirror,
+            lock,
+            escape_string(value)
+        ),
+        r#" )"#.to_string(),
+    ]
+}
+
+/// Types of component attributes and their base units.
+const ATTRIBUTE_TYPES: [(&str, &str); 8] = [
+    ("string", "none"),
+    ("voltage", "volt"),
+    ("current", "ampere"),
+    ("resistance", "ohm"),
+    ("capacitance", "farad"),
+    ("inductance", "henry"),
+    ("frequency", "hertz"),
+    ("power", "watt"),
+];
+
+/// A component attribute added with --attribute.
+#[derive(Debug, PartialEq, Clone)]
+struct Attribute {
+    key: String,
+    attribute_type: &'static str,
+    unit: String,
+    value: String,
+}
+
+/// Parse an --attribute value like `KEY=VALUE` or `KEY:TYPE:UNIT=VALUE`.
+///
+/// The type defaults to `string` and the unit to the base unit of the type.
+fn parse_attribute(value: &str) -> Result<Attribute> {
+    let error = |reason: String| Error::InvalidMetadata {
+        field: "attribute",
+        value: value.to_string(),
+        reason,
+    };
+    let (definition, attribute_value) = value
+        .split_once('=')
+        .ok_or_else(|| error("expected KEY=VALUE".into()))?;
+    let mut parts = definition.split(':');
+    let key = parts.next().unwrap_or("");
+    validation::validate_attribute_key(key).map_err(|reason| error(format!("key {}", reason)))?;
+    validation::validate_single_line(attribute_value)
+        .map_err(|reason| error(format!("value {}", reason)))?;
+    let type_name = parts.next().unwrap_or("string");
+    let (attribute_type, base_unit) = ATTRIBUTE_TYPES
+        .iter()
+        .find(|(name, _)| *name == type_name)
+        .ok_or_else(|| {
+            let types: Vec<_> = ATTRIBUTE_TYPES.iter().map(|(name, _)| *name).collect();
+            error(format!(
+                "unknown type {:?} (expected one of {})",
+                type_name,
+                types.join(", ")
+            ))
+        })?;
+    let unit = parts.next().unwrap_or(base_unit);
+    // Units are the base unit with an optional SI prefix (e.g. "millivolt")
+    if !unit.ends_with(base_unit) || (*attribute_type == "string" && unit != "none") {
+        return Err(error(format!(
+            "invalid unit {:?} for type {}",
+            unit, attribute_type
+        )));
+    }
+    if parts.next().is_some() {
+        return Err(error("expected KEY[:TYPE[:UNIT]]=VALUE".into()));
+    }
+    Ok(Attribute {
+        key: key.to_string(),
+        attribute_type,
+        unit: unit.to_string(),
+        value: attribute_value.to_string(),
+    })
+}
+
+/// A text added to every footprint with --footprint-text.
+#[derive(Debug, PartialEq, Clone)]
+struct FootprintText {
+    value: String,
+    layer: &'static str,
+    /// Position in LibrePCB coordinates (after the alignment)
+    position: (f64, f64),
+    height: f64,
+    rotation: f64,
+    /// Horizontal and vertical alignment, e.g. "left bottom"
+    align: String,
+    /// Whether the text is mirrored (on the bottom side)
+    mirror: bool,
+}
+
+/// Parse a --footprint-text value like `{{BOARD}};x=1;y=-2;layer=top_legend`.
+///
+/// The text may contain LibrePCB variables like `{{BOARD}}` or `{{DATE}}`.
+fn parse_footprint_text(
+    value: &str,
+    config: &Config,
+    layer_names: LayerNames,
+) -> Result<FootprintText> {
+    let error = |reason: String| Error::FootprintText {
+        text: value.to_string(),
+        reason,
+    };
+    let mut parts = value.split(';');
+    let mut text = FootprintText {
+        value: parts.next().unwrap_or("").to_string(),
+        layer: Layer::TopLegend.name(layer_names),
+        position: (0.0, 0.0),
+        height: 1.0,
+        rotation: 0.0,
+        align: "left bottom".to_string(),
+        mirror: false,
+    };
+    for option in parts {
+        let (key, option_value) = option
+            .split_once('=')
+            .ok_or_else(|| error(format!("Expected key=value instead of {:?}", option)))?;
+        let number = || {
+            option_value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| error(format!("Invalid number {:?} for {}", option_value, key)))
+        };
+        match key.trim() {
+            "x" => text.position.0 = number()?,
+            "y" => text.position.1 = number()?,
+            "height" => text.height = number()?,
+            "rotation" => text.rotation = number()?,
+            "layer" => {
+                text.layer = config
+                    .resolve_layer(option_value.trim())
+                    .ok_or_else(|| error(format!("Unknown layer {:?}", option_value)))?
+                    .name(layer_names)
+            }
+            "align" => {
+                let words: Vec<&str> = option_value.split_whitespace().collect();
+                match words[..] {
+                    [horizontal @ ("left" | "center" | "right"), vertical @ ("top" | "center" | "bottom")] => {
+                        text.align = format!("{} {}", horizontal, vertical)
+                    }
+                    _ => {
+                        return Err(error(format!(
+                            "Invalid alignment {:?} (e.g. \"center bottom\")",
+                            option_value
+                        )))
+                    }
+                }
+            }
+            key => return Err(error(format!("Unknown option {:?}", key))),
+        }
+    }
+    validation::validate_text_height(text.height)
+        .map_err(|reason| error(format!("Invalid height {}: {}", text.height, reason)))?;
+    Ok(text)
+}
+
+/// Load the nodes of an S-expression snippet passed on the command line.
+///
+/// Snippets starting with `@` are read from the file with the given path.
+/// Every `$uuid` placeholder is replaced with a new UUID.
+fn load_snippet(value: &str) -> Result<Vec<String>> {
+    let text = match value.strip_prefix('@') {
+        Some(path) => read_to_string(path).map_err(|source| Error::ReadFile {
+            path: path.into(),
+            source,
+        })?,
+        None => value.to_string(),
+    };
+    let mut parts = text.split("$uuid");
+    let mut text = parts.next().unwrap_or("").to_string();
+    for part in parts {
+        text.push_str(&make_uuid().to_string());
+        text.push_str(part);
+    }
+    let source = format!("(snippet\n{}\n)", text);
+    let root = sexpr::parse(&source).map_err(|reason| Error::Snippet {
+        snippet: value.to_string(),
+        reason,
+    })?;
+    Ok(root.lists().flat_map(|list| list.to_lines(1)).collect())
+}
+
+/// Generate an approval for a library check message.
+///
+/// Approved messages are no longer reported by the LibrePCB library editor.
+fn make_approval(message: &str, footprint: Option<&Uuid>) -> Vec<String> {
+    match footprint {
+        Some(uuid) => vec![
+            format!(" (approved {}", message),
+            format!("  (footprint {})", uuid),
+            " )".to_string(),
+        ],
+        None => vec![format!(" (approved {})", message)],
+    }
+}
+
+/// Generate a rectangle around the (centered) paths of a symbol.
+fn make_symbol_rectangle(
+    paths: &[geometry::Path],
+    layer: SymbolLayer,
+    fill: bool,
+    grab_area: bool,
+) -> Vec<String> {
+    let polylines: Vec<Polyline> = paths.iter().map(|path| geometry::points(path)).collect();
+    let rect = match geometry::bounds(&polylines) {
+        Some(rect) => rect,
+        None => return vec![],
+    };
+    let (x, y) = (
+        (rect.x_max - rect.x_min) / 2.0,
+        (rect.y_max - rect.y_min) / 2.0,
+    );
+    let mut lines = vec![format!(
+        r#" (polygon {} (layer {})"#,
+        make_uuid(),
+        layer.name()
+    )];
+    lines.push(format!(
+        r#"  (width {}) (fill {}) (grab_area {})"#,
+        format_float(if fill { 0.0 } else { SYMBOL_LINE_WIDTH }),
+        fill,
+        grab_area
+    ));
+    for (x, y) in [(-x, y), (x, y), (x, -y), (-x, -y), (-x, y)] {
+        lines.push(format!(
+            r#"  (vertex (position {} {}) (angle 0.0))"#,
+            format_float(x),
+            format_float(y)
+        ));
+    }
+    lines.push(" )".to_string());
     lines
 }
 
 fn make_symbol(
     uuid: &str,
-    name: &str,
-    description: &str,
-    keywords: &str,
-    author: &str,
-    version: &str,
+    metadata: &Metadata,
     uuid_cmpcat: Option<&str>,
-    polylines: &[Polyline],
+    format_version: FormatVersion,
+    approve_checks: bool,
+    grab_area: GrabArea,
+    layer: SymbolLayer,
+    hidden_grab_area: bool,
+    bounding_box: bool,
+    labels: (f64, HorizontalAlign),
+    paths: &[geometry::Path],
+    texts: &[Text],
 ) -> Vec<String> {
     let mut lines: Vec<String> = vec![];
     lines.push(format!(r#"(librepcb_symbol {}"#, uuid));
-    lines.push(format!(r#" (name "{}")"#, name));
-    lines.push(format!(r#" (description "{}")"#, description));
-    lines.push(format!(r#" (keywords "{}")"#, keywords));
-    lines.push(format!(r#" (author "{}")"#, author));
-    lines.push(format!(r#" (version "{}")"#, version));
-    lines.push(format!(
-        r#" (created {})"#,
-        Utc::now().to_rfc3339().replace("+00:00", "Z")
-    ));
+    lines.extend(metadata.lines());
     lines.push(" (deprecated false)".to_string());
+    if format_version == FormatVersion::V1 {
+        lines.push(r#" (generated_by "svg2librepcb")"#.to_string());
+    }
     if let Some(uuid) = uuid_cmpcat {
         lines.push(format!(r#" (category {})"#, uuid));
     }
 
     // Polygon
-    let polygon = make_polygon("sym_outlines", Align::Center, polylines);
-    lines.extend_from_slice(&polygon.lines);
+    let (offset, bounds) = alignment(Align::Center, paths);
+    let texts = if bounding_box {
+        // Just the outline of the artwork (centered), without its texts
+        lines.extend(make_symbol_rectangle(
+            paths,
+            layer,
+            false,
+            grab_area != GrabArea::None,
+        ));
+        &[]
+    } else {
+        lines.extend(make_polygon(layer.name(), offset, grab_area, paths));
+        texts
+    };
+    if hidden_grab_area {
+        lines.extend(make_symbol_rectangle(
+            paths,
+            SymbolLayer::HiddenGrabAreas,
+            true,
+            true,
+        ));
+    }
 
-    // Label: Value
-    lines.push(format!(
-        r#" (text {} (layer sym_values) (value "{{{{VALUE}}}}")"#,
-        make_uuid()
-    ));
-    lines.push(format!(
-        r#"  (align center top) (height 2.5) (position 0.0 {}) (rotation 0.0)"#,
-        format_float(polygon.transformed_bounds.y_min - 1.27)
-    ));
-    lines.push(" )".to_string());
+    // Texts of the drawing
+    let (dx, dy) = offset;
+    for text in texts {
+        lines.push(format!(
+            r#" (text {} (layer {}) (value "{}")"#,
+            make_uuid(),
+            layer.name(),
+            escape_string(&text.content)
+        ));
+        lines.push(format!(
+            r#"  (align {} top) (height {}) (position {} {}) (rotation {})"#,
+            text.align,
+            format_float(text.height),
+            format_float(text.position.x + dx),
+            format_float(-(text.position.y + dy)), // Invert axis
+            format_float(text.rotation)
+        ));
+        lines.push(" )".to_string());
+    }
 
-    // Label: Name
-    lines.push(format!(
-        r#" (text {} (layer sym_names) (value "{{{{NAME}}}}")"#,
-        make_uuid()
-    ));
-    lines.push(format!(
-        r#"  (align center bottom) (height 2.5) (position 0.0 {}) (rotation 0.0)"#,
-        format_float(polygon.transformed_bounds.y_max + 1.27)
-    ));
-    lines.push(" )".to_string());
+    // Labels: Value below and name above the artwork
+    let (label_height, label_align) = labels;
+    let x = match label_align {
+        HorizontalAlign::Left => bounds.x_min,
+        HorizontalAlign::Center => 0.0,
+        HorizontalAlign::Right => bounds.x_max,
+    };
+    for (layer, value, vertical, y) in [
+        ("sym_values", "VALUE", "top", bounds.y_min - 1.27),
+        ("sym_names", "NAME", "bottom", bounds.y_max + 1.27),
+    ] {
+        lines.push(format!(
+            r#" (text {} (layer {}) (value "{{{{{}}}}}")"#,
+            make_uuid(),
+            layer,
+            value
+        ));
+        lines.push(format!(
+            r#"  (align {} {}) (height {}) (position {} {}) (rotation 0.0)"#,
+            label_align.name(),
+            vertical,
+            format_float(label_height),
+            format_float(x),
+            format_float(y)
+        ));
+        lines.push(" )".to_string());
+    }
 
+    if approve_checks && uuid_cmpcat.is_none() {
+        lines.extend(make_approval("missing_categories", None));
+    }
     lines.push(")".to_string());
     lines
 }
 
 fn make_component(
     uuid: &str,
-    name: &str,
-    description: &str,
-    keywords: &str,
-    author: &str,
-    version: &str,
+    metadata: &Metadata,
     uuid_sym: &str,
     uuid_cmpcat: Option<&str>,
+    prefix: &str,
+    default_value: &str,
+    schematic_only: bool,
+    attributes: &[Attribute],
+    variant: (&str, &str, &str),
+    gate: (&str, bool),
+    format_version: FormatVersion,
+    approve_checks: bool,
 ) -> Vec<String> {
     let mut lines: Vec<String> = vec![];
     lines.push(format!(r#"(librepcb_component {}"#, uuid));
-    lines.push(format!(r#" (name "{}")"#, name));
-    lines.push(format!(r#" (description "{}")"#, description));
-    lines.push(format!(r#" (keywords "{}")"#, keywords));
-    lines.push(format!(r#" (author "{}")"#, author));
-    lines.push(format!(r#" (version "{}")"#, version));
-    lines.push(format!(
-        r#" (created {})"#,
-        Utc::now().to_rfc3339().replace("+00:00", "Z")
-    ));
+    lines.extend(metadata.lines());
     lines.push(r#" (deprecated false)"#.to_string());
+    if format_version == FormatVersion::V1 {
+        lines.push(r#" (generated_by "svg2librepcb")"#.to_string());
+    }
     if let Some(uuid) = uuid_cmpcat {
         lines.push(format!(r#" (category {})"#, uuid));
     }
-    lines.push(format!(r#" (schematic_only false)"#));
-    lines.push(format!(r#" (default_value "")"#));
-    lines.push(format!(r#" (prefix "")"#));
-    lines.push(format!(r#" (variant {} (norm "")"#, make_uuid()));
-    lines.push(format!(r#"  (name "default")"#));
-    lines.push(format!(r#"  (description "")"#));
+    lines.push(format!(r#" (schematic_only {})"#, schematic_only));
+    lines.push(format!(
+        r#" (default_value "{}")"#,
+        escape_string(default_value)
+    ));
+    lines.push(format!(r#" (prefix "{}")"#, escape_string(prefix)));
+    for attribute in attributes {
+        lines.push(format!(
+            r#" (attribute "{}" (type {}) (unit {}) (value "{}"))"#,
+            attribute.key,
+            attribute.attribute_type,
+            attribute.unit,
+            escape_string(&attribute.value)
+        ));
+    }
+    let (variant_name, variant_description, norm) = variant;
+    lines.push(format!(
+        r#" (variant {} (norm "{}")"#,
+        make_uuid(),
+        escape_string(norm)
+    ));
+    lines.push(format!(r#"  (name "{}")"#, escape_string(variant_name)));
+    lines.push(format!(
+        r#"  (description "{}")"#,
+        escape_string(variant_description)
+    ));
     lines.push(format!(r#"  (gate {}"#, make_uuid()));
     lines.push(format!(r#"   (symbol {})"#, uuid_sym));
+    let (gate_suffix, gate_required) = gate;
     lines.push(format!(
-        r#"   (position 0.0 0.0) (rotation 0.0) (required true) (suffix "")"#
+        r#"   (position 0.0 0.0) (rotation 0.0) (required {}) (suffix "{}")"#,
+        gate_required,
+        escape_string(gate_suffix)
     ));
     lines.push(format!(r#"  )"#));
     lines.push(format!(r#" )"#));
+    if approve_checks {
+        if uuid_cmpcat.is_none() {
+            lines.extend(make_approval("missing_categories", None));
+        }
+        if default_value.is_empty() {
+            lines.extend(make_approval("missing_default_value", None));
+        }
+        if prefix.is_empty() {
+            lines.extend(make_approval("missing_prefix", None));
+        }
+    }
     lines.push(format!(")"));
     lines
 }
 
 fn make_package(
     uuid: &str,
-    name: &str,
-    description: &str,
-    keywords: &str,
-    author: &str,
-    version: &str,
+    metadata: &Metadata,
     uuid_pkgcat: Option<&str>,
-    footprints: &[Vec<String>],
+    format_version: FormatVersion,
+    approve_checks: bool,
+    pads: &[String],
+    footprints: &[Footprint],
 ) -> Vec<String> {
     let mut lines: Vec<String> = vec![];
     lines.push(format!(r#"(librepcb_package {}"#, uuid));
-    lines.push(format!(r#" (name "{}")"#, name));
-    lines.push(format!(r#" (description "{}")"#, description));
-    lines.push(format!(r#" (keywords "{}")"#, keywords));
-    lines.push(format!(r#" (author "{}")"#, author));
-    lines.push(format!(r#" (version "{}")"#, version));
-    lines.push(format!(
-        r#" (created {})"#,
-        Utc::now().to_rfc3339().replace("+00:00", "Z")
-    ));
+    lines.extend(metadata.lines());
     lines.push(" (deprecated false)".to_string());
+    if format_version == FormatVersion::V1 {
+        lines.push(r#" (generated_by "svg2librepcb")"#.to_string());
+    }
     if let Some(uuid) = uuid_pkgcat {
         lines.push(format!(r#" (category {})"#, uuid));
     }
+    if format_version == FormatVersion::V1 {
+        // Graphics only, nothing to assemble
+        lines.push(" (assembly_type none)".to_string());
+    }
+    for (i, uuid) in pads.iter().enumerate() {
+        lines.push(format!(r#" (pad {} (name "TP{}"))"#, uuid, i + 1));
+    }
     for footprint in footprints {
-        for line in footprint {
+        for line in &footprint.lines {
             lines.push(format!(" {}", line));
         }
     }
+    if approve_checks {
+        if uuid_pkgcat.is_none() {
+            lines.extend(make_approval("missing_categories", None));
+        }
+        // Artwork footprints have no courtyard and no name/value texts
+        for footprint in footprints {
+            for message in [
+                "missing_courtyard",
+                "missing_name_text",
+                "missing_value_text",
+            ] {
+                lines.extend(make_approval(message, Some(&footprint.uuid)));
+            }
+        }
+    }
     lines.push(")".to_string());
     lines
 }
 
 fn make_device(
     uuid: &str,
-    name: &str,
-    description: &str,
-    keywords: &str,
-    author: &str,
-    version: &str,
+    metadata: &Metadata,
     uuid_cmp: &str,
     uuid_pkg: &str,
     uuid_cmpcat: Option<&str>,
+    format_version: FormatVersion,
+    approve_checks: bool,
+    pads: &[String],
+    part: Option<(&str, &str)>,
 ) -> Vec<String> {
     let mut lines: Vec<String> = vec![];
     lines.push(format!(r#"(librepcb_device {}"#, uuid));
-    lines.push(format!(r#" (name "{}")"#, name));
-    lines.push(format!(r#" (description "{}")"#, description));
-    lines.push(format!(r#" (keywords "{}")"#, keywords));
-    lines.push(format!(r#" (author "{}")"#, author));
-    lines.push(format!(r#" (version "{}")"#, version));
-    lines.push(format!(
-        r#" (created {})"#,
-        Utc::now().to_rfc3339().replace("+00:00", "Z")
-    ));
+    lines.extend(metadata.lines());
     lines.push(r#" (deprecated false)"#.to_string());
+    if format_version == FormatVersion::V1 {
+        lines.push(r#" (generated_by "svg2librepcb")"#.to_string());
+    }
     if let Some(uuid) = uuid_cmpcat {
         lines.push(format!(r#" (category {})"#, uuid));
     }
     lines.push(format!(r#" (component {})"#, uuid_cmp));
     lines.push(format!(r#" (package {})"#, uuid_pkg));
+    for uuid in pads {
+        lines.push(format!(r#" (pad {} (signal none))"#, uuid));
+    }
+    if let Some((mpn, manufacturer)) = part {
+        lines.push(format!(
+            r#" (part "{}" (manufacturer "{}")"#,
+            escape_string(mpn),
+            escape_string(manufacturer)
+        ));
+        lines.push(" )".to_string());
+    }
+    if approve_checks && uuid_cmpcat.is_none() {
+        lines.extend(make_approval("missing_categories", None));
+    }
     lines.push(format!(")"));
     lines
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Directory of an element within the library.
+fn element_dir(lib_path: &Path, kind: ElementKind, uuid: &str) -> PathBuf {
+    lib_path.join(kind.dir_name()).join(uuid)
+}
+
+/// UUIDs of the other elements of a kind in the library with the given name.
+/// Elements which cannot be read are skipped.
+fn elements_named(lib_path: &Path, kind: ElementKind, name: &str, uuid: &str) -> Vec<String> {
+    let entries = match fs::read_dir(lib_path.join(kind.dir_name())) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut uuids: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|other| other != uuid)
+        .filter(|other| {
+            let path = element_dir(lib_path, kind, other).join(kind.file_name());
+            fs::read_to_string(path).is_ok_and(|source| {
+                sexpr::parse(&source)
+                    .is_ok_and(|root| root.child("name").and_then(|list| list.arg(0)) == Some(name))
+            })
+        })
+        .collect();
+    uuids.sort();
+    uuids
+}
+
+/// Move an existing element directory into a timestamped backup directory
+/// and return the new location.
+fn backup_element(
+    lib_path: &Path,
+    backup_root: &Path,
+    kind: ElementKind,
+    uuid: &str,
+) -> Result<PathBuf> {
+    let dir = element_dir(lib_path, kind, uuid);
+    let backup_dir = backup_root.join(kind.dir_name());
+    fs::create_dir_all(&backup_dir).map_err(|source| Error::Write {
+        element: kind,
+        operation: "create backup directory",
+        path: backup_dir.clone(),
+        source,
+    })?;
+    let target = backup_dir.join(uuid);
+    fs::rename(&dir, &target).map_err(|source| Error::Write {
+        element: kind,
+        operation: "move to backup",
+        path: dir,
+        source,
+    })?;
+    Ok(target)
+}
 
-    // Load and parse SVG
-    let svg_string = load_svg(&args.svgfile).context("Could not read SVG file")?;
-    let polylines = svg2polylines::parse(&svg_string, args.flattening_tolerance, true)
-        .expect("Could not parse SVG file");
+/// Write an element directory (version file, S-expression file and list of
+/// generated UUIDs) into the library.
+fn write_element(
+    lib_path: &Path,
+    kind: ElementKind,
+    uuid: &str,
+    format_version: FormatVersion,
+    contents: &str,
+    generated: &[String],
+) -> Result<()> {
+    let dir = element_dir(lib_path, kind, uuid);
+    log!("Writing {} to {}", kind, dir.display());
+    fs::create_dir_all(&dir).map_err(|source| Error::Write {
+        element: kind,
+        operation: "create directory",
+        path: dir.clone(),
+        source,
+    })?;
+    let mut files = vec![
+        (
+            format!(".librepcb-{}", kind.dir_name()),
+            format!("{}\n", format_version.file_version()),
+        ),
+        (kind.file_name().to_string(), contents.to_string()),
+    ];
+    if !generated.is_empty() {
+        files.push((
+            update::GENERATED_FILE.to_string(),
+            generated.join("\n") + "\n",
+        ));
+    }
+    for (name, contents) in &files {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|source| Error::Write {
+            element: kind,
+            operation: "write file",
+            path,
+            source,
+        })?;
+    }
+    Ok(())
+}
 
-    // Ensure that output library path exists
-    let lib_path = match args.outpath.canonicalize() {
-        Ok(path) => path,
-        Err(e) => {
-            eprintln!("Error: Invalid output path: {}", e);
-            exit(1);
+fn main() {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let result = match args.command.take() {
+        Some(Subcommand::Measure(args)) => measure(&args),
+        Some(Subcommand::Validate(args)) => validate(&args),
+        Some(Subcommand::Doctor(args)) => doctor(&args),
+        Some(Subcommand::Regen(args)) => regen(&args),
+        None => {
+            args.format_version_given =
+                matches.value_source("format-version") == Some(clap::ValueSource::CommandLine);
+            args.passed_args = provenance::passed_args(env::args().skip(1), &args.svgfile);
+            log::init(args.verbose, args.log_file.as_deref()).and_then(|_| run(args))
         }
     };
-    if !lib_path.exists() {
-        eprintln!("Error: Output path {:?} does not exist", lib_path);
+    if let Err(e) = result {
+        log::write_file(format_args!("error: {}", e));
+        eprintln!("{:?}", miette::Report::new(e));
         exit(1);
     }
-    if !lib_path.is_dir() {
-        eprintln!("Error: Output path {:?} is not a directory", lib_path);
-        exit(1);
+}
+
+/// Sanitize an element name (`--sanitize-names`), with a warning if it had to
+/// be shortened.
+fn sanitize_name(name: &str) -> String {
+    let sanitized = validation::sanitize_name(name);
+    if sanitized.ends_with('…') && !name.trim_end().ends_with('…') {
+        Warning::ShortenedName {
+            name: name.to_string(),
+            shortened: sanitized.clone(),
+        }
+        .report();
     }
+    sanitized
+}
 
-    // Generate footprints
-    let mut footprints = vec![];
-    if args.layer_copper {
-        footprints.push(make_footprint(
-            "top_cu",
-            "Top Copper",
-            "",
-            args.align,
-            &polylines,
-        ));
+/// Name of an element derived from the main name, e.g. `Logo (Mask Window)`.
+/// Overly long names are shortened with a warning.
+fn derived_name(name: &str, suffix: &str) -> String {
+    let derived = validation::name_with_suffix(name, &format!(" ({})", suffix));
+    if !derived.starts_with(name) {
+        Warning::ShortenedName {
+            name: format!("{} ({})", name, suffix),
+            shortened: derived.clone(),
+        }
+        .report();
     }
-    if args.layer_placement {
-        footprints.push(make_footprint(
-            "top_placement",
-            "Top Placement",
-            "",
-            args.align,
-            &polylines,
-        ));
+    derived
+}
+
+/// Ensure that the metadata will be accepted by LibrePCB.
+fn validate_metadata(args: &Args) -> Result<()> {
+    fn check(
+        field: &'static str,
+        value: &str,
+        result: std::result::Result<(), String>,
+    ) -> Result<()> {
+        result.map_err(|reason| Error::InvalidMetadata {
+            field,
+            value: value.to_string(),
+            reason,
+        })
     }
-    if args.layer_stopmask {
-        footprints.push(make_footprint(
-            "top_stop_mask",
-            "Top Stop Mask",
-            "",
-            args.align,
-            &polylines,
-        ));
+    check("name", &args.name, validation::validate_name(&args.name))?;
+    check(
+        "version",
+        &args.version,
+        validation::validate_version(&args.version),
+    )?;
+    check(
+        "keywords",
+        &args.keywords,
+        validation::validate_single_line(&args.keywords),
+    )?;
+    check(
+        "prefix",
+        &args.prefix,
+        validation::validate_prefix(&args.prefix),
+    )?;
+    check(
+        "default value",
+        &args.default_value,
+        validation::validate_single_line(&args.default_value),
+    )?;
+    check(
+        "norm",
+        &args.norm,
+        validation::validate_single_line(&args.norm),
+    )?;
+    if let Some(uuid) = &args.existing_cmp {
+        check(
+            "component UUID",
+            uuid,
+            Uuid::parse_str(uuid).map(|_| ()).map_err(|e| e.to_string()),
+        )?;
     }
+    check(
+        "variant name",
+        &args.variant_name,
+        validation::validate_name(&args.variant_name),
+    )?;
+    check(
+        "gate suffix",
+        &args.gate_suffix,
+        validation::validate_gate_suffix(&args.gate_suffix),
+    )?;
+    Ok(())
+}
 
-    // Generate symbol
-    let uuid_sym = args.uuid_sym.unwrap_or_else(|| make_uuid().to_string());
-    let sym = make_symbol(
-        &uuid_sym,
-        &args.name,
-        &args.description,
-        &args.author,
-        &args.keywords,
-        &args.version,
-        args.uuid_cmpcat.as_deref(),
-        &polylines,
-    );
+fn run(mut args: Args) -> Result<()> {
+    match (args.clipboard, args.svgfile.as_os_str().is_empty()) {
+        (true, false) => Err(Error::ConflictingOptions("--clipboard", "an input file")),
+        (false, true) => Err(Error::MissingInput),
+        (true, true) => {
+            // The clipboard contents are converted like a file, so that
+            // error messages can refer to it
+            let svg = convert::read_clipboard().map_err(Error::Clipboard)?;
+            let dir = TempDir::new("clipboard").map_err(|source| Error::WriteFile {
+                path: env::temp_dir(),
+                source,
+            })?;
+            let path = dir.path().join("clipboard.svg");
+            fs::write(&path, svg).map_err(|source| Error::WriteFile {
+                path: path.clone(),
+                source,
+            })?;
+            args.svgfile = path;
+            run_input(args)
+        }
+        (false, false) => run_input(args),
+    }
+}
 
-    // Generate component
-    let uuid_cmp = args.uuid_cmp.unwrap_or_else(|| make_uuid().to_string());
-    let cmp = make_component(
-        &uuid_cmp,
-        &args.name,
-        &args.description,
-        &args.author,
-        &args.keywords,
-        &args.version,
-        &uuid_sym,
-        args.uuid_cmpcat.as_deref(),
-    );
+/// Convert the input file (with --clipboard, a temporary copy of the
+/// clipboard contents), downloading it first if it is a URL.
+fn run_input(mut args: Args) -> Result<()> {
+    if let Some(url) = args.svgfile.to_str().filter(|input| remote::is_url(input)) {
+        // The download is converted like a file, keeping its name for the
+        // file type and default element name
+        let url = url.to_string();
+        let data = remote::fetch(&url).map_err(|reason| Error::Download {
+            url: url.clone(),
+            reason,
+        })?;
+        let dir = TempDir::new("download").map_err(|source| Error::WriteFile {
+            path: env::temp_dir(),
+            source,
+        })?;
+        let path = dir.path().join(remote::file_name(&url));
+        fs::write(&path, data).map_err(|source| Error::WriteFile {
+            path: path.clone(),
+            source,
+        })?;
+        args.svgfile = path;
+        return run_input(args);
+    }
 
-    // Generate package
-    let uuid_pkg = args.uuid_pkg.unwrap_or_else(|| make_uuid().to_string());
-    let pkg = make_package(
-        &uuid_pkg,
-        &args.name,
-        &args.description,
-        &args.author,
-        &args.keywords,
-        &args.version,
-        args.uuid_pkgcat.as_deref(),
-        &footprints,
-    );
+    if let Some(value) = args.metadata_json.take() {
+        apply_metadata_json(&mut args, JsonMetadata::parse(&value)?);
+    }
 
-    // Generate device
-    let uuid_dev = args.uuid_dev.unwrap_or_else(|| make_uuid().to_string());
-    let dev = make_device(
-        &uuid_dev,
-        &args.name,
-        &args.description,
-        &args.author,
-        &args.keywords,
-        &args.version,
-        &uuid_cmp,
-        &uuid_pkg,
-        args.uuid_cmpcat.as_deref(),
-    );
+    // The output path and author can be omitted after the first conversion
+    let mut settings_path = Settings::path();
+    let settings = match settings_path.as_deref().map(Settings::load) {
+        Some(Ok(settings)) => settings,
+        // The settings are not needed if both are given
+        Some(Err(e)) if !args.outpaths.is_empty() && !args.author.is_empty() => {
+            Warning::LoadSettings {
+                path: settings_path.take().expect("The settings were loaded"),
+                reason: e.to_string(),
+            }
+            .report();
+            Settings::default()
+        }
+        Some(Err(e)) => return Err(e),
+        None => Settings::default(),
+    };
+    if args.outpaths.is_empty() {
+        let outpath = settings
+            .outpath
+            .clone()
+            .ok_or(Error::MissingOption("--outpath"))?;
+        args.outpaths.push(outpath);
+    }
 
-    // Write files to library
-    let sym_path = lib_path.join("sym").join(&uuid_sym);
-    let cmp_path = lib_path.join("cmp").join(&uuid_cmp);
-    let pkg_path = lib_path.join("pkg").join(&uuid_pkg);
-    let dev_path = lib_path.join("dev").join(&uuid_dev);
-    fs::create_dir_all(&sym_path).unwrap();
-    fs::create_dir_all(&cmp_path).unwrap();
-    fs::create_dir_all(&pkg_path).unwrap();
-    fs::create_dir_all(&dev_path).unwrap();
-    fs::write(sym_path.join(".librepcb-sym"), "0.1").unwrap();
-    fs::write(cmp_path.join(".librepcb-cmp"), "0.1").unwrap();
-    fs::write(pkg_path.join(".librepcb-pkg"), "0.1").unwrap();
-    fs::write(dev_path.join(".librepcb-dev"), "0.1").unwrap();
-    fs::write(sym_path.join("symbol.lp"), sym.join("\n")).unwrap();
-    fs::write(cmp_path.join("component.lp"), cmp.join("\n")).unwrap();
-    fs::write(pkg_path.join("package.lp"), pkg.join("\n")).unwrap();
-    fs::write(dev_path.join("device.lp"), dev.join("\n")).unwrap();
-
-    // Echo original SVG on stdout for compatibility with Inkscape.
-    println!("{}", svg_string);
+    // Only an author given by the user is remembered, not one set by a
+    // library config
+    let author = Some(args.author.clone())
+        .filter(|author| !author.is_empty())
+        .or_else(|| settings.author.clone());
 
-    Ok(())
-}
+    let written = error::report_once(|| convert_libraries(&args, settings.author.as_deref()))?;
+    finish_conversions(&args, &written)?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Only the first output path is remembered
+    let outpath = &args.outpaths[0];
+    let used = Settings {
+        outpath: Some(outpath.canonicalize().unwrap_or_else(|_| outpath.clone())),
+        author,
+    };
+    if let Some(path) = settings_path.filter(|_| used != settings) {
+        if let Err(reason) = used.save(&path) {
+            Warning::SaveSettings { path, reason }.report();
+        }
+    }
+    error::check_promoted()
+}
 
-    #[test]
+/// Run the conversion for every output path (with its own config file and
+/// UUIDs, unless given), and write the elements into the libraries once all
+/// of them were checked. The author defaults to the remembered one.
+fn convert_libraries(args: &Args, author: Option<&str>) -> Result<Vec<Written>> {
+    if let Some(seed) = args.uuid_seed {
+        seed_uuids(seed);
+    }
+    let mut pending = vec![];
+    for outpath in &args.outpaths {
+        let mut args = args.clone();
+        args.outpath = outpath.clone();
+        if let Some(library_config) = LibraryConfig::load(&args.outpath)? {
+            apply_library_config(&mut args, library_config)?;
+        }
+        if args.author.is_empty() {
+            args.author = author
+                .map(str::to_string)
+                .ok_or(Error::MissingOption("--author"))?;
+        }
+        pending.push(run_conversion(args)?);
+    }
+    pending.into_iter().map(|write| write()).collect()
+}
+
+/// Write the report, print the generated elements and echo the SVG for
+/// Inkscape, once for all libraries.
+fn finish_conversions(args: &Args, written: &[Written]) -> Result<()> {
+    if let Some(path) = &args.report {
+        let report = batch::Report {
+            libraries: written
+                .iter()
+                .map(|written| (written.library.as_path(), &written.files[..]))
+                .collect(),
+            skipped: written.first().map_or(&[][..], |written| &written.skipped),
+        };
+        report.write(path)?;
+    }
+
+    // The files are the same in every library, their elements are printed
+    // together
+    if let Some(format) = args.print {
+        let files = written.first().map_or(0, |written| written.files.len());
+        for index in 0..files {
+            let elements: Vec<(&Path, ElementKind, &str)> = written
+                .iter()
+                .flat_map(|written| {
+                    let entry = &written.files[index];
+                    entry
+                        .elements
+                        .iter()
+                        .map(move |(kind, uuid)| (written.library.as_path(), *kind, uuid.as_str()))
+                })
+                .collect();
+            print!(
+                "{}",
+                format_result(format, &written[0].files[index].name, &elements)
+            );
+        }
+    }
+
+    // Echo original SVG on stdout for compatibility with Inkscape. Inkscape
+    // passes the selection with --id, which is also used to detect older
+    // versions of the extension file without the --inkscape flag.
+    if args.inkscape || !args.ids.is_empty() {
+        if let Some(source) = written.first().and_then(|written| written.source.as_ref()) {
+            println!("{}", source);
+        }
+    }
+    Ok(())
+}
+
+/// Regenerate packages from their stored input file and options.
+fn regen(args: &RegenArgs) -> Result<()> {
+    let mut packages = vec![];
+    for path in &args.paths {
+        packages.extend(provenance::find_packages(path)?);
+    }
+    if packages.is_empty() {
+        return Err(Error::Regen {
+            path: args.paths[0].clone(),
+            reason: "no packages generated with --keep-source found".into(),
+        });
+    }
+    for package_dir in packages {
+        let error = |reason: String| Error::Regen {
+            path: package_dir.clone(),
+            reason,
+        };
+        let provenance = provenance::Provenance::load(&package_dir)?;
+        let options = provenance::merge_args(&provenance.args, &args.overrides);
+        if options.iter().any(|arg| arg == "--device-per-scale") {
+            return Err(error(
+                "packages generated with --device-per-scale cannot be regenerated".into(),
+            ));
+        }
+        let library = package_dir
+            .parent()
+            .and_then(Path::parent)
+            .ok_or_else(|| error("the package is not in a library".into()))?;
+        let source = provenance.source_path(&package_dir);
+
+        // The elements are replaced, keeping their UUIDs
+        let mut command_line: Vec<OsString> = vec![
+            "svg2librepcb".into(),
+            source.clone().into(),
+            "--outpath".into(),
+            library.into(),
+            "--force".into(),
+            "--keep-source".into(),
+        ];
+        let uuids = [
+            ("--uuid-sym", &provenance.uuids.symbol),
+            ("--uuid-cmp", &provenance.uuids.component),
+            ("--uuid-pkg", &provenance.uuids.package),
+            ("--uuid-dev", &provenance.uuids.device),
+        ];
+        let existing_cmp = options.iter().any(|arg| arg.starts_with("--existing-cmp"));
+        for (option, uuid) in uuids {
+            if let Some(uuid) = uuid {
+                if !(existing_cmp && ["--uuid-sym", "--uuid-cmp"].contains(&option)) {
+                    command_line.push(format!("{}={}", option, uuid).into());
+                }
+            }
+        }
+        command_line.extend(options.iter().map(OsString::from));
+        if args.dry_run {
+            let command_line: Vec<String> = command_line
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            println!("{}", command_line.join(" "));
+            continue;
+        }
+
+        let mut conversion_args =
+            Args::try_parse_from(&command_line).map_err(|e| error(e.to_string()))?;
+        conversion_args.outpath = library.to_owned();
+        conversion_args.passed_args = provenance::passed_args(options, &source);
+        let finish_args = conversion_args.clone();
+        if let Some(seed) = conversion_args.uuid_seed {
+            seed_uuids(seed);
+        }
+        run_conversion(conversion_args)
+            .and_then(|write| write())
+            .and_then(|written| finish_conversions(&finish_args, &[written]))
+            .map_err(|e| Error::BatchFile {
+                path: package_dir.clone(),
+                source: Box::new(e),
+            })?;
+    }
+    Ok(())
+}
+
+/// Use the defaults of the library config file for the options which are not
+/// given on the command line.
+fn apply_library_config(args: &mut Args, library_config: LibraryConfig) -> Result<()> {
+    let path = args.outpath.join(config::LIBRARY_CONFIG_FILE);
+    if let Some(version) = &library_config.format_version {
+        if !args.format_version_given {
+            args.format_version =
+                FormatVersion::from_str(version, false).map_err(|_| Error::Config {
+                    path: path.clone(),
+                    reason: format!(
+                        "Unknown format version {:?} (expected \"0.1\" or \"1\")",
+                        version
+                    ),
+                })?;
+        }
+    }
+    if args.author.is_empty() {
+        if let Some(author) = &library_config.author {
+            args.author = author.clone();
+        }
+    }
+    if args.uuid_cmpcat.is_none() {
+        args.uuid_cmpcat = library_config.component_category.clone();
+    }
+    if args.uuid_pkgcat.is_none() {
+        args.uuid_pkgcat = library_config.package_category.clone();
+    }
+    if args.layers.is_empty() {
+        args.layers = library_config.layers.clone();
+    }
+    args.library_config = library_config;
+    Ok(())
+}
+
+/// Use the metadata of --metadata-json (which conflicts with the individual
+/// options).
+fn apply_metadata_json(args: &mut Args, metadata: JsonMetadata) {
+    let fields = [
+        (metadata.name, &mut args.name),
+        (metadata.description, &mut args.description),
+        (metadata.author, &mut args.author),
+        (metadata.version, &mut args.version),
+        (metadata.keywords, &mut args.keywords),
+        (metadata.prefix, &mut args.prefix),
+        (metadata.default_value, &mut args.default_value),
+    ];
+    for (value, field) in fields {
+        if let Some(value) = value {
+            *field = value;
+        }
+    }
+    args.uuid_cmpcat = metadata.component_category.or(args.uuid_cmpcat.take());
+    args.uuid_pkgcat = metadata.package_category.or(args.uuid_pkgcat.take());
+}
+
+/// Convert a file or directory.
+fn run_conversion(args: Args) -> Result<Pending<Written>> {
+    if args.svgfile.is_dir() {
+        return run_batch(args);
+    }
+    for (option, used) in [
+        ("--metadata-csv", args.metadata_csv.is_some()),
+        ("--report", args.report.is_some()),
+    ] {
+        if used {
+            return Err(Error::RequiresBatchMode(option));
+        }
+    }
+    let (output_format, verify) = (args.output_format, args.verify);
+    let outpath = args.outpath.clone();
+    let file_name = args
+        .svgfile
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let pending = convert(args)?;
+    Ok(Box::new(move || {
+        let converted = pending()?;
+        if output_format == OutputFormat::Librepcb {
+            finish_library(&outpath, verify)?;
+        }
+        Ok(Written {
+            library: converted.library,
+            files: vec![batch::ReportEntry {
+                file: file_name,
+                name: converted.name,
+                elements: converted.elements,
+                warnings: vec![],
+            }],
+            skipped: vec![],
+            source: Some(converted.source),
+        })
+    }))
+}
+
+/// Print the dimensions and statistics of the artwork.
+fn measure(args: &MeasureArgs) -> Result<()> {
+    let input = Input::load(
+        &args.svgfile,
+        &InputOptions {
+            clip_viewbox: args.clip_viewbox,
+            text_to_path: args.text_to_path,
+            native_text: false,
+            pcb_art: args.pcb_art,
+            ids: &args.ids,
+            kicad_layers: &args.kicad_layers,
+            min_opacity: args.min_opacity.unwrap_or(0.0),
+            outline_strokes: None,
+            treat_all_closed: args.treat_all_closed,
+            ellipse_tolerance: ELLIPSE_TOLERANCE,
+            rasterize_gradients: None,
+            report_refs: true,
+            limits: Limits::default(),
+        },
+    )?;
+    let (polylines, layers) = if args.pcb_art {
+        let (polylines, roles) =
+            input.parse_art(&art_palette(&args.art_color)?, args.flattening_tolerance)?;
+        let layers = art_layers(&roles)
+            .into_iter()
+            .map(|(layer, polylines)| (layer.title(), polylines))
+            .collect();
+        (polylines, layers)
+    } else {
+        let polylines = input.parse(args.flattening_tolerance)?;
+        (polylines.clone(), vec![("Artwork", polylines)])
+    };
+
+    let bounds = match geometry::bounds(&polylines) {
+        Some(bounds) => bounds,
+        None => {
+            println!("The artwork is empty");
+            return Ok(());
+        }
+    };
+    let (width, height) = (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min);
+    println!(
+        "Size: {} x {} mm",
+        format_float(width),
+        format_float(height)
+    );
+    for &scaled_width in &args.scales {
+        if width > 0.0 {
+            println!(
+                "  scaled to {} mm: {} x {} mm",
+                format_float(scaled_width),
+                format_float(scaled_width),
+                format_float(height * scaled_width / width)
+            );
+        }
+    }
+    println!();
+    println!(
+        "{:<16} {:>9} {:>9} {:>9}",
+        "Layer", "Polylines", "Closed", "Vertices"
+    );
+    for (title, polylines) in &layers {
+        println!(
+            "{:<16} {:>9} {:>9} {:>9}",
+            title,
+            polylines.len(),
+            polylines
+                .iter()
+                .filter(|polyline| geometry::is_closed(polyline))
+                .count(),
+            polylines.iter().map(Vec::len).sum::<usize>()
+        );
+    }
+    Ok(())
+}
+
+/// The external references of an SVG file which can not be resolved, with a
+/// message for each. Relative paths are resolved from the directory of the
+/// file, remote URLs are never fetched.
+fn unresolved_refs(path: &Path, source: &str) -> Vec<(Range<usize>, String)> {
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    svg::external_refs(source)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|reference| {
+            let href = reference.href.split('#').next().unwrap_or("");
+            let file = href.strip_prefix("file://").unwrap_or(href);
+            let message = if file.contains("://") {
+                format!("remote {} {:?} is not loaded", reference.kind, href)
+            } else if !base.join(file).exists() {
+                format!("{} {:?} not found", reference.kind, href)
+            } else {
+                return None;
+            };
+            Some((reference.range, message))
+        })
+        .collect()
+}
+
+/// Check files for problems, printing one line per problem.
+fn validate(args: &ValidateArgs) -> Result<()> {
+    let mut failed = 0;
+    let (mut errors, mut warnings) = (0, 0);
+    for path in &args.files {
+        let mut problems = vec![];
+        let mut report = |severity, location: String, message: String| {
+            match severity {
+                svg::Severity::Error => errors += 1,
+                svg::Severity::Warning => warnings += 1,
+            }
+            problems.push((severity, location, message));
+        };
+        let options = InputOptions {
+            clip_viewbox: false,
+            text_to_path: false,
+            // Texts are reported by the checks below
+            native_text: true,
+            pcb_art: false,
+            ids: &[],
+            kicad_layers: &[],
+            min_opacity: 0.0,
+            outline_strokes: None,
+            treat_all_closed: false,
+            ellipse_tolerance: ELLIPSE_TOLERANCE,
+            rasterize_gradients: None,
+            // Reported by the checks below
+            report_refs: false,
+            limits: Limits::default(),
+        };
+        match Input::load(path, &options)
+            .and_then(|input| input.parse(args.flattening_tolerance).map(|_| input))
+        {
+            Ok(input) if input.format == InputFormat::Svg => {
+                for problem in svg::check(&input.source).unwrap_or_default() {
+                    let (line, column) = line_column(&input.source, problem.range.start);
+                    let location = format!("{}:{}:{}", path.display(), line, column);
+                    report(problem.severity, location, problem.message);
+                }
+                for (range, message) in unresolved_refs(path, &input.source) {
+                    let (line, column) = line_column(&input.source, range.start);
+                    let location = format!("{}:{}:{}", path.display(), line, column);
+                    let message = format!("Unresolved reference: {}", message);
+                    report(svg::Severity::Error, location, message);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => report(
+                svg::Severity::Error,
+                path.display().to_string(),
+                e.to_string(),
+            ),
+        }
+        let has_errors = problems
+            .iter()
+            .any(|(severity, _, _)| *severity == svg::Severity::Error || args.strict);
+        if has_errors {
+            failed += 1;
+        }
+        if !args.quiet {
+            for (severity, location, message) in problems {
+                let severity = match severity {
+                    svg::Severity::Error => "error",
+                    svg::Severity::Warning => "warning",
+                };
+                println!("{}: {}: {}", location, severity, message);
+            }
+        }
+    }
+
+    let total = args.files.len();
+    println!(
+        "{}: {} files checked, {} errors, {} warnings",
+        if failed == 0 { "PASS" } else { "FAIL" },
+        total,
+        errors,
+        warnings
+    );
+    match failed {
+        0 => Ok(()),
+        failed => Err(Error::ValidationFailed { failed, total }),
+    }
+}
+
+/// Diagnose the environment, failing if there are errors.
+fn doctor(args: &DoctorArgs) -> Result<()> {
+    let findings = doctor::diagnose(args.outpath.as_deref(), args.config.as_deref());
+    for finding in &findings {
+        let status = match finding.status {
+            doctor::Status::Ok => "ok",
+            doctor::Status::Warning => "warning",
+            doctor::Status::Error => "error",
+        };
+        println!("{}: {}", status, finding.message);
+        if let Some(hint) = &finding.hint {
+            println!("    hint: {}", hint);
+        }
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.status == doctor::Status::Error)
+        .count();
+    match errors {
+        0 => Ok(()),
+        errors => Err(Error::DoctorFailed(errors)),
+    }
+}
+
+/// Line and column (both starting at 1) of a byte offset.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Convert all files in a directory.
+fn run_batch(args: Args) -> Result<Pending<Written>> {
+    let per_file_options = [
+        ("--name", !args.name.is_empty()),
+        ("--uuid-pkg", args.uuid_pkg.is_some()),
+        ("--uuid-sym", args.uuid_sym.is_some()),
+        ("--uuid-cmp", args.uuid_cmp.is_some()),
+        ("--uuid-dev", args.uuid_dev.is_some()),
+        ("--id", !args.ids.is_empty()),
+        ("--gui", args.gui),
+    ];
+    if let Some((option, _)) = per_file_options.iter().find(|(_, used)| *used) {
+        return Err(Error::NotInBatchMode(option));
+    }
+    let mut metadata = match &args.metadata_csv {
+        Some(path) => batch::load_metadata(path)?,
+        None => HashMap::new(),
+    };
+
+    let (files, unsupported) = batch::input_files(&args.svgfile)?;
+    let mut skipped = vec![];
+    for path in unsupported {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        skipped.push((file_name.into_owned(), "not a supported input file"));
+    }
+
+    // All files are checked before writing any of them
+    error::take_reported();
+    let mut pending = vec![];
+    for path in files {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_metadata = metadata.remove(&file_name).unwrap_or_default();
+        let mut file_args = args.clone();
+        // Without a name in the CSV file, the name template is expanded
+        // when converting the file
+        file_args.name = match file_metadata.name {
+            Some(name) => {
+                file_args.name_template = None;
+                name
+            }
+            None if args.name_template.is_some() => String::new(),
+            None => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        if let Some(description) = file_metadata.description {
+            file_args.description = description;
+            file_args.description_template = None;
+        }
+        if let Some(keywords) = file_metadata.keywords {
+            file_args.keywords = keywords;
+        }
+        if let Some(scales) = file_metadata.scales {
+            file_args.scales = scales;
+        }
+        file_args.svgfile = path.clone();
+        let converted = convert(file_args).map_err(|e| Error::BatchFile {
+            path: path.clone(),
+            source: Box::new(e),
+        })?;
+        pending.push((path, file_name, converted, error::take_reported()));
+    }
+
+    let mut unused: Vec<_> = metadata.into_keys().collect();
+    unused.sort();
+    for file_name in unused {
+        skipped.push((
+            file_name.clone(),
+            "listed in the metadata CSV file, but not found",
+        ));
+        Warning::UnusedMetadata(file_name).report();
+    }
+    Ok(Box::new(move || {
+        let mut files = vec![];
+        for (path, file_name, pending, warnings) in pending {
+            let converted = pending().map_err(|e| Error::BatchFile {
+                path,
+                source: Box::new(e),
+            })?;
+            files.push(batch::ReportEntry {
+                file: file_name,
+                name: converted.name,
+                elements: converted.elements,
+                warnings,
+            });
+        }
+        if args.output_format == OutputFormat::Librepcb {
+            finish_library(&args.outpath, args.verify)?;
+        }
+        Ok(Written {
+            library: args.outpath,
+            files,
+            skipped,
+            source: None,
+        })
+    }))
+}
+
+/// Default names of Inkscape layers (without the number, in the most common
+/// languages), which say nothing about the content.
+const DEFAULT_LAYER_NAMES: [&str; 6] = ["layer", "ebene", "calque", "capa", "livello", "laag"];
+
+/// Whether a layer name is a default name like "Layer 1".
+fn is_default_layer_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let word = name.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+    DEFAULT_LAYER_NAMES.contains(&word.trim_start())
+}
+
+/// Append keywords derived from the conversion to the given keywords (comma
+/// separated), skipping duplicates: The words of the file name, the names of
+/// the Inkscape layers (except default names), the detected content and the
+/// sizes in mm.
+fn auto_keywords(
+    keywords: &str,
+    file_name: &str,
+    layers: &[String],
+    content: &[&str],
+    sizes: &[(f64, f64)],
+) -> String {
+    let mut all: Vec<String> = keywords
+        .split(',')
+        .map(|keyword| keyword.trim().to_string())
+        .filter(|keyword| !keyword.is_empty())
+        .collect();
+    let words = file_name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase);
+    let layers = layers
+        .iter()
+        .filter(|layer| !is_default_layer_name(layer))
+        .map(|layer| layer.trim().to_lowercase());
+    let content = content.iter().map(|keyword| keyword.to_string());
+    let sizes = sizes.iter().map(|(width, height)| {
+        let round = |value: f64| format_float((value * 10.0).round() / 10.0);
+        format!("{}x{}mm", round(*width), round(*height))
+    });
+    for keyword in words.chain(layers).chain(content).chain(sizes) {
+        // Commas separate the keywords in LibrePCB
+        let keyword = keyword.replace(',', " ");
+        if !keyword.is_empty() && !all.iter().any(|other| other.eq_ignore_ascii_case(&keyword)) {
+            all.push(keyword);
+        }
+    }
+    all.join(",")
+}
+
+/// Parse a `--footprint-align` option, e.g. `Top Placement=bottom-left`.
+fn parse_footprint_align(value: &str) -> Result<(String, Align)> {
+    let error = |reason: String| Error::FootprintAlign {
+        value: value.to_string(),
+        reason,
+    };
+    let (title, align) = value
+        .split_once('=')
+        .ok_or_else(|| error("expected FOOTPRINT=ALIGN".to_string()))?;
+    let align = Align::from_str(align.trim(), true).map_err(|_| {
+        let names: Vec<String> = Align::value_variants()
+            .iter()
+            .filter_map(|align| align.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        error(format!(
+            "unknown alignment {:?} (use {})",
+            align,
+            names.join(", ")
+        ))
+    })?;
+    Ok((title.trim().to_string(), align))
+}
+
+/// The alignment of a footprint given with --footprint-align (for both sides).
+fn footprint_align(overrides: &[(String, Align)], title: &str) -> Option<Align> {
+    let title = title.strip_suffix(" (Bottom)").unwrap_or(title);
+    overrides
+        .iter()
+        .rev()
+        .find(|(other, _)| other.eq_ignore_ascii_case(title))
+        .map(|(_, align)| *align)
+}
+
+/// A metadata field which can be overridden per element with
+/// --element-metadata.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum MetadataField {
+    Description,
+    Keywords,
+    Author,
+    Version,
+    Created,
+}
+
+/// Parse an `--element-metadata` option, e.g. `symbol.description=Outline`.
+fn parse_element_metadata(value: &str) -> Result<(ElementKind, MetadataField, String)> {
+    let error = |reason: String| Error::ElementMetadata {
+        value: value.to_string(),
+        reason,
+    };
+    let (key, field_value) = value
+        .split_once('=')
+        .ok_or_else(|| error("expected ELEMENT.FIELD=VALUE".to_string()))?;
+    let (kind, field) = key
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| error("expected ELEMENT.FIELD=VALUE".to_string()))?;
+    let kind = match kind {
+        "symbol" => ElementKind::Symbol,
+        "component" => ElementKind::Component,
+        "package" => ElementKind::Package,
+        "device" => ElementKind::Device,
+        _ => {
+            return Err(error(format!(
+                "unknown element {:?} (use symbol, component, package or device)",
+                kind
+            )))
+        }
+    };
+    let (field, result) = match field {
+        "description" => (MetadataField::Description, Ok(())),
+        "keywords" => (
+            MetadataField::Keywords,
+            validation::validate_single_line(field_value),
+        ),
+        "author" => (
+            MetadataField::Author,
+            validation::validate_single_line(field_value),
+        ),
+        "version" => (
+            MetadataField::Version,
+            validation::validate_version(field_value),
+        ),
+        "created" => {
+            // Normalized to UTC, like LibrePCB writes it
+            let created = DateTime::parse_from_rfc3339(field_value)
+                .map_err(|_| error("expected a timestamp like 2024-01-31T12:00:00Z".into()))?;
+            let created = created
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true);
+            return Ok((kind, MetadataField::Created, created));
+        }
+        _ => {
+            return Err(error(format!(
+                "unknown field {:?} (use description, keywords, author, version or created)",
+                field
+            )))
+        }
+    };
+    result.map_err(|reason| error(format!("the {} {}", key.trim(), reason)))?;
+    Ok((kind, field, field_value.to_string()))
+}
+
+/// The value of a metadata field of an element: The last --element-metadata
+/// override for it, or the value given for all elements.
+fn element_metadata<'a>(
+    overrides: &'a [(ElementKind, MetadataField, String)],
+    kind: ElementKind,
+    field: MetadataField,
+    default: &'a str,
+) -> &'a str {
+    overrides
+        .iter()
+        .rev()
+        .find(|(other_kind, other_field, _)| *other_kind == kind && *other_field == field)
+        .map_or(default, |(_, _, value)| value)
+}
+
+/// Parse a `--warning` option, e.g. `ignored-text=error`.
+fn parse_warning_level(value: &str) -> Result<(String, Level)> {
+    let error = |reason: String| Error::WarningLevel {
+        value: value.to_string(),
+        reason,
+    };
+    let (name, level) = value
+        .split_once('=')
+        .ok_or_else(|| error("expected NAME=LEVEL".to_string()))?;
+    if !error::WARNING_NAMES.contains(&name) {
+        return Err(error(format!(
+            "unknown warning {:?} (known warnings: {})",
+            name,
+            error::WARNING_NAMES.join(", ")
+        )));
+    }
+    let level = Level::from_name(level).ok_or_else(|| {
+        error(format!(
+            "unknown level {:?} (use ignore, warning or error)",
+            level
+        ))
+    })?;
+    Ok((name.to_string(), level))
+}
+
+/// Fit artwork with the given bounds to --width-mm and --height-mm. Returns
+/// the factor to scale the artwork with, the factor to stretch the y-axis
+/// with before scaling, and the area to crop to after stretching.
+fn fit_target(
+    bounds: geometry::Rect,
+    width: Option<f64>,
+    height: Option<f64>,
+    fit: Fit,
+) -> Result<(f64, f64, Option<geometry::Rect>)> {
+    let factor = |option, target: Option<f64>, size: f64| -> Result<Option<f64>> {
+        match target.map(|target| positive(option, target)).transpose()? {
+            Some(target) if size <= 0.0 => Err(Error::InvalidParameter {
+                option,
+                value: target,
+                reason: "the artwork has no extent in this direction",
+            }),
+            target => Ok(target.map(|target| target / size)),
+        }
+    };
+    let factor_x = factor("--width-mm", width, bounds.x_max - bounds.x_min)?;
+    let factor_y = factor("--height-mm", height, bounds.y_max - bounds.y_min)?;
+    let (factor_x, factor_y) = match (factor_x, factor_y) {
+        (Some(factor_x), Some(factor_y)) => (factor_x, factor_y),
+        (Some(factor), None) | (None, Some(factor)) => return Ok((factor, 1.0, None)),
+        (None, None) => return Ok((1.0, 1.0, None)),
+    };
+    Ok(match fit {
+        Fit::Contain => (factor_x.min(factor_y), 1.0, None),
+        Fit::Stretch => (factor_x, factor_y / factor_x, None),
+        Fit::Cover => {
+            // Crop the middle of the artwork in the direction which sticks out
+            let factor = factor_x.max(factor_y);
+            let (center_x, center_y) = (
+                (bounds.x_min + bounds.x_max) / 2.0,
+                (bounds.y_min + bounds.y_max) / 2.0,
+            );
+            let (half_width, half_height) = (
+                width.unwrap_or_default() / factor / 2.0,
+                height.unwrap_or_default() / factor / 2.0,
+            );
+            let crop = geometry::Rect {
+                x_min: center_x - half_width,
+                y_min: center_y - half_height,
+                x_max: center_x + half_width,
+                y_max: center_y + half_height,
+            };
+            let covered = crop.x_min <= bounds.x_min + 1e-9
+                && crop.x_max >= bounds.x_max - 1e-9
+                && crop.y_min <= bounds.y_min + 1e-9
+                && crop.y_max >= bounds.y_max - 1e-9;
+            (factor, 1.0, (!covered).then_some(crop))
+        }
+    })
+}
+
+/// The file name and stem of the input file, which are empty for the
+/// contents of the clipboard (converted from a temporary file).
+fn input_names(args: &Args) -> (String, String) {
+    if args.clipboard {
+        return (String::new(), String::new());
+    }
+    let name = |name: Option<&OsStr>| {
+        name.map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    (
+        name(args.svgfile.file_name()),
+        name(args.svgfile.file_stem()),
+    )
+}
+
+/// Generate the name and description from --name-template and
+/// --description-template, which can refer to the size of the footprint.
+fn expand_metadata_templates(
+    args: &Args,
+    polylines: &[Polyline],
+) -> Result<(Option<String>, Option<String>)> {
+    let (mut width, mut height) = geometry::bounds(polylines)
+        .map(|bounds| (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min))
+        .unwrap_or((0.0, 0.0));
+    if let Some(&scaled_width) = args.scales.first().filter(|_| width > 0.0) {
+        height *= scaled_width / width;
+        width = scaled_width;
+    }
+    let (file, stem) = input_names(args);
+    let timestamp = created_timestamp();
+    let values = batch::TemplateValues {
+        file: &file,
+        stem: &stem,
+        width,
+        height,
+        date: &timestamp[..10],
+    };
+    let expand = |template: &Option<String>| {
+        template
+            .as_deref()
+            .map(|template| batch::expand_template(template, &values))
+            .transpose()
+    };
+    Ok((
+        expand(&args.name_template)?,
+        expand(&args.description_template)?,
+    ))
+}
+
+/// Result of converting a file.
+struct Converted {
+    /// The SVG source (echoed for Inkscape)
+    source: String,
+    /// Name of the elements
+    name: String,
+    /// The library (or directory) the files were written into
+    library: PathBuf,
+    /// The generated LibrePCB elements with their UUIDs
+    elements: Vec<(ElementKind, String)>,
+}
+
+/// The files written into one library by a conversion.
+struct Written {
+    library: PathBuf,
+    /// The converted files with their elements and warnings
+    files: Vec<batch::ReportEntry>,
+    /// Files of a directory which were not converted, with the reason
+    skipped: Vec<(String, &'static str)>,
+    /// The SVG source of a single file (echoed for Inkscape)
+    source: Option<String>,
+}
+
+/// A checked conversion, which writes its files when called. This allows
+/// checking all conversions before writing anything.
+type Pending<T> = Box<dyn FnOnce() -> Result<T>>;
+
+/// Check the options of a conversion which do not depend on the input.
+fn validate_options(args: &Args) -> Result<()> {
+    for (option, used) in [
+        ("--device-per-scale", args.device_per_scale),
+        ("--package-per-color", args.package_per_color),
+        ("--mask-window", args.mask_window),
+    ] {
+        if !used {
+            continue;
+        }
+        if args.uuid_pkg.is_some() {
+            return Err(Error::ConflictingOptions("--uuid-pkg", option));
+        }
+        if args.uuid_dev.is_some() {
+            return Err(Error::ConflictingOptions("--uuid-dev", option));
+        }
+    }
+    for (option, used) in [
+        ("--package-per-color", args.package_per_color),
+        ("--mask-window", args.mask_window),
+        ("--circle-pads", args.circle_pads.is_some()),
+        ("--origin-cross", args.origin_cross.is_some()),
+        ("--element-metadata", !args.element_metadata.is_empty()),
+    ] {
+        if used && args.output_format != OutputFormat::Librepcb {
+            return Err(Error::ConflictingOptions(option, "--format"));
+        }
+    }
+    for (option, value) in [
+        ("--origin-cross", args.origin_cross),
+        ("--rasterize-gradients", args.rasterize_gradients),
+        ("--ellipse-tolerance", args.ellipse_tolerance),
+        ("--circle-pads", args.circle_pads),
+        ("--round-corners", args.round_corners),
+        ("--max-segment-length", args.max_segment_length),
+        ("--min-area", args.min_area),
+        ("--fiducial-diameter", Some(args.fiducial_diameter)),
+    ] {
+        if let Some(value) = value {
+            positive(option, value)?;
+        }
+    }
+    for (option, value) in [
+        ("--mask-window-margin", args.mask_window_margin),
+        ("--silkscreen-clearance", args.silkscreen_clearance),
+        ("--bridge-width", args.bridge_width),
+    ] {
+        if let Some(value) = value {
+            non_negative(option, value)?;
+        }
+    }
+    if let Some(height) = args.label_height {
+        validation::validate_text_height(height).map_err(|reason| Error::InvalidParameter {
+            option: "--label-height",
+            value: height,
+            reason,
+        })?;
+    }
+    if let Some(sweep) = args.max_arc_sweep {
+        if sweep <= 0.0 || sweep > 180.0 || sweep.is_nan() {
+            return Err(Error::InvalidParameter {
+                option: "--max-arc-sweep",
+                value: sweep,
+                reason: "the angle must be above 0 and at most 180 degrees",
+            });
+        }
+    }
+    if let Some(vertices) = args.max_polygon_vertices {
+        if vertices < 8 {
+            return Err(Error::InvalidParameter {
+                option: "--max-polygon-vertices",
+                value: vertices as f64,
+                reason: "at least 8 vertices are required",
+            });
+        }
+    }
+    if let Some(opacity) = args.min_opacity {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(Error::InvalidParameter {
+                option: "--min-opacity",
+                value: opacity,
+                reason: "the opacity must be between 0 and 1",
+            });
+        }
+    }
+    if args.round_corners.is_some() && args.fit_arcs {
+        return Err(Error::ConflictingOptions("--round-corners", "--fit-arcs"));
+    }
+    if args.text_to_path && args.native_text {
+        return Err(Error::ConflictingOptions("--text-to-path", "--native-text"));
+    }
+    if args.bridge_count == Some(0) {
+        return Err(Error::InvalidParameter {
+            option: "--bridge-count",
+            value: 0.0,
+            reason: "at least one bridge is required",
+        });
+    }
+    if args.pcb_art && args.exposed_copper {
+        return Err(Error::ConflictingOptions("--pcb-art", "--exposed-copper"));
+    }
+    // Inkscape reads the modified drawing from stdout
+    if args.print.is_some() && (args.inkscape || !args.ids.is_empty()) {
+        return Err(Error::ConflictingOptions("--print", "--inkscape"));
+    }
+    if args.format_version != FormatVersion::V1 {
+        if args.mpn.is_some() {
+            return Err(Error::RequiresFormatV1("--mpn"));
+        }
+        if args.approve_checks {
+            return Err(Error::RequiresFormatV1("--approve-checks"));
+        }
+    }
+    Ok(())
+}
+
+/// Check that a parameter is positive (and not NaN).
+fn positive(option: &'static str, value: f64) -> Result<f64> {
+    if value > 0.0 {
+        Ok(value)
+    } else {
+        Err(Error::InvalidParameter {
+            option,
+            value,
+            reason: "the value must be positive",
+        })
+    }
+}
+
+/// Check that a parameter is not negative (and not NaN).
+fn non_negative(option: &'static str, value: f64) -> Result<f64> {
+    if value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(Error::InvalidParameter {
+            option,
+            value,
+            reason: "the value must not be negative",
+        })
+    }
+}
+
+/// Convert a single file. All checks are done before returning, the files
+/// are written by the returned closure.
+fn convert(mut args: Args) -> Result<Pending<Converted>> {
+    if let Some(path) = &args.description_file {
+        let description = read_to_string(path).map_err(|source| Error::ReadFile {
+            path: path.clone(),
+            source,
+        })?;
+        args.description = description.trim_end().to_string();
+    }
+    let templated = args.name_template.is_some() || args.description_template.is_some();
+    if !templated {
+        if args.sanitize_names {
+            args.name = sanitize_name(&args.name);
+        }
+        validate_metadata(&args)?;
+    }
+    let mut config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    for (alias, target) in &args.library_config.layer_aliases {
+        config
+            .layer_aliases
+            .entry(alias.clone())
+            .or_insert_with(|| target.clone());
+    }
+    let mut levels = config.warnings.clone();
+    for value in &args.warning {
+        let (name, level) = parse_warning_level(value)?;
+        levels.insert(name, level);
+    }
+    let default_level = if args.strict {
+        Level::Error
+    } else {
+        Level::Warning
+    };
+    error::set_levels(levels, default_level);
+    validate_options(&args)?;
+    let load_snippets = |snippets: &[String]| -> Result<Vec<String>> {
+        let mut lines = vec![];
+        for snippet in snippets {
+            lines.extend(load_snippet(snippet)?);
+        }
+        Ok(lines)
+    };
+    let symbol_snippets = load_snippets(&args.symbol_snippet)?;
+    let templates = template::Templates::load(&args.templates)?;
+    let rules = args.rules.as_deref().map(rules::Rules::load).transpose()?;
+    let package_snippets = load_snippets(&args.package_snippet)?;
+    let layer_names = args
+        .layer_names
+        .unwrap_or_else(|| args.format_version.layer_names());
+    let extra_layers = args
+        .layers
+        .iter()
+        .map(|name| {
+            config
+                .resolve_layer(name)
+                .ok_or_else(|| Error::UnknownLayer(name.clone()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let attributes = args
+        .attributes
+        .iter()
+        .map(|value| parse_attribute(value))
+        .collect::<Result<Vec<_>>>()?;
+    let footprint_texts = args
+        .footprint_text
+        .iter()
+        .map(|value| parse_footprint_text(value, &config, layer_names))
+        .collect::<Result<Vec<_>>>()?;
+    let footprint_aligns = args
+        .footprint_align
+        .iter()
+        .map(|value| parse_footprint_align(value))
+        .collect::<Result<Vec<_>>>()?;
+    let metadata_overrides = args
+        .element_metadata
+        .iter()
+        .map(|value| parse_element_metadata(value))
+        .collect::<Result<Vec<_>>>()?;
+    let fiducials = args
+        .fiducial
+        .iter()
+        .map(|value| parse_fiducial(value))
+        .collect::<Result<Vec<_>>>()?;
+    let tile = args
+        .tile
+        .as_deref()
+        .map(|tile| parse_tile(tile, args.pitch.as_deref()))
+        .transpose()?;
+    if args.pick_colors {
+        if !io::stdin().is_terminal() {
+            return Err(Error::NotInteractive("--pick-colors"));
+        }
+        args.pcb_art = true;
+    }
+    if args.package_per_color && !args.pcb_art {
+        return Err(Error::RequiresOption("--package-per-color", "--pcb-art"));
+    }
+    // Without PCB art, the footprints are alternatives of the same artwork,
+    // so there is no exposed copper to clip the silkscreen against
+    if args.silkscreen_clearance.is_some() && !args.pcb_art {
+        return Err(Error::RequiresOption("--silkscreen-clearance", "--pcb-art"));
+    }
+    let mut art_palette = art_palette(&args.art_color)?;
+
+    // Let the user adjust the settings interactively
+    if args.gui {
+        let settings = show_preview(&args, &art_palette)?;
+        args.flattening_tolerance = settings.tolerance;
+        if let Some(width) = settings.width {
+            args.scales = vec![width];
+        }
+    }
+
+    // Load and parse input file
+    let input = Input::load(
+        &args.svgfile,
+        &InputOptions {
+            clip_viewbox: args.clip_viewbox,
+            text_to_path: args.text_to_path,
+            native_text: args.native_text,
+            pcb_art: args.pcb_art,
+            ids: &args.ids,
+            kicad_layers: &args.kicad_layers,
+            min_opacity: args.min_opacity.unwrap_or(0.0),
+            outline_strokes: args
+                .outline_strokes
+                .then_some((args.stroke_cap, args.stroke_join)),
+            treat_all_closed: args.treat_all_closed,
+            ellipse_tolerance: args.ellipse_tolerance.unwrap_or(ELLIPSE_TOLERANCE),
+            rasterize_gradients: args.rasterize_gradients,
+            report_refs: true,
+            limits: input_limits(&args),
+        },
+    )?;
+    if args.pick_colors {
+        let colors = input.colors()?;
+        let picked = picker::pick_roles(
+            &colors,
+            &art_palette,
+            &mut io::stdin().lock(),
+            &mut io::stderr(),
+        )
+        .map_err(|e| Error::Terminal(e.to_string()))?
+        .ok_or(Error::Cancelled)?;
+        eprintln!(
+            "To convert with these colors again, use: {}\n",
+            picker::options(&picked)
+        );
+        let mappings = picked
+            .iter()
+            .map(|(color, role)| picker::mapping(*color, *role));
+        args.art_color.splice(0..0, mappings);
+        art_palette.splice(0..0, picked);
+    }
+    let (polylines, art_roles) = if args.pcb_art {
+        input.parse_art(&art_palette, args.flattening_tolerance)?
+    } else {
+        (input.parse(args.flattening_tolerance)?, vec![])
+    };
+    if args.silkscreen_clearance.is_some()
+        && !art_roles
+            .iter()
+            .any(|(role, _)| role.layers().contains(&Layer::TopStopMask))
+    {
+        return Err(Error::RequiresOption(
+            "--silkscreen-clearance",
+            "exposed copper or substrate in the PCB art",
+        ));
+    }
+    let texts = if args.native_text {
+        input.texts()?
+    } else {
+        vec![]
+    };
+    let symbol_polylines = match args.symbol_tolerance {
+        Some(tolerance) if tolerance != args.flattening_tolerance && !args.pcb_art => {
+            input.parse(tolerance)?
+        }
+        _ => polylines.clone(),
+    };
+
+    // Fit the footprints to --width-mm and --height-mm by stretching and
+    // cropping the artwork, then scaling it like with a single --scales width
+    let mut stretch = 1.0;
+    let mut crop = None;
+    let (polylines, art_roles, texts) = match geometry::bounds(&polylines) {
+        Some(bounds) if args.width_mm.is_some() || args.height_mm.is_some() => {
+            let factor;
+            (factor, stretch, crop) = fit_target(bounds, args.width_mm, args.height_mm, args.fit)?;
+            let transform = |polylines: &[Polyline]| {
+                let stretched: Vec<Polyline> = polylines
+                    .iter()
+                    .map(|polyline| {
+                        polyline
+                            .iter()
+                            .map(|point| geometry::Point::new(point.x, point.y * stretch))
+                            .collect()
+                    })
+                    .collect();
+                match &crop {
+                    Some(rect) => geometry::clip(&stretched, rect),
+                    None => stretched,
+                }
+            };
+            let polylines = transform(&polylines);
+            let art_roles = art_roles
+                .iter()
+                .map(|(role, polylines)| (*role, transform(polylines)))
+                .collect();
+            let texts = texts
+                .into_iter()
+                .map(|text| Text {
+                    position: geometry::Point::new(text.position.x, text.position.y * stretch),
+                    ..text
+                })
+                .filter(|text| crop.is_none_or(|rect| rect.contains(text.position)))
+                .collect();
+            args.scales = vec![geometry::width(&polylines) * factor];
+            (polylines, art_roles, texts)
+        }
+        _ => (polylines, art_roles, texts),
+    };
+
+    // Filled circles of the copper become test point pads instead of
+    // polygons: The exposed copper of PCB art, or the artwork if only copper
+    // footprints are generated
+    if args.circle_pads.is_some() && !args.pcb_art {
+        let other_layers = args.layer_placement
+            || args.layer_stopmask
+            || extra_layers.iter().any(|layer| *layer != Layer::TopCopper);
+        if other_layers || !(args.layer_copper || args.exposed_copper) {
+            return Err(Error::RequiresOption(
+                "--circle-pads",
+                "--pcb-art or only copper footprints (--layer-placement false --layer-stopmask false)",
+            ));
+        }
+    }
+    let (polylines, art_roles, pads) = match args.circle_pads {
+        Some(min_diameter) => {
+            // Circles are flattened with the finer of both tolerances
+            let tolerance = args
+                .flattening_tolerance
+                .max(args.ellipse_tolerance.unwrap_or(ELLIPSE_TOLERANCE));
+            let as_pad = |polyline: &Polyline| {
+                geometry::fit_circle(polyline, tolerance)
+                    .filter(|(_, diameter)| *diameter >= min_diameter)
+                    .map(|(position, diameter)| Hole { position, diameter })
+            };
+            let mut pads: Vec<Hole> = vec![];
+            let mut remove_pads = |polylines: &[Polyline]| -> Vec<Polyline> {
+                let (circles, rest): (Vec<Polyline>, Vec<Polyline>) = polylines
+                    .iter()
+                    .cloned()
+                    .partition(|polyline| as_pad(polyline).is_some());
+                pads.extend(circles.iter().filter_map(as_pad));
+                rest
+            };
+            let (polylines, art_roles) = if args.pcb_art {
+                // The circles still count for the size of the artwork
+                let art_roles = art_roles
+                    .iter()
+                    .map(|(role, polylines)| match role {
+                        art::Role::Copper => (*role, remove_pads(polylines)),
+                        _ => (*role, polylines.clone()),
+                    })
+                    .collect();
+                (polylines, art_roles)
+            } else {
+                (remove_pads(&polylines), art_roles)
+            };
+            log!("Converting {} circles to test point pads", pads.len());
+            (polylines, art_roles, pads)
+        }
+        None => (polylines, art_roles, vec![]),
+    };
+    if templated {
+        // The metadata is validated once the size is known
+        let (name, description) = expand_metadata_templates(&args, &polylines)?;
+        if let Some(name) = name {
+            args.name = name;
+        }
+        if let Some(description) = description {
+            args.description = description;
+        }
+        if args.sanitize_names {
+            args.name = sanitize_name(&args.name);
+        }
+        validate_metadata(&args)?;
+    }
+
+    // Ensure that output library path exists. With --mkdirs, it is only
+    // created when writing the elements.
+    let create_outpath = !args.outpath.exists();
+    if create_outpath && !args.mkdirs {
+        return Err(Error::InvalidOutpath {
+            path: args.outpath,
+            reason: "does not exist (use --mkdirs to create it)".into(),
+        });
+    }
+    if !create_outpath && !args.outpath.is_dir() {
+        return Err(Error::InvalidOutpath {
+            path: args.outpath,
+            reason: "not a directory".into(),
+        });
+    }
+    let lib_path = if create_outpath {
+        env::current_dir().map(|dir| dir.join(&args.outpath))
+    } else {
+        args.outpath.canonicalize()
+    }
+    .map_err(|e| Error::InvalidOutpath {
+        path: args.outpath.clone(),
+        reason: e.to_string(),
+    })?;
+    // The package and device are added to an existing component
+    if let Some(uuid) = &args.existing_cmp {
+        let kind = ElementKind::Component;
+        let path = element_dir(&lib_path, kind, uuid).join(kind.file_name());
+        if !path.is_file() {
+            return Err(Error::MissingElement {
+                element: kind,
+                path,
+            });
+        }
+    }
+    let library_files = if create_outpath && args.output_format == OutputFormat::Librepcb {
+        make_library(&lib_path, &args.author, args.format_version)
+    } else {
+        vec![]
+    };
+
+    // Generate footprints
+    let mut footprint_layers = vec![];
+    if args.layer_copper {
+        footprint_layers.push(Layer::TopCopper);
+    }
+    if args.layer_placement {
+        footprint_layers.push(Layer::TopLegend);
+    }
+    if args.layer_stopmask {
+        footprint_layers.push(Layer::TopStopMask);
+    }
+    footprint_layers.extend(extra_layers);
+
+    // Footprint titles with the layers and polylines of each footprint, scaled
+    // by a factor (small shapes are removed, holes are bridged and the mask
+    // expansion is applied after scaling, huge polygons are split last)
+    let bridges = args.bridge_holes.then(|| geometry::Bridges {
+        width: args.bridge_width.unwrap_or(0.2),
+        count: args.bridge_count.unwrap_or(1),
+        angle: args.bridge_angle.unwrap_or(270.0),
+    });
+    let scale = |polylines: &[Polyline], factor: f64| {
+        let scaled = geometry::scale(polylines, factor);
+        let scaled = match &bridges {
+            Some(bridges) => geometry::bridge_holes(&scaled, bridges),
+            None => scaled,
+        };
+        match args.min_area {
+            Some(min_area) => {
+                let kept = geometry::remove_small(&scaled, min_area);
+                log!(
+                    "Removed {} of {} paths smaller than {} mm²",
+                    scaled.len() - kept.len(),
+                    scaled.len(),
+                    min_area
+                );
+                kept
+            }
+            None => scaled,
+        }
+    };
+    let footprint_specs = |factor: f64| -> Vec<(String, bool, FootprintLayers)> {
+        let art_footprint = |roles: &[(art::Role, Vec<Polyline>)]| -> FootprintLayers {
+            art_layers(roles)
+                .iter()
+                .map(|(layer, polylines)| (*layer, scale(polylines, factor)))
+                .collect()
+        };
+        let mut specs: Vec<(&str, FootprintLayers)> = if args.package_per_color {
+            art_roles
+                .iter()
+                .map(|role| (role.0.title(), art_footprint(std::slice::from_ref(role))))
+                .collect()
+        } else if args.pcb_art {
+            vec![("PCB Art", art_footprint(&art_roles))]
+        } else {
+            let scaled = scale(&polylines, factor);
+            let mut specs: Vec<(&str, FootprintLayers)> = footprint_layers
+                .iter()
+                .map(|layer| (layer.title(), vec![(*layer, scaled.clone())]))
+                .collect();
+            if args.exposed_copper {
+                let mask = geometry::offset(&scaled, args.mask_expansion);
+                let layers = vec![(Layer::TopCopper, scaled), (Layer::TopStopMask, mask)];
+                specs.push(("Exposed Copper", layers));
+            }
+            specs
+        };
+        if args.mask_window {
+            let window = mask_window(
+                &scale(&polylines, factor),
+                args.mask_window_margin.unwrap_or(1.0),
+                args.flattening_tolerance,
+            );
+            specs.push((MASK_WINDOW, vec![(Layer::TopStopMask, window)]));
+        }
+        if let Some(clearance) = args.silkscreen_clearance {
+            clip_silkscreen(&mut specs, clearance);
+        }
+        if let Some(max_vertices) = args.max_polygon_vertices {
+            for (_, layers) in &mut specs {
+                for (_, polylines) in layers.iter_mut() {
+                    *polylines = geometry::split(polylines, max_vertices);
+                }
+            }
+        }
+        if let Some(tile) = tile {
+            for (_, layers) in &mut specs {
+                let pitch = tile.pitch.unwrap_or_else(|| {
+                    let all: Vec<Polyline> = layers
+                        .iter()
+                        .flat_map(|(_, polylines)| polylines.iter().cloned())
+                        .collect();
+                    geometry::bounds(&all).map_or((0.0, 0.0), |rect| {
+                        (rect.x_max - rect.x_min, rect.y_max - rect.y_min)
+                    })
+                });
+                for (_, polylines) in layers.iter_mut() {
+                    *polylines = geometry::tile(polylines, tile.columns, tile.rows, pitch);
+                }
+            }
+        }
+
+        // Footprints for the bottom side are mirrored, as seen from the top
+        let mut sided = vec![];
+        for (title, layers) in specs {
+            let mirrored: Option<FootprintLayers> = args.both_sides.then(|| {
+                layers
+                    .iter()
+                    .map(|(layer, polylines)| (layer.mirrored(), geometry::mirror(polylines)))
+                    .collect()
+            });
+            let bottom_title = match &layers[..] {
+                [(layer, _)] if layer.title() == title => layer.mirrored().title().to_string(),
+                _ => format!("{} (Bottom)", title),
+            };
+            sided.push((title.to_string(), false, layers));
+            if let Some(mirrored) = mirrored {
+                sided.push((bottom_title, true, mirrored));
+            }
+        }
+        sided
+    };
+
+    // With --scales, one set of footprints is generated per width
+    let holes = match &args.drill_file {
+        Some(path) => {
+            let source = read_to_string(path).map_err(|source| Error::ReadFile {
+                path: path.clone(),
+                source,
+            })?;
+            excellon::parse(&source).map_err(|reason| Error::ParseInput {
+                path: path.clone(),
+                reason,
+            })?
+        }
+        None => vec![],
+    };
+    let holes: Vec<Hole> = holes
+        .into_iter()
+        .map(|hole| Hole {
+            position: geometry::Point::new(hole.position.x, hole.position.y * stretch),
+            ..hole
+        })
+        .filter(|hole| crop.is_none_or(|rect| rect.contains(hole.position)))
+        .collect();
+    let mut variants: Vec<Variant> = vec![];
+    if args.scales.is_empty() {
+        if let Some(bounds) = geometry::bounds(&polylines) {
+            let (width, height) = (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min);
+            if width.max(height) < MIN_ARTWORK_SIZE {
+                return Err(Error::ArtworkTooSmall { width, height });
+            }
+        }
+        variants.push((None, footprint_specs(1.0), holes, pads, texts.clone()));
+    } else {
+        let original_width = geometry::width(&polylines);
+        for &width in &args.scales {
+            if width <= 0.0 || width.is_nan() {
+                return Err(Error::InvalidScale {
+                    width,
+                    reason: "the width must be positive",
+                });
+            }
+            if original_width <= 0.0 {
+                return Err(Error::InvalidScale {
+                    width,
+                    reason: "the drawing has no width",
+                });
+            }
+            let factor = width / original_width;
+            let scale_holes = |holes: &[Hole]| -> Vec<Hole> {
+                holes
+                    .iter()
+                    .map(|hole| Hole {
+                        position: geometry::Point::new(
+                            hole.position.x * factor,
+                            hole.position.y * factor,
+                        ),
+                        diameter: hole.diameter * factor,
+                    })
+                    .collect()
+            };
+            let scaled_texts = texts
+                .iter()
+                .map(|text| Text {
+                    position: geometry::Point::new(
+                        text.position.x * factor,
+                        text.position.y * factor,
+                    ),
+                    height: text.height * factor,
+                    ..text.clone()
+                })
+                .collect();
+            variants.push((
+                Some(width),
+                footprint_specs(factor),
+                scale_holes(&holes),
+                scale_holes(&pads),
+                scaled_texts,
+            ));
+        }
+    }
+    if args.auto_keywords {
+        let sizes: Vec<(f64, f64)> = geometry::bounds(&polylines)
+            .map(|bounds| {
+                let (width, height) = (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min);
+                if args.scales.is_empty() {
+                    vec![(width, height)]
+                } else {
+                    args.scales
+                        .iter()
+                        .map(|scale| (*scale, height * scale / width))
+                        .collect()
+                }
+            })
+            .unwrap_or_default();
+        let mut content = vec![];
+        if args.pcb_art {
+            content.push("pcb art");
+        }
+        if !variants[0].3.is_empty() {
+            content.push("test point");
+        }
+        let (_, file_name) = input_names(&args);
+        let layers = svg::layer_names(&input.source).unwrap_or_default();
+        args.keywords = auto_keywords(&args.keywords, &file_name, &layers, &content, &sizes);
+        log!("Keywords: {}", args.keywords);
+    }
+    for (title, _) in &footprint_aligns {
+        let mut titles: Vec<&str> = variants[0]
+            .1
+            .iter()
+            .map(|(title, _, _)| title.as_str())
+            .filter(|title| !title.ends_with(" (Bottom)"))
+            .collect();
+        titles.dedup();
+        if !titles.iter().any(|other| other.eq_ignore_ascii_case(title)) {
+            return Err(Error::FootprintAlign {
+                value: title.clone(),
+                reason: format!("no such footprint (footprints: {})", titles.join(", ")),
+            });
+        }
+    }
+
+    for (width, footprints, holes, _, _) in &variants {
+        for (title, _, layers) in footprints {
+            for (layer, polylines) in layers {
+                log!(
+                    "Footprint {:?}{}: {} polylines on {}",
+                    title,
+                    width.map_or(String::new(), |width| format!(" at {} mm", width)),
+                    polylines.len(),
+                    layer.title()
+                );
+            }
+            if !holes.is_empty() {
+                log!("Footprint {:?}: {} holes", title, holes.len());
+            }
+        }
+    }
+
+    // Check the footprints against the fabrication rules
+    if let Some(rules) = &rules {
+        for (width, footprints, holes, _, _) in &variants {
+            for (title, _, layers) in footprints {
+                let footprint = match width {
+                    Some(width) => format!("{} {} mm", title, format_float(*width)),
+                    None => title.clone(),
+                };
+                for violation in rules::check(rules, layers, holes, OPEN_LINE_WIDTH) {
+                    Warning::RuleViolation {
+                        footprint: footprint.clone(),
+                        layer: violation.layer,
+                        rule: violation.rule,
+                        value: format_float(violation.value),
+                        limit: format_float(violation.limit),
+                        x: format_float(violation.position.x),
+                        y: format_float(violation.position.y),
+                    }
+                    .report();
+                }
+            }
+        }
+    }
+
+    // Nothing is written if warnings are treated as errors
+    error::check_promoted()?;
+
+    // Other EDA tools only get the footprints
+    if args.output_format != OutputFormat::Librepcb {
+        let footprints = export_footprints(
+            &args,
+            &variants,
+            &footprint_texts,
+            &footprint_aligns,
+            &fiducials,
+            layer_names,
+        );
+        let files = match args.output_format {
+            OutputFormat::Kicad => footprints
+                .iter()
+                .map(|footprint| {
+                    let path = lib_path.join(format!("{}.kicad_mod", footprint.name));
+                    let lines = kicad::make_footprint(footprint, &args.description, &args.keywords);
+                    (path, lines)
+                })
+                .collect(),
+            _ => {
+                let path = lib_path.join(format!("{}.lbr", export::file_name(&args.name)));
+                vec![(path, eagle::make_library(&args.description, &footprints))]
+            }
+        };
+        // Like for LibrePCB elements, nothing is written if a file exists
+        if !args.force {
+            if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+                return Err(Error::FileExists(path.clone()));
+            }
+        }
+        let source = input.source;
+        return Ok(Box::new(move || {
+            if create_outpath {
+                create_library_dir(&lib_path, &[])?;
+            }
+            let paths = write_files(&args, &lib_path, files)?;
+            if let Some(command) = &args.post_hook {
+                run_post_hook(command, &paths)?;
+            }
+            Ok(Converted {
+                source,
+                name: args.name,
+                library: lib_path,
+                elements: vec![],
+            })
+        }));
+    }
+
+    // With --device-per-scale, every width gets its own package and device.
+    // With --package-per-color, every role gets its own package and device,
+    // with the footprints of that role (the holes, texts and fiducials are
+    // only added to the first one). The --mask-window footprints get their own
+    // package as well.
+    let mut variant_groups: Vec<(String, &[_], Option<&str>)> = if args.device_per_scale {
+        variants
+            .chunks(1)
+            .map(|group| match group[0].0 {
+                Some(width) => (
+                    derived_name(&args.name, &format!("{} mm", format_float(width))),
+                    group,
+                    None,
+                ),
+                None => (args.name.clone(), group, None),
+            })
+            .collect()
+    } else if args.package_per_color {
+        art_roles
+            .iter()
+            .map(|(role, _)| {
+                let name = derived_name(&args.name, role.title());
+                (name, &variants[..], Some(role.title()))
+            })
+            .collect()
+    } else {
+        vec![(args.name.clone(), &variants[..], None)]
+    };
+    if args.mask_window {
+        let name = derived_name(&args.name, MASK_WINDOW);
+        variant_groups.push((name, &variants[..], Some(MASK_WINDOW)));
+    }
+    error::check_promoted()?;
+    let mut packages = vec![];
+    for (index, (name, group, role)) in variant_groups.into_iter().enumerate() {
+        let uuid_pkg = match &args.uuid_pkg {
+            Some(uuid) => uuid.clone(),
+            None => make_uuid().to_string(),
+        };
+        let mut pkg_edits = load_edits(&args, &lib_path, ElementKind::Package, &uuid_pkg)?;
+        // The holes, pads, texts and fiducials are only added to the first
+        // package of a split
+        let with_extras = role.is_none() || index == 0;
+        let pad_count = group.first().map_or(0, |(_, _, _, pads, _)| pads.len());
+        let pad_uuids: Vec<String> = (0..pad_count)
+            .filter(|_| with_extras)
+            .map(|_| make_uuid().to_string())
+            .collect();
+        let footprints = make_package_footprints(
+            &args,
+            group,
+            role,
+            with_extras,
+            &pad_uuids,
+            &mut pkg_edits,
+            &footprint_texts,
+            &footprint_aligns,
+            &fiducials,
+            layer_names,
+        );
+        packages.push((name, uuid_pkg, pad_uuids, footprints, pkg_edits));
+    }
+
+    // Metadata of the elements, the same for all of them unless overridden
+    let created = created_timestamp();
+    let metadata = |kind| {
+        let value = |field, default| element_metadata(&metadata_overrides, kind, field, default);
+        Metadata {
+            name: &args.name,
+            description: value(MetadataField::Description, &args.description),
+            keywords: value(MetadataField::Keywords, &args.keywords),
+            author: value(MetadataField::Author, &args.author),
+            version: value(MetadataField::Version, &args.version),
+            created: value(MetadataField::Created, &created),
+        }
+    };
+
+    // Generate symbol and component, unless an existing component is used
+    let (uuid_cmp, symbol_component) = match &args.existing_cmp {
+        Some(uuid) => (uuid.clone(), None),
+        None => {
+            let (uuid_cmp, generated) = make_symbol_component(
+                &args,
+                &lib_path,
+                &metadata,
+                &symbol_polylines,
+                &texts,
+                symbol_snippets,
+                &attributes,
+            )?;
+            (uuid_cmp, Some(generated))
+        }
+    };
+
+    // Generate packages and devices
+    let mut pkgs = vec![];
+    let mut devs = vec![];
+    for (name, uuid_pkg, pad_uuids, footprints, pkg_edits) in packages {
+        let (pkg, dev) = make_package_device(
+            &args,
+            &lib_path,
+            &metadata,
+            &name,
+            uuid_pkg,
+            &pad_uuids,
+            &footprints,
+            pkg_edits,
+            &uuid_cmp,
+            package_snippets.clone(),
+        )?;
+        pkgs.push(pkg);
+        devs.push(dev);
+    }
+    let mut elements = vec![];
+    if let Some((uuid_sym, sym, _, cmp)) = &symbol_component {
+        elements.push((ElementKind::Symbol, uuid_sym, sym));
+        elements.push((ElementKind::Component, &uuid_cmp, cmp));
+    }
+    for (uuid, pkg, _) in &pkgs {
+        elements.push((ElementKind::Package, uuid, pkg));
+    }
+    for (uuid, dev, _) in &devs {
+        elements.push((ElementKind::Device, uuid, dev));
+    }
+
+    // Render the files (with the custom templates, if any) before writing
+    // anything, like the following checks
+    let contents = elements
+        .iter()
+        .map(|(kind, _, lines)| {
+            let lines = canonical::sort_nodes(lines);
+            Ok(match templates.render(*kind, &lines)? {
+                Some(rendered) => rendered,
+                None => lines.join("\n") + "\n",
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Elements with the same name are hard to tell apart when choosing them
+    // in LibrePCB
+    for (kind, uuid, lines) in &elements {
+        let source = lines.join("\n");
+        let name = match sexpr::parse(&source) {
+            Ok(root) => root
+                .child("name")
+                .and_then(|list| list.arg(0))
+                .map(str::to_string),
+            Err(_) => None,
+        };
+        if let Some(name) = name {
+            let uuids = elements_named(&lib_path, *kind, &name, uuid);
+            if !uuids.is_empty() {
+                Warning::DuplicateName {
+                    element: *kind,
+                    name,
+                    uuids: uuids.join(", "),
+                }
+                .report();
+            }
+        }
+    }
+    error::check_promoted()?;
+
+    // Refuse to replace existing elements unless requested. This is checked
+    // for all elements before writing anything, to avoid partial updates.
+    let existing: Vec<_> = elements
+        .iter()
+        .filter(|(kind, uuid, _)| element_dir(&lib_path, *kind, uuid).exists())
+        .collect();
+    if let Some((kind, uuid, _)) = existing.first() {
+        if !args.force {
+            return Err(Error::ElementExists {
+                element: *kind,
+                path: element_dir(&lib_path, *kind, uuid),
+            });
+        }
+    }
+
+    let existing: Vec<(ElementKind, String)> = existing
+        .iter()
+        .map(|(kind, uuid, _)| (*kind, uuid.to_string()))
+        .collect();
+    let elements: Vec<(ElementKind, String)> = elements
+        .iter()
+        .map(|(kind, uuid, _)| (*kind, uuid.to_string()))
+        .collect();
+    let source = input.source;
+    Ok(Box::new(move || {
+        if create_outpath {
+            create_library_dir(&lib_path, &library_files)?;
+        }
+        // Move elements that will be replaced out of the way
+        if !args.no_backup && !existing.is_empty() {
+            let backup_parent = args
+                .backup_dir
+                .clone()
+                .unwrap_or_else(|| lib_path.join(".svg2librepcb-backup"));
+            let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+            let mut backup_root = backup_parent.join(&timestamp);
+            let mut counter = 1;
+            while backup_root.exists() {
+                backup_root = backup_parent.join(format!("{}-{}", timestamp, counter));
+                counter += 1;
+            }
+            for (kind, uuid) in &existing {
+                let target = backup_element(&lib_path, &backup_root, *kind, uuid)?;
+                eprintln!("Moved existing {} to {:?}", kind, target);
+            }
+        }
+
+        // Write files to library
+        for ((kind, uuid), contents) in elements.iter().zip(&contents) {
+            let generated = match kind {
+                ElementKind::Symbol => symbol_component
+                    .as_ref()
+                    .map_or(&[][..], |(_, _, generated, _)| &generated[..]),
+                ElementKind::Package => pkgs
+                    .iter()
+                    .find(|(pkg_uuid, _, _)| pkg_uuid == uuid)
+                    .map_or(&[][..], |(_, _, generated)| &generated[..]),
+                ElementKind::Device => devs
+                    .iter()
+                    .find(|(dev_uuid, _, _)| dev_uuid == uuid)
+                    .map_or(&[][..], |(_, _, generated)| &generated[..]),
+                _ => &[],
+            };
+            write_element(
+                &lib_path,
+                *kind,
+                uuid,
+                args.format_version,
+                contents,
+                generated,
+            )?;
+        }
+
+        // Keep the input file and options for regenerating the packages
+        if args.keep_source {
+            let uuid_sym = symbol_component
+                .as_ref()
+                .map(|(uuid, _, _, _)| uuid.clone());
+            for ((uuid_pkg, _, _), (uuid_dev, _, _)) in pkgs.iter().zip(&devs) {
+                let uuids = provenance::Uuids {
+                    symbol: uuid_sym.clone(),
+                    component: Some(uuid_cmp.clone()),
+                    package: Some(uuid_pkg.clone()),
+                    device: Some(uuid_dev.clone()),
+                };
+                provenance::write(
+                    &element_dir(&lib_path, ElementKind::Package, uuid_pkg),
+                    &args.svgfile,
+                    &source,
+                    recorded_args(&args),
+                    uuids,
+                )?;
+            }
+        }
+
+        print_summary(&args.name, &lib_path, &elements);
+
+        if let Some(command) = &args.post_hook {
+            let paths: Vec<PathBuf> = elements
+                .iter()
+                .map(|(kind, uuid)| element_dir(&lib_path, *kind, uuid))
+                .collect();
+            run_post_hook(command, &paths)?;
+        }
+
+        Ok(Converted {
+            source,
+            name: args.name,
+            library: lib_path,
+            elements,
+        })
+    }))
+}
+
+/// Generate the footprints of a package from the variants: With a role (see
+/// --package-per-color and --mask-window), only the footprints of that role.
+/// The pads get the given UUIDs, and the holes, texts and fiducials are only
+/// added with `with_extras`. Manual edits of the footprints are taken from
+/// `pkg_edits`.
+fn make_package_footprints(
+    args: &Args,
+    group: &[Variant],
+    role: Option<&str>,
+    with_extras: bool,
+    pad_uuids: &[String],
+    pkg_edits: &mut Option<update::Edits>,
+    footprint_texts: &[FootprintText],
+    footprint_aligns: &[(String, Align)],
+    fiducials: &[(f64, f64)],
+    layer_names: LayerNames,
+) -> Vec<Footprint> {
+    let mut footprints = vec![];
+    for (width, specs, holes, pads, texts) in group {
+        for (title, bottom, layers) in specs {
+            let in_package = match role {
+                Some(role) => title.strip_suffix(" (Bottom)").unwrap_or(title) == role,
+                None => !is_mask_window(title),
+            };
+            if !in_package {
+                continue;
+            }
+            let name = match width {
+                Some(width) if !args.device_per_scale => {
+                    format!("{} ({} mm)", title, format_float(*width))
+                }
+                _ => title.to_string(),
+            };
+            let edits = pkg_edits
+                .as_mut()
+                .and_then(|edits| edits.take_footprint(&name));
+            let layers: Vec<_> = layers
+                .iter()
+                .map(|(layer, polylines)| {
+                    let paths = make_paths(
+                        polylines,
+                        args.round_corners,
+                        args.fit_arcs.then_some(args.flattening_tolerance),
+                        args.max_segment_length,
+                        args.max_arc_sweep,
+                    );
+                    (layer.name(layer_names), paths)
+                })
+                .collect();
+            let (mut holes, mut texts, footprint_texts) = if *bottom {
+                mirror_extras(holes, texts, footprint_texts, layer_names)
+            } else {
+                (holes.clone(), texts.clone(), footprint_texts.to_vec())
+            };
+            let pads = if *bottom {
+                mirror_holes(pads)
+            } else {
+                pads.clone()
+            };
+            let pads: Vec<(&str, Hole)> = pad_uuids.iter().map(String::as_str).zip(pads).collect();
+            let mut footprint_fiducials = fiducials;
+            if !with_extras {
+                holes.clear();
+                texts.clear();
+                footprint_fiducials = &[];
+            }
+
+            // The footprints of all roles (and the mask window) are aligned
+            // together by the artwork, so that they line up when placed
+            // at the same position
+            let aligned_paths: Vec<geometry::Path> = if role.is_some() {
+                specs
+                    .iter()
+                    .filter(|(title, other_bottom, _)| {
+                        other_bottom == bottom && !is_mask_window(title)
+                    })
+                    .flat_map(|(_, _, layers)| layers)
+                    .flat_map(|(_, polylines)| polylines)
+                    .map(|polyline| geometry::to_path(polyline))
+                    .collect()
+            } else {
+                layers
+                    .iter()
+                    .flat_map(|(_, paths)| paths.iter().cloned())
+                    .collect()
+            };
+            let (offset, _) = match footprint_align(footprint_aligns, title) {
+                Some(align) => {
+                    let paths: Vec<geometry::Path> = layers
+                        .iter()
+                        .flat_map(|(_, paths)| paths.iter().cloned())
+                        .collect();
+                    alignment(align, &paths)
+                }
+                None => alignment(args.align, &aligned_paths),
+            };
+            let mut footprint = make_footprint(
+                edits.as_ref().map_or_else(make_uuid, |edits| edits.uuid),
+                &name,
+                "",
+                offset,
+                args.grab_area,
+                args.format_version,
+                &layers,
+                &pads,
+                &holes,
+                &texts,
+                *bottom,
+                &footprint_texts,
+            );
+            for &(x, y) in footprint_fiducials {
+                let fiducial = make_fiducial(
+                    if *bottom { (-x, y) } else { (x, y) },
+                    args.fiducial_diameter,
+                    *bottom,
+                    layer_names,
+                    args.format_version,
+                );
+                update::insert_before_end(&mut footprint.lines, fiducial);
+            }
+            if let Some(size) = args.origin_cross {
+                let cross = make_origin_cross(size, *bottom, layer_names);
+                update::insert_before_end(&mut footprint.lines, cross);
+            }
+            if let Some(edits) = edits {
+                update::insert_before_end(&mut footprint.lines, edits.nodes);
+            }
+            footprints.push(footprint);
+        }
+    }
+    footprints
+}
+
+/// Whether a footprint title is the one of --mask-window (or its bottom side).
+fn is_mask_window(title: &str) -> bool {
+    title.strip_suffix(" (Bottom)").unwrap_or(title) == MASK_WINDOW
+}
+
+/// Load the manual edits of an existing element, which are carried over when
+/// replacing it.
+fn load_edits(
+    args: &Args,
+    lib_path: &Path,
+    kind: ElementKind,
+    uuid: &str,
+) -> Result<Option<update::Edits>> {
+    if args.force && !args.discard_edits {
+        update::load_edits(lib_path, kind, uuid)
+    } else {
+        Ok(None)
+    }
+}
+
+/// A generated symbol (UUID, lines and the UUIDs generated for its nodes)
+/// with the lines of its component.
+type GeneratedSymbol = (String, Vec<String>, Vec<String>, Vec<String>);
+
+/// Generate the symbol and component, with the manual edits of existing
+/// ones. Returns the UUID of the component with the generated elements.
+fn make_symbol_component<'a>(
+    args: &'a Args,
+    lib_path: &Path,
+    metadata: &impl Fn(ElementKind) -> Metadata<'a>,
+    symbol_polylines: &[Polyline],
+    texts: &[Text],
+    symbol_snippets: Vec<String>,
+    attributes: &[Attribute],
+) -> Result<(String, GeneratedSymbol)> {
+    // Generate symbol
+    let uuid_sym = args
+        .uuid_sym
+        .clone()
+        .unwrap_or_else(|| make_uuid().to_string());
+    let mut sym = make_symbol(
+        &uuid_sym,
+        &metadata(ElementKind::Symbol),
+        args.uuid_cmpcat.as_deref(),
+        args.format_version,
+        args.approve_checks,
+        args.grab_area,
+        args.symbol_layer,
+        args.symbol_grab_area,
+        args.bounding_box_symbol,
+        (args.label_height.unwrap_or(2.5), args.label_align),
+        &make_paths(
+            symbol_polylines,
+            args.round_corners,
+            args.fit_arcs
+                .then(|| args.symbol_tolerance.unwrap_or(args.flattening_tolerance)),
+            args.max_segment_length,
+            args.max_arc_sweep,
+        ),
+        texts,
+    );
+    update::insert_before_end(&mut sym, symbol_snippets);
+
+    // Generate component
+    let uuid_cmp = args
+        .uuid_cmp
+        .clone()
+        .unwrap_or_else(|| make_uuid().to_string());
+    let mut cmp = make_component(
+        &uuid_cmp,
+        &metadata(ElementKind::Component),
+        &uuid_sym,
+        args.uuid_cmpcat.as_deref(),
+        &args.prefix,
+        &args.default_value,
+        args.schematic_only,
+        attributes,
+        (&args.variant_name, &args.variant_description, &args.norm),
+        (&args.gate_suffix, args.gate_required),
+        args.format_version,
+        args.approve_checks,
+    );
+
+    // Merge manual edits into the regenerated symbol and component
+    let sym_generated = update::generated_uuids(&sym);
+    if let Some(edits) = load_edits(args, lib_path, ElementKind::Symbol, &uuid_sym)? {
+        update::insert_before_end(&mut sym, edits.nodes);
+    }
+    if let Some(edits) = load_edits(args, lib_path, ElementKind::Component, &uuid_cmp)? {
+        update::insert_into_gate(&mut cmp, edits.gate_pins);
+        update::insert_before_end(&mut cmp, edits.nodes);
+    }
+    Ok((uuid_cmp, (uuid_sym, sym, sym_generated, cmp)))
+}
+
+/// A generated element: UUID, lines and the UUIDs generated for its nodes.
+type GeneratedElement = (String, Vec<String>, Vec<String>);
+
+/// Generate a package with the manual edits of the existing one (if any),
+/// and its device with the manual edits of an existing device.
+fn make_package_device<'a>(
+    args: &'a Args,
+    lib_path: &Path,
+    metadata: &impl Fn(ElementKind) -> Metadata<'a>,
+    name: &str,
+    uuid_pkg: String,
+    pad_uuids: &[String],
+    footprints: &[Footprint],
+    pkg_edits: Option<update::Edits>,
+    uuid_cmp: &str,
+    package_snippets: Vec<String>,
+) -> Result<(GeneratedElement, GeneratedElement)> {
+    let manufacturer = args.manufacturer.as_deref().unwrap_or("");
+    let mut pkg = make_package(
+        &uuid_pkg,
+        &Metadata {
+            name,
+            ..metadata(ElementKind::Package)
+        },
+        args.uuid_pkgcat.as_deref(),
+        args.format_version,
+        args.approve_checks,
+        pad_uuids,
+        footprints,
+    );
+    update::insert_before_end(&mut pkg, package_snippets);
+
+    let uuid_dev = match &args.uuid_dev {
+        Some(uuid) => uuid.clone(),
+        None => make_uuid().to_string(),
+    };
+    let mut dev = make_device(
+        &uuid_dev,
+        &Metadata {
+            name,
+            ..metadata(ElementKind::Device)
+        },
+        uuid_cmp,
+        &uuid_pkg,
+        args.uuid_cmpcat.as_deref(),
+        args.format_version,
+        args.approve_checks,
+        pad_uuids,
+        args.mpn.as_deref().map(|mpn| (mpn, manufacturer)),
+    );
+
+    // Merge manual edits into the regenerated elements
+    let pkg_generated = update::generated_uuids(&pkg);
+    if let Some(edits) = pkg_edits {
+        let mut nodes = edits.nodes;
+        for footprint in edits.footprints {
+            nodes.extend(footprint.lines);
+        }
+        update::insert_before_end(&mut pkg, nodes);
+    }
+    let dev_generated = update::generated_uuids(&dev);
+    if let Some(edits) = load_edits(args, lib_path, ElementKind::Device, &uuid_dev)? {
+        update::insert_before_end(&mut dev, edits.nodes);
+    }
+    Ok((
+        (uuid_pkg, pkg, pkg_generated),
+        (uuid_dev, dev, dev_generated),
+    ))
+}
+
+/// Options of a conversion with their effective values, which reproduce the
+/// conversion (see --keep-source).
+fn recorded_args(args: &Args) -> Vec<String> {
+    let format_version = args
+        .format_version
+        .to_possible_value()
+        .expect("No skipped format versions");
+    let mut recorded = vec![
+        format!("--name={}", args.name),
+        format!("--author={}", args.author),
+        format!("--description={}", args.description),
+        format!("--keywords={}", args.keywords),
+        format!("--version={}", args.version),
+        format!("--prefix={}", args.prefix),
+        format!("--default-value={}", args.default_value),
+        format!("--format-version={}", format_version.get_name()),
+        format!("--flattening-tolerance={}", args.flattening_tolerance),
+    ];
+    for (option, value) in [
+        ("--uuid-cmpcat", &args.uuid_cmpcat),
+        ("--uuid-pkgcat", &args.uuid_pkgcat),
+    ] {
+        if let Some(value) = value {
+            recorded.push(format!("{}={}", option, value));
+        }
+    }
+    for layer in &args.layers {
+        recorded.push(format!("--layer={}", layer));
+    }
+    if args.pcb_art {
+        recorded.push("--pcb-art".to_string());
+    }
+    for mapping in &args.art_color {
+        recorded.push(format!("--art-color={}", mapping));
+    }
+    // The width of --width-mm and --height-mm is recorded as given
+    if !args.scales.is_empty() && args.width_mm.is_none() && args.height_mm.is_none() {
+        let scales: Vec<String> = args.scales.iter().map(|width| width.to_string()).collect();
+        recorded.push(format!("--scales={}", scales.join(",")));
+    }
+    recorded.extend(args.passed_args.iter().cloned());
+    recorded
+}
+
+/// Prepare the footprints for other EDA tools (see the `export` module).
+///
+/// The footprints are named after the package and the footprint, e.g.
+/// `Logo_Top_Copper`.
+fn export_footprints(
+    args: &Args,
+    variants: &[Variant],
+    footprint_texts: &[FootprintText],
+    footprint_aligns: &[(String, Align)],
+    fiducials: &[(f64, f64)],
+    layer_names: LayerNames,
+) -> Vec<export::Footprint> {
+    let mut footprints = vec![];
+    for (width, specs, holes, _, texts) in variants {
+        for (title, bottom, layers) in specs {
+            let name = export::file_name(&match width {
+                Some(width) => format!("{} {} {} mm", args.name, title, format_float(*width)),
+                None => format!("{} {}", args.name, title),
+            });
+            let layers: Vec<(Layer, Vec<geometry::Path>)> = layers
+                .iter()
+                .map(|(layer, polylines)| {
+                    let paths = make_paths(
+                        polylines,
+                        args.round_corners,
+                        args.fit_arcs.then_some(args.flattening_tolerance),
+                        args.max_segment_length,
+                        args.max_arc_sweep,
+                    );
+                    (*layer, paths)
+                })
+                .collect();
+            let (holes, texts, footprint_texts) = if *bottom {
+                mirror_extras(holes, texts, footprint_texts, layer_names)
+            } else {
+                (holes.clone(), texts.clone(), footprint_texts.to_vec())
+            };
+
+            // Align and invert the axis, like in make_footprint()
+            let all_paths: Vec<geometry::Path> = layers
+                .iter()
+                .flat_map(|(_, paths)| paths.iter().cloned())
+                .collect();
+            let align = footprint_align(footprint_aligns, title).unwrap_or(args.align);
+            let ((dx, dy), _) = alignment(align, &all_paths);
+            let position =
+                |point: geometry::Point| geometry::Point::new(point.x + dx, -(point.y + dy));
+            let layers: Vec<(Layer, Vec<geometry::Path>)> = layers
+                .into_iter()
+                .map(|(layer, paths)| {
+                    let paths = paths
+                        .iter()
+                        .map(|path| {
+                            let aligned: geometry::Path = path
+                                .iter()
+                                .map(|vertex| geometry::Vertex {
+                                    position: geometry::Point::new(
+                                        vertex.position.x + dx,
+                                        vertex.position.y + dy,
+                                    ),
+                                    ..*vertex
+                                })
+                                .collect();
+                            export::invert_y(&aligned)
+                        })
+                        .collect();
+                    (layer, paths)
+                })
+                .collect();
+            let mut export_texts = vec![];
+            if let Some((text_layer, _)) = layers.first() {
+                export_texts.extend(texts.iter().map(|text| export::Text {
+                    layer: *text_layer,
+                    value: text.content.clone(),
+                    position: position(text.position),
+                    height: text.height,
+                    rotation: text.rotation,
+                    align: format!("{} top", text.align),
+                    mirror: *bottom,
+                }));
+            }
+            export_texts.extend(footprint_texts.iter().filter_map(|text| {
+                Some(export::Text {
+                    layer: Layer::from_name(text.layer)?,
+                    value: text.value.clone(),
+                    position: geometry::Point::new(text.position.0, text.position.1),
+                    height: text.height,
+                    rotation: text.rotation,
+                    align: text.align.clone(),
+                    mirror: text.mirror,
+                })
+            }));
+            footprints.push(export::Footprint {
+                name,
+                bottom: *bottom,
+                layers,
+                holes: holes
+                    .iter()
+                    .map(|hole| (position(hole.position), hole.diameter))
+                    .collect(),
+                texts: export_texts,
+                fiducials: fiducials
+                    .iter()
+                    .map(|&(x, y)| {
+                        let x = if *bottom { -x } else { x };
+                        (geometry::Point::new(x, y), args.fiducial_diameter)
+                    })
+                    .collect(),
+            });
+        }
+    }
+    footprints
+}
+
+/// Write files for other EDA tools into the output directory and return
+/// their paths.
+fn write_files(
+    args: &Args,
+    dir: &Path,
+    files: Vec<(PathBuf, Vec<String>)>,
+) -> Result<Vec<PathBuf>> {
+    eprintln!("Generated {:?} in {}:", args.name, dir.display());
+    for (path, lines) in &files {
+        let mut content = lines.join("\n");
+        content.push('\n');
+        fs::write(path, content).map_err(|source| Error::WriteFile {
+            path: path.clone(),
+            source,
+        })?;
+        if let Some(file_name) = path.file_name() {
+            eprintln!("  {}", file_name.to_string_lossy());
+        }
+    }
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Hint printed after the summary of the generated elements.
+const RESCAN_HINT: &str =
+    "Restart LibrePCB or rescan the libraries (Library Manager) to see the new elements.";
+
+/// Verify the library with librepcb-cli (if requested) and print how to get
+/// LibrePCB to show the new elements.
+///
+/// LibrePCB only scans the libraries on startup or when requested in the
+/// Library Manager, running instances cannot be notified.
+fn finish_library(lib_path: &Path, verify: bool) -> Result<()> {
+    if verify {
+        let output = Command::new("librepcb-cli")
+            .arg("open-library")
+            .arg("--all")
+            .arg(lib_path)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| Error::Verify(e.to_string()))?;
+        if !output.status.success() {
+            // librepcb-cli prints the elements which could not be loaded
+            let messages = String::from_utf8_lossy(&output.stdout).into_owned()
+                + &String::from_utf8_lossy(&output.stderr);
+            let messages: Vec<&str> = messages
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+            return Err(Error::Verify(match messages[..] {
+                [] => output.status.to_string(),
+                _ => messages.join("\n"),
+            }));
+        }
+        eprintln!("Verified the library with librepcb-cli, all elements could be loaded.");
+    }
+    eprintln!("{}", RESCAN_HINT);
+    Ok(())
+}
+
+/// Create the output path (with --mkdirs), with the given files of a new
+/// library unless they exist already.
+fn create_library_dir(lib_path: &Path, files: &[(&str, String)]) -> Result<()> {
+    let error = |e: io::Error| Error::InvalidOutpath {
+        path: lib_path.to_owned(),
+        reason: format!("could not create directory: {}", e),
+    };
+    fs::create_dir_all(lib_path).map_err(error)?;
+    for (name, contents) in files {
+        let path = lib_path.join(name);
+        if !path.exists() {
+            fs::write(&path, contents).map_err(error)?;
+        }
+    }
+    Ok(())
+}
+
+/// The version file and `library.lp` of a new library, named after its
+/// directory.
+fn make_library(
+    lib_path: &Path,
+    author: &str,
+    format_version: FormatVersion,
+) -> Vec<(&'static str, String)> {
+    let name = lib_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let created = created_timestamp();
+    let metadata = Metadata {
+        name: &name,
+        description: "",
+        keywords: "",
+        author,
+        version: "0.1",
+        created: &created,
+    };
+    let mut lines = vec![format!("(librepcb_library {}", make_uuid())];
+    lines.extend(metadata.lines());
+    lines.push(" (deprecated false)".to_string());
+    lines.push(r#" (url "")"#.to_string());
+    if format_version == FormatVersion::V1 {
+        lines.push(r#" (manufacturer "")"#.to_string());
+    }
+    lines.push(")".to_string());
+    vec![
+        (
+            ".librepcb-lib",
+            format!("{}\n", format_version.file_version()),
+        ),
+        ("library.lp", lines.join("\n") + "\n"),
+    ]
+}
+
+/// Print the generated elements to stderr, so users know where to find them.
+fn print_summary(name: &str, lib_path: &Path, elements: &[(ElementKind, String)]) {
+    eprintln!("Generated {:?} in {}:", name, lib_path.display());
+    for (kind, uuid) in elements {
+        eprintln!("  {:<9}  {}/{}", kind, kind.dir_name(), uuid);
+    }
+}
+
+/// The generated elements (in one or more libraries) in the format of
+/// `--print`.
+fn format_result(
+    format: PrintFormat,
+    name: &str,
+    elements: &[(&Path, ElementKind, &str)],
+) -> String {
+    let kinds = [
+        ElementKind::Symbol,
+        ElementKind::Component,
+        ElementKind::Package,
+        ElementKind::Device,
+    ];
+    match format {
+        // Multiple packages and devices (--device-per-scale) are separated by
+        // spaces, to loop over them
+        PrintFormat::Uuids => kinds
+            .iter()
+            .filter_map(|kind| {
+                let uuids: Vec<&str> = elements
+                    .iter()
+                    .filter(|(_, k, _)| k == kind)
+                    .map(|(_, _, uuid)| *uuid)
+                    .collect();
+                match &uuids[..] {
+                    [] => None,
+                    [uuid] => Some(format!("{}={}\n", kind.dir_name(), uuid)),
+                    uuids => Some(format!("{}='{}'\n", kind.dir_name(), uuids.join(" "))),
+                }
+            })
+            .collect(),
+        PrintFormat::Json => {
+            let mut libraries: Vec<&Path> = vec![];
+            for (lib_path, _, _) in elements {
+                if !libraries.contains(lib_path) {
+                    libraries.push(lib_path);
+                }
+            }
+            let elements: Vec<serde_json::Value> = elements
+                .iter()
+                .map(|(lib_path, kind, uuid)| {
+                    serde_json::json!({
+                        "kind": kind.to_string(),
+                        "uuid": uuid,
+                        "path": element_dir(lib_path, *kind, uuid),
+                    })
+                })
+                .collect();
+            let result = serde_json::json!({
+                "name": name,
+                "libraries": libraries,
+                "elements": elements,
+            });
+            format!("{}\n", result)
+        }
+    }
+}
+
+/// Run the post-generation hook with the element directories as arguments.
+///
+/// The command is run by the shell, its output is redirected to stderr to
+/// keep stdout clean for Inkscape.
+fn run_post_hook(command: &str, paths: &[PathBuf]) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell
+            .arg("-c")
+            .arg(format!("{} \"$@\"", command))
+            .arg("svg2librepcb");
+        shell
+    };
+    let error = |reason: String| Error::PostHook {
+        command: command.to_string(),
+        reason,
+    };
+    let status = shell
+        .args(paths)
+        .stdin(Stdio::null())
+        .stdout(io::stderr())
+        .status()
+        .map_err(|e| error(e.to_string()))?;
+    if !status.success() {
+        return Err(error(status.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
     fn test_format_float() {
         let cases = [
             (3.14456, "3.145"),
             (-7.0, "-7.0"),
             (0.4, "0.4"),
             (-0.0, "0.0"),
+            (-0.0001, "0.0"),
         ];
         for case in cases {
             assert_eq!(format_float(case.0), case.1);
         }
     }
+
+    #[test]
+    fn test_seeded_uuids() {
+        seed_uuids(42);
+        let first: Vec<Uuid> = (0..3).map(|_| make_uuid()).collect();
+        seed_uuids(42);
+        let second: Vec<Uuid> = (0..3).map(|_| make_uuid()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[1]);
+        assert_eq!(first[0].get_version_num(), 4);
+    }
+
+    #[test]
+    fn test_load_snippet() {
+        let lines =
+            load_snippet("(text $uuid (layer sym_names)\n (value \"{{NAME}}\")) (x)").unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(" (text "));
+        assert!(!lines[0].contains("$uuid"));
+        assert_eq!(lines[1], "  (value \"{{NAME}}\"))");
+        assert_eq!(lines[2], " (x)");
+        assert!(load_snippet("(text").is_err());
+    }
+
+    #[test]
+    fn test_parse_attribute() {
+        let attribute = parse_attribute("ART_REVISION=B 2").unwrap();
+        assert_eq!(attribute.key, "ART_REVISION");
+        assert_eq!(attribute.attribute_type, "string");
+        assert_eq!(attribute.unit, "none");
+        assert_eq!(attribute.value, "B 2");
+        let attribute = parse_attribute("VOLTAGE:voltage:millivolt=500").unwrap();
+        assert_eq!(attribute.attribute_type, "voltage");
+        assert_eq!(attribute.unit, "millivolt");
+        assert_eq!(parse_attribute("POWER:power=1").unwrap().unit, "watt");
+        for invalid in [
+            "KEY",
+            "key=1",
+            "KEY:foo=1",
+            "KEY:voltage:ampere=1",
+            "KEY:string:volt=1",
+            "KEY:string:none:x=1",
+        ] {
+            assert!(parse_attribute(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_footprint_text() {
+        let config = Config::default();
+        let text = parse_footprint_text(
+            "{{BOARD}};x=1;y=-2.5;layer=top_documentation;align=center top",
+            &config,
+            LayerNames::Modern,
+        )
+        .unwrap();
+        assert_eq!(text.value, "{{BOARD}}");
+        assert_eq!(text.layer, "top_documentation");
+        assert_eq!(text.position, (1.0, -2.5));
+        assert_eq!(text.align, "center top");
+        let text = parse_footprint_text("Logo", &config, LayerNames::Legacy).unwrap();
+        assert_eq!(text.layer, "top_placement");
+        for invalid in [
+            "x;y",
+            "x;foo=1",
+            "x;x=a",
+            "x;layer=foo",
+            "x;align=top",
+            "x;height=0",
+        ] {
+            assert!(parse_footprint_text(invalid, &config, LayerNames::Modern).is_err());
+        }
+    }
+
+    #[test]
+    fn test_elements_named() {
+        let dir = TempDir::new("test").unwrap();
+        let lib_path = dir.path();
+        for (uuid, name) in [("a", "Logo"), ("b", "Other"), ("c", "Logo")] {
+            let dir = element_dir(lib_path, ElementKind::Package, uuid);
+            fs::create_dir_all(&dir).unwrap();
+            let source = format!("(librepcb_package {}\n (name \"{}\")\n)\n", uuid, name);
+            fs::write(dir.join("package.lp"), source).unwrap();
+        }
+        let named = elements_named(lib_path, ElementKind::Package, "Logo", "c");
+        let missing = elements_named(lib_path, ElementKind::Device, "Logo", "c");
+        assert_eq!(named, vec!["a".to_string()]);
+        assert_eq!(missing, Vec::<String>::new());
+    }
+
+    /// Arguments to convert a square, written to `dir`.
+    fn test_args(dir: &Path, options: &[&str]) -> Args {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10mm" height="10mm" viewBox="0 0 10 10"><path d="M0 0H10V10H0Z"/></svg>"#;
+        let path = dir.join("logo.svg");
+        fs::write(&path, svg).unwrap();
+        let mut command_line = vec!["svg2librepcb", path.to_str().unwrap()];
+        command_line.extend(["--name", "Logo", "--author", "Test"]);
+        command_line.extend(options);
+        Args::try_parse_from(command_line).unwrap()
+    }
+
+    /// Contents of the elements of the given kind written by a conversion.
+    fn written_elements(written: &Written, kind: ElementKind) -> Vec<String> {
+        written.files[0]
+            .elements
+            .iter()
+            .filter(|(element, _)| *element == kind)
+            .map(|(_, uuid)| {
+                let dir = element_dir(&written.library, kind, uuid);
+                fs::read_to_string(dir.join(kind.file_name())).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_convert_libraries() {
+        let temp = TempDir::new("test").unwrap();
+        let dir = temp.path();
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        let outpaths = [
+            "--outpath",
+            a.to_str().unwrap(),
+            "--outpath",
+            b.to_str().unwrap(),
+        ];
+        let written = convert_libraries(&test_args(dir, &outpaths), None);
+        let named: Vec<_> = [&a, &b]
+            .iter()
+            .map(|lib| elements_named(lib, ElementKind::Package, "Logo", "").len())
+            .collect();
+
+        // A library which cannot be written to fails the conversion before
+        // anything is written (or created with --mkdirs)
+        let (missing, file) = (dir.join("missing"), dir.join("file"));
+        fs::write(&file, "").unwrap();
+        let outpaths = [
+            "--mkdirs",
+            "--outpath",
+            missing.to_str().unwrap(),
+            "--outpath",
+            file.to_str().unwrap(),
+        ];
+        let failed = convert_libraries(&test_args(dir, &outpaths), None);
+        let missing_created = missing.exists();
+
+        // Every library gets the same elements
+        let written = written.unwrap();
+        assert_eq!(written.len(), 2);
+        let kinds: Vec<Vec<ElementKind>> = written
+            .iter()
+            .map(|written| {
+                written.files[0]
+                    .elements
+                    .iter()
+                    .map(|(kind, _)| *kind)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(kinds[0], kinds[1]);
+        assert!(kinds[0].contains(&ElementKind::Package));
+        assert_eq!(named, vec![1, 1]);
+        assert!(failed.is_err());
+        assert!(!missing_created);
+    }
+
+    #[test]
+    fn test_mkdirs_library() {
+        let dir = TempDir::new("test").unwrap();
+        let lib_path = dir.path().join("Logos.lplib");
+        let options = ["--mkdirs", "--outpath", lib_path.to_str().unwrap()];
+        let written = convert_libraries(&test_args(dir.path(), &options), None);
+        let version = fs::read_to_string(lib_path.join(".librepcb-lib"));
+        let library = fs::read_to_string(lib_path.join("library.lp"));
+
+        // The created directory is a LibrePCB library
+        assert!(written.is_ok());
+        assert_eq!(version.unwrap(), "0.1\n");
+        let library = library.unwrap();
+        assert!(library.starts_with("(librepcb_library "));
+        assert!(library.contains(" (name \"Logos\")\n (description \"\")"));
+        assert!(library.contains(" (author \"Test\")"));
+    }
+
+    #[test]
+    fn test_existing_cmp() {
+        let temp = TempDir::new("test").unwrap();
+        let dir = temp.path();
+        let uuid = "6e1a7b3e-0c54-4f1f-9a4b-3b7f2ad0c3c1";
+        let options = ["--existing-cmp", uuid, "--outpath", dir.to_str().unwrap()];
+        let missing = convert_libraries(&test_args(dir, &options), None);
+        let component = element_dir(dir, ElementKind::Component, uuid);
+        fs::create_dir_all(&component).unwrap();
+        fs::write(component.join("component.lp"), "").unwrap();
+        let written = convert_libraries(&test_args(dir, &options), None);
+
+        assert!(matches!(missing, Err(Error::MissingElement { .. })));
+        let kinds: Vec<ElementKind> = written.unwrap()[0].files[0]
+            .elements
+            .iter()
+            .map(|(kind, _)| *kind)
+            .collect();
+        assert_eq!(kinds, vec![ElementKind::Package, ElementKind::Device]);
+
+        // Options of the symbol and component cannot be used
+        for option in [
+            "--prefix=U",
+            "--norm=IEC",
+            "--schematic-only",
+            "--bounding-box-symbol",
+        ] {
+            let command_line = ["svg2librepcb", "--existing-cmp", uuid, option, "logo.svg"];
+            assert!(Args::try_parse_from(command_line).is_err(), "{}", option);
+        }
+    }
+
+    #[test]
+    fn test_backup_element() {
+        let dir = TempDir::new("test").unwrap();
+        let uuid = "c6b1f6a5-3f0e-4a2e-9d53-0a8f1b2c3d4e";
+        let element = element_dir(dir.path(), ElementKind::Package, uuid);
+        fs::create_dir_all(&element).unwrap();
+        fs::write(element.join("package.lp"), "old").unwrap();
+        let backup_root = dir.path().join("backup");
+        let target = backup_element(dir.path(), &backup_root, ElementKind::Package, uuid).unwrap();
+
+        assert_eq!(target, backup_root.join("pkg").join(uuid));
+        assert!(!element.exists());
+        assert_eq!(
+            fs::read_to_string(target.join("package.lp")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_backup_replaced() {
+        let dir = TempDir::new("test").unwrap();
+        let backup_parent = dir.path().join(".svg2librepcb-backup");
+        let options = [
+            "--uuid-seed",
+            "1",
+            "--outpath",
+            dir.path().to_str().unwrap(),
+        ];
+
+        // New elements are not backed up
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        assert!(!backup_parent.exists());
+        let (_, uuid) = written[0].files[0]
+            .elements
+            .iter()
+            .find(|(kind, _)| *kind == ElementKind::Package)
+            .unwrap();
+        let package = element_dir(dir.path(), ElementKind::Package, uuid);
+        let old = fs::read_to_string(package.join("package.lp")).unwrap();
+
+        // Existing elements are only replaced with --force
+        let replaced = convert_libraries(&test_args(dir.path(), &options), None);
+        assert!(matches!(replaced, Err(Error::ElementExists { .. })));
+        assert!(!backup_parent.exists());
+
+        let options = [&options[..], &["--force"]].concat();
+        convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        let backups: Vec<_> = fs::read_dir(&backup_parent)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let backup = backups[0].join("pkg").join(uuid);
+        assert_eq!(fs::read_to_string(backup.join("package.lp")).unwrap(), old);
+        assert!(package.join("package.lp").exists());
+    }
+
+    #[test]
+    fn test_device_per_scale() {
+        let dir = TempDir::new("test").unwrap();
+        let options = [
+            "--scales=10,20",
+            "--device-per-scale",
+            "--outpath",
+            dir.path().to_str().unwrap(),
+        ];
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+
+        // One package and device per width, all for the same component
+        let packages = written_elements(&written[0], ElementKind::Package);
+        let devices = written_elements(&written[0], ElementKind::Device);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(
+            written_elements(&written[0], ElementKind::Component).len(),
+            1
+        );
+        for (width, device) in ["10.0", "20.0"].iter().zip(&devices) {
+            assert!(device.contains(&format!(" (name \"Logo ({} mm)\")", width)));
+        }
+        for (package, device) in packages.iter().zip(&devices) {
+            let uuid = package.split_whitespace().nth(1).unwrap();
+            assert!(device.contains(&format!(" (package {})", uuid)));
+        }
+    }
+
+    #[test]
+    fn test_both_sides() {
+        let dir = TempDir::new("test").unwrap();
+        let options = ["--both-sides", "--outpath", dir.path().to_str().unwrap()];
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        let package = &written_elements(&written[0], ElementKind::Package)[0];
+        let vertices = |footprint: &str| -> Vec<(f64, f64)> {
+            footprint
+                .split("(vertex (position ")
+                .skip(1)
+                .map(|vertex| {
+                    let mut coordinates = vertex.split([' ', ')']).map(|c| c.parse().unwrap());
+                    (coordinates.next().unwrap(), coordinates.next().unwrap())
+                })
+                .collect()
+        };
+
+        // Every footprint is followed by its mirrored copy on the bottom
+        let footprints: Vec<&str> = package.split(" (footprint ").skip(1).collect();
+        assert!(!footprints.is_empty());
+        assert_eq!(footprints.len() % 2, 0);
+        for pair in footprints.chunks(2) {
+            let (top, bottom) = (pair[0], pair[1]);
+            assert!(top.contains("(name \"Top "));
+            assert!(bottom.contains("(name \"Bottom "));
+            assert!(!bottom.contains("(layer top_"));
+            let mirrored: Vec<_> = vertices(top).iter().map(|(x, y)| (-x, *y)).collect();
+            assert_eq!(vertices(bottom), mirrored);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box_symbol() {
+        let dir = TempDir::new("test").unwrap();
+        let options = [
+            "--bounding-box-symbol",
+            "--outpath",
+            dir.path().to_str().unwrap(),
+        ];
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        let symbol = &written_elements(&written[0], ElementKind::Symbol)[0];
+
+        // A single closed rectangle, not filled
+        assert_eq!(symbol.matches(" (polygon ").count(), 1);
+        assert!(symbol.contains("(layer sym_outlines)\n  (width 0.254) (fill false)"));
+        let vertices: Vec<&str> = symbol
+            .split("(vertex (position ")
+            .skip(1)
+            .map(|vertex| &vertex[..vertex.find(')').unwrap()])
+            .collect();
+        assert_eq!(vertices.len(), 5);
+        assert_eq!(vertices[0], vertices[4]);
+        let coordinates: Vec<Vec<&str>> = vertices
+            .iter()
+            .map(|vertex| vertex.split(' ').collect())
+            .collect();
+        assert_eq!(coordinates[0][1], coordinates[1][1]);
+        assert_eq!(coordinates[1][0], coordinates[2][0]);
+        assert_eq!(coordinates[2][1], coordinates[3][1]);
+        assert_eq!(coordinates[3][0], coordinates[0][0]);
+        assert!(symbol.contains("(value \"{{NAME}}\")"));
+        assert!(symbol.contains("(value \"{{VALUE}}\")"));
+    }
+
+    #[test]
+    fn test_symbol_layer() {
+        let dir = TempDir::new("test").unwrap();
+        let outpath = dir.path().to_str().unwrap();
+        for (layer, option) in [
+            ("sym_outlines", None),
+            (
+                "schematic_documentation",
+                Some("--symbol-layer=schematic_documentation"),
+            ),
+            (
+                "sym_hidden_grab_areas",
+                Some("--symbol-layer=sym_hidden_grab_areas"),
+            ),
+        ] {
+            let mut options = vec!["--outpath", outpath, "--force"];
+            options.extend(option);
+            let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+            let symbol = &written_elements(&written[0], ElementKind::Symbol)[0];
+            assert_eq!(symbol.matches(" (polygon ").count(), 1, "{}", layer);
+            assert!(symbol.contains(&format!("(layer {})", layer)), "{}", layer);
+        }
+    }
+
+    #[test]
+    fn test_symbol_grab_area() {
+        let dir = TempDir::new("test").unwrap();
+        let outpath = dir.path().to_str().unwrap();
+        let options = ["--symbol-grab-area", "--outpath", outpath];
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        let symbol = &written_elements(&written[0], ElementKind::Symbol)[0];
+
+        // A filled rectangle on the hidden grab area layer, besides the artwork
+        assert_eq!(symbol.matches(" (polygon ").count(), 2);
+        let grab_area = symbol
+            .split(" (polygon ")
+            .find(|polygon| polygon.contains("(layer sym_hidden_grab_areas)"))
+            .unwrap();
+        assert!(grab_area.contains("(fill true) (grab_area true)"));
+        assert_eq!(grab_area.matches("(vertex ").count(), 5);
+        assert!(symbol.contains("(layer sym_outlines)"));
+
+        let written = convert_libraries(&test_args(dir.path(), &["--outpath", outpath]), None);
+        let symbol = &written_elements(&written.unwrap()[0], ElementKind::Symbol)[0];
+        assert!(!symbol.contains("sym_hidden_grab_areas"));
+    }
+
+    #[test]
+    fn test_parse_fiducial() {
+        assert_eq!(parse_fiducial("1.5,-2").unwrap(), (1.5, -2.0));
+        assert_eq!(parse_fiducial(" 0 , 3 ").unwrap(), (0.0, 3.0));
+        assert!(parse_fiducial("1.5").is_err());
+        assert!(parse_fiducial("a,b").is_err());
+    }
+
+    #[test]
+    fn test_load_svgz() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let dir = TempDir::new("test").unwrap();
+        let path = dir.path().join("logo.svgz");
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"<svg/>").unwrap();
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+        assert_eq!(load_svg(&path).unwrap(), "<svg/>");
+    }
+
+    #[test]
+    fn test_parse_warning_level() {
+        assert_eq!(
+            parse_warning_level("ignored-text=error").unwrap(),
+            ("ignored-text".to_string(), Level::Error)
+        );
+        for value in ["ignored-text", "texts=error", "ignored-text=fatal"] {
+            assert!(parse_warning_level(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_metadata_lines() {
+        let metadata = Metadata {
+            name: "Logo",
+            description: "The \"ACME\" logo",
+            keywords: "logo,acme",
+            author: "Logo Team",
+            version: "0.1.0",
+            created: "2024-01-31T12:00:00Z",
+        };
+        assert_eq!(
+            metadata.lines(),
+            vec![
+                r#" (name "Logo")"#,
+                r#" (description "The \"ACME\" logo")"#,
+                r#" (keywords "logo,acme")"#,
+                r#" (author "Logo Team")"#,
+                r#" (version "0.1.0")"#,
+                r#" (created 2024-01-31T12:00:00Z)"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_element_metadata() {
+        let overrides: Vec<_> = [
+            "symbol.description=Outline of the logo",
+            "package.version=2.0",
+            "device.created=2024-01-31T13:00:00+01:00",
+            "package.version=2.1",
+        ]
+        .iter()
+        .map(|value| parse_element_metadata(value).unwrap())
+        .collect();
+        let value = |kind, field| element_metadata(&overrides, kind, field, "default");
+        assert_eq!(
+            value(ElementKind::Symbol, MetadataField::Description),
+            "Outline of the logo"
+        );
+        assert_eq!(
+            value(ElementKind::Component, MetadataField::Description),
+            "default"
+        );
+        assert_eq!(value(ElementKind::Package, MetadataField::Version), "2.1");
+        assert_eq!(
+            value(ElementKind::Device, MetadataField::Created),
+            "2024-01-31T12:00:00Z"
+        );
+        for invalid in [
+            "symbol.description",
+            "description=Logo",
+            "footprint.description=Logo",
+            "symbol.name=Logo",
+            "symbol.version=v1",
+            "symbol.keywords=a\nb",
+            "symbol.created=yesterday",
+        ] {
+            assert!(parse_element_metadata(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let args = Args::try_parse_from(["svg2librepcb", "measure", "logo.svg"]).unwrap();
+        assert!(matches!(args.command, Some(Subcommand::Measure(_))));
+        let args = Args::try_parse_from(["svg2librepcb", "regen", "lib", "--", "--fit-arcs"]);
+        match args.unwrap().command {
+            Some(Subcommand::Regen(regen)) => assert_eq!(regen.overrides, vec!["--fit-arcs"]),
+            command => panic!("{:?}", command),
+        }
+
+        // Conversions don't need a subcommand
+        let args = Args::try_parse_from(["svg2librepcb", "--name", "Logo", "logo.svg"]).unwrap();
+        assert!(args.command.is_none());
+        let args = Args::try_parse_from(["svg2librepcb", "--name", "Logo", "validate", "x.svg"]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_input_names() {
+        let mut args = Args::try_parse_from(["svg2librepcb", "logos/Tux.svg"]).unwrap();
+        assert_eq!(input_names(&args), ("Tux.svg".into(), "Tux".into()));
+
+        // The temporary file of the clipboard contents is not named
+        args.clipboard = true;
+        let dir = TempDir::new("clipboard").unwrap();
+        args.svgfile = dir.path().join("clipboard.svg");
+        assert_eq!(input_names(&args), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn test_auto_keywords() {
+        let layers = vec![
+            "Logo".to_string(),
+            "Text, small".to_string(),
+            "Layer 1".to_string(),
+            " Ebene 12".to_string(),
+        ];
+        assert_eq!(
+            auto_keywords(
+                "acme, Logo",
+                "acme-logo_v2 (1)",
+                &layers,
+                &["pcb art"],
+                &[(10.0, 4.96)],
+            ),
+            "acme,Logo,v2,text  small,pcb art,10.0x5.0mm"
+        );
+        assert!(is_default_layer_name("Layer"));
+        assert!(!is_default_layer_name("Copper layer"));
+        assert_eq!(auto_keywords("", "", &[], &[], &[]), "");
+    }
+
+    #[test]
+    fn test_footprint_align() {
+        let overrides = vec![
+            parse_footprint_align("Top Placement=bottom-left").unwrap(),
+            parse_footprint_align(" top copper = Center").unwrap(),
+        ];
+        assert_eq!(
+            overrides[0],
+            ("Top Placement".to_string(), Align::BottomLeft)
+        );
+        assert_eq!(
+            footprint_align(&overrides, "Top Copper (Bottom)"),
+            Some(Align::Center)
+        );
+        assert_eq!(footprint_align(&overrides, "Top Stop Mask"), None);
+        for value in ["Top Placement", "Top Placement=left"] {
+            assert!(parse_footprint_align(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_origin_cross() {
+        let top = make_origin_cross(2.0, false, LayerNames::Modern).join("\n");
+        assert_eq!(top.matches(" (polygon ").count(), 2);
+        assert_eq!(top.matches("(layer top_documentation)").count(), 2);
+        assert!(top.contains("(vertex (position -1.0 0.0) (angle 0.0))"));
+        assert!(top.contains("(vertex (position 1.0 0.0) (angle 0.0))"));
+        assert!(top.contains("(vertex (position 0.0 -1.0) (angle 0.0))"));
+        assert!(top.contains("(vertex (position 0.0 1.0) (angle 0.0))"));
+        let bottom = make_origin_cross(2.0, true, LayerNames::Modern).join("\n");
+        assert_eq!(bottom.matches("(layer bot_documentation)").count(), 2);
+
+        // Every footprint gets a cross
+        let dir = TempDir::new("test").unwrap();
+        let options = ["--origin-cross", "--outpath", dir.path().to_str().unwrap()];
+        let written = convert_libraries(&test_args(dir.path(), &options), None).unwrap();
+        let package = &written_elements(&written[0], ElementKind::Package)[0];
+        let footprints: Vec<&str> = package.split(" (footprint ").skip(1).collect();
+        assert!(!footprints.is_empty());
+        for footprint in footprints {
+            assert!(footprint.contains("(vertex (position -0.5 0.0) (angle 0.0))"));
+        }
+    }
+
+    #[test]
+    fn test_fit_target() {
+        let bounds = geometry::Rect {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 20.0,
+            y_max: 10.0,
+        };
+        let fit = |width, height, fit| fit_target(bounds, width, height, fit).unwrap();
+        assert_eq!(fit(Some(10.0), None, Fit::Cover), (0.5, 1.0, None));
+        assert_eq!(fit(None, Some(10.0), Fit::Contain), (1.0, 1.0, None));
+        assert_eq!(fit(Some(10.0), Some(10.0), Fit::Contain), (0.5, 1.0, None));
+        assert_eq!(fit(Some(10.0), Some(10.0), Fit::Stretch), (0.5, 2.0, None));
+        assert_eq!(
+            fit(Some(10.0), Some(10.0), Fit::Cover),
+            (
+                1.0,
+                1.0,
+                Some(geometry::Rect {
+                    x_min: 5.0,
+                    y_min: 0.0,
+                    x_max: 15.0,
+                    y_max: 10.0,
+                })
+            )
+        );
+        assert_eq!(fit(Some(40.0), Some(20.0), Fit::Cover), (2.0, 1.0, None));
+        assert!(fit_target(bounds, Some(-1.0), None, Fit::Contain).is_err());
+    }
+
+    #[test]
+    fn test_format_result() {
+        let (lib_path, team_path) = (Path::new("/lib"), Path::new("/team"));
+        let elements = [
+            (
+                lib_path,
+                ElementKind::Symbol,
+                "c1fbc16a-a380-4387-aee7-a3facd5f50aa",
+            ),
+            (
+                lib_path,
+                ElementKind::Package,
+                "8d92aac5-2fe0-460c-baad-35e9361d5f79",
+            ),
+            (
+                team_path,
+                ElementKind::Package,
+                "2288621c-6056-4531-90d1-21e9f6f72175",
+            ),
+        ];
+        assert_eq!(
+            format_result(PrintFormat::Uuids, "Logo", &elements),
+            "sym=c1fbc16a-a380-4387-aee7-a3facd5f50aa\n\
+             pkg='8d92aac5-2fe0-460c-baad-35e9361d5f79 2288621c-6056-4531-90d1-21e9f6f72175'\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&format_result(PrintFormat::Json, "Logo", &elements)).unwrap();
+        assert_eq!(json["name"], "Logo");
+        assert_eq!(json["libraries"], serde_json::json!(["/lib", "/team"]));
+        assert_eq!(json["elements"][0]["kind"], "symbol");
+        assert_eq!(
+            json["elements"][0]["path"],
+            "/lib/sym/c1fbc16a-a380-4387-aee7-a3facd5f50aa"
+        );
+        assert_eq!(
+            json["elements"][2]["path"],
+            "/team/pkg/2288621c-6056-4531-90d1-21e9f6f72175"
+        );
+    }
+
+    #[test]
+    fn test_parse_tile() {
+        assert_eq!(
+            parse_tile("3x2", None).unwrap(),
+            Tile {
+                columns: 3,
+                rows: 2,
+                pitch: None
+            }
+        );
+        assert_eq!(
+            parse_tile("1X4", Some("5,-2.5")).unwrap(),
+            Tile {
+                columns: 1,
+                rows: 4,
+                pitch: Some((5.0, -2.5))
+            }
+        );
+        assert!(parse_tile("3", None).is_err());
+        assert!(parse_tile("0x2", None).is_err());
+        assert!(parse_tile("3x2", Some("5")).is_err());
+    }
+
+    #[test]
+    fn test_mask_window() {
+        let square: Polyline = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ]
+        .iter()
+        .map(|&(x, y)| geometry::Point::new(x, y))
+        .collect();
+        let window = mask_window(&[square], 1.0, 0.01);
+        assert_eq!(window.len(), 1);
+        assert!((geometry::area(&window[0]).abs() - 44.0).abs() < 1e-9);
+        let bounds = geometry::bounds(&window).unwrap();
+        assert_eq!((bounds.x_min, bounds.y_max), (-1.0, 11.0));
+        assert_eq!(mask_window(&[], 1.0, 0.01), Vec::<Polyline>::new());
+    }
+
+    #[test]
+    fn test_escape_string() {
+        let cases = [
+            ("plain", "plain"),
+            ("Line 1\nLine 2", r"Line 1\nLine 2"),
+            (r#"A "quoted" \ backslash"#, r#"A \"quoted\" \\ backslash"#),
+            ("tab\there\r\n", r"tab\there\r\n"),
+        ];
+        for case in cases {
+            assert_eq!(escape_string(case.0), case.1);
+        }
+    }
 }
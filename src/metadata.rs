@@ -0,0 +1,70 @@
+//! Metadata passed as a single JSON object (`--metadata-json`), e.g. by
+//! wrapper scripts and GUIs:
+//!
+//! ```json
+//! {"name": "ACME Logo", "author": "Logo Team", "keywords": "logo,acme"}
+//! ```
+//!
+//! The keys are the names of the corresponding command line options. All of
+//! them are optional.
+
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct JsonMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub version: Option<String>,
+    pub keywords: Option<String>,
+    pub prefix: Option<String>,
+    pub default_value: Option<String>,
+    /// Component category UUID
+    pub component_category: Option<String>,
+    /// Package category UUID
+    pub package_category: Option<String>,
+}
+
+impl JsonMetadata {
+    /// Parse the value of `--metadata-json`: A JSON object, or the path of a
+    /// JSON file prefixed with `@`.
+    pub fn parse(value: &str) -> Result<JsonMetadata> {
+        let contents = match value.strip_prefix('@') {
+            Some(path) => fs::read_to_string(path).map_err(|source| Error::ReadFile {
+                path: Path::new(path).to_owned(),
+                source,
+            })?,
+            None => value.to_string(),
+        };
+        serde_json::from_str(&contents).map_err(|e| Error::MetadataJson(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let metadata =
+            JsonMetadata::parse(r#"{"name": "Logo", "default-value": "LOGO", "version": "1.0"}"#)
+                .unwrap();
+        assert_eq!(
+            metadata,
+            JsonMetadata {
+                name: Some("Logo".into()),
+                default_value: Some("LOGO".into()),
+                version: Some("1.0".into()),
+                ..JsonMetadata::default()
+            }
+        );
+        assert!(JsonMetadata::parse(r#"{"title": "Logo"}"#).is_err());
+        assert!(JsonMetadata::parse(r#"{"name": 1}"#).is_err());
+        assert!(JsonMetadata::parse("@/nonexistent.json").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Interactive assignment of the colors of a drawing to PCB art roles
+//! (`--pick-colors`).
+//!
+//! The detected colors are listed with the role suggested by the palette and
+//! the layers of that role. The user changes roles until the assignment is
+//! right, and gets the equivalent `--art-color` options to reuse it.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    art::{self, Role},
+    svg::Color,
+};
+
+/// All roles, in the order they are listed.
+const ROLES: [(&str, Role); 5] = [
+    ("copper", Role::Copper),
+    ("masked-copper", Role::MaskedCopper),
+    ("silkscreen", Role::Silkscreen),
+    ("substrate", Role::Substrate),
+    ("soldermask", Role::Soldermask),
+];
+
+fn role_name(role: Role) -> &'static str {
+    ROLES
+        .iter()
+        .find(|(_, r)| *r == role)
+        .map(|(name, _)| *name)
+        .expect("All roles are listed")
+}
+
+/// Color in the notation used by `--art-color`, e.g. `#ffd700`.
+pub fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Let the user assign a role to each color. The suggestions are taken from
+/// the palette. Returns `None` if the user cancels.
+pub fn pick_roles(
+    colors: &[Color],
+    palette: &[(Color, Role)],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Option<Vec<(Color, Role)>>> {
+    let mut roles: Vec<(Color, Role)> = colors
+        .iter()
+        .map(|color| {
+            let role = art::classify(*color, palette).unwrap_or(Role::Soldermask);
+            (*color, role)
+        })
+        .collect();
+    loop {
+        writeln!(output, "Colors of the drawing:")?;
+        for (i, (color, role)) in roles.iter().enumerate() {
+            let layers: Vec<&str> = role.layers().iter().map(|layer| layer.title()).collect();
+            let layers = match &layers[..] {
+                [] => "no polygons".to_string(),
+                layers => layers.join(", "),
+            };
+            writeln!(
+                output,
+                "  {:>2}  {}  {:<14} ({})",
+                i + 1,
+                hex(*color),
+                role_name(*role),
+                layers
+            )?;
+        }
+        writeln!(
+            output,
+            "Enter NUMBER=ROLE to change a color (roles: {}),",
+            ROLES.map(|(name, _)| name).join(", ")
+        )?;
+        write!(output, "an empty line to continue or q to cancel: ")?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(None);
+        }
+        match line.trim() {
+            "" => return Ok(Some(roles)),
+            "q" | "quit" => return Ok(None),
+            line => {
+                if let Err(reason) = change_role(&mut roles, line) {
+                    writeln!(output, "{}", reason)?;
+                }
+            }
+        }
+        writeln!(output)?;
+    }
+}
+
+/// Apply a `NUMBER=ROLE` change.
+fn change_role(roles: &mut [(Color, Role)], change: &str) -> Result<(), String> {
+    let (number, role) = change
+        .split_once('=')
+        .ok_or_else(|| format!("Expected NUMBER=ROLE, not {:?}", change))?;
+    let entry = number
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| roles.get_mut(number.checked_sub(1)?))
+        .ok_or_else(|| format!("There is no color number {:?}", number.trim()))?;
+    entry.1 =
+        Role::from_name(role.trim()).ok_or_else(|| format!("Unknown role {:?}", role.trim()))?;
+    Ok(())
+}
+
+/// The `--art-color` mapping of a color, e.g. `#ffd700=copper`.
+pub fn mapping(color: Color, role: Role) -> String {
+    format!("{}={}", hex(color), role_name(role))
+}
+
+/// Options which reproduce the assignment without the picker.
+pub fn options(roles: &[(Color, Role)]) -> String {
+    roles
+        .iter()
+        .map(|(color, role)| format!("--art-color={}", mapping(*color, *role)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_roles() {
+        let colors = [[255, 215, 0], [250, 250, 250]];
+        let mut input = "2=copper\n3=copper\n1=paint\n\n".as_bytes();
+        let mut output = vec![];
+        let roles = pick_roles(&colors, &art::DEFAULT_PALETTE, &mut input, &mut output)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            roles,
+            vec![
+                ([255, 215, 0], Role::Copper),
+                ([250, 250, 250], Role::Copper)
+            ]
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("   2  #fafafa  silkscreen     (Top Placement)"));
+        assert!(output.contains("There is no color number \"3\""));
+        assert!(output.contains("Unknown role \"paint\""));
+        assert_eq!(
+            options(&roles),
+            "--art-color=#ffd700=copper --art-color=#fafafa=copper"
+        );
+
+        let mut input = "q\n".as_bytes();
+        let result = pick_roles(&colors, &[], &mut input, &mut vec![]).unwrap();
+        assert_eq!(result, None);
+    }
+}
@@ -0,0 +1,376 @@
+//! Preview window (`--gui`, requires the `gui` feature).
+//!
+//! The window shows the parsed artwork with the colors of its layers, the
+//! origin of the footprints and a millimeter grid. Two sliders at the bottom
+//! change the flattening tolerance (top) and the width of the footprints
+//! (bottom), the current values and dimensions are shown in the title bar.
+//! Enter continues the conversion with these settings, Escape cancels it.
+
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+use crate::{
+    error::{Error, Result},
+    geometry::{self, Point, Polyline, Rect},
+    layers::Layer,
+};
+
+/// The artwork, as shown in the preview.
+pub struct Scene {
+    /// Polylines of each layer (in mm, with the y-axis pointing down)
+    pub layers: Vec<(Layer, Vec<Polyline>)>,
+    /// Origin of the footprints
+    pub origin: Point,
+}
+
+/// Settings chosen in the preview.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Settings {
+    pub tolerance: f64,
+    /// Width of the footprints in mm
+    pub width: f64,
+}
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+/// Height of the slider area at the bottom of the window.
+const SLIDER_AREA: usize = 60;
+const MARGIN: f64 = 20.0;
+
+const BACKGROUND: u32 = 0x1f2a1f;
+const GRID: u32 = 0x2c3a2c;
+const ORIGIN: u32 = 0xff4040;
+
+/// Range of the sliders (logarithmic), the width relative to the original
+/// width.
+const TOLERANCE_RANGE: (f64, f64) = (0.01, 1.0);
+const WIDTH_RANGE: (f64, f64) = (0.1, 10.0);
+
+/// Color of a layer in the preview.
+fn color(layer: Layer) -> u32 {
+    match layer {
+        Layer::TopCopper | Layer::BotCopper => 0xd8a040,
+        Layer::TopStopMask | Layer::BotStopMask => 0x60b060,
+        Layer::TopLegend | Layer::BotLegend => 0xf0f0f0,
+        Layer::TopSolderPaste | Layer::BotSolderPaste => 0xa0a0b0,
+        _ => 0x80a0e0,
+    }
+}
+
+/// Show the preview window until the user accepts (returning the chosen
+/// settings) or cancels. The scene is regenerated with `scene` whenever the
+/// tolerance changes.
+pub fn show(
+    scene: impl Fn(f64) -> Result<Scene>,
+    initial: Settings,
+    original_width: f64,
+) -> Result<Settings> {
+    let mut window = Window::new(
+        "svg2librepcb",
+        WIDTH,
+        HEIGHT,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )
+    .map_err(|e| Error::Preview(e.to_string()))?;
+    window.set_target_fps(30);
+
+    let mut settings = initial;
+    let mut current = scene(settings.tolerance)?;
+    let mut rendered_tolerance = settings.tolerance;
+    let mut buffer = vec![];
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            return Ok(settings);
+        }
+        let (width, height) = window.get_size();
+        let sliders = [
+            (height - SLIDER_AREA + 20, TOLERANCE_RANGE, 1.0),
+            (height - SLIDER_AREA + 45, WIDTH_RANGE, original_width),
+        ];
+
+        // Drag the sliders with the mouse
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+            for (i, (slider_y, range, base)) in sliders.iter().enumerate() {
+                if mouse_down && (y - *slider_y as f32).abs() < 10.0 {
+                    let t = (f64::from(x) - MARGIN) / (width as f64 - 2.0 * MARGIN);
+                    let value = slider_value(t.clamp(0.0, 1.0), *range) * base;
+                    match i {
+                        0 => settings.tolerance = value,
+                        _ => settings.width = value,
+                    }
+                }
+            }
+        }
+
+        // Flattening is slow for complex artwork, so it is only redone when
+        // the slider is released
+        if !mouse_down && settings.tolerance != rendered_tolerance {
+            current = scene(settings.tolerance)?;
+            rendered_tolerance = settings.tolerance;
+        }
+
+        let factor = settings.width / original_width;
+        let all: Vec<Polyline> = current
+            .layers
+            .iter()
+            .flat_map(|(_, polylines)| polylines.iter().cloned())
+            .collect();
+        let bounds = geometry::bounds(&geometry::scale(&all, factor));
+        window.set_title(&title(&settings, bounds));
+
+        buffer.clear();
+        buffer.resize(width * height, BACKGROUND);
+        let view = Rect {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: width as f64,
+            y_max: (height.saturating_sub(SLIDER_AREA)) as f64,
+        };
+        if let Some(bounds) = bounds {
+            let transform = fit(&bounds, &view);
+            draw_grid(&mut buffer, width, &view, transform);
+            for (layer, polylines) in &current.layers {
+                let polylines: Vec<Polyline> = geometry::scale(polylines, factor)
+                    .iter()
+                    .map(|polyline| polyline.iter().map(|p| transform.apply(*p)).collect())
+                    .collect();
+                fill(&mut buffer, width, &polylines, color(*layer));
+            }
+            let origin = transform.apply(Point::new(
+                current.origin.x * factor,
+                current.origin.y * factor,
+            ));
+            for (dx, dy) in [(-8.0, 0.0), (0.0, -8.0)] {
+                let from = Point::new(origin.x + dx, origin.y + dy);
+                let to = Point::new(origin.x - dx, origin.y - dy);
+                draw_line(&mut buffer, width, from, to, ORIGIN);
+            }
+        }
+        for (i, (slider_y, range, base)) in sliders.iter().enumerate() {
+            let value = if i == 0 {
+                settings.tolerance
+            } else {
+                settings.width
+            };
+            let t = slider_position(value / base, *range);
+            draw_slider(&mut buffer, width, *slider_y, t);
+        }
+        window
+            .update_with_buffer(&buffer, width, height)
+            .map_err(|e| Error::Preview(e.to_string()))?;
+    }
+    Err(Error::Cancelled)
+}
+
+fn title(settings: &Settings, bounds: Option<Rect>) -> String {
+    let size = bounds.map_or(String::new(), |b| {
+        format!("{:.2} × {:.2} mm — ", b.x_max - b.x_min, b.y_max - b.y_min)
+    });
+    format!(
+        "svg2librepcb — {}tolerance {:.3} — Enter: convert, Esc: cancel",
+        size, settings.tolerance
+    )
+}
+
+/// Value of a logarithmic slider at position `t` (0 to 1).
+fn slider_value(t: f64, (min, max): (f64, f64)) -> f64 {
+    min * (max / min).powf(t)
+}
+
+/// Position of a value on a logarithmic slider.
+fn slider_position(value: f64, (min, max): (f64, f64)) -> f64 {
+    ((value / min).ln() / (max / min).ln()).clamp(0.0, 1.0)
+}
+
+/// Scale and translation from artwork to window coordinates.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Transform {
+    scale: f64,
+    dx: f64,
+    dy: f64,
+}
+
+impl Transform {
+    fn apply(&self, point: Point) -> Point {
+        Point::new(
+            point.x * self.scale + self.dx,
+            point.y * self.scale + self.dy,
+        )
+    }
+}
+
+/// Fit the bounds into the view (keeping a margin), centered.
+fn fit(bounds: &Rect, view: &Rect) -> Transform {
+    let (width, height) = (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min);
+    let scale = ((view.x_max - view.x_min - 2.0 * MARGIN) / width)
+        .min((view.y_max - view.y_min - 2.0 * MARGIN) / height);
+    let scale = if scale.is_finite() && scale > 0.0 {
+        scale
+    } else {
+        1.0
+    };
+    Transform {
+        scale,
+        dx: (view.x_min + view.x_max - width * scale) / 2.0 - bounds.x_min * scale,
+        dy: (view.y_min + view.y_max - height * scale) / 2.0 - bounds.y_min * scale,
+    }
+}
+
+/// Draw grid lines every millimeter, or every 10 mm when zoomed out.
+fn draw_grid(buffer: &mut [u32], width: usize, view: &Rect, transform: Transform) {
+    let spacing = if transform.scale >= 6.0 { 1.0 } else { 10.0 } * transform.scale;
+    if spacing < 3.0 {
+        return;
+    }
+    let mut x = transform.dx.rem_euclid(spacing);
+    while x < view.x_max {
+        let (from, to) = (Point::new(x, view.y_min), Point::new(x, view.y_max - 1.0));
+        draw_line(buffer, width, from, to, GRID);
+        x += spacing;
+    }
+    let mut y = transform.dy.rem_euclid(spacing);
+    while y < view.y_max {
+        let (from, to) = (Point::new(view.x_min, y), Point::new(view.x_max - 1.0, y));
+        draw_line(buffer, width, from, to, GRID);
+        y += spacing;
+    }
+}
+
+fn draw_slider(buffer: &mut [u32], width: usize, y: usize, t: f64) {
+    let (start, end) = (MARGIN, width as f64 - MARGIN);
+    for dy in [-1.0, 0.0, 1.0] {
+        let y = y as f64 + dy;
+        draw_line(
+            buffer,
+            width,
+            Point::new(start, y),
+            Point::new(end, y),
+            0x808080,
+        );
+    }
+    let x = start + t * (end - start);
+    for dx in -3..=3 {
+        let x = x + f64::from(dx);
+        let (from, to) = (Point::new(x, y as f64 - 7.0), Point::new(x, y as f64 + 7.0));
+        draw_line(buffer, width, from, to, 0xffffff);
+    }
+}
+
+/// Set a pixel, ignoring coordinates outside of the buffer.
+fn set_pixel(buffer: &mut [u32], width: usize, x: i64, y: i64, color: u32) {
+    if x >= 0 && y >= 0 && (x as usize) < width {
+        if let Some(pixel) = buffer.get_mut(y as usize * width + x as usize) {
+            *pixel = color;
+        }
+    }
+}
+
+fn draw_line(buffer: &mut [u32], width: usize, from: Point, to: Point, color: u32) {
+    let steps = (to.x - from.x)
+        .abs()
+        .max((to.y - from.y).abs())
+        .ceil()
+        .max(1.0);
+    for i in 0..=steps as i64 {
+        let t = i as f64 / steps;
+        let x = from.x + (to.x - from.x) * t;
+        let y = from.y + (to.y - from.y) * t;
+        set_pixel(buffer, width, x.round() as i64, y.round() as i64, color);
+    }
+}
+
+/// Fill the closed polylines (with the even-odd rule) and draw the open ones.
+fn fill(buffer: &mut [u32], width: usize, polylines: &[Polyline], color: u32) {
+    let height = buffer.len() / width;
+    let closed: Vec<&Polyline> = polylines
+        .iter()
+        .filter(|polyline| geometry::is_closed(polyline))
+        .collect();
+    for row in 0..height {
+        // Sample at the pixel centers
+        let y = row as f64 + 0.5;
+        let mut crossings: Vec<f64> = closed
+            .iter()
+            .flat_map(|polyline| polyline.windows(2))
+            .filter(|edge| (edge[0].y <= y) != (edge[1].y <= y))
+            .map(|edge| {
+                let t = (y - edge[0].y) / (edge[1].y - edge[0].y);
+                edge[0].x + t * (edge[1].x - edge[0].x)
+            })
+            .collect();
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        for span in crossings.chunks_exact(2) {
+            let start = (span[0] - 0.5).ceil().max(0.0) as i64;
+            let end = (span[1] - 0.5).floor().min(width as f64 - 1.0) as i64;
+            for x in start..=end {
+                set_pixel(buffer, width, x, row as i64, color);
+            }
+        }
+    }
+    for polyline in polylines.iter().filter(|p| !geometry::is_closed(p)) {
+        for segment in polyline.windows(2) {
+            draw_line(buffer, width, segment[0], segment[1], color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slider() {
+        assert!((slider_value(0.0, WIDTH_RANGE) - 0.1).abs() < 1e-9);
+        assert!((slider_value(0.5, WIDTH_RANGE) - 1.0).abs() < 1e-9);
+        assert!((slider_position(1.0, WIDTH_RANGE) - 0.5).abs() < 1e-9);
+        assert_eq!(slider_position(100.0, WIDTH_RANGE), 1.0);
+    }
+
+    #[test]
+    fn test_fit() {
+        let bounds = Rect {
+            x_min: 10.0,
+            y_min: 0.0,
+            x_max: 20.0,
+            y_max: 5.0,
+        };
+        let view = Rect {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 140.0,
+            y_max: 140.0,
+        };
+        let transform = fit(&bounds, &view);
+        assert_eq!(transform.scale, 10.0);
+        assert_eq!(
+            transform.apply(Point::new(10.0, 0.0)),
+            Point::new(20.0, 45.0)
+        );
+        assert_eq!(
+            transform.apply(Point::new(20.0, 5.0)),
+            Point::new(120.0, 95.0)
+        );
+    }
+
+    #[test]
+    fn test_fill() {
+        let square = |min: f64, max: f64| {
+            vec![
+                Point::new(min, min),
+                Point::new(max, min),
+                Point::new(max, max),
+                Point::new(min, max),
+                Point::new(min, min),
+            ]
+        };
+        let mut buffer = vec![0; 10 * 10];
+        fill(&mut buffer, 10, &[square(1.0, 9.0), square(3.0, 7.0)], 1);
+        // The outer square is filled, the inner one is a hole
+        assert_eq!(buffer.iter().filter(|p| **p == 1).count(), 64 - 16);
+        assert_eq!(buffer[10 + 1], 1);
+        assert_eq!(buffer[5 * 10 + 5], 0);
+    }
+}
@@ -0,0 +1,337 @@
+//! Storing the input file and options with a generated package
+//! (`--keep-source`).
+//!
+//! The input file is copied into the package directory, together with the
+//! effective options of the conversion (without the input file and output
+//! path) and the UUIDs of the generated elements. This allows future
+//! maintainers to regenerate or tweak the artwork without the original file,
+//! e.g. with the `regen` command.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Name of the file with the options, in the package directory.
+pub const OPTIONS_FILE: &str = ".svg2librepcb-options.toml";
+
+/// Name of the copied input file (followed by its extension).
+const SOURCE_FILE: &str = ".svg2librepcb-source";
+
+/// Options which take a value and are not recorded as given. The input and
+/// output paths and the UUIDs are specific to the invocation, the others are
+/// recorded with their effective values (e.g. the author from the settings or
+/// the description read from a file). The log file, the report and the
+/// printed format only concern the original run.
+const REPLACED_OPTIONS: [&str; 27] = [
+    "--outpath",
+    "--uuid-pkg",
+    "--uuid-sym",
+    "--uuid-cmp",
+    "--uuid-dev",
+    "--name",
+    "--author",
+    "--description",
+    "--description-file",
+    "--keywords",
+    "--version",
+    "--prefix",
+    "--default-value",
+    "--format-version",
+    "--uuid-cmpcat",
+    "--uuid-pkgcat",
+    "--layer",
+    "--art-color",
+    "--flattening-tolerance",
+    "--scales",
+    "--metadata-csv",
+    "--name-template",
+    "--description-template",
+    "--metadata-json",
+    "--log-file",
+    "--report",
+    "--print",
+];
+
+/// Flags which are not recorded: Interactive or only relevant for writing
+/// the files (`--pcb-art` is recorded with its effective value).
+const REPLACED_FLAGS: [&str; 8] = [
+    "--clipboard",
+    "--gui",
+    "--pick-colors",
+    "--force",
+    "--keep-source",
+    "--inkscape",
+    "--pcb-art",
+    "--verify",
+];
+
+/// Options with a file or directory value, which are recorded as absolute
+/// paths to work from any directory.
+const PATH_OPTIONS: [&str; 4] = ["--config", "--drill-file", "--rules", "--backup-dir"];
+
+/// UUIDs of the elements generated together with a package.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Uuids {
+    pub symbol: Option<String>,
+    pub component: Option<String>,
+    pub package: Option<String>,
+    pub device: Option<String>,
+}
+
+/// Contents of the options file.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Provenance {
+    /// Version of svg2librepcb which generated the package
+    pub version: String,
+    /// Name of the copied input file
+    pub source: String,
+    /// Command line options (without the input file and output path)
+    pub args: Vec<String>,
+    pub uuids: Uuids,
+}
+
+impl Provenance {
+    /// Path of the copied input file.
+    pub fn source_path(&self, package_dir: &Path) -> PathBuf {
+        package_dir.join(&self.source)
+    }
+
+    /// Load the options file of a package.
+    pub fn load(package_dir: &Path) -> Result<Provenance> {
+        let path = package_dir.join(OPTIONS_FILE);
+        let contents = fs::read_to_string(&path).map_err(|source| Error::ReadFile {
+            path: path.clone(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|e| Error::Config {
+            path,
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Remove the input file and the replaced options from the command line
+/// arguments (without the program name). Relative paths of the path options
+/// are resolved against the current directory.
+pub fn passed_args(args: impl IntoIterator<Item = String>, svgfile: &Path) -> Vec<String> {
+    let cwd = env::current_dir().unwrap_or_default();
+    let mut passed = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let name = arg.split('=').next().unwrap_or("");
+        if REPLACED_OPTIONS.contains(&name) {
+            if !arg.contains('=') {
+                // The value is the next argument
+                args.next();
+            }
+        } else if PATH_OPTIONS.contains(&name) {
+            match arg.split_once('=') {
+                Some((name, value)) => passed.push(format!("{}={}", name, absolute(&cwd, value))),
+                None => {
+                    passed.push(arg);
+                    passed.extend(args.next().map(|value| absolute(&cwd, &value)));
+                }
+            }
+        } else if !REPLACED_FLAGS.contains(&name) && Path::new(&arg) != svgfile {
+            passed.push(arg);
+        }
+    }
+    passed
+}
+
+/// Path relative to a directory, unless it is absolute.
+fn absolute(dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+/// Name of an option argument, e.g. `--align` for `--align=center`.
+fn option_name(arg: &str) -> Option<&str> {
+    arg.starts_with("--")
+        .then(|| arg.split('=').next().unwrap_or(arg))
+}
+
+/// Replace recorded options with the overrides. Options which are
+/// overridden are removed (with their values) before appending the
+/// overrides.
+pub fn merge_args(recorded: &[String], overrides: &[String]) -> Vec<String> {
+    let overridden: Vec<&str> = overrides
+        .iter()
+        .filter_map(|arg| option_name(arg))
+        .collect();
+    let mut merged = vec![];
+    let mut skip_value = false;
+    for arg in recorded {
+        match option_name(arg) {
+            Some(name) => {
+                skip_value = overridden.contains(&name) && !arg.contains('=');
+                if !overridden.contains(&name) {
+                    merged.push(arg.clone());
+                }
+            }
+            None if skip_value => skip_value = false,
+            None => merged.push(arg.clone()),
+        }
+    }
+    merged.extend(overrides.iter().cloned());
+    merged
+}
+
+/// Find the packages with an options file, given package or library
+/// directories.
+pub fn find_packages(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.join(OPTIONS_FILE).is_file() {
+        return Ok(vec![path.to_owned()]);
+    }
+    let packages_dir = path.join("pkg");
+    let entries = fs::read_dir(&packages_dir).map_err(|source| Error::ReadFile {
+        path: packages_dir.clone(),
+        source,
+    })?;
+    let mut packages: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|dir| dir.join(OPTIONS_FILE).is_file())
+        .collect();
+    packages.sort();
+    Ok(packages)
+}
+
+/// Copy the input file and write the options file into a package directory.
+/// The extension of the input file is kept (PDF and AI files are stored as
+/// converted SVG, SVGZ files uncompressed).
+pub fn write(
+    package_dir: &Path,
+    svgfile: &Path,
+    source: &str,
+    args: Vec<String>,
+    uuids: Uuids,
+) -> Result<()> {
+    let extension = svgfile
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .filter(|extension| !["pdf", "ai", "svgz"].contains(&extension.as_str()))
+        .unwrap_or_else(|| "svg".to_string());
+    let provenance = Provenance {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        source: format!("{}.{}", SOURCE_FILE, extension),
+        args,
+        uuids,
+    };
+    let contents = toml::to_string(&provenance).expect("The options can be serialized");
+    for (path, contents) in [
+        (provenance.source_path(package_dir), source),
+        (package_dir.join(OPTIONS_FILE), &contents[..]),
+    ] {
+        fs::write(&path, contents).map_err(|source| Error::WriteFile { path, source })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn test_passed_args() {
+        let args = [
+            "--outpath",
+            "/lib",
+            "--name=Logo",
+            "--align",
+            "center",
+            "--force",
+            "logo.svg",
+            "--pcb-art=true",
+            "--footprint-text={{NAME}}",
+            "--log-file",
+            "run.log",
+            "--verify",
+            "--print=json",
+            "--rules",
+            "/etc/rules.toml",
+        ];
+        assert_eq!(
+            passed_args(
+                args.iter().map(|arg| arg.to_string()),
+                Path::new("logo.svg")
+            ),
+            vec![
+                "--align",
+                "center",
+                "--footprint-text={{NAME}}",
+                "--rules",
+                "/etc/rules.toml"
+            ]
+        );
+
+        // Relative paths are resolved
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(
+            passed_args(
+                ["--config=lib.toml", "--drill-file", "drill.txt"]
+                    .iter()
+                    .map(|arg| arg.to_string()),
+                Path::new("logo.svg")
+            ),
+            vec![
+                format!("--config={}", cwd.join("lib.toml").display()),
+                "--drill-file".to_string(),
+                cwd.join("drill.txt").display().to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_args() {
+        let recorded: Vec<String> = ["--name=Logo", "--align", "center", "--fit-arcs"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let overrides = vec!["--align=top-left".to_string(), "--min-area=0.1".to_string()];
+        assert_eq!(
+            merge_args(&recorded, &overrides),
+            vec![
+                "--name=Logo",
+                "--fit-arcs",
+                "--align=top-left",
+                "--min-area=0.1"
+            ]
+        );
+        assert_eq!(merge_args(&recorded, &[]), recorded);
+    }
+
+    #[test]
+    fn test_write() {
+        let temp = TempDir::new("test").unwrap();
+        let dir = temp.path().to_owned();
+        let uuids = Uuids {
+            package: Some("8d92aac5-2fe0-460c-baad-35e9361d5f79".to_string()),
+            ..Uuids::default()
+        };
+        let args = vec!["--name=Logo".to_string()];
+        write(
+            &dir,
+            Path::new("logo.PDF"),
+            "<svg/>",
+            args.clone(),
+            uuids.clone(),
+        )
+        .unwrap();
+        let provenance = Provenance::load(&dir).unwrap();
+        assert_eq!(provenance.args, args);
+        assert_eq!(provenance.uuids, uuids);
+        assert_eq!(provenance.source, ".svg2librepcb-source.svg");
+        assert_eq!(
+            fs::read_to_string(provenance.source_path(&dir)).unwrap(),
+            "<svg/>"
+        );
+        assert_eq!(find_packages(&dir).unwrap(), vec![dir.clone()]);
+    }
+}
@@ -0,0 +1,74 @@
+//! Fetching input files from `https://` URLs, e.g. logos from a brand asset
+//! server in CI.
+
+use std::io::Read;
+
+/// Maximum size of a downloaded file.
+pub const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Whether the input is a URL instead of a path.
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("https://")
+}
+
+/// File name of the downloaded file: The last path segment of the URL
+/// (without query and fragment), or `download.svg` if it has no extension.
+pub fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or("");
+    let valid = name.contains('.')
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if valid && path.matches('/').count() > 2 {
+        name.to_string()
+    } else {
+        "download.svg".to_string()
+    }
+}
+
+/// Download a file, failing if it is larger than `MAX_SIZE`.
+pub fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => {
+            format!("HTTP status {} {}", code, response.status_text())
+        }
+        ureq::Error::Transport(transport) => match transport.message() {
+            Some(message) => format!("{}: {}", transport.kind(), message),
+            None => transport.kind().to_string(),
+        },
+    })?;
+    let mut data = vec![];
+    response
+        .into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    if data.len() as u64 > MAX_SIZE {
+        return Err(format!(
+            "The file is larger than {} MiB",
+            MAX_SIZE / 1024 / 1024
+        ));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        let cases = [
+            ("https://example.com/brand/logo.svg", "logo.svg"),
+            ("https://example.com/logo.svgz?v=2#top", "logo.svgz"),
+            ("https://example.com/assets/logo", "download.svg"),
+            ("https://example.com", "download.svg"),
+            ("https://example.com/../.hidden", "download.svg"),
+        ];
+        for (url, name) in cases {
+            assert_eq!(file_name(url), name, "{}", url);
+        }
+    }
+}
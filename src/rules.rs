@@ -0,0 +1,392 @@
+//! Fabrication rules checked against the generated footprints (`--rules`).
+//!
+//! The rules are read from a TOML file, with all values in mm. Rules which
+//! are not given are not checked:
+//!
+//! ```toml
+//! min-trace-width = 0.15
+//! min-clearance = 0.15
+//! min-drill = 0.3
+//! min-silk-width = 0.15
+//! ```
+//!
+//! Widths and clearances are measured perpendicular to the outlines of the
+//! polygons (at the vertices and at least every rule distance along the
+//! edges), so that thin necks and narrow gaps are found without rasterizing
+//! the artwork.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    geometry::{self, Hole, Point, Polyline},
+    layers::Layer,
+};
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rules {
+    /// Minimum width of copper features
+    pub min_trace_width: Option<f64>,
+    /// Minimum gap between copper features
+    pub min_clearance: Option<f64>,
+    /// Minimum drill diameter
+    pub min_drill: Option<f64>,
+    /// Minimum width of silkscreen features
+    pub min_silk_width: Option<f64>,
+}
+
+impl Rules {
+    /// Load and validate a rules file.
+    pub fn load(path: &Path) -> Result<Rules> {
+        let contents = fs::read_to_string(path).map_err(|source| Error::ReadFile {
+            path: path.to_owned(),
+            source,
+        })?;
+        let rules: Rules = toml::from_str(&contents).map_err(|e| Error::Config {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })?;
+        let values = [
+            rules.min_trace_width,
+            rules.min_clearance,
+            rules.min_drill,
+            rules.min_silk_width,
+        ];
+        if values
+            .iter()
+            .flatten()
+            .any(|value| *value <= 0.0 || value.is_nan())
+        {
+            return Err(Error::Config {
+                path: path.to_owned(),
+                reason: "The rules must be positive".to_string(),
+            });
+        }
+        Ok(rules)
+    }
+}
+
+/// The worst violation of a rule on a layer.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    /// Layer title, or "Drills"
+    pub layer: &'static str,
+    pub rule: &'static str,
+    /// The measured width, clearance or diameter
+    pub value: f64,
+    pub limit: f64,
+    /// Location in the (scaled) drawing
+    pub position: Point,
+}
+
+/// Check the layers of a footprint and its holes against the rules. Open
+/// polylines are drawn with `line_width`.
+pub fn check(
+    rules: &Rules,
+    layers: &[(Layer, Vec<Polyline>)],
+    holes: &[Hole],
+    line_width: f64,
+) -> Vec<Violation> {
+    let mut violations = vec![];
+    for (layer, polylines) in layers {
+        let (min_width, min_clearance) = match layer {
+            Layer::TopCopper | Layer::BotCopper => (rules.min_trace_width, rules.min_clearance),
+            Layer::TopLegend | Layer::BotLegend => (rules.min_silk_width, None),
+            _ => continue,
+        };
+        let mut report = |rule, limit, worst: Option<(f64, Point)>| {
+            if let Some((value, position)) = worst {
+                violations.push(Violation {
+                    layer: layer.title(),
+                    rule,
+                    value,
+                    limit,
+                    position,
+                });
+            }
+        };
+        if let Some(limit) = min_width {
+            let open = polylines
+                .iter()
+                .find(|polyline| !geometry::is_closed(polyline))
+                .filter(|_| line_width < limit)
+                .map(|polyline| (line_width, polyline[0]));
+            report(
+                "width",
+                limit,
+                open.or_else(|| narrowest(polylines, true, limit)),
+            );
+        }
+        if let Some(limit) = min_clearance {
+            report("clearance", limit, narrowest(polylines, false, limit));
+        }
+    }
+    if let Some(limit) = rules.min_drill {
+        let smallest = holes
+            .iter()
+            .filter(|hole| hole.diameter < limit)
+            .min_by(|a, b| a.diameter.total_cmp(&b.diameter));
+        if let Some(hole) = smallest {
+            violations.push(Violation {
+                layer: "Drills",
+                rule: "drill diameter",
+                value: hole.diameter,
+                limit,
+                position: hole.position,
+            });
+        }
+    }
+    violations
+}
+
+/// An edge of a polygon outline.
+type Edge = (Point, Point);
+
+/// Find the narrowest place below the limit, measuring inside the polygons
+/// (widths) or outside of them (clearances). Returns the distance and the
+/// position where it was measured.
+fn narrowest(polylines: &[Polyline], inside: bool, limit: f64) -> Option<(f64, Point)> {
+    let polygons: Vec<&Polyline> = polylines
+        .iter()
+        .filter(|polyline| polyline.len() >= 4 && geometry::is_closed(polyline))
+        .collect();
+    let edges = outline_edges(&polygons);
+    let grid = Grid::new(&edges, limit);
+    let is_filled = |point: Point| {
+        polygons
+            .iter()
+            .filter(|polygon| geometry::contains(polygon, point))
+            .count()
+            % 2
+            == 1
+    };
+
+    let mut narrowest: Option<(f64, Point)> = None;
+    for &(a, b) in &edges {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx.hypot(dy);
+        // The side of the edge to measure on is determined at its center
+        let mut normal = (dy / length, -dx / length);
+        let probe = Point::new(
+            a.x + dx / 2.0 + normal.0 * limit * 1e-3,
+            a.y + dy / 2.0 + normal.1 * limit * 1e-3,
+        );
+        if is_filled(probe) != inside {
+            normal = (-normal.0, -normal.1);
+        }
+        // Measure perpendicular to the edge, at its start and at least every
+        // limit distance along it
+        let steps = (length / limit).ceil().max(1.0) as usize;
+        for step in 0..steps {
+            let t = step as f64 / steps as f64;
+            let origin = Point::new(a.x + dx * t, a.y + dy * t);
+            let distance = match grid.cast(origin, normal, limit) {
+                Some(distance) => distance,
+                None => continue,
+            };
+            if narrowest.is_none_or(|(min, _)| distance < min) {
+                narrowest = Some((distance, origin));
+            }
+        }
+    }
+    narrowest
+}
+
+/// The edges of the polygons, without zero-width cuts connecting holes to
+/// their outlines (which run back and forth along the same edge).
+fn outline_edges(polygons: &[&Polyline]) -> Vec<Edge> {
+    let key = |a: Point, b: Point| (a.x.to_bits(), a.y.to_bits(), b.x.to_bits(), b.y.to_bits());
+    let edges: Vec<Edge> = polygons
+        .iter()
+        .flat_map(|polygon| polygon.windows(2).map(|pair| (pair[0], pair[1])))
+        .filter(|(a, b)| a != b)
+        .collect();
+    let keys: HashSet<_> = edges.iter().map(|&(a, b)| key(a, b)).collect();
+    edges
+        .into_iter()
+        .filter(|&(a, b)| !keys.contains(&key(b, a)))
+        .collect()
+}
+
+/// Edges sorted into square cells, to only test nearby edges.
+struct Grid<'a> {
+    edges: &'a [Edge],
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl<'a> Grid<'a> {
+    fn new(edges: &'a [Edge], cell_size: f64) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, &(a, b)) in edges.iter().enumerate() {
+            for cell in Self::crossed_cells(a, b, cell_size) {
+                cells.entry(cell).or_default().push(i);
+            }
+        }
+        Grid {
+            edges,
+            cell_size,
+            cells,
+        }
+    }
+
+    fn cell(x: f64, y: f64, cell_size: f64) -> (i64, i64) {
+        (
+            (x / cell_size).floor() as i64,
+            (y / cell_size).floor() as i64,
+        )
+    }
+
+    /// The cells crossed by an edge, walking along it from cell to cell
+    /// (instead of all cells of its bounding box, which are many for long
+    /// diagonal edges).
+    fn crossed_cells(a: Point, b: Point, cell_size: f64) -> Vec<(i64, i64)> {
+        let (mut x, mut y) = Self::cell(a.x, a.y, cell_size);
+        let end = Self::cell(b.x, b.y, cell_size);
+        // Position along the edge (0 to 1) where the next cell boundary in
+        // each direction is crossed, and the distance between the boundaries
+        let boundary = |cell: i64, start: f64, delta: f64| {
+            if delta == 0.0 {
+                return (f64::INFINITY, 0.0);
+            }
+            let next = if delta > 0.0 { cell + 1 } else { cell };
+            let t = (next as f64 * cell_size - start) / delta;
+            (t, cell_size / delta.abs())
+        };
+        let (mut t_x, step_x) = boundary(x, a.x, b.x - a.x);
+        let (mut t_y, step_y) = boundary(y, a.y, b.y - a.y);
+        // The number of steps is known, which keeps rounding errors from
+        // walking past the end
+        let (mut left_x, mut left_y) = ((end.0 - x).abs(), (end.1 - y).abs());
+        let mut cells = Vec::with_capacity((left_x + left_y + 1) as usize);
+        cells.push((x, y));
+        while left_x + left_y > 0 {
+            if left_y == 0 || (left_x > 0 && t_x < t_y) {
+                x += (end.0 - x).signum();
+                t_x += step_x;
+                left_x -= 1;
+            } else {
+                y += (end.1 - y).signum();
+                t_y += step_y;
+                left_y -= 1;
+            }
+            cells.push((x, y));
+        }
+        cells
+    }
+
+    /// Distance from the origin to the first edge hit by a ray, if it is
+    /// shorter than the maximum distance. Edges through the origin are
+    /// ignored.
+    fn cast(&self, origin: Point, direction: (f64, f64), max_distance: f64) -> Option<f64> {
+        let end = Point::new(
+            origin.x + direction.0 * max_distance,
+            origin.y + direction.1 * max_distance,
+        );
+        let (x_min, y_min) = Self::cell(origin.x.min(end.x), origin.y.min(end.y), self.cell_size);
+        let (x_max, y_max) = Self::cell(origin.x.max(end.x), origin.y.max(end.y), self.cell_size);
+        let mut nearest: Option<f64> = None;
+        for x in x_min..=x_max {
+            for y in y_min..=y_max {
+                for &i in self.cells.get(&(x, y)).into_iter().flatten() {
+                    let (a, b) = self.edges[i];
+                    let hit = match geometry::ray_intersection(origin, direction, a, b) {
+                        Some(t) if t > max_distance * 1e-6 && t < max_distance => t,
+                        _ => continue,
+                    };
+                    if nearest.is_none_or(|nearest| hit < nearest) {
+                        nearest = Some(hit);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Polyline {
+        vec![
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x + width, y + height),
+            Point::new(x, y + height),
+            Point::new(x, y),
+        ]
+    }
+
+    #[test]
+    fn test_check() {
+        let rules = Rules {
+            min_trace_width: Some(0.2),
+            min_clearance: Some(0.2),
+            min_drill: Some(0.3),
+            min_silk_width: Some(0.08),
+        };
+        // A thin trace, 0.15 mm away from a pad
+        let copper = vec![rect(0.0, 0.0, 5.0, 0.1), rect(0.0, 0.25, 5.0, 2.0)];
+        let silkscreen = vec![rect(0.0, 0.0, 5.0, 0.1)];
+        let holes = [Hole {
+            position: Point::new(1.0, 1.0),
+            diameter: 0.2,
+        }];
+        let layers = vec![(Layer::TopCopper, copper), (Layer::TopLegend, silkscreen)];
+        let violations = check(&rules, &layers, &holes, 0.2);
+        let summary: Vec<_> = violations
+            .iter()
+            .map(|v| (v.layer, v.rule, (v.value * 1000.0).round() / 1000.0))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Top Copper", "width", 0.1),
+                ("Top Copper", "clearance", 0.15),
+                ("Drills", "drill diameter", 0.2),
+            ]
+        );
+
+        let relaxed = Rules {
+            min_trace_width: Some(0.05),
+            min_clearance: Some(0.1),
+            ..Rules::default()
+        };
+        assert_eq!(check(&relaxed, &layers, &holes, 0.2), vec![]);
+    }
+
+    #[test]
+    fn test_hole_cut() {
+        // A ring (0.5 mm wide) with the hole connected by a zero-width cut
+        let outline = rect(0.0, 0.0, 3.0, 3.0);
+        let hole = rect(0.5, 0.5, 2.0, 2.0);
+        let mut ring = outline[..1].to_vec();
+        ring.extend(hole.iter().rev());
+        ring.extend_from_slice(&outline);
+        assert_eq!(narrowest(&[ring.clone()], true, 0.4), None);
+        let (width, _) = narrowest(&[ring], true, 1.0).unwrap();
+        assert!((width - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_crossed_cells() {
+        let cells = Grid::crossed_cells(Point::new(0.5, 0.5), Point::new(2.5, 1.5), 1.0);
+        assert_eq!(cells, vec![(0, 0), (1, 0), (1, 1), (2, 1)]);
+        let cells = Grid::crossed_cells(Point::new(2.5, 1.5), Point::new(0.5, 0.5), 1.0);
+        assert_eq!(cells, vec![(2, 1), (1, 1), (1, 0), (0, 0)]);
+
+        // Long diagonal edges only cross a few of the cells of their
+        // bounding box
+        let cells = Grid::crossed_cells(Point::new(0.5, 0.5), Point::new(100.5, 100.7), 1.0);
+        assert_eq!(cells.len(), 201);
+        assert_eq!(cells[200], (100, 100));
+    }
+}
@@ -0,0 +1,88 @@
+//! Settings remembered between invocations.
+//!
+//! The output path and author of the last conversion are saved in the user
+//! configuration directory (`$XDG_CONFIG_HOME/svg2librepcb/settings.toml`,
+//! `~/.config/svg2librepcb/settings.toml` or
+//! `%APPDATA%\svg2librepcb\settings.toml`), so that they can be omitted next
+//! time, e.g. in the Inkscape extension.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// The last used output path
+    pub outpath: Option<PathBuf>,
+    /// The last used author
+    pub author: Option<String>,
+}
+
+impl Settings {
+    /// Location of the settings file, if the configuration directory is
+    /// known.
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("svg2librepcb").join("settings.toml"))
+    }
+
+    /// Load the settings, which are empty if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Settings> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(source) => {
+                return Err(Error::ReadFile {
+                    path: path.to_owned(),
+                    source,
+                })
+            }
+        };
+        toml::from_str(&contents).map_err(|e| Error::Settings {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Save the settings, creating the configuration directory if needed.
+    pub fn save(&self, path: &Path) -> std::result::Result<(), String> {
+        let contents = toml::to_string(self).map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn test_load_save() {
+        let dir = TempDir::new("test").unwrap();
+        let path = dir.path().join("svg2librepcb").join("settings.toml");
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+
+        let settings = Settings {
+            outpath: Some(PathBuf::from("/libraries/local/Logos.lplib")),
+            author: Some("Danilo".to_string()),
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path).unwrap(), settings);
+
+        fs::write(&path, "outpath = 1\n").unwrap();
+        assert!(Settings::load(&path).is_err());
+    }
+}
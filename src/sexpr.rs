@@ -0,0 +1,236 @@
+//! Minimal parser for the S-expression files written by LibrePCB.
+//!
+//! The parser only supports what is needed to inspect existing elements. Every
+//! list keeps a reference to its source text, so that nodes can be copied into
+//! regenerated files verbatim.
+
+/// A node in an S-expression.
+#[derive(Debug, PartialEq)]
+pub enum Node<'a> {
+    List(List<'a>),
+    /// An unquoted token, e.g. a UUID, number or keyword
+    Token(&'a str),
+    /// A quoted (and unescaped) string
+    Str(String),
+}
+
+/// A parenthesized list.
+#[derive(Debug, PartialEq)]
+pub struct List<'a> {
+    pub children: Vec<Node<'a>>,
+    /// Source text of the list, including the parentheses
+    pub source: &'a str,
+    /// Column of the opening parenthesis in the source file
+    pub indent: usize,
+}
+
+impl<'a> List<'a> {
+    /// Name of the list, i.e. the leading token.
+    pub fn name(&self) -> Option<&'a str> {
+        match self.children.first() {
+            Some(Node::Token(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Value of the n-th argument after the name, if it is a token or string.
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        match self.children.get(index + 1) {
+            Some(Node::Token(value)) => Some(value),
+            Some(Node::Str(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all child lists.
+    pub fn lists(&self) -> impl Iterator<Item = &List<'a>> {
+        self.children.iter().filter_map(|node| match node {
+            Node::List(list) => Some(list),
+            _ => None,
+        })
+    }
+
+    /// Return the first child list with the given name.
+    pub fn child(&self, name: &str) -> Option<&List<'a>> {
+        self.lists().find(|list| list.name() == Some(name))
+    }
+
+    /// Return the source of this list as lines, re-indented for the given
+    /// nesting depth.
+    pub fn to_lines(&self, depth: usize) -> Vec<String> {
+        let prefix = " ".repeat(depth);
+        self.source
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                if i == 0 {
+                    format!("{}{}", prefix, line)
+                } else {
+                    let leading = line.len() - line.trim_start_matches(' ').len();
+                    format!("{}{}", prefix, &line[leading.min(self.indent)..])
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parse a document consisting of a single root list.
+pub fn parse(input: &str) -> Result<List<'_>, String> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_whitespace();
+    let root = match parser.parse_node()? {
+        Node::List(list) => list,
+        _ => return Err("Expected a list at the top level".into()),
+    };
+    parser.skip_whitespace();
+    if parser.pos != input.len() {
+        return Err(format!("Unexpected content at offset {}", parser.pos));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn parse_node(&mut self) -> Result<Node<'a>, String> {
+        match self.peek() {
+            Some('(') => self.parse_list().map(Node::List),
+            Some('"') => self.parse_string().map(Node::Str),
+            Some(')') => Err(format!("Unexpected ')' at offset {}", self.pos)),
+            Some(_) => Ok(Node::Token(self.parse_token())),
+            None => Err("Unexpected end of input".into()),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<List<'a>, String> {
+        let start = self.pos;
+        let line_start = self.input[..start].rfind('\n').map_or(0, |i| i + 1);
+        let indent = self.input[line_start..start].chars().count();
+        self.pos += 1; // '('
+        let mut children = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(List {
+                        children,
+                        source: &self.input[start..self.pos],
+                        indent,
+                    });
+                }
+                None => return Err(format!("Unclosed list at offset {}", start)),
+                _ => children.push(self.parse_node()?),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        self.pos += 1; // '"'
+        let mut value = String::new();
+        let mut chars = self.input[self.pos..].chars();
+        while let Some(c) = chars.next() {
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escaped = chars
+                        .next()
+                        .ok_or_else(|| format!("Unclosed string at offset {}", start))?;
+                    self.pos += escaped.len_utf8();
+                    value.push(match escaped {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'v' => '\x0B',
+                        'f' => '\x0C',
+                        other => other,
+                    });
+                }
+                c => value.push(c),
+            }
+        }
+        Err(format!("Unclosed string at offset {}", start))
+    }
+
+    fn parse_token(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        &self.input[start..self.pos]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: &str = r#"(librepcb_package 8d92aac5-2fe0-460c-baad-35e9361d5f79
+ (name "My \"Logo\"")
+ (pad 0a1b2c3d-0000-4000-8000-000000000000 (name "1"))
+ (footprint 334c948e-c55d-4440-a47e-22a625289b02
+  (name "Top Copper")
+  (polygon 0cd1ed31-59d5-47ff-b2d4-381311927aae (layer top_cu)
+   (width 0.0) (fill true) (grab_area true)
+  )
+ )
+)
+"#;
+
+    #[test]
+    fn test_parse() {
+        let root = parse(PACKAGE).unwrap();
+        assert_eq!(root.name(), Some("librepcb_package"));
+        assert_eq!(root.arg(0), Some("8d92aac5-2fe0-460c-baad-35e9361d5f79"));
+        assert_eq!(root.child("name").unwrap().arg(0), Some(r#"My "Logo""#));
+        let footprint = root.child("footprint").unwrap();
+        assert_eq!(footprint.child("name").unwrap().arg(0), Some("Top Copper"));
+        let polygon = footprint.child("polygon").unwrap();
+        assert_eq!(polygon.child("layer").unwrap().arg(0), Some("top_cu"));
+        assert_eq!(polygon.lists().count(), 4);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("(a (b)").is_err());
+        assert!(parse("(a) (b)").is_err());
+        assert!(parse(r#"(a "b)"#).is_err());
+        assert!(parse("a").is_err());
+    }
+
+    #[test]
+    fn test_to_lines() {
+        let root = parse(PACKAGE).unwrap();
+        let footprint = root.child("footprint").unwrap();
+        let polygon = footprint.child("polygon").unwrap();
+        assert_eq!(
+            polygon.to_lines(1),
+            vec![
+                " (polygon 0cd1ed31-59d5-47ff-b2d4-381311927aae (layer top_cu)",
+                "  (width 0.0) (fill true) (grab_area true)",
+                " )",
+            ]
+        );
+    }
+}
@@ -0,0 +1,2071 @@
+//! Inspection of the SVG document itself.
+//!
+//! The paths are flattened by svg2polylines, which ignores everything else
+//! in the document. Additional information (e.g. the viewBox) is read here.
+
+use std::ops::Range;
+
+use roxmltree::{Document, Node};
+use svgtypes::{PathParser, PathSegment};
+
+use crate::{
+    css::{self, StyleSheet},
+    geometry::{self, arc_steps, LineCap, LineJoin, Point, Polyline, Rect, Stroke},
+    log::log,
+};
+
+/// Cap height of a font relative to its font size, used to convert the font
+/// size to the height of LibrePCB texts.
+const CAP_HEIGHT: f64 = 0.7;
+
+/// A text element of the document.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Text {
+    /// Position of the top of the first line (at the text anchor)
+    pub position: Point,
+    /// Cap height
+    pub height: f64,
+    /// Rotation in degrees, counterclockwise as displayed
+    pub rotation: f64,
+    /// Horizontal alignment (`left`, `center` or `right`)
+    pub align: &'static str,
+    /// Text lines, separated by newlines
+    pub content: String,
+}
+
+/// An affine transformation `[a b c d e f]` as used by the SVG `transform`
+/// attribute.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Transform([f64; 6]);
+
+impl Transform {
+    const IDENTITY: Transform = Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    /// Parse a `transform` attribute.
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid transform {:?}", value);
+        let mut transform = Transform::IDENTITY;
+        for part in value.split(')') {
+            let part = part.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+            if part.is_empty() {
+                continue;
+            }
+            let (name, args) = part.split_once('(').ok_or_else(invalid)?;
+            let args = args
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|arg| !arg.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| invalid())?;
+            let next = match (name.trim(), &args[..]) {
+                ("matrix", &[a, b, c, d, e, f]) => Transform([a, b, c, d, e, f]),
+                ("translate", &[x]) => Transform([1.0, 0.0, 0.0, 1.0, x, 0.0]),
+                ("translate", &[x, y]) => Transform([1.0, 0.0, 0.0, 1.0, x, y]),
+                ("scale", &[s]) => Transform([s, 0.0, 0.0, s, 0.0, 0.0]),
+                ("scale", &[x, y]) => Transform([x, 0.0, 0.0, y, 0.0, 0.0]),
+                ("rotate", &[angle]) => Transform::rotation(angle),
+                ("rotate", &[angle, x, y]) => Transform([1.0, 0.0, 0.0, 1.0, x, y])
+                    .then(Transform::rotation(angle))
+                    .then(Transform([1.0, 0.0, 0.0, 1.0, -x, -y])),
+                ("skewX", &[angle]) => {
+                    Transform([1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0])
+                }
+                ("skewY", &[angle]) => {
+                    Transform([1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0])
+                }
+                _ => return Err(invalid()),
+            };
+            transform = transform.then(next);
+        }
+        Ok(transform)
+    }
+
+    fn rotation(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Transform([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Apply `other` within the coordinate system of this transformation.
+    fn then(self, other: Transform) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [oa, ob, oc, od, oe, of] = other.0;
+        Transform([
+            a * oa + c * ob,
+            b * oa + d * ob,
+            a * oc + c * od,
+            b * oc + d * od,
+            a * oe + c * of + e,
+            b * oe + d * of + f,
+        ])
+    }
+
+    fn apply(self, p: Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+        Point::new(a * p.x + c * p.y + e, b * p.x + d * p.y + f)
+    }
+
+    /// The inverse transformation, unless it is degenerate.
+    fn inverse(self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.0;
+        let determinant = a * d - b * c;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let (a, b, c, d) = (
+            d / determinant,
+            -b / determinant,
+            -c / determinant,
+            a / determinant,
+        );
+        Some(Transform([a, b, c, d, -(a * e + c * f), -(b * e + d * f)]))
+    }
+
+    /// The factor by which lengths are scaled (the geometric mean for
+    /// non-uniform scaling).
+    fn scale(self) -> f64 {
+        let [a, b, c, d, _, _] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+}
+
+/// The transformation of an element, accumulated from its own and the
+/// `transform` attributes of all ancestors.
+fn accumulated_transform(node: Node) -> Result<Transform, String> {
+    let mut transform = Transform::IDENTITY;
+    for ancestor in node
+        .ancestors()
+        .filter(Node::is_element)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+    {
+        if let Some(value) = ancestor.attribute("transform") {
+            transform = transform.then(Transform::parse(value)?);
+        }
+    }
+    Ok(transform)
+}
+
+/// Parse the `viewBox` of the root element, if present.
+pub fn view_box(svg: &str) -> Result<Option<Rect>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let value = match document.root_element().attribute("viewBox") {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let invalid = || format!("Invalid viewBox {:?}", value);
+    let numbers = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| invalid())?;
+    match numbers[..] {
+        [x, y, width, height] if width > 0.0 && height > 0.0 => Ok(Some(Rect {
+            x_min: x,
+            y_min: y,
+            x_max: x + width,
+            y_max: y + height,
+        })),
+        _ => Err(invalid()),
+    }
+}
+
+/// Number of elements of the document and length of the longest path data
+/// (`d` attribute).
+pub fn size(svg: &str) -> Result<(usize, usize), String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let elements = document.descendants().filter(Node::is_element).count();
+    let longest_path = document
+        .descendants()
+        .filter_map(|node| node.attribute("d"))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    Ok((elements, longest_path))
+}
+
+/// Estimated number of vertices of the shapes after flattening, to fail on
+/// overly detailed documents before flattening them. Curves are estimated
+/// from their curvature and the tolerance (or counted as a single segment
+/// without one, if they are flattened later), arcs, circles and ellipses
+/// with the ellipse tolerance.
+pub fn estimate_vertices(
+    svg: &str,
+    tolerance: Option<f64>,
+    ellipse_tolerance: f64,
+) -> Result<usize, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let number = |node: Node, name: &str| -> f64 {
+        let value = node.attribute(name).unwrap_or("0").trim();
+        value
+            .strip_suffix("px")
+            .unwrap_or(value)
+            .parse()
+            .unwrap_or(0.0)
+    };
+    let mut vertices: usize = 0;
+    for node in document.descendants().filter(|node| is_shape(*node)) {
+        let count = match node.tag_name().name() {
+            "path" => node
+                .attribute("d")
+                .map_or(0, |data| path_vertices(data, tolerance, ellipse_tolerance)),
+            "circle" | "ellipse" => {
+                let radius = number(node, "r")
+                    .max(number(node, "rx"))
+                    .max(number(node, "ry"));
+                arc_steps(std::f64::consts::TAU, radius, ellipse_tolerance) + 1
+            }
+            "polyline" | "polygon" => node.attribute("points").map_or(0, |points| {
+                points
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|value| !value.is_empty())
+                    .count()
+                    / 2
+                    + 1
+            }),
+            _ => 5,
+        };
+        vertices = vertices.saturating_add(count);
+    }
+    Ok(vertices)
+}
+
+/// Estimated number of vertices of path data after flattening (see
+/// `estimate_vertices`). Invalid data is counted up to the error.
+fn path_vertices(data: &str, tolerance: Option<f64>, ellipse_tolerance: f64) -> usize {
+    // Segments of a curve with the given maximum second difference of its
+    // control points (scaled by the degree), so that the distance between
+    // the segments and the curve is at most the tolerance
+    let curve_steps = |second_difference: f64| match tolerance {
+        Some(tolerance) => (second_difference / (4.0 * tolerance))
+            .sqrt()
+            .ceil()
+            .max(1.0) as usize,
+        None => 1,
+    };
+    let difference = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+        (a.0 - 2.0 * b.0 + c.0).hypot(a.1 - 2.0 * b.1 + c.1)
+    };
+    let mut vertices: usize = 0;
+    // Current point, start of the subpath and last control point (for the
+    // smooth curves)
+    let (mut current, mut start, mut control) = ((0.0, 0.0), (0.0, 0.0), None);
+    for segment in PathParser::from(data).map_while(|segment| segment.ok()) {
+        let point = |abs: bool, x: f64, y: f64| match abs {
+            true => (x, y),
+            false => (current.0 + x, current.1 + y),
+        };
+        let reflected = |control: Option<(f64, f64)>| match control {
+            Some((x, y)) => (2.0 * current.0 - x, 2.0 * current.1 - y),
+            None => current,
+        };
+        let (end, steps, next_control) = match segment {
+            PathSegment::MoveTo { abs, x, y } => {
+                start = point(abs, x, y);
+                (start, 1, None)
+            }
+            PathSegment::LineTo { abs, x, y } => (point(abs, x, y), 1, None),
+            PathSegment::HorizontalLineTo { abs, x } => {
+                ((point(abs, x, 0.0).0, current.1), 1, None)
+            }
+            PathSegment::VerticalLineTo { abs, y } => ((current.0, point(abs, 0.0, y).1), 1, None),
+            PathSegment::ClosePath { .. } => (start, 1, None),
+            PathSegment::CurveTo {
+                abs,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (first, second, end) =
+                    (point(abs, x1, y1), point(abs, x2, y2), point(abs, x, y));
+                let second_difference =
+                    difference(current, first, second).max(difference(first, second, end));
+                (end, curve_steps(3.0 * second_difference), Some(second))
+            }
+            PathSegment::SmoothCurveTo { abs, x2, y2, x, y } => {
+                let (first, second, end) =
+                    (reflected(control), point(abs, x2, y2), point(abs, x, y));
+                let second_difference =
+                    difference(current, first, second).max(difference(first, second, end));
+                (end, curve_steps(3.0 * second_difference), Some(second))
+            }
+            PathSegment::Quadratic { abs, x1, y1, x, y } => {
+                let (first, end) = (point(abs, x1, y1), point(abs, x, y));
+                (
+                    end,
+                    curve_steps(difference(current, first, end)),
+                    Some(first),
+                )
+            }
+            PathSegment::SmoothQuadratic { abs, x, y } => {
+                let (first, end) = (reflected(control), point(abs, x, y));
+                (
+                    end,
+                    curve_steps(difference(current, first, end)),
+                    Some(first),
+                )
+            }
+            PathSegment::EllipticalArc {
+                abs,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                let end = point(abs, x, y);
+                let arc = Arc {
+                    radii: (rx.abs(), ry.abs()),
+                    rotation: x_axis_rotation.to_radians(),
+                    large_arc,
+                    sweep,
+                };
+                let steps = arc.flatten(current, end, ellipse_tolerance).len();
+                (end, steps, None)
+            }
+        };
+        vertices = vertices.saturating_add(steps);
+        current = end;
+        control = next_control;
+    }
+    vertices
+}
+
+/// Byte ranges of the text elements, which are not converted.
+pub fn text_ranges(svg: &str) -> Vec<Range<usize>> {
+    match Document::parse(svg) {
+        Ok(document) => document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "text")
+            .map(|node| node.range())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Value of a presentation attribute or style property, inherited from the
+/// ancestors.
+fn property<'a>(styles: &'a StyleSheet, node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.ancestors()
+        .filter(Node::is_element)
+        .find_map(|node| own_property(styles, node, name))
+}
+
+/// Value of a style property (from the `style` attribute or the style
+/// sheet) or presentation attribute of the element itself, in the order of
+/// the CSS cascade.
+fn own_property<'a>(styles: &'a StyleSheet, node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = node.attribute("style").and_then(|style| {
+        css::declarations(style)
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+            .last()
+    });
+    from_style
+        .or_else(|| styles.get(node, name))
+        .or_else(|| node.attribute(name))
+}
+
+/// Parse a length, ignoring the unit (i.e. assuming user units).
+fn length(value: &str) -> Option<f64> {
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// Read the text elements of the document.
+///
+/// Lines are formed by `tspan` elements with their own `y` coordinate, like
+/// the multi-line texts created by Inkscape.
+pub fn texts(svg: &str) -> Result<Vec<Text>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let styles = StyleSheet::new(&document);
+    let mut texts = vec![];
+    for node in document.descendants() {
+        if node.tag_name().name() != "text" {
+            continue;
+        }
+        let mut lines: Vec<String> = vec![];
+        for child in node.descendants().skip(1) {
+            if child.is_element() && child.attribute("y").is_some() {
+                lines.push(String::new());
+            }
+            // Whitespace between the tspan elements is only formatting
+            match child.text().filter(|_| child.is_text()) {
+                Some(text) if !text.trim().is_empty() => {
+                    if lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.last_mut().unwrap().push_str(text);
+                }
+                _ => {}
+            }
+        }
+        if lines.iter().all(|line| line.trim().is_empty()) {
+            continue;
+        }
+
+        // The first line (possibly a tspan) defines position and size
+        let first = node
+            .descendants()
+            .skip(1)
+            .find(|n| n.is_element() && n.attribute("y").is_some())
+            .unwrap_or(node);
+        let coordinate = |name| {
+            first
+                .attribute(name)
+                .and_then(|value| length(value.split_whitespace().next().unwrap_or("")))
+                .unwrap_or(0.0)
+        };
+        let font_size = property(&styles, first, "font-size")
+            .and_then(length)
+            .unwrap_or(16.0);
+        let align = match property(&styles, first, "text-anchor") {
+            Some("middle") => "center",
+            Some("end") => "right",
+            _ => "left",
+        };
+
+        let transform = accumulated_transform(node)?;
+        let [a, b, _, _, _, _] = transform.0;
+        let scale = transform.scale();
+        let height = font_size * CAP_HEIGHT;
+        let baseline = Point::new(coordinate("x"), coordinate("y") - height);
+        texts.push(Text {
+            position: transform.apply(baseline),
+            height: height * scale,
+            rotation: (-b.atan2(a).to_degrees()).rem_euclid(360.0),
+            align,
+            content: lines
+                .iter()
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        });
+    }
+    Ok(texts)
+}
+
+/// Check that the document is well-formed XML, returning the byte offset and
+/// description of the first error otherwise.
+pub fn check_xml(svg: &str) -> Result<(), (usize, String)> {
+    let error = match Document::parse(svg) {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let pos = error.pos();
+    let line_start: usize = svg
+        .split_inclusive('\n')
+        .take((pos.row as usize).saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let column: usize = svg[line_start..]
+        .chars()
+        .take((pos.col as usize).saturating_sub(1))
+        .map(char::len_utf8)
+        .sum();
+    Err((line_start + column, error.to_string()))
+}
+
+/// Remove all elements from the document, except for the elements with the
+/// given ids (including their children and ancestors).
+pub fn select_ids(svg: &str, ids: &[String]) -> Result<String, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let is_selected =
+        |node: &Node| matches!(node.attribute("id"), Some(id) if ids.iter().any(|i| i == id));
+    if let Some(id) = ids.iter().find(|id| {
+        !document
+            .descendants()
+            .any(|node| node.attribute("id") == Some(id))
+    }) {
+        return Err(format!("No element with id {:?}", id));
+    }
+
+    fn collect(node: Node, is_selected: &dyn Fn(&Node) -> bool, removed: &mut Vec<Range<usize>>) {
+        for child in node.children().filter(Node::is_element) {
+            // The style sheets are kept for the selected elements
+            if is_selected(&child) || child.tag_name().name() == "style" {
+                continue;
+            }
+            if child.descendants().any(|n| is_selected(&n)) {
+                collect(child, is_selected, removed);
+            } else {
+                removed.push(child.range());
+            }
+        }
+    }
+    let mut removed = vec![];
+    collect(document.root_element(), &is_selected, &mut removed);
+
+    let mut selected = String::with_capacity(svg.len());
+    let mut pos = 0;
+    for range in removed {
+        selected.push_str(&svg[pos..range.start]);
+        pos = range.end;
+    }
+    selected.push_str(&svg[pos..]);
+    Ok(selected)
+}
+
+/// Replace `<circle>` and `<ellipse>` elements with paths, and flatten them
+/// as well as the elliptical arcs of all paths with the given chord error.
+///
+/// Arcs are flattened by their angle instead of as Bézier curves, which
+/// keeps the segments evenly spaced around ellipses. Elements with
+/// unsupported coordinates (e.g. percentages) and paths with invalid data are
+/// left unchanged.
+pub fn flatten_ellipses(svg: &str, tolerance: f64) -> Result<String, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let number = |node: Node, name: &str| -> Option<f64> {
+        let value = node.attribute(name)?.trim();
+        value
+            .strip_suffix("px")
+            .unwrap_or(value)
+            .trim()
+            .parse()
+            .ok()
+    };
+    let mut replacements: Vec<(Range<usize>, String)> = vec![];
+    for node in document.descendants() {
+        match node.tag_name().name() {
+            "path" => {
+                let attribute = match node.attribute_node("d") {
+                    Some(attribute) => attribute,
+                    None => continue,
+                };
+                if let Some(data) = flatten_arcs(attribute.value(), tolerance) {
+                    replacements.push((attribute.range_value(), data));
+                }
+            }
+            name @ ("circle" | "ellipse") => {
+                let (rx, ry) = if name == "circle" {
+                    let r = number(node, "r");
+                    (r, r)
+                } else {
+                    // A missing radius is the same as the other one ("auto")
+                    let (rx, ry) = (number(node, "rx"), number(node, "ry"));
+                    (rx.or(ry), ry.or(rx))
+                };
+                // A missing center coordinate is 0, an unsupported one
+                // (e.g. a percentage) leaves the element unchanged
+                let coordinate = |name| match node.attribute(name) {
+                    Some(_) => number(node, name),
+                    None => Some(0.0),
+                };
+                let (cx, cy) = match (coordinate("cx"), coordinate("cy")) {
+                    (Some(cx), Some(cy)) => (cx, cy),
+                    _ => continue,
+                };
+                let (rx, ry) = match (rx, ry) {
+                    (Some(rx), Some(ry)) if rx > 0.0 && ry > 0.0 => (rx, ry),
+                    // Not rendered
+                    _ => continue,
+                };
+                // Rename the element and add the outline, the other
+                // attributes (e.g. the style and transform) are kept
+                let range = node.range();
+                let source = &svg[range.clone()];
+                let tag_end = source[1..]
+                    .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .map_or(source.len(), |end| end + 1);
+                let tag = &source[1..tag_end];
+                let prefix = tag.strip_suffix(name).unwrap_or("");
+                let steps = arc_steps(std::f64::consts::TAU, rx.max(ry), tolerance);
+                let mut data = format!("M {},{}", cx + rx, cy);
+                for step in 1..steps {
+                    let angle = std::f64::consts::TAU * step as f64 / steps as f64;
+                    data += &format!(" L {},{}", cx + rx * angle.cos(), cy + ry * angle.sin());
+                }
+                data += " Z";
+                let mut path = format!(r#"<{}path d="{}"{}"#, prefix, data, &source[tag_end..]);
+                if let Some(start) = path.rfind(&format!("</{}>", tag)) {
+                    path.replace_range(start.., &format!("</{}path>", prefix));
+                }
+                replacements.push((range, path));
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = String::with_capacity(svg.len());
+    let mut pos = 0;
+    for (range, replacement) in replacements {
+        result.push_str(&svg[pos..range.start]);
+        result.push_str(&replacement);
+        pos = range.end;
+    }
+    result.push_str(&svg[pos..]);
+    Ok(result)
+}
+
+/// Replace the elliptical arcs of path data with line segments, or return
+/// `None` if there are no arcs (or the data is invalid).
+fn flatten_arcs(data: &str, tolerance: f64) -> Option<String> {
+    let segments: Vec<PathSegment> = PathParser::from(data).collect::<Result<_, _>>().ok()?;
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, PathSegment::EllipticalArc { .. }))
+    {
+        return None;
+    }
+    let mut result = String::with_capacity(data.len() * 2);
+    // Current point and start of the subpath, for the relative coordinates
+    let (mut current, mut start) = ((0.0, 0.0), (0.0, 0.0));
+    for segment in segments {
+        let point = |abs: bool, x: f64, y: f64| match abs {
+            true => (x, y),
+            false => (current.0 + x, current.1 + y),
+        };
+        let (command, abs, values, end) = match segment {
+            PathSegment::MoveTo { abs, x, y } => ('M', abs, vec![x, y], point(abs, x, y)),
+            PathSegment::LineTo { abs, x, y } => ('L', abs, vec![x, y], point(abs, x, y)),
+            PathSegment::HorizontalLineTo { abs, x } => {
+                ('H', abs, vec![x], (point(abs, x, 0.0).0, current.1))
+            }
+            PathSegment::VerticalLineTo { abs, y } => {
+                ('V', abs, vec![y], (current.0, point(abs, 0.0, y).1))
+            }
+            PathSegment::CurveTo {
+                abs,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => ('C', abs, vec![x1, y1, x2, y2, x, y], point(abs, x, y)),
+            PathSegment::SmoothCurveTo { abs, x2, y2, x, y } => {
+                ('S', abs, vec![x2, y2, x, y], point(abs, x, y))
+            }
+            PathSegment::Quadratic { abs, x1, y1, x, y } => {
+                ('Q', abs, vec![x1, y1, x, y], point(abs, x, y))
+            }
+            PathSegment::SmoothQuadratic { abs, x, y } => ('T', abs, vec![x, y], point(abs, x, y)),
+            PathSegment::ClosePath { abs } => ('Z', abs, vec![], start),
+            PathSegment::EllipticalArc {
+                abs,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                let end = point(abs, x, y);
+                let arc = Arc {
+                    radii: (rx.abs(), ry.abs()),
+                    rotation: x_axis_rotation.to_radians(),
+                    large_arc,
+                    sweep,
+                };
+                for (x, y) in arc.flatten(current, end, tolerance) {
+                    result += &format!(" L {},{}", x, y);
+                }
+                current = end;
+                continue;
+            }
+        };
+        result.push(' ');
+        result.push(match abs {
+            true => command,
+            false => command.to_ascii_lowercase(),
+        });
+        if !values.is_empty() {
+            let values: Vec<String> = values.iter().map(f64::to_string).collect();
+            result.push(' ');
+            result += &values.join(",");
+        }
+        current = end;
+        if command == 'M' {
+            start = current;
+        }
+    }
+    Some(result.trim_start().to_string())
+}
+
+/// An elliptical arc of a path, without its start and end point.
+struct Arc {
+    radii: (f64, f64),
+    /// Rotation of the x-axis of the ellipse in radians
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+}
+
+impl Arc {
+    /// Points along the arc from `from` to `to` (excluding the start point),
+    /// following the implementation notes of the SVG specification.
+    fn flatten(&self, from: (f64, f64), to: (f64, f64), tolerance: f64) -> Vec<(f64, f64)> {
+        let (mut rx, mut ry) = self.radii;
+        if from == to {
+            return vec![];
+        }
+        if rx == 0.0 || ry == 0.0 {
+            return vec![to];
+        }
+        let (sin, cos) = self.rotation.sin_cos();
+        let (dx, dy) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+        let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+        // Radii which are too small are scaled up to reach the end point
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let sign = if self.large_arc == self.sweep {
+            -1.0
+        } else {
+            1.0
+        };
+        let factor = sign * (numerator / denominator).max(0.0).sqrt();
+        let (cx1, cy1) = (factor * rx * y1 / ry, -factor * ry * x1 / rx);
+        let center = (
+            cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.0,
+            sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.0,
+        );
+        let angle = |x: f64, y: f64| y.atan2(x);
+        let start = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut sweep = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - start;
+        if self.sweep && sweep < 0.0 {
+            sweep += std::f64::consts::TAU;
+        } else if !self.sweep && sweep > 0.0 {
+            sweep -= std::f64::consts::TAU;
+        }
+        let steps = arc_steps(sweep, rx.max(ry), tolerance);
+        let mut points: Vec<(f64, f64)> = (1..steps)
+            .map(|step| {
+                let (sin_t, cos_t) = (start + sweep * step as f64 / steps as f64).sin_cos();
+                (
+                    center.0 + rx * cos_t * cos - ry * sin_t * sin,
+                    center.1 + rx * cos_t * sin + ry * sin_t * cos,
+                )
+            })
+            .collect();
+        points.push(to);
+        points
+    }
+}
+
+/// Namespace of the Inkscape-specific attributes.
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+
+/// Names of the Inkscape layers (and sublayers), in document order.
+pub fn layer_names(svg: &str) -> Result<Vec<String>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    Ok(document
+        .descendants()
+        .filter(|node| node.attribute((INKSCAPE_NS, "groupmode")) == Some("layer"))
+        .filter_map(|node| node.attribute((INKSCAPE_NS, "label")))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parse an opacity value (a number or percentage), clamped to 0..1.
+fn opacity(value: &str) -> Option<f64> {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse().ok()?,
+    };
+    Some(opacity.clamp(0.0, 1.0))
+}
+
+/// Remove the elements which are not rendered, i.e. with `display:none`,
+/// `visibility:hidden` (or `collapse`) or an opacity of zero, e.g. hidden
+/// construction layers in Inkscape.
+///
+/// Shapes with an opacity below `min_opacity` are removed as well, e.g.
+/// semi-transparent guide overlays. The opacity of a shape is its fill
+/// opacity multiplied with the opacity of the shape and its ancestors.
+pub fn remove_hidden(svg: &str, min_opacity: f64) -> Result<String, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let styles = StyleSheet::new(&document);
+
+    fn collect(
+        styles: &StyleSheet,
+        node: Node,
+        parent_opacity: f64,
+        min_opacity: f64,
+        removed: &mut Vec<Range<usize>>,
+    ) {
+        for child in node.children().filter(Node::is_element) {
+            let own_opacity = own_property(styles, child, "opacity").and_then(opacity);
+            let group_opacity = parent_opacity * own_opacity.unwrap_or(1.0);
+            let is_shape = is_shape(child);
+            let fill_opacity = property(styles, child, "fill-opacity").and_then(opacity);
+            let hidden = own_property(styles, child, "display") == Some("none")
+                || group_opacity <= 0.0
+                || (is_shape && group_opacity * fill_opacity.unwrap_or(1.0) < min_opacity)
+                // Visibility is inherited, but can be overridden by children
+                || (is_shape
+                    && matches!(property(styles, child, "visibility"), Some("hidden" | "collapse")));
+            if hidden {
+                log!(
+                    "Skipping hidden <{}>{} at byte {}",
+                    child.tag_name().name(),
+                    child
+                        .attribute("id")
+                        .map_or(String::new(), |id| format!(" {:?}", id)),
+                    child.range().start
+                );
+                removed.push(child.range());
+            } else {
+                collect(styles, child, group_opacity, min_opacity, removed);
+            }
+        }
+    }
+    let mut removed = vec![];
+    collect(
+        &styles,
+        document.root_element(),
+        1.0,
+        min_opacity,
+        &mut removed,
+    );
+
+    let mut visible = String::with_capacity(svg.len());
+    let mut pos = 0;
+    for range in removed {
+        visible.push_str(&svg[pos..range.start]);
+        pos = range.end;
+    }
+    visible.push_str(&svg[pos..]);
+    Ok(visible)
+}
+
+/// Severity of a problem of the document.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Severity {
+    /// Parts of the drawing are lost or distorted
+    Error,
+    /// The result may differ from what is expected
+    Warning,
+}
+
+/// A feature of the document which is not converted as expected.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    /// Byte range of the affected element
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// Check the document for features which are not converted as expected.
+pub fn check(svg: &str) -> Result<Vec<Problem>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let styles = StyleSheet::new(&document);
+    let mut problems = vec![];
+    let mut report = |severity, node: Node, message: String| {
+        problems.push(Problem {
+            severity,
+            range: node.range(),
+            message,
+        })
+    };
+
+    let root = document.root_element();
+    if let Some(problem) = unit_problem(root) {
+        report(Severity::Warning, root, problem);
+    }
+    for node in document.descendants().filter(Node::is_element) {
+        let name = node.tag_name().name();
+        // Other shapes and transformations are converted by the preprocessing
+        // of svg2polylines (usvg), so they are not reported
+        if name == "image" {
+            report(Severity::Error, node, "Images are not converted".into());
+        } else if name == "text" {
+            let message =
+                "Texts are ignored, unless converted with --text-to-path or --native-text";
+            report(Severity::Warning, node, message.into());
+        } else if is_shape(node) && !in_template(node) {
+            if has_paint_server(&styles, node) {
+                let message = if is_gradient_fill(&styles, node) {
+                    "Gradients are converted to a solid area, unless rasterized with --rasterize-gradients"
+                } else {
+                    "Patterns and gradient strokes are converted to a solid area"
+                };
+                report(Severity::Warning, node, message.into());
+            }
+            if name == "path" && has_open_subpath(node.attribute("d").unwrap_or("")) {
+                let message = "Open paths are converted to 0.2 mm lines instead of filled areas";
+                report(Severity::Warning, node, message.into());
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Whether an element is filled or stroked with a gradient or pattern
+/// (`url(#...)`).
+fn has_paint_server(styles: &StyleSheet, node: Node) -> bool {
+    ["fill", "stroke"].iter().any(|name| {
+        shape_property(styles, node, name)
+            .is_some_and(|value| value.trim_start().starts_with("url("))
+    })
+}
+
+/// Shapes with a gradient or pattern, which are converted to solid areas.
+/// Shapes filled with a gradient are not included if they are `rasterized`
+/// (see `gradient_fills()`). Returns their ranges and ids.
+pub fn gradient_shapes(svg: &str, rasterized: bool) -> Vec<(Range<usize>, Option<String>)> {
+    let document = match Document::parse(svg) {
+        Ok(document) => document,
+        Err(_) => return vec![],
+    };
+    let styles = StyleSheet::new(&document);
+    document
+        .descendants()
+        .filter(|&node| is_shape(node) && !in_template(node))
+        .filter(|&node| has_paint_server(&styles, node))
+        .filter(|&node| !(rasterized && is_gradient_fill(&styles, node)))
+        .map(|node| (node.range(), node.attribute("id").map(str::to_string)))
+        .collect()
+}
+
+/// A shape filled with a linear or radial gradient, which can be rasterized
+/// (see `gradient_fills()`).
+#[derive(Debug, PartialEq, Clone)]
+pub struct GradientFill {
+    /// Byte range of the shape
+    pub range: Range<usize>,
+    /// Transformation of the shape
+    transform: Transform,
+    /// Transformation of the gradient vector (`gradientTransform`)
+    gradient_transform: Transform,
+    /// Whether the gradient vector is given relative to the bounding box of
+    /// the shape (`objectBoundingBox` units)
+    bounding_box_units: bool,
+    shape: GradientShape,
+    /// Offsets (0 to 1) and colors of the stops, with the opacity as alpha
+    stops: Vec<(f64, svgtypes::Color)>,
+}
+
+/// The geometry of a gradient, in the units of the gradient vector.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum GradientShape {
+    Linear { start: Point, end: Point },
+    Radial { center: Point, radius: f64 },
+}
+
+impl GradientFill {
+    /// The color at each point (in the coordinates of the document) of the
+    /// shape, given as the flattened polylines (which determine the bounding
+    /// box). Returns `None` for degenerate transformations.
+    ///
+    /// Colors beyond the gradient vector are padded (`spreadMethod` is
+    /// ignored) and the focal point of radial gradients is assumed to be the
+    /// center.
+    pub fn colors(&self, polylines: &[Polyline]) -> Option<impl Fn(Point) -> Option<Color> + '_> {
+        let to_local = self.transform.inverse()?;
+        let mut to_gradient = self.gradient_transform;
+        if self.bounding_box_units {
+            let points: Vec<Polyline> = polylines
+                .iter()
+                .map(|polyline| polyline.iter().map(|p| to_local.apply(*p)).collect())
+                .collect();
+            let bounds = geometry::bounds(&points)?;
+            let (width, height) = (bounds.x_max - bounds.x_min, bounds.y_max - bounds.y_min);
+            to_gradient = Transform([width, 0.0, 0.0, height, bounds.x_min, bounds.y_min])
+                .then(self.gradient_transform);
+        }
+        let to_gradient = to_gradient.inverse()?.then(to_local);
+        Some(move |point: Point| {
+            let p = to_gradient.apply(point);
+            let offset = match self.shape {
+                GradientShape::Linear { start, end } => {
+                    let (dx, dy) = (end.x - start.x, end.y - start.y);
+                    let length = dx * dx + dy * dy;
+                    if length == 0.0 {
+                        1.0
+                    } else {
+                        ((p.x - start.x) * dx + (p.y - start.y) * dy) / length
+                    }
+                }
+                GradientShape::Radial { center, radius } => {
+                    (p.x - center.x).hypot(p.y - center.y) / radius
+                }
+            };
+            let color = self.color_at(offset.clamp(0.0, 1.0));
+            // Mostly transparent parts are not painted
+            (color.alpha >= 128).then_some([color.red, color.green, color.blue])
+        })
+    }
+
+    /// The color at an offset of the gradient vector, interpolated between
+    /// the stops.
+    fn color_at(&self, offset: f64) -> svgtypes::Color {
+        let next = self.stops.iter().position(|(stop, _)| *stop > offset);
+        let ((start, from), (end, to)) = match next {
+            Some(0) => return self.stops[0].1,
+            Some(index) => (self.stops[index - 1], self.stops[index]),
+            None => return self.stops[self.stops.len() - 1].1,
+        };
+        let t = (offset - start) / (end - start);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        svgtypes::Color::new_rgba(
+            mix(from.red, to.red),
+            mix(from.green, to.green),
+            mix(from.blue, to.blue),
+            mix(from.alpha, to.alpha),
+        )
+    }
+}
+
+/// Whether the shape is filled with a gradient (and not stroked with a
+/// gradient or pattern), so that it can be rasterized.
+fn is_gradient_fill(styles: &StyleSheet, node: Node) -> bool {
+    let stroke = shape_property(styles, node, "stroke").unwrap_or("none");
+    matches!(
+        shape_property(styles, node, "fill").map(svgtypes::Paint::from_str),
+        Some(Ok(svgtypes::Paint::FuncIRI(id, _))) if gradient(node, id).is_some()
+    ) && !stroke.trim_start().starts_with("url(")
+}
+
+/// The linear or radial gradient with the given id.
+fn gradient<'a, 'input>(node: Node<'a, 'input>, id: &str) -> Option<Node<'a, 'input>> {
+    node.document().descendants().find(|node| {
+        node.attribute("id") == Some(id)
+            && matches!(node.tag_name().name(), "linearGradient" | "radialGradient")
+    })
+}
+
+/// The shapes filled with a linear or radial gradient, in document order.
+/// Shapes with invalid gradients are not included.
+pub fn gradient_fills(svg: &str) -> Result<Vec<GradientFill>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let styles = StyleSheet::new(&document);
+    let root_size = view_box(svg)?.map(|rect| (rect.x_max - rect.x_min, rect.y_max - rect.y_min));
+    let mut fills = vec![];
+    for node in document.descendants().filter(|&node| is_split_shape(node)) {
+        if !is_gradient_fill(&styles, node) {
+            continue;
+        }
+        let id = match shape_property(&styles, node, "fill").map(svgtypes::Paint::from_str) {
+            Some(Ok(svgtypes::Paint::FuncIRI(id, _))) => id,
+            _ => continue,
+        };
+        match gradient_fill(&styles, node, id, root_size) {
+            Some(fill) => fills.push(fill),
+            None => log!("Invalid gradient {:?} at byte {}", id, node.range().start),
+        }
+    }
+    Ok(fills)
+}
+
+/// Read the gradient of a shape, following the references to other
+/// gradients (`href`) for the attributes and stops which are not given.
+fn gradient_fill(
+    styles: &StyleSheet,
+    node: Node,
+    id: &str,
+    root_size: Option<(f64, f64)>,
+) -> Option<GradientFill> {
+    let first = gradient(node, id)?;
+    let mut chain = vec![first];
+    // Limit the depth, in case of circular references
+    while chain.len() < 10 {
+        let href = chain[chain.len() - 1]
+            .attributes()
+            .find(|attribute| attribute.name() == "href")
+            .and_then(|attribute| attribute.value().trim().strip_prefix('#'));
+        match href.and_then(|id| gradient(node, id)) {
+            Some(next) => chain.push(next),
+            None => break,
+        }
+    }
+    let attribute = |name: &str| chain.iter().find_map(|gradient| gradient.attribute(name));
+    let bounding_box_units = attribute("gradientUnits") != Some("userSpaceOnUse");
+    // Percentages are relative to the bounding box or to the document
+    let (width, height) = match (bounding_box_units, root_size) {
+        (false, Some(size)) => size,
+        _ => (1.0, 1.0),
+    };
+    let coordinate = |name: &str, default: f64, size: f64| -> Option<f64> {
+        match attribute(name).map(str::trim) {
+            Some(value) => match value.strip_suffix('%') {
+                Some(percent) => Some(percent.trim().parse::<f64>().ok()? / 100.0 * size),
+                None => value.parse().ok(),
+            },
+            None => Some(default * size),
+        }
+    };
+    let shape = if first.tag_name().name() == "linearGradient" {
+        GradientShape::Linear {
+            start: Point::new(
+                coordinate("x1", 0.0, width)?,
+                coordinate("y1", 0.0, height)?,
+            ),
+            end: Point::new(
+                coordinate("x2", 1.0, width)?,
+                coordinate("y2", 0.0, height)?,
+            ),
+        }
+    } else {
+        let diagonal = width.hypot(height) / std::f64::consts::SQRT_2;
+        let radius = coordinate("r", 0.5, diagonal)?;
+        if radius <= 0.0 {
+            return None;
+        }
+        GradientShape::Radial {
+            center: Point::new(
+                coordinate("cx", 0.5, width)?,
+                coordinate("cy", 0.5, height)?,
+            ),
+            radius,
+        }
+    };
+    let gradient_transform = match attribute("gradientTransform") {
+        Some(value) => Transform::parse(value).ok()?,
+        None => Transform::IDENTITY,
+    };
+
+    let stops: Vec<Node> = chain
+        .iter()
+        .map(|gradient| {
+            gradient
+                .children()
+                .filter(|child| child.tag_name().name() == "stop")
+                .collect::<Vec<_>>()
+        })
+        .find(|stops| !stops.is_empty())?;
+    let mut previous = 0.0;
+    let mut parsed = vec![];
+    for stop in stops {
+        let offset = stop.attribute("offset").and_then(opacity).unwrap_or(0.0);
+        // Offsets are increasing
+        previous = offset.max(previous);
+        let mut color: svgtypes::Color = match own_property(styles, stop, "stop-color") {
+            Some(color) => color.trim().parse().ok()?,
+            None => svgtypes::Color::black(),
+        };
+        let stop_opacity = own_property(styles, stop, "stop-opacity")
+            .and_then(opacity)
+            .unwrap_or(1.0);
+        color.alpha = (color.alpha as f64 * stop_opacity).round() as u8;
+        parsed.push((previous, color));
+    }
+    Some(GradientFill {
+        range: node.range(),
+        transform: accumulated_transform(node).ok()?,
+        gradient_transform,
+        bounding_box_units,
+        shape,
+        stops: parsed,
+    })
+}
+
+/// Remove the elements with the given byte ranges from the document.
+pub fn remove_ranges(svg: &str, ranges: &[Range<usize>]) -> String {
+    let mut result = String::with_capacity(svg.len());
+    let mut pos = 0;
+    for range in ranges {
+        result.push_str(&svg[pos..range.start]);
+        pos = range.end;
+    }
+    result.push_str(&svg[pos..]);
+    result
+}
+
+/// A copy of the document which only contains the shape with the given byte
+/// range (the other shapes are removed).
+pub fn isolate_shape(svg: &str, range: &Range<usize>) -> Result<String, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let others: Vec<Range<usize>> = document
+        .descendants()
+        .filter(|&node| is_split_shape(node) && node.range() != *range)
+        .map(|node| node.range())
+        .collect();
+    Ok(remove_ranges(svg, &others))
+}
+
+/// Check whether user units correspond to millimeters, which are assumed by
+/// the conversion.
+fn unit_problem(root: Node) -> Option<String> {
+    let width = root.attribute("width")?;
+    let unit =
+        width.trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | '-' | '+'));
+    if unit != "mm" {
+        let unit = if unit.is_empty() { "px" } else { unit };
+        return Some(format!(
+            "The document width is specified in {}, but the drawing is converted assuming millimeters",
+            unit
+        ));
+    }
+    let width = length(width)?;
+    let view_box_width = root
+        .attribute("viewBox")?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .nth(2)?
+        .parse::<f64>()
+        .ok()?;
+    if (view_box_width - width).abs() > 1e-6 * width.abs().max(1.0) {
+        return Some(format!(
+            "One user unit is {} mm, but the drawing is converted assuming 1 user unit = 1 mm",
+            width / view_box_width
+        ));
+    }
+    None
+}
+
+/// Whether the path data contains a subpath which is not closed.
+fn has_open_subpath(data: &str) -> bool {
+    let mut drawing = false;
+    let mut rest = data;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E') {
+        let command = rest[start..].chars().next().unwrap_or('M');
+        let args = &rest[start + 1..];
+        let end = args
+            .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+            .unwrap_or(args.len());
+        match command {
+            'M' | 'm' => {
+                if drawing {
+                    return true;
+                }
+                // Additional coordinate pairs are implicit line commands
+                let numbers = args[..end]
+                    .replace('-', " -")
+                    .replace("e -", "e-")
+                    .replace("E -", "E-")
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|value| !value.is_empty())
+                    .count();
+                drawing = numbers > 2;
+            }
+            'Z' | 'z' => drawing = false,
+            _ => drawing = true,
+        }
+        rest = &args[end..];
+    }
+    drawing
+}
+
+/// A reference to a file outside of the document.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExternalRef {
+    /// What is referenced (`image`, `font`, `style sheet` or `file`)
+    pub kind: &'static str,
+    /// URL or path, as written in the document
+    pub href: String,
+    /// Byte range of the referencing element
+    pub range: Range<usize>,
+}
+
+/// Find the references to external files: Linked images, fonts and style
+/// sheets, and elements used from other documents. References within the
+/// document (`#id`) and embedded data (`data:` URLs) are not included.
+pub fn external_refs(svg: &str) -> Result<Vec<ExternalRef>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let mut refs = vec![];
+    let mut add = |kind, href: &str, node: Node| {
+        let href = href.trim().trim_matches(|c| c == '"' || c == '\'');
+        if !href.is_empty() && !href.starts_with('#') && !href.starts_with("data:") {
+            refs.push(ExternalRef {
+                kind,
+                href: href.to_string(),
+                range: node.range(),
+            });
+        }
+    };
+    for node in document.descendants() {
+        if let Some(pi) = node.pi().filter(|pi| pi.target == "xml-stylesheet") {
+            let href = pi
+                .value
+                .unwrap_or("")
+                .split_once("href=")
+                .and_then(|(_, rest)| {
+                    let quote = rest.chars().next()?;
+                    rest[1..].split(quote).next()
+                });
+            if let Some(href) = href {
+                add("style sheet", href, node);
+            }
+            continue;
+        }
+        if !node.is_element() {
+            continue;
+        }
+        // `href` and `xlink:href`
+        let href = node
+            .attributes()
+            .find(|attribute| attribute.name() == "href")
+            .map(|attribute| attribute.value());
+        if let Some(href) = href {
+            let kind = match node.tag_name().name() {
+                "image" => "image",
+                "font-face-uri" => "font",
+                _ => "file",
+            };
+            add(kind, href, node);
+        }
+        if node.tag_name().name() == "style" {
+            let css: String = node.children().filter_map(|child| child.text()).collect();
+            for (kind, href) in css_refs(&css) {
+                add(kind, href, node);
+            }
+        }
+    }
+    Ok(refs)
+}
+
+/// The URLs of a style sheet: Imported style sheets, fonts (in `@font-face`
+/// rules) and other files (e.g. images used as backgrounds).
+fn css_refs(css: &str) -> Vec<(&'static str, &str)> {
+    let mut refs = vec![];
+    let mut rest = css;
+    while let Some(start) = [rest.find("url("), rest.find("@import")]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+    {
+        let before = &css[..css.len() - rest.len() + start];
+        let (kind, value) = if rest[start..].starts_with("@import") {
+            ("style sheet", rest[start + 7..].trim_start())
+        } else {
+            let in_font_face = before
+                .rfind("@font-face")
+                .is_some_and(|index| !before[index..].contains('}'));
+            let kind = if in_font_face { "font" } else { "file" };
+            (kind, &rest[start + 4..])
+        };
+        // Also `@import url(...)`
+        let value = value.strip_prefix("url(").unwrap_or(value);
+        let end = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].find(quote).map(|end| end + 2),
+            _ => value.find(')'),
+        }
+        .unwrap_or(value.len());
+        refs.push((kind, &value[..end]));
+        rest = &value[end..];
+    }
+    refs
+}
+
+/// An RGB color.
+pub type Color = [u8; 3];
+
+/// Parse a CSS color, e.g. `#rrggbb`, `rgb(r, g, b)`, `hsl(h, s%, l%)` or a
+/// color name. The alpha channel is ignored.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let color: svgtypes::Color = value.trim().parse().ok()?;
+    Some([color.red, color.green, color.blue])
+}
+
+/// The shape elements, which are split by `split_by_color()` and
+/// `split_by_stroke()`.
+const SHAPES: [&str; 8] = [
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon", "use",
+];
+
+/// Elements whose contents are only rendered where they are referenced.
+const TEMPLATES: [&str; 6] = ["defs", "symbol", "clipPath", "mask", "marker", "pattern"];
+
+/// Whether the element is a shape (including `<use>` clones).
+fn is_shape(node: Node) -> bool {
+    node.is_element() && SHAPES.contains(&node.tag_name().name())
+}
+
+/// Whether the element is inside a template (e.g. `<defs>`).
+fn in_template(node: Node) -> bool {
+    node.ancestors()
+        .skip(1)
+        .any(|ancestor| TEMPLATES.contains(&ancestor.tag_name().name()))
+}
+
+/// The element referenced by a `<use>` element.
+fn use_target<'a, 'input>(node: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    let href = node
+        .attributes()
+        .find(|attribute| attribute.name() == "href")?
+        .value();
+    let id = href.trim().strip_prefix('#')?;
+    node.document()
+        .descendants()
+        .find(|node| node.attribute("id") == Some(id))
+}
+
+/// Whether a shape is split by its paint. Clones of rendered elements are
+/// not split, since they are drawn like their original (which is split);
+/// clones of templates are split like the other shapes.
+fn is_split_shape(node: Node) -> bool {
+    is_shape(node)
+        && !in_template(node)
+        && (node.tag_name().name() != "use" || use_target(node).is_some_and(in_template))
+}
+
+/// Value of a property of a shape. For clones, the property of the cloned
+/// shape takes precedence, since it is not inherited from the `<use>`
+/// element.
+fn shape_property<'a>(styles: &'a StyleSheet, node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let target = match node.tag_name().name() {
+        "use" => use_target(node),
+        _ => None,
+    };
+    target
+        .and_then(|target| own_property(styles, target, name))
+        .or_else(|| property(styles, node, name))
+}
+
+/// The color of a paint (the value of `fill` or `stroke`) of a shape.
+/// Gradients are drawn with the color of their first stop. Returns `None`
+/// for invisible paints and an error for paints without a color (e.g.
+/// patterns).
+fn paint_color(styles: &StyleSheet, node: Node, paint: &str) -> Result<Option<Color>, String> {
+    let unsupported = || format!("Unsupported paint {:?}", paint);
+    let current_color = || match shape_property(styles, node, "color") {
+        Some(color) => color.trim().parse().map_err(|_| unsupported()),
+        None => Ok(svgtypes::Color::black()),
+    };
+    let color = match svgtypes::Paint::from_str(paint).map_err(|_| unsupported())? {
+        svgtypes::Paint::None => return Ok(None),
+        svgtypes::Paint::Inherit => return Err(unsupported()),
+        svgtypes::Paint::CurrentColor => current_color()?,
+        svgtypes::Paint::Color(color) => color,
+        svgtypes::Paint::FuncIRI(id, fallback) => {
+            match (gradient_color(styles, node, id), fallback) {
+                (Some(color), _) => color,
+                (None, Some(svgtypes::PaintFallback::None)) => return Ok(None),
+                (None, Some(svgtypes::PaintFallback::CurrentColor)) => current_color()?,
+                (None, Some(svgtypes::PaintFallback::Color(color))) => color,
+                (None, None) => return Err(unsupported()),
+            }
+        }
+    };
+    Ok((color.alpha > 0).then_some([color.red, color.green, color.blue]))
+}
+
+/// The color of the first stop of a gradient, following the references to
+/// other gradients (`href`) for gradients without stops.
+fn gradient_color(styles: &StyleSheet, node: Node, id: &str) -> Option<svgtypes::Color> {
+    let mut id = id;
+    // Limit the depth, in case of circular references
+    for _ in 0..10 {
+        let gradient = node.document().descendants().find(|node| {
+            node.attribute("id") == Some(id)
+                && matches!(node.tag_name().name(), "linearGradient" | "radialGradient")
+        })?;
+        let stop = gradient
+            .children()
+            .find(|child| child.tag_name().name() == "stop");
+        if let Some(stop) = stop {
+            return match own_property(styles, stop, "stop-color") {
+                Some(color) => color.trim().parse().ok(),
+                None => Some(svgtypes::Color::black()),
+            };
+        }
+        id = gradient
+            .attributes()
+            .find(|attribute| attribute.name() == "href")?
+            .value()
+            .trim()
+            .strip_prefix('#')?;
+    }
+    None
+}
+
+/// Copies of a document returned by `split_by_color()` or
+/// `split_by_stroke()`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Split<T> {
+    /// The distinct values with the copy of the document containing their
+    /// shapes
+    pub groups: Vec<(T, String)>,
+    /// Byte ranges of the shapes which are not contained in any copy, since
+    /// their value is not supported, and the reasons
+    pub skipped: Vec<(Range<usize>, String)>,
+}
+
+/// Split the document by the color of the shapes, returning one copy of the
+/// document per color, which only contains the shapes of that color.
+///
+/// The color of a shape is its fill or, for unfilled shapes, its stroke.
+/// Shapes without fill and stroke are invisible and removed. Shapes painted
+/// with a pattern are skipped.
+pub fn split_by_color(svg: &str) -> Result<Split<Color>, String> {
+    split_shapes(svg, |styles, node| {
+        // Shapes are filled with black by default
+        let fill = shape_property(styles, node, "fill").unwrap_or("black");
+        match paint_color(styles, node, fill)? {
+            Some(color) => Ok(Some(color)),
+            None => match shape_property(styles, node, "stroke") {
+                Some(stroke) => paint_color(styles, node, stroke),
+                None => Ok(None),
+            },
+        }
+    })
+}
+
+/// How the shapes of a document returned by `split_by_stroke()` are drawn.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Paint {
+    pub fill: bool,
+    pub stroke: Option<Stroke>,
+}
+
+/// Split the document by how the shapes are filled and stroked, returning
+/// one copy of the document per paint, which only contains the shapes drawn
+/// with it.
+///
+/// The line cap and join are taken from the SVG properties, unless they are
+/// given. The stroke width is scaled by the transformations of the shape and
+/// its ancestors. Shapes without fill and stroke are invisible and removed.
+pub fn split_by_stroke(
+    svg: &str,
+    cap: Option<LineCap>,
+    join: Option<LineJoin>,
+) -> Result<Split<Paint>, String> {
+    split_shapes(svg, |styles, node| {
+        let fill = shape_property(styles, node, "fill") != Some("none");
+        let width = shape_property(styles, node, "stroke-width")
+            .map(|value| length(value).ok_or_else(|| format!("Invalid stroke width {:?}", value)))
+            .transpose()?
+            .unwrap_or(1.0)
+            * accumulated_transform(node)?.scale();
+        let stroked = shape_property(styles, node, "stroke").is_some_and(|stroke| stroke != "none");
+        let stroke = (stroked && width > 0.0).then(|| Stroke {
+            width,
+            cap: cap.unwrap_or(match shape_property(styles, node, "stroke-linecap") {
+                Some("round") => LineCap::Round,
+                Some("square") => LineCap::Square,
+                _ => LineCap::Butt,
+            }),
+            join: join.unwrap_or(match shape_property(styles, node, "stroke-linejoin") {
+                Some("round") => LineJoin::Round,
+                Some("bevel") => LineJoin::Bevel,
+                _ => LineJoin::Miter,
+            }),
+        });
+        Ok((fill || stroke.is_some()).then_some(Paint { fill, stroke }))
+    })
+}
+
+/// Split the document by a property of the shapes, returning one copy of the
+/// document per distinct value (in the order of their first use), which only
+/// contains the shapes with that value. Shapes without a value are removed,
+/// shapes whose value is not supported (an error) are skipped.
+fn split_shapes<T: PartialEq + Copy>(
+    svg: &str,
+    value: impl Fn(&StyleSheet, Node) -> Result<Option<T>, String>,
+) -> Result<Split<T>, String> {
+    let document = Document::parse(svg).map_err(|e| e.to_string())?;
+    let styles = StyleSheet::new(&document);
+    let mut shapes = vec![];
+    let mut skipped = vec![];
+    for node in document.descendants().filter(|&node| is_split_shape(node)) {
+        match value(&styles, node) {
+            Ok(value) => shapes.push((value, node.range())),
+            Err(reason) => {
+                log!(
+                    "Skipping <{}> at byte {}: {}",
+                    node.tag_name().name(),
+                    node.range().start,
+                    reason
+                );
+                shapes.push((None, node.range()));
+                skipped.push((node.range(), reason));
+            }
+        }
+    }
+
+    let mut values: Vec<T> = vec![];
+    for value in shapes.iter().filter_map(|(value, _)| *value) {
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+    let groups = values
+        .into_iter()
+        .map(|value| {
+            let mut split = String::with_capacity(svg.len());
+            let mut pos = 0;
+            for (_, range) in shapes.iter().filter(|(v, _)| *v != Some(value)) {
+                split.push_str(&svg[pos..range.start]);
+                pos = range.end;
+            }
+            split.push_str(&svg[pos..]);
+            (value, split)
+        })
+        .collect();
+    Ok(Split { groups, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_box() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-5 0,20 10"/>"#;
+        assert_eq!(
+            view_box(svg),
+            Ok(Some(Rect {
+                x_min: -5.0,
+                y_min: 0.0,
+                x_max: 15.0,
+                y_max: 10.0,
+            }))
+        );
+        assert_eq!(view_box("<svg/>"), Ok(None));
+        assert!(view_box(r#"<svg viewBox="0 0 10"/>"#).is_err());
+        assert!(view_box(r#"<svg viewBox="0 0 -1 10"/>"#).is_err());
+    }
+
+    #[test]
+    fn test_size() {
+        let svg = r#"<svg><g><path d="M 0 0 L 1 1"/><path d="M 0 0 L 10 10 Z"/></g></svg>"#;
+        assert_eq!(size(svg), Ok((4, 15)));
+        assert!(size("<svg>").is_err());
+    }
+
+    #[test]
+    fn test_estimate_vertices() {
+        let svg = r#"<svg>
+            <path d="M 0 0 L 10 0 L 10 10 Z"/>
+            <path d="M 0 0 Q 5 10 10 0"/>
+            <polygon points="0,0 1,0 1,1"/>
+        </svg>"#;
+        // The quadratic curve has a second difference of 20
+        assert_eq!(estimate_vertices(svg, Some(0.05), 0.02), Ok(4 + 1 + 10 + 4));
+        assert_eq!(estimate_vertices(svg, None, 0.02), Ok(4 + 2 + 4));
+        // Finer tolerances need more segments
+        let circle = r#"<svg><circle r="10"/></svg>"#;
+        assert!(
+            estimate_vertices(circle, None, 0.001).unwrap()
+                > estimate_vertices(circle, None, 0.1).unwrap()
+        );
+        assert!(estimate_vertices("<svg>", None, 0.02).is_err());
+    }
+
+    #[test]
+    fn test_text_ranges() {
+        assert_eq!(
+            text_ranges(r#"<svg><g><text>Logo</text></g></svg>"#),
+            vec![8..25]
+        );
+        assert!(text_ranges(r#"<svg><path d="M 0 0 L 1 1"/></svg>"#).is_empty());
+    }
+
+    #[test]
+    fn test_texts() {
+        let svg = r#"<svg><g transform="translate(10, 5)">
+          <text x="1" y="20" style="font-size:10px;text-anchor:middle" transform="rotate(-90 1 20)"><tspan x="1" y="20">Hello</tspan><tspan x="1" y="32">World</tspan></text>
+          <text x="0" y="0"> </text>
+        </g></svg>"#;
+        let texts = texts(svg).unwrap();
+        assert_eq!(texts.len(), 1);
+        let text = &texts[0];
+        assert_eq!(text.content, "Hello\nWorld");
+        assert_eq!(text.align, "center");
+        assert!((text.height - 7.0).abs() < 1e-9);
+        assert!((text.rotation - 90.0).abs() < 1e-9);
+        // Rotated around the baseline point, the top is to the left of it
+        assert!((text.position.x - 4.0).abs() < 1e-9);
+        assert!((text.position.y - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_transform() {
+        let transform = Transform::parse("translate(1,2) scale(2)").unwrap();
+        assert_eq!(transform.apply(Point::new(1.0, 1.0)), Point::new(3.0, 4.0));
+        assert!(Transform::parse("rotate(1 2)").is_err());
+        assert!(Transform::parse("foo(1)").is_err());
+    }
+
+    #[test]
+    fn test_check_xml() {
+        assert_eq!(check_xml("<svg>\n<path/></svg>"), Ok(()));
+        let (offset, _) = check_xml("<svg>\n  <päth></svg>").unwrap_err();
+        assert_eq!(offset, 15);
+    }
+
+    #[test]
+    fn test_remove_hidden() {
+        let svg = r#"<svg>
+<g style="display:none"><path id="a"/></g>
+<g visibility="hidden"><path id="b"/><path id="c" visibility="visible"/></g>
+<path id="d" style="fill:red;opacity:0"/>
+<path id="e" opacity="0.5"/>
+<g visibility="hidden"><rect id="f"/></g>
+</svg>"#;
+        let visible = remove_hidden(svg, 0.0).unwrap();
+        for id in ["a", "b", "d", "f"] {
+            assert!(!visible.contains(&format!(r#"id="{}""#, id)), "{}", id);
+        }
+        for id in ["c", "e"] {
+            assert!(visible.contains(&format!(r#"id="{}""#, id)), "{}", id);
+        }
+
+        // Opacities of groups are multiplied
+        let svg = r#"<svg><g opacity="50%">
+<path id="a" fill-opacity="0.5"/><path id="b"/>
+</g><path id="c" style="fill-opacity:0.3"/><circle id="d" fill-opacity="0.1"/></svg>"#;
+        let visible = remove_hidden(svg, 0.4).unwrap();
+        assert!(!visible.contains(r#"id="d""#));
+        assert!(!visible.contains(r#"id="a""#));
+        assert!(visible.contains(r#"id="b""#));
+        assert!(!visible.contains(r#"id="c""#));
+    }
+
+    #[test]
+    fn test_flatten_ellipses() {
+        let svg = concat!(
+            r#"<svg xmlns:svg="http://www.w3.org/2000/svg">"#,
+            r#"<circle id="c" cx="5" cy="5" r="2" fill="red"/>"#,
+            r#"<svg:ellipse rx="4" ry="1"></svg:ellipse><circle r="0"/><circle cx="50%" r="1"/>"#,
+            r#"<path d="M 0,0 h 10 a 5,5 0 0 1 -10,0 z m 1,1 l 1,1"/><path d="M 0,0 L 1,1"/></svg>"#,
+        );
+        let flattened = flatten_ellipses(svg, 0.01).unwrap();
+        let document = Document::parse(&flattened).unwrap();
+        let paths: Vec<Node> = document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "path")
+            .collect();
+        assert_eq!(paths.len(), 4);
+        assert_eq!(paths[0].attribute("id"), Some("c"));
+        assert_eq!(paths[0].attribute("fill"), Some("red"));
+        assert!(flattened.contains(r#"<svg:path d="M 4,0 L"#));
+        assert!(flattened.contains(r#"<circle r="0"/>"#));
+        assert!(flattened.contains(r#"<circle cx="50%" r="1"/>"#));
+        assert!(flattened.contains(r#"<path d="M 0,0 L 1,1"/>"#));
+
+        // The points are on the circle, and close enough to each other
+        let data = paths[2].attribute("d").unwrap();
+        assert!(data.starts_with("M 0,0 h 10 L "), "{}", data);
+        assert!(data.ends_with(" L 0,0 z m 1,1 l 1,1"), "{}", data);
+        let points: Vec<(f64, f64)> = data
+            .split(" L ")
+            .skip(1)
+            .map(|point| {
+                let point = point.split(' ').next().unwrap();
+                let (x, y) = point.split_once(',').unwrap();
+                (x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+        for pair in points.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            assert!(((x1 - 5.0f64).hypot(y1) - 5.0).abs() < 1e-9);
+            assert!(y1 >= -1e-9, "{:?}", points);
+            // The sagitta of the chords is at most the tolerance
+            let chord = (x2 - x1).hypot(y2 - y1);
+            assert!(5.0 - (25.0 - chord * chord / 4.0).sqrt() <= 0.01);
+        }
+        assert_eq!(arc_steps(std::f64::consts::TAU, 1.0, 2.0), 4);
+    }
+
+    #[test]
+    fn test_layer_names() {
+        let svg = concat!(
+            r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">"#,
+            r#"<g inkscape:groupmode="layer" inkscape:label="Logo">"#,
+            r#"<g inkscape:label="Group"/><g inkscape:groupmode="layer" inkscape:label="Text"/>"#,
+            r#"</g></svg>"#,
+        );
+        assert_eq!(layer_names(svg).unwrap(), vec!["Logo", "Text"]);
+    }
+
+    #[test]
+    fn test_select_ids() {
+        let svg = r#"<svg><path id="a"/><g id="b"><path id="c"/><path id="d"/></g></svg>"#;
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            select_ids(svg, &ids(&["c"])),
+            Ok(r#"<svg><g id="b"><path id="c"/></g></svg>"#.to_string())
+        );
+        assert_eq!(select_ids(svg, &ids(&["a", "b"])), Ok(svg.to_string()));
+        assert!(select_ids(svg, &ids(&["x"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#FFD700"), Some([255, 215, 0]));
+        assert_eq!(parse_color("#fff"), Some([255, 255, 255]));
+        assert_eq!(parse_color("rgb(1, 2, 3)"), Some([1, 2, 3]));
+        assert_eq!(parse_color("White"), Some([255, 255, 255]));
+        assert_eq!(
+            parse_color("rgba(10%, 20%, 100%, 0.5)"),
+            Some([26, 51, 255])
+        );
+        assert_eq!(parse_color("hsl(120, 100%, 50%)"), Some([0, 255, 0]));
+        assert_eq!(parse_color("darkslategray"), Some([47, 79, 79]));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("url(#gradient)"), None);
+    }
+
+    #[test]
+    fn test_split_by_color() {
+        let svg = concat!(
+            r#"<svg><g style="fill:#ffd700">"#,
+            r#"<path d="M 0,0 L 1,0"/><path fill="white" d="M 0,0 L 2,0"/></g>"#,
+            r#"<path d="M 0,0 L 3,0"/><path style="fill:none" d="M 0,0 L 4,0"/>"#,
+            r#"<path style="fill:none;stroke:#fff" d="M 0,0 L 5,0"/></svg>"#,
+        );
+        let split = split_by_color(svg).unwrap().groups;
+        assert_eq!(split.len(), 3);
+        assert_eq!(split[0].0, [255, 215, 0]);
+        assert!(split[0].1.contains("1,0") && !split[0].1.contains("2,0"));
+        assert_eq!(split[1].0, [255, 255, 255]);
+        assert!(split[1].1.contains("2,0") && split[1].1.contains("5,0"));
+        assert_eq!(split[2].0, [0, 0, 0]);
+        assert!(split[2].1.contains("3,0") && !split[2].1.contains("4,0"));
+
+        // All shapes are split, clones of templates by the paint of the
+        // cloned shape
+        let svg = concat!(
+            r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink">"#,
+            r##"<defs><path id="p" fill="red" d="M 0,0 L 6,0"/><path id="q" d="M 0,0 L 7,0"/></defs>"##,
+            r#"<rect width="1" height="1" fill="rgba(255, 0, 0, 0.5)"/><circle r="1" fill="white"/>"#,
+            r##"<use href="#p" fill="white"/><use xlink:href="#q" fill="white"/>"##,
+            r#"<polygon points="0,0 1,0 1,1" fill="transparent"/></svg>"#,
+        );
+        let split = split_by_color(svg).unwrap();
+        assert!(split.skipped.is_empty());
+        assert_eq!(split.groups.len(), 2);
+        let (red, white) = (&split.groups[0], &split.groups[1]);
+        assert_eq!((red.0, white.0), ([255, 0, 0], [255, 255, 255]));
+        assert!(red.1.contains("<rect") && red.1.contains("#p") && !red.1.contains("#q"));
+        assert!(!red.1.contains("<circle") && white.1.contains("<circle"));
+        assert!(white.1.contains("#q") && !white.1.contains(r##""#p""##));
+        assert!(!red.1.contains("<polygon") && !white.1.contains("<polygon"));
+
+        // Gradients count as their first stop, shapes with patterns are
+        // skipped
+        let svg = concat!(
+            r##"<svg><linearGradient id="g"><stop stop-color="gold"/><stop stop-color="white"/></linearGradient>"##,
+            r##"<linearGradient id="h" href="#g"/><pattern id="p"/>"##,
+            r##"<path fill="url(#h)" d="M 0,0 L 1,0"/><path fill="url(#p)" d="M 0,0 L 2,0"/>"##,
+            r##"<path fill="url(#p) white" d="M 0,0 L 3,0"/><path style="color:red;fill:currentColor" d="M 0,0 L 4,0"/></svg>"##,
+        );
+        let split = split_by_color(svg).unwrap();
+        let colors: Vec<Color> = split.groups.iter().map(|(color, _)| *color).collect();
+        assert_eq!(colors, vec![[255, 215, 0], [255, 255, 255], [255, 0, 0]]);
+        assert_eq!(split.skipped.len(), 1);
+        assert!(svg[split.skipped[0].0.clone()].contains("2,0"));
+        assert!(split.groups.iter().all(|(_, group)| !group.contains("2,0")));
+    }
+
+    #[test]
+    fn test_style_sheet() {
+        let svg = concat!(
+            r#"<svg><style>.cls-1 { fill: #fff } .guide { display: none }</style>"#,
+            r##"<path class="cls-1" d="M 0,0 L 1,0"/><path class="cls-1" fill="#000" d="M 0,0 L 2,0"/>"##,
+            r#"<path class="cls-1" style="fill:black" d="M 0,0 L 3,0"/>"#,
+            r#"<path class="guide" d="M 0,0 L 4,0"/></svg>"#,
+        );
+        let visible = remove_hidden(svg, 0.0).unwrap();
+        assert!(!visible.contains("4,0"));
+        let split = split_by_color(&visible).unwrap().groups;
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].0, [255, 255, 255]);
+        assert!(split[0].1.contains("1,0") && split[0].1.contains("2,0"));
+        assert_eq!(split[1].0, [0, 0, 0]);
+        assert!(split[1].1.contains("3,0"));
+        let selected = select_ids(&svg.replace("guide\"", "guide\" id=\"g\""), &["g".into()]);
+        assert!(selected.unwrap().contains(".cls-1"));
+    }
+
+    #[test]
+    fn test_split_by_stroke() {
+        let svg = concat!(
+            r#"<svg><g style="stroke:#000;stroke-width:0.5;stroke-linejoin:round">"#,
+            r#"<path fill="none" d="M 0,0 L 1,0"/><path d="M 0,0 L 2,0"/></g>"#,
+            r#"<path d="M 0,0 L 3,0"/><path style="fill:none" d="M 0,0 L 4,0"/>"#,
+            r#"<path style="fill:none;stroke:#fff;stroke-linecap:round" d="M 0,0 L 5,0"/></svg>"#,
+        );
+        let split = split_by_stroke(svg, None, None).unwrap().groups;
+        let paints: Vec<Paint> = split.iter().map(|(paint, _)| *paint).collect();
+        let stroke = |width, cap, join| Some(Stroke { width, cap, join });
+        assert_eq!(
+            paints,
+            vec![
+                Paint {
+                    fill: false,
+                    stroke: stroke(0.5, LineCap::Butt, LineJoin::Round),
+                },
+                Paint {
+                    fill: true,
+                    stroke: stroke(0.5, LineCap::Butt, LineJoin::Round),
+                },
+                Paint {
+                    fill: true,
+                    stroke: None,
+                },
+                Paint {
+                    fill: false,
+                    stroke: stroke(1.0, LineCap::Round, LineJoin::Miter),
+                },
+            ]
+        );
+        assert!(split[2].1.contains("3,0") && !split[2].1.contains("4,0"));
+
+        let split = split_by_stroke(svg, Some(LineCap::Square), Some(LineJoin::Bevel))
+            .unwrap()
+            .groups;
+        assert_eq!(
+            split[3].0.stroke,
+            stroke(1.0, LineCap::Square, LineJoin::Bevel)
+        );
+
+        // The stroke width is scaled like the shape, other shapes than paths
+        // are split as well
+        let svg = concat!(
+            r#"<svg><g transform="scale(2)"><rect width="1" height="1" fill="none" stroke="red" stroke-width="0.5"/></g>"#,
+            r#"<circle r="1" fill="none" stroke="red" stroke-width="0.5"/></svg>"#,
+        );
+        let split = split_by_stroke(svg, None, None).unwrap().groups;
+        let widths: Vec<f64> = split
+            .iter()
+            .filter_map(|(paint, _)| paint.stroke)
+            .map(|stroke| stroke.width)
+            .collect();
+        assert_eq!(widths, vec![1.0, 0.5]);
+        assert!(split[0].1.contains("<rect") && !split[0].1.contains("<circle"));
+        assert!(split[1].1.contains("<circle") && !split[1].1.contains("<rect"));
+    }
+
+    #[test]
+    fn test_check() {
+        let svg = concat!(
+            r#"<svg width="100px" viewBox="0 0 100 100">"#,
+            r#"<rect width="1" height="1"/><text>Logo</text>"#,
+            r#"<g transform="translate(5,5)"><path d="M 0,0 1,0 1,1 Z m 2,0 h 1 z"/></g>"#,
+            r#"<g transform="scale(2)"><path d="M 0,0 L 1,1"/></g></svg>"#,
+        );
+        let problems = check(svg).unwrap();
+        let summary: Vec<_> = problems
+            .iter()
+            .map(|problem| (problem.severity, &svg[problem.range.clone()][..5]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Warning, "<svg "),
+                (Severity::Warning, "<text"),
+                (Severity::Warning, "<path"),
+            ]
+        );
+        let svg = r#"<svg width="20mm" viewBox="0 0 20 10"><path d="M0 0 1 1z"/></svg>"#;
+        assert_eq!(check(svg), Ok(vec![]));
+        let svg = r#"<svg width="20mm" viewBox="0 0 20 10"><rect fill="url(#p)"/></svg>"#;
+        assert_eq!(check(svg).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_gradient_shapes() {
+        let svg = concat!(
+            r#"<svg><style>.shine { fill: url(#gradient) }</style>"#,
+            r#"<linearGradient id="gradient"><stop/></linearGradient>"#,
+            r#"<path id="logo" fill="url(#gradient)" d="M0 0 1 1z"/>"#,
+            r#"<g style="stroke: url(#pattern)"><circle r="1"/></g>"#,
+            r#"<ellipse class="shine" rx="1" ry="2"/><path fill="red" d="M0 0 1 1z"/>"#,
+            r#"<rect width="1" height="1" fill="url(#pattern)"/></svg>"#,
+        );
+        let shapes = |rasterized| -> Vec<_> {
+            gradient_shapes(svg, rasterized)
+                .into_iter()
+                .map(|(range, id)| (&svg[range][..4], id))
+                .collect()
+        };
+        assert_eq!(
+            shapes(false),
+            vec![
+                ("<pat", Some("logo".to_string())),
+                ("<cir", None),
+                ("<ell", None),
+                ("<rec", None),
+            ]
+        );
+        assert_eq!(shapes(true), vec![("<cir", None), ("<rec", None)]);
+    }
+
+    #[test]
+    fn test_gradient_fills() {
+        let svg = concat!(
+            r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink">"#,
+            r#"<linearGradient id="a"><stop offset="0" stop-color="black"/><stop offset="1" stop-color="white"/></linearGradient>"#,
+            r##"<linearGradient id="b" xlink:href="#a" gradientUnits="userSpaceOnUse" x1="0" x2="0" y1="0" y2="10"/>"##,
+            r#"<radialGradient id="c"><stop stop-color="red"/><stop offset="50%" stop-color="red" stop-opacity="0"/></radialGradient>"#,
+            r#"<rect fill="url(#a)" transform="translate(10,0)" width="4" height="4"/>"#,
+            r#"<rect fill="url(#b)" width="4" height="10"/><rect fill="url(#c)" width="2" height="2"/>"#,
+            r#"<rect fill="url(#missing)" width="1" height="1"/></svg>"#,
+        );
+        let fills = gradient_fills(svg).unwrap();
+        assert_eq!(fills.len(), 3);
+        let square = |x: f64, size: f64| {
+            vec![
+                Point::new(x, 0.0),
+                Point::new(x + size, 0.0),
+                Point::new(x + size, size),
+                Point::new(x, size),
+                Point::new(x, 0.0),
+            ]
+        };
+
+        // Along the bounding box of the transformed shape
+        let colors = fills[0].colors(&[square(10.0, 4.0)]).unwrap();
+        assert_eq!(colors(Point::new(9.0, 1.0)), Some([0, 0, 0]));
+        assert_eq!(colors(Point::new(12.0, 1.0)), Some([128, 128, 128]));
+        assert_eq!(colors(Point::new(13.0, 3.0)), Some([191, 191, 191]));
+
+        // In user space, with the stops of the referenced gradient
+        let colors = fills[1].colors(&[square(0.0, 10.0)]).unwrap();
+        assert_eq!(colors(Point::new(3.0, 2.5)), Some([64, 64, 64]));
+
+        // Transparent parts are not painted
+        let colors = fills[2].colors(&[square(0.0, 2.0)]).unwrap();
+        assert_eq!(colors(Point::new(1.0, 1.0)), Some([255, 0, 0]));
+        assert_eq!(colors(Point::new(0.1, 0.1)), None);
+    }
+
+    #[test]
+    fn test_has_open_subpath() {
+        assert!(!has_open_subpath("M 0,0 L 1,0 L 1,1 Z"));
+        assert!(!has_open_subpath("m0-1 1 1-1e-5 0z M 5 5 h 1 v 1 z"));
+        assert!(has_open_subpath("M 0,0 L 1,0 Z M 5,5 L 6,6"));
+        assert!(has_open_subpath("M 0,0 1,0 1,1"));
+        assert!(!has_open_subpath("M 0,0"));
+    }
+
+    #[test]
+    fn test_external_refs() {
+        let svg = concat!(
+            r#"<?xml-stylesheet type="text/css" href="theme.css"?>"#,
+            r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink">"#,
+            r#"<style>@import url("base.css"); @font-face { src: url(fonts/logo.woff) }"#,
+            r#" path { fill: url(#gradient) } .bg { background: url('bg.png') }</style>"#,
+            r#"<image xlink:href="photo.png"/><image href="data:image/png;base64,AA"/>"#,
+            r##"<use href="#logo"/><use xlink:href="parts.svg#logo"/></svg>"##,
+        );
+        let refs: Vec<_> = external_refs(svg)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.kind, r.href))
+            .collect();
+        let expected = [
+            ("style sheet", "theme.css"),
+            ("style sheet", "base.css"),
+            ("font", "fonts/logo.woff"),
+            ("file", "bg.png"),
+            ("image", "photo.png"),
+            ("file", "parts.svg#logo"),
+        ];
+        assert_eq!(
+            refs,
+            expected
+                .iter()
+                .map(|&(kind, href)| (kind, href.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
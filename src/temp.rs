@@ -0,0 +1,69 @@
+//! Temporary directories for intermediate files.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+/// A new temporary directory, removed with its contents when dropped.
+///
+/// The name has a random suffix and the directory is created exclusively
+/// (only accessible by the user on Unix), so that other users of the shared
+/// temporary directory cannot prepare files or symlinks in it.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create a directory named `svg2librepcb-<purpose>-<random>`.
+    pub fn new(purpose: &str) -> io::Result<Self> {
+        loop {
+            let name = format!("svg2librepcb-{}-{}", purpose, Uuid::new_v4().simple());
+            let path = env::temp_dir().join(name);
+            match create_private_dir(&path) {
+                Ok(()) => return Ok(TempDir(path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Create a directory, failing if it exists.
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+/// Create a directory, failing if it exists.
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir() {
+        let (a, b) = (TempDir::new("test").unwrap(), TempDir::new("test").unwrap());
+        assert_ne!(a.path(), b.path());
+        assert!(a.path().is_dir());
+        fs::write(a.path().join("file"), "").unwrap();
+        let path = a.path().to_owned();
+        drop(a);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,269 @@
+//! Custom templates for the generated element files (`--template`).
+//!
+//! Templates use the Jinja2 syntax and replace the S-expression file of an
+//! element. The variables are taken from the generated file, so the template
+//! always sees the final metadata and geometry (e.g. including the UUIDs of
+//! regenerated elements):
+//!
+//! - `element`: `symbol`, `component`, `package` or `device`
+//! - `uuid`, `name`, `description`, `keywords`, `author`, `version`
+//! - `generated`: the generated file
+//! - `nodes`: the child nodes of the generated element, one line each
+//! - `polygons`: the polygons of a symbol, with `layer`, `width`, `fill`,
+//!   `grab_area` and `vertices` (`x`, `y`, `angle`)
+//! - `footprints`: the footprints of a package, with `uuid`, `name` and
+//!   `polygons`
+//!
+//! The filters `escape_string` and `format_float` format values like the
+//! generated files.
+
+use std::{fs, path::PathBuf};
+
+use minijinja::{Environment, UndefinedBehavior};
+use serde::Serialize;
+
+use crate::{
+    error::{Error, Result},
+    escape_string, format_float,
+    sexpr::{self, List},
+    ElementKind,
+};
+
+/// Variables available in a template.
+#[derive(Serialize, Debug, PartialEq)]
+struct Context {
+    element: String,
+    uuid: String,
+    name: String,
+    description: String,
+    keywords: String,
+    author: String,
+    version: String,
+    generated: String,
+    nodes: Vec<String>,
+    polygons: Vec<Polygon>,
+    footprints: Vec<Footprint>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Footprint {
+    uuid: String,
+    name: String,
+    polygons: Vec<Polygon>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Polygon {
+    layer: String,
+    width: f64,
+    fill: bool,
+    grab_area: bool,
+    vertices: Vec<Vertex>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Vertex {
+    x: f64,
+    y: f64,
+    angle: f64,
+}
+
+/// The templates passed on the command line.
+pub struct Templates {
+    env: Environment<'static>,
+    paths: Vec<(ElementKind, PathBuf)>,
+}
+
+impl Templates {
+    /// Load the templates given as `ELEMENT=FILE`.
+    pub fn load(values: &[String]) -> Result<Templates> {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        env.add_filter("escape_string", |value: String| escape_string(&value));
+        env.add_filter("format_float", format_float);
+        let mut paths = vec![];
+        for value in values {
+            let error = |reason: String| Error::Template {
+                template: value.clone(),
+                reason,
+            };
+            let (element, path) = value
+                .split_once('=')
+                .ok_or_else(|| error("expected ELEMENT=FILE".into()))?;
+            let kind = match element {
+                "symbol" => ElementKind::Symbol,
+                "component" => ElementKind::Component,
+                "package" => ElementKind::Package,
+                "device" => ElementKind::Device,
+                _ => {
+                    return Err(error(format!(
+                        "unknown element {:?} (expected symbol, component, package or device)",
+                        element
+                    )))
+                }
+            };
+            let source = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+            env.add_template_owned(kind.to_string(), source)
+                .map_err(|e| error(e.to_string()))?;
+            paths.push((kind, PathBuf::from(path)));
+        }
+        Ok(Templates { env, paths })
+    }
+
+    /// Render the file of an element with its template, if there is one.
+    pub fn render(&self, kind: ElementKind, lines: &[String]) -> Result<Option<String>> {
+        let path = match self.paths.iter().find(|(k, _)| *k == kind) {
+            Some((_, path)) => path,
+            None => return Ok(None),
+        };
+        let error = |reason: String| Error::Template {
+            template: path.display().to_string(),
+            reason,
+        };
+        let context = context(kind, lines).map_err(error)?;
+        let template = self
+            .env
+            .get_template(&kind.to_string())
+            .map_err(|e| error(e.to_string()))?;
+        let rendered = template.render(context).map_err(|e| error(e.to_string()))?;
+        Ok(Some(rendered))
+    }
+}
+
+/// Collect the template variables from a generated element.
+fn context(kind: ElementKind, lines: &[String]) -> std::result::Result<Context, String> {
+    let generated = lines.join("\n") + "\n";
+    let root = sexpr::parse(&generated)?;
+    let child_arg = |name: &str| {
+        root.child(name)
+            .and_then(|child| child.arg(0))
+            .unwrap_or("")
+            .to_string()
+    };
+    let footprints = root
+        .lists()
+        .filter(|list| list.name() == Some("footprint"))
+        .map(|footprint| Footprint {
+            uuid: footprint.arg(0).unwrap_or("").to_string(),
+            name: footprint
+                .child("name")
+                .and_then(|name| name.arg(0))
+                .unwrap_or("")
+                .to_string(),
+            polygons: polygons(footprint),
+        })
+        .collect();
+    Ok(Context {
+        element: kind.to_string(),
+        uuid: root.arg(0).unwrap_or("").to_string(),
+        name: child_arg("name"),
+        description: child_arg("description"),
+        keywords: child_arg("keywords"),
+        author: child_arg("author"),
+        version: child_arg("version"),
+        nodes: lines
+            .get(1..lines.len().saturating_sub(1))
+            .unwrap_or_default()
+            .to_vec(),
+        polygons: polygons(&root),
+        footprints,
+        generated: generated.clone(),
+    })
+}
+
+/// The polygons which are direct children of a list.
+fn polygons(list: &List) -> Vec<Polygon> {
+    let number = |list: Option<&List>, index: usize| {
+        list.and_then(|list| list.arg(index))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.0)
+    };
+    let flag = |polygon: &List, name: &str| {
+        polygon.child(name).and_then(|child| child.arg(0)) == Some("true")
+    };
+    list.lists()
+        .filter(|child| child.name() == Some("polygon"))
+        .map(|polygon| Polygon {
+            layer: polygon
+                .child("layer")
+                .and_then(|layer| layer.arg(0))
+                .unwrap_or("")
+                .to_string(),
+            width: number(polygon.child("width"), 0),
+            fill: flag(polygon, "fill"),
+            grab_area: flag(polygon, "grab_area"),
+            vertices: polygon
+                .lists()
+                .filter(|child| child.name() == Some("vertex"))
+                .map(|vertex| Vertex {
+                    x: number(vertex.child("position"), 0),
+                    y: number(vertex.child("position"), 1),
+                    angle: number(vertex.child("angle"), 0),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    const PACKAGE: &str = r#"(librepcb_package 8d92aac5-2fe0-460c-baad-35e9361d5f79
+ (name "Logo \"A\"")
+ (author "Danilo")
+ (footprint 0b1f4ee5-6c02-4bd6-8fa7-2d5a2b2b5ef5
+  (name "Top Copper")
+  (polygon 2f3a5e1c-1f9e-4a65-9d3a-3c3c4f0b8a51 (layer top_cu)
+   (width 0.0) (fill true) (grab_area false)
+   (vertex (position 0.0 0.0) (angle 0.0))
+   (vertex (position 1.5 -2.0) (angle 90.0))
+  )
+ )
+)"#;
+
+    #[test]
+    fn test_context() {
+        let lines: Vec<String> = PACKAGE.lines().map(String::from).collect();
+        let context = context(ElementKind::Package, &lines).unwrap();
+        assert_eq!(context.element, "package");
+        assert_eq!(context.uuid, "8d92aac5-2fe0-460c-baad-35e9361d5f79");
+        assert_eq!(context.name, "Logo \"A\"");
+        assert_eq!(context.nodes.len(), lines.len() - 2);
+        assert!(context.polygons.is_empty());
+        let polygon = &context.footprints[0].polygons[0];
+        assert_eq!(polygon.layer, "top_cu");
+        assert!(polygon.fill && !polygon.grab_area);
+        assert_eq!(
+            polygon.vertices[1],
+            Vertex {
+                x: 1.5,
+                y: -2.0,
+                angle: 90.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let dir = TempDir::new("test").unwrap();
+        let path = dir.path().join("package.lp.j2");
+        fs::write(
+            &path,
+            "(package \"{{ name | escape_string }}\"{% for fp in footprints %} {{ fp.polygons[0].vertices[1].x | format_float }}{% endfor %})\n",
+        )
+        .unwrap();
+        let templates = Templates::load(&[format!("package={}", path.display())]).unwrap();
+        let lines: Vec<String> = PACKAGE.lines().map(String::from).collect();
+        assert_eq!(
+            templates.render(ElementKind::Package, &lines).unwrap(),
+            Some("(package \"Logo \\\"A\\\"\" 1.5)\n".to_string())
+        );
+        assert_eq!(templates.render(ElementKind::Device, &lines).unwrap(), None);
+
+        assert!(Templates::load(&["package".to_string()]).is_err());
+        assert!(Templates::load(&["pin=x.j2".to_string()]).is_err());
+    }
+}
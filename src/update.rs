@@ -0,0 +1,293 @@
+//! Regenerating existing elements without losing manual edits.
+//!
+//! The UUIDs of all polygons, texts, holes, fiducials and test point pads
+//! created by this tool are recorded in a file next to the element. When an
+//! element is regenerated, everything else (e.g. pins, pads, signals or
+//! additional polygons added in the library editor) is carried over into the
+//! new element.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use uuid::Uuid;
+
+use crate::{
+    element_dir,
+    error::{Error, Result},
+    sexpr::{self, List},
+    ElementKind,
+};
+
+/// Name of the file listing the UUIDs of generated polygons, texts, holes,
+/// fiducials and pads.
+pub const GENERATED_FILE: &str = ".svg2librepcb-generated";
+
+/// Nodes which are generated by this tool.
+const GENERATED_NODES: [&str; 7] = [
+    "polygon",
+    "text",
+    "stroke_text",
+    "hole",
+    "circle",
+    "zone",
+    "pad",
+];
+
+/// Element header nodes, which are always regenerated.
+const HEADER_NODES: [&str; 11] = [
+    "name",
+    "description",
+    "keywords",
+    "author",
+    "version",
+    "created",
+    "deprecated",
+    "generated_by",
+    "category",
+    "assembly_type",
+    "approved",
+];
+
+/// Nodes of a component which are always regenerated.
+const COMPONENT_NODES: [&str; 5] = [
+    "schematic_only",
+    "default_value",
+    "prefix",
+    "attribute",
+    "variant",
+];
+
+/// Footprint header nodes, which are always regenerated.
+const FOOTPRINT_HEADER_NODES: [&str; 4] = ["name", "description", "3d_position", "3d_rotation"];
+
+/// Manual edits of an existing element.
+#[derive(Default, Debug)]
+pub struct Edits {
+    /// Additional nodes at the root of the element
+    pub nodes: Vec<String>,
+    /// Existing footprints of a package
+    pub footprints: Vec<FootprintEdits>,
+    /// Pin-signal maps of the gate of a component
+    pub gate_pins: Vec<String>,
+}
+
+/// Manual edits of an existing footprint.
+#[derive(Debug)]
+pub struct FootprintEdits {
+    pub name: String,
+    pub uuid: Uuid,
+    /// Additional nodes within the footprint
+    pub nodes: Vec<String>,
+    /// The complete footprint, used if it is not regenerated
+    pub lines: Vec<String>,
+}
+
+impl Edits {
+    /// Remove and return the edits of the footprint with the given name.
+    pub fn take_footprint(&mut self, name: &str) -> Option<FootprintEdits> {
+        let index = self.footprints.iter().position(|f| f.name == name)?;
+        Some(self.footprints.remove(index))
+    }
+}
+
+/// Load the manual edits of an existing element.
+///
+/// Returns `None` if the element does not exist yet.
+pub fn load_edits(lib_path: &Path, kind: ElementKind, uuid: &str) -> Result<Option<Edits>> {
+    let dir = element_dir(lib_path, kind, uuid);
+    let path = dir.join(kind.file_name());
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path).map_err(|source| Error::ReadFile {
+        path: path.clone(),
+        source,
+    })?;
+    let root = sexpr::parse(&contents).map_err(|reason| Error::ParseElement {
+        element: kind,
+        path: path.clone(),
+        reason,
+    })?;
+
+    // Without a list of generated nodes, assume that all polygons, texts and
+    // holes were generated (but no pads, which were not generated back then).
+    let generated: Option<HashSet<String>> = fs::read_to_string(dir.join(GENERATED_FILE))
+        .ok()
+        .map(|list| list.lines().map(str::to_string).collect());
+    let is_generated = |list: &List| {
+        let name = list.name().unwrap_or("");
+        GENERATED_NODES.contains(&name)
+            && match &generated {
+                Some(uuids) => uuids.contains(list.arg(0).unwrap_or("")),
+                None => name != "pad",
+            }
+    };
+
+    let mut edits = Edits::default();
+    for list in root.lists() {
+        let name = list.name().unwrap_or("");
+        match kind {
+            ElementKind::Symbol | ElementKind::Package => {
+                if name == "footprint" {
+                    edits
+                        .footprints
+                        .extend(footprint_edits(list, &is_generated));
+                } else if !HEADER_NODES.contains(&name) && !is_generated(list) {
+                    edits.nodes.extend(list.to_lines(1));
+                }
+            }
+            ElementKind::Device => {
+                // Keep pad assignments for pads added to the package
+                if name == "pad" && !is_generated(list) {
+                    edits.nodes.extend(list.to_lines(1));
+                }
+            }
+            ElementKind::Component => {
+                // Keep signals added in the library editor
+                if !HEADER_NODES.contains(&name) && !COMPONENT_NODES.contains(&name) {
+                    edits.nodes.extend(list.to_lines(1));
+                }
+            }
+        }
+    }
+    if kind == ElementKind::Component {
+        // Keep the pin-signal maps of the generated gate
+        let gate = root
+            .child("variant")
+            .and_then(|variant| variant.child("gate"));
+        edits.gate_pins = gate
+            .into_iter()
+            .flat_map(|gate| gate.lists())
+            .filter(|child| child.name() == Some("pin"))
+            .flat_map(|pin| pin.to_lines(3))
+            .collect();
+    }
+    Ok(Some(edits))
+}
+
+/// Extract the manual edits of a footprint.
+fn footprint_edits(list: &List, is_generated: &dyn Fn(&List) -> bool) -> Option<FootprintEdits> {
+    let name = list.child("name")?.arg(0)?;
+    let uuid = Uuid::parse_str(list.arg(0)?).ok()?;
+    let nodes = list
+        .lists()
+        .filter(|child| {
+            !FOOTPRINT_HEADER_NODES.contains(&child.name().unwrap_or("")) && !is_generated(child)
+        })
+        .flat_map(|child| child.to_lines(1))
+        .collect();
+    Some(FootprintEdits {
+        name: name.to_string(),
+        uuid,
+        nodes,
+        lines: list.to_lines(1),
+    })
+}
+
+/// Insert lines before the closing parenthesis of an element.
+pub fn insert_before_end(lines: &mut Vec<String>, extra: Vec<String>) {
+    let end = lines.len().saturating_sub(1);
+    lines.splice(end..end, extra);
+}
+
+/// Insert lines at the end of the (first) gate of a component.
+pub fn insert_into_gate(lines: &mut Vec<String>, extra: Vec<String>) {
+    let end = lines
+        .iter()
+        .position(|line| line.starts_with("  (gate "))
+        .and_then(|gate| {
+            let end = lines[gate..].iter().position(|line| line == "  )")?;
+            Some(gate + end)
+        });
+    if let Some(end) = end {
+        lines.splice(end..end, extra);
+    }
+}
+
+/// Collect the UUIDs of all generated nodes of a freshly generated element
+/// (including those within footprints).
+pub fn generated_uuids(lines: &[String]) -> Vec<String> {
+    fn collect(list: &List, uuids: &mut Vec<String>) {
+        for child in list.lists() {
+            match child.name() {
+                Some("footprint") => collect(child, uuids),
+                Some(name) if GENERATED_NODES.contains(&name) => {
+                    uuids.extend(child.arg(0).map(str::to_string));
+                }
+                _ => {}
+            }
+        }
+    }
+    let source = lines.join("\n");
+    let mut uuids = vec![];
+    if let Ok(root) = sexpr::parse(&source) {
+        collect(&root, &mut uuids);
+    }
+    uuids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn test_generated_uuids() {
+        let lines: Vec<String> = [
+            "(librepcb_package 8d92aac5-2fe0-460c-baad-35e9361d5f79",
+            " (name \"Logo\")",
+            " (footprint 334c948e-c55d-4440-a47e-22a625289b02",
+            "  (name \"Top Copper\")",
+            "  (polygon 0cd1ed31-59d5-47ff-b2d4-381311927aae (layer top_cu)",
+            "  )",
+            " )",
+            ")",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        assert_eq!(
+            generated_uuids(&lines),
+            vec!["0cd1ed31-59d5-47ff-b2d4-381311927aae"]
+        );
+    }
+
+    #[test]
+    fn test_load_component_edits() {
+        let dir = TempDir::new("test").unwrap();
+        let lib_path = dir.path().join("Logos.lplib");
+        let uuid = "f7b4a9e5-4b50-4c0f-a5a8-4c7e6e0bd1c4";
+        let dir = element_dir(&lib_path, ElementKind::Component, uuid);
+        fs::create_dir_all(&dir).unwrap();
+        let component = [
+            "(librepcb_component f7b4a9e5-4b50-4c0f-a5a8-4c7e6e0bd1c4",
+            " (name \"Logo\")",
+            " (prefix \"LOGO\")",
+            " (signal 0a6c9a4b-7e0e-4d36-8a6e-7dbd9a4b0c11 (name \"GND\") (role passive)",
+            "  (required false) (negated false) (clock false) (forced_net \"\")",
+            " )",
+            " (variant 3e7b1c26-1f4e-4f8d-b0b5-2f6b0a1c9d22 (norm \"\")",
+            "  (name \"default\")",
+            "  (gate 5d2c8f31-6a1e-4b7c-9e3d-4f5a6b7c8d93",
+            "   (symbol 8d92aac5-2fe0-460c-baad-35e9361d5f79)",
+            "   (pin 1b2c3d4e-5f60-4718-89a0-b1c2d3e4f5a6 (signal 0a6c9a4b-7e0e-4d36-8a6e-7dbd9a4b0c11) (text pin))",
+            "  )",
+            " )",
+            ")",
+        ];
+        fs::write(dir.join("component.lp"), component.join("\n")).unwrap();
+        let edits = load_edits(&lib_path, ElementKind::Component, uuid)
+            .unwrap()
+            .unwrap();
+        assert_eq!(edits.nodes, component[3..6]);
+        assert_eq!(edits.gate_pins, component[10..11]);
+
+        // The signals and pins are inserted into the regenerated component
+        let mut lines: Vec<String> = component.iter().map(|l| l.to_string()).collect();
+        lines.drain(3..6);
+        lines.remove(7);
+        insert_into_gate(&mut lines, edits.gate_pins);
+        insert_before_end(&mut lines, edits.nodes);
+        assert_eq!(lines[7], component[10]);
+        assert_eq!(lines[10..13], component[3..6]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Checks for metadata values, following the rules that the LibrePCB library
+//! editor applies when loading an element.
+
+/// Maximum length of an element name.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// Maximum length of a component prefix.
+const MAX_PREFIX_LENGTH: usize = 16;
+
+/// Maximum length of a gate suffix.
+const MAX_GATE_SUFFIX_LENGTH: usize = 16;
+
+/// Maximum length of a component attribute key.
+const MAX_ATTRIBUTE_KEY_LENGTH: usize = 40;
+
+/// Maximum number of numeric segments in a version string.
+const MAX_VERSION_SEGMENTS: usize = 10;
+
+/// Maximum number of digits per version segment.
+const MAX_VERSION_SEGMENT_DIGITS: usize = 5;
+
+/// Validate an element name.
+///
+/// Names must not be empty, must not start or end with whitespace, must not
+/// contain line breaks or other control characters and must not be longer
+/// than [`MAX_NAME_LENGTH`] characters.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("must not be empty".into());
+    }
+    if name.trim() != name {
+        return Err("must not start or end with whitespace".into());
+    }
+    if name.chars().any(char::is_control) {
+        return Err("must not contain line breaks or control characters".into());
+    }
+    let length = name.chars().count();
+    if length > MAX_NAME_LENGTH {
+        return Err(format!(
+            "must not be longer than {} characters (is {})",
+            MAX_NAME_LENGTH, length
+        ));
+    }
+    Ok(())
+}
+
+/// Turn an arbitrary string into a valid element name.
+///
+/// Line breaks and other control characters are replaced with spaces, runs of
+/// whitespace are collapsed, and the result is trimmed and shortened to
+/// [`MAX_NAME_LENGTH`] characters. Note that the result may still be empty.
+pub fn sanitize_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(collapsed.trim(), MAX_NAME_LENGTH)
+}
+
+/// Shorten a string to at most `length` characters, ending it with an
+/// ellipsis if anything was cut off.
+pub fn truncate(value: &str, length: usize) -> String {
+    if value.chars().count() <= length {
+        return value.to_string();
+    }
+    let kept: String = value.chars().take(length.saturating_sub(1)).collect();
+    format!("{}…", kept.trim_end())
+}
+
+/// Append a suffix (e.g. ` (Top Copper)`) to an element name, shortening the
+/// name so that the result is not longer than [`MAX_NAME_LENGTH`] characters.
+pub fn name_with_suffix(name: &str, suffix: &str) -> String {
+    let length = MAX_NAME_LENGTH.saturating_sub(suffix.chars().count());
+    format!("{}{}", truncate(name, length), suffix)
+}
+
+/// Validate a version string.
+///
+/// Versions consist of 1 to 10 numeric segments separated by dots, e.g.
+/// `0.1` or `1.2.3`. Every segment has at most 5 digits.
+pub fn validate_version(version: &str) -> Result<(), String> {
+    let segments: Vec<&str> = version.split('.').collect();
+    if segments.len() > MAX_VERSION_SEGMENTS {
+        return Err(format!(
+            "must not have more than {} segments",
+            MAX_VERSION_SEGMENTS
+        ));
+    }
+    for segment in segments {
+        if segment.is_empty()
+            || segment.len() > MAX_VERSION_SEGMENT_DIGITS
+            || !segment.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err("must consist of numbers separated by dots (e.g. \"0.1.0\")".into());
+        }
+    }
+    Ok(())
+}
+
+/// Validate a component prefix.
+///
+/// Prefixes consist of up to 16 ASCII letters or underscores (e.g. `R` or
+/// `LOGO`). An empty prefix is allowed.
+pub fn validate_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.len() > MAX_PREFIX_LENGTH {
+        return Err(format!(
+            "must not be longer than {} characters",
+            MAX_PREFIX_LENGTH
+        ));
+    }
+    if !prefix.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+        return Err("must only contain letters and underscores".into());
+    }
+    Ok(())
+}
+
+/// Validate the suffix of a component gate.
+///
+/// Suffixes consist of up to 16 ASCII letters, digits, underscores, dashes or
+/// dots (e.g. `A` or `1`). An empty suffix is allowed.
+pub fn validate_gate_suffix(suffix: &str) -> Result<(), String> {
+    if suffix.len() > MAX_GATE_SUFFIX_LENGTH {
+        return Err(format!(
+            "must not be longer than {} characters",
+            MAX_GATE_SUFFIX_LENGTH
+        ));
+    }
+    if !suffix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+    {
+        return Err("must only contain letters, digits, underscores, dashes and dots".into());
+    }
+    Ok(())
+}
+
+/// Validate a component attribute key.
+///
+/// Keys consist of 1 to 40 uppercase ASCII letters, digits or underscores
+/// (e.g. `MANUFACTURER`).
+pub fn validate_attribute_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_ATTRIBUTE_KEY_LENGTH {
+        return Err(format!(
+            "must be 1 to {} characters long",
+            MAX_ATTRIBUTE_KEY_LENGTH
+        ));
+    }
+    if !key
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err("must only contain uppercase letters, digits and underscores".into());
+    }
+    Ok(())
+}
+
+/// Validate a single-line value, e.g. keywords or a default value.
+///
+/// The value must not contain line breaks or other control characters.
+pub fn validate_single_line(value: &str) -> Result<(), String> {
+    if value.chars().any(char::is_control) {
+        return Err("must not contain line breaks or control characters".into());
+    }
+    Ok(())
+}
+
+/// Largest text height in mm, to catch heights given in other units (e.g.
+/// points or mils).
+const MAX_TEXT_HEIGHT: f64 = 100.0;
+
+/// Validate the height of a text in mm.
+pub fn validate_text_height(height: f64) -> Result<(), &'static str> {
+    if height <= 0.0 || height.is_nan() {
+        return Err("the height must be positive");
+    }
+    if height > MAX_TEXT_HEIGHT {
+        return Err("the height is given in mm and must be at most 100");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_text_height() {
+        assert!(validate_text_height(2.5).is_ok());
+        for height in [0.0, -1.0, f64::NAN, 254.0] {
+            assert!(validate_text_height(height).is_err(), "{}", height);
+        }
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("My Logo").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(" My Logo").is_err());
+        assert!(validate_name("My\nLogo").is_err());
+        assert!(validate_name(&"x".repeat(MAX_NAME_LENGTH)).is_ok());
+        assert!(validate_name(&"x".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("My Logo"), "My Logo");
+        assert_eq!(sanitize_name("  my_logo\t(v2)\n"), "my_logo (v2)");
+        assert_eq!(sanitize_name("a\r\n\r\nb"), "a b");
+        let long = format!("{} {}", "x".repeat(MAX_NAME_LENGTH - 2), "yy");
+        assert_eq!(
+            sanitize_name(&long),
+            format!("{}…", "x".repeat(MAX_NAME_LENGTH - 2))
+        );
+        assert!(validate_name(&sanitize_name(&"z".repeat(500))).is_ok());
+    }
+
+    #[test]
+    fn test_name_with_suffix() {
+        assert_eq!(
+            name_with_suffix("Logo", " (Mask Window)"),
+            "Logo (Mask Window)"
+        );
+        let name = name_with_suffix(&"x".repeat(MAX_NAME_LENGTH), " (10.0 mm)");
+        assert_eq!(name.chars().count(), MAX_NAME_LENGTH);
+        assert!(name.ends_with("x… (10.0 mm)"));
+        assert!(validate_name(&name).is_ok());
+    }
+
+    #[test]
+    fn test_validate_prefix() {
+        for valid in ["", "R", "LOGO", "MY_ART"] {
+            assert!(validate_prefix(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["R1", "LO GO", "Ä", "ABCDEFGHIJKLMNOPQ"] {
+            assert!(validate_prefix(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validate_gate_suffix() {
+        for valid in ["", "A", "1", "U_1.a-b"] {
+            assert!(validate_gate_suffix(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["A B", "Ä", "(A)", &"A".repeat(17)] {
+            assert!(validate_gate_suffix(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validate_attribute_key() {
+        for valid in ["MANUFACTURER", "ART_REVISION", "V2"] {
+            assert!(validate_attribute_key(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["", "Manufacturer", "ART-REVISION", &"X".repeat(41)] {
+            assert!(validate_attribute_key(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validate_version() {
+        for valid in ["0", "0.1", "0.1.0", "1.2.3.4.5.6.7.8.9.10", "99999"] {
+            assert!(validate_version(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "1.",
+            ".1",
+            "1.0-beta",
+            "v1",
+            "1..2",
+            "123456",
+            "1.2.3.4.5.6.7.8.9.10.11",
+        ] {
+            assert!(validate_version(invalid).is_err(), "{}", invalid);
+        }
+    }
+}